@@ -0,0 +1,339 @@
+use std::io::Read;
+
+use crate::error::*;
+use crate::structs::*;
+
+/// The length of the main header of a CCP4/MRC file in bytes
+const HEADER_LENGTH: usize = 1024;
+
+/// Read a CCP4/MRC density map.
+///
+/// The file starts with a header of 256 four byte words, followed by an optional extended header
+/// (of `NSYMBT` bytes), followed by the values ordered by column, row, and section. The axes
+/// corresponding to columns, rows, and sections are given by `MAPC`, `MAPR`, and `MAPS`, the
+/// values are reordered to x, y, z order. The byte order is taken from the machine stamp, or
+/// guessed from the header if the machine stamp is not set. The supported modes are 0 (signed
+/// 8-bit integers), 1 (16-bit integers), 2 (32-bit floats), 6 (unsigned 16-bit integers), and 12
+/// (16-bit floats).
+///
+/// MRC files from electron microscopy often give the position of the map with `ORIGIN` (words
+/// 50-52) instead of the start indices. If `ORIGIN` is set it is used as the position of the first
+/// grid point of the map.
+pub(crate) fn open_map_raw(mut input: impl Read, context: Context) -> Result<DensityMap, PDBError> {
+    let error = |message: &str| {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid map file",
+            message,
+            context.clone(),
+        )
+    };
+    let io_error = |e: std::io::Error| {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Buffer could not be read",
+            format!("The map could not be read: {e}."),
+            context.clone(),
+        )
+    };
+
+    let mut header = vec![0; HEADER_LENGTH];
+    input.read_exact(&mut header).map_err(io_error)?;
+    let big_endian = match (header[212], header[213]) {
+        (0x44 | 0x4C, _) => false,
+        (0x11, 0x11) => true,
+        // No (valid) machine stamp, use the byte order in which the mode makes sense
+        _ => !matches!(
+            i32::from_le_bytes([header[12], header[13], header[14], header[15]]),
+            0..=2 | 6 | 12
+        ),
+    };
+    let word = |index: usize| {
+        let bytes = [
+            header[4 * index],
+            header[4 * index + 1],
+            header[4 * index + 2],
+            header[4 * index + 3],
+        ];
+        if big_endian {
+            i32::from_be_bytes(bytes)
+        } else {
+            i32::from_le_bytes(bytes)
+        }
+    };
+    #[allow(clippy::cast_sign_loss)]
+    let float = |index: usize| f64::from(f32::from_bits(word(index) as u32));
+
+    let count = |index: usize| usize::try_from(word(index)).ok().filter(|n| *n > 0);
+    let dimensions = [count(0), count(1), count(2)];
+    let sampling = [count(7), count(8), count(9)];
+    let (dimensions, sampling) = match (dimensions, sampling) {
+        ([Some(c), Some(r), Some(s)], [Some(x), Some(y), Some(z)]) => ([c, r, s], [x, y, z]),
+        _ => return Err(error("The number of grid points is not positive.")),
+    };
+    let mode = word(3);
+    let axes = [word(16), word(17), word(18)];
+    let mut sorted = axes;
+    sorted.sort_unstable();
+    if sorted != [1, 2, 3] {
+        return Err(error(
+            "The axis order (MAPC, MAPR, MAPS) is not a permutation of 1, 2, and 3.",
+        ));
+    }
+    #[allow(clippy::cast_sign_loss)]
+    let axes = axes.map(|axis| axis as usize - 1);
+    let unit_cell = UnitCell::new(
+        float(10),
+        float(11),
+        float(12),
+        float(13),
+        float(14),
+        float(15),
+    );
+    let mut start = [0; 3];
+    let mut size = [0; 3];
+    for (file_axis, axis) in axes.iter().enumerate() {
+        start[*axis] = word(4 + file_axis) as isize;
+        size[*axis] = dimensions[file_axis];
+    }
+
+    let extended_header = usize::try_from(word(23)).unwrap_or_default();
+    std::io::copy(
+        &mut (&mut input).take(extended_header as u64),
+        &mut std::io::sink(),
+    )
+    .map_err(io_error)?;
+
+    let voxel_length = match mode {
+        0 => 1,
+        1 | 6 | 12 => 2,
+        2 => 4,
+        _ => {
+            return Err(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Unsupported map mode",
+                format!(
+                    "Mode {mode} is not supported, only modes 0, 1, 2, 6, and 12 are supported."
+                ),
+                context.clone(),
+            ))
+        }
+    };
+    let voxels = dimensions
+        .iter()
+        .try_fold(1_usize, |acc, n| acc.checked_mul(*n))
+        .ok_or_else(|| error("The map is too big."))?;
+    let length = voxels
+        .checked_mul(voxel_length)
+        .ok_or_else(|| error("The map is too big."))?;
+    // Only the data that is present is read, so the size of the map is checked before allocating
+    let mut bytes = Vec::new();
+    (&mut input)
+        .take(length as u64)
+        .read_to_end(&mut bytes)
+        .map_err(io_error)?;
+    if bytes.len() != length {
+        return Err(error("The file ended before all values were read."));
+    }
+    let values = bytes.chunks_exact(voxel_length).map(|chunk| match mode {
+        0 => f32::from(i8::from_ne_bytes([chunk[0]])),
+        1 | 6 | 12 => {
+            let value = if big_endian {
+                u16::from_be_bytes([chunk[0], chunk[1]])
+            } else {
+                u16::from_le_bytes([chunk[0], chunk[1]])
+            };
+            match mode {
+                #[allow(clippy::cast_possible_wrap)]
+                1 => f32::from(value as i16),
+                6 => f32::from(value),
+                _ => half_to_float(value),
+            }
+        }
+        _ => {
+            let bytes = [chunk[0], chunk[1], chunk[2], chunk[3]];
+            if big_endian {
+                f32::from_be_bytes(bytes)
+            } else {
+                f32::from_le_bytes(bytes)
+            }
+        }
+    });
+
+    // Reorder the values from column, row, section order to x, y, z order
+    let mut data = vec![0.0; voxels];
+    let stride = [1, size[0], size[0] * size[1]];
+    let mut position = [0; 3];
+    for value in values {
+        let index: usize = (0..3).map(|axis| position[axis] * stride[axes[axis]]).sum();
+        data[index] = value;
+        position[0] += 1;
+        if position[0] == dimensions[0] {
+            position[0] = 0;
+            position[1] += 1;
+            if position[1] == dimensions[1] {
+                position[1] = 0;
+                position[2] += 1;
+            }
+        }
+    }
+
+    let mut map = DensityMap::new(size, start, sampling, unit_cell, [0.0; 3], data)
+        .ok_or_else(|| error("The unit cell has no volume."))?;
+    let origin = [float(49), float(50), float(51)];
+    if origin
+        .iter()
+        .any(|value| *value != 0.0 && value.is_finite())
+    {
+        // Place the first grid point at the origin
+        #[allow(clippy::cast_precision_loss)]
+        let first = map.grid_position(start.map(|value| value as f64));
+        map.set_origin([
+            origin[0] - first.0,
+            origin[1] - first.1,
+            origin[2] - first.2,
+        ]);
+    }
+    Ok(map)
+}
+
+/// Convert an IEEE 754 half precision float to a single precision float
+fn half_to_float(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1F);
+    let fraction = f32::from(bits & 0x3FF);
+    sign * match exponent {
+        0 => fraction * 2.0_f32.powi(-24),
+        31 if fraction == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + fraction / 1024.0) * 2.0_f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// Create a map file with a 3x2x4 grid in a 30x20x40 Å cell, with the given axis order and
+    /// values in file order
+    fn file(mode: i32, axes: [i32; 3], values: &[u8], big_endian: bool) -> Vec<u8> {
+        let int = |value: i32| {
+            if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+        let float = |value: f32| int(value.to_bits() as i32);
+        let size = [3, 2, 4];
+        let mut header = Vec::new();
+        for axis in axes {
+            header.extend(int(size[axis as usize - 1]));
+        }
+        header.extend(int(mode));
+        header.extend(int(1)); // Start along the column axis
+        header.extend(int(0));
+        header.extend(int(0));
+        header.extend(int(3));
+        header.extend(int(2));
+        header.extend(int(4));
+        for value in [30.0, 20.0, 40.0, 90.0, 90.0, 90.0] {
+            header.extend(float(value));
+        }
+        for axis in axes {
+            header.extend(int(axis));
+        }
+        header.extend([0; 8 * 4]); // DMIN ... NSYMBT (10 byte extended header added below)
+        header[23 * 4..24 * 4].copy_from_slice(&int(10));
+        header.resize(52 * 4, 0);
+        header.extend(b"MAP ");
+        header.extend(if big_endian {
+            [0x11, 0x11, 0, 0]
+        } else {
+            [0x44, 0x44, 0, 0]
+        });
+        header.resize(HEADER_LENGTH, 0);
+        header.extend([0; 10]);
+        header.extend(values);
+        header
+    }
+
+    #[test]
+    fn float_map_with_axis_order() {
+        // Columns along z, rows along x, sections along y
+        let values: Vec<u8> = (0..24).flat_map(|v| (v as f32).to_le_bytes()).collect();
+        let map = open_map_raw(&file(2, [3, 1, 2], &values, false)[..], Context::None).unwrap();
+        assert_eq!(map.size(), [3, 2, 4]);
+        assert_eq!(map.start(), [0, 0, 1]);
+        assert_eq!(map.sampling(), [3, 2, 4]);
+        assert_eq!(map.unit_cell().c(), 40.0);
+        // Value index = z' + 4 * (x + 3 * y) with z' = z - 1
+        assert_eq!(map.value([0, 0, 1]), Some(0.0));
+        assert_eq!(map.value([0, 0, 2]), Some(1.0));
+        assert_eq!(map.value([1, 0, 1]), Some(4.0));
+        assert_eq!(map.value([2, 1, 4]), Some(23.0));
+        assert_eq!(map.max(), 23.0);
+    }
+
+    #[test]
+    fn integer_modes() {
+        let values: Vec<u8> = (0..24_i16).flat_map(|v| (v - 12).to_be_bytes()).collect();
+        let map = open_map_raw(&file(1, [1, 2, 3], &values, true)[..], Context::None).unwrap();
+        assert_eq!(map.value([1, 0, 0]), Some(-12.0));
+        assert_eq!(map.min(), -12.0);
+
+        let values: Vec<u8> = (0..24_u8).map(|v| v.wrapping_sub(12)).collect();
+        let map = open_map_raw(&file(0, [1, 2, 3], &values, false)[..], Context::None).unwrap();
+        assert_eq!(map.value([1, 0, 0]), Some(-12.0));
+
+        let values: Vec<u8> = [0x3C00_u16, 0xC000, 0x3555]
+            .iter()
+            .cycle()
+            .take(24)
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let map = open_map_raw(&file(12, [1, 2, 3], &values, false)[..], Context::None).unwrap();
+        assert_eq!(map.value([1, 0, 0]), Some(1.0));
+        assert_eq!(map.value([2, 0, 0]), Some(-2.0));
+        assert!((map.value([0, 0, 0]).unwrap() - 0.333).abs() < 1e-3);
+    }
+
+    #[test]
+    fn origin() {
+        let values: Vec<u8> = (0..24).flat_map(|v| (v as f32).to_le_bytes()).collect();
+        let mut bytes = file(2, [1, 2, 3], &values, false);
+        for (index, value) in [5.0_f32, 6.0, 7.0].iter().enumerate() {
+            bytes[(49 + index) * 4..(50 + index) * 4].copy_from_slice(&value.to_le_bytes());
+        }
+        let map = open_map_raw(&bytes[..], Context::None).unwrap();
+        let first = map.grid_position([1.0, 0.0, 0.0]);
+        assert!((first.0 - 5.0).abs() < 1e-9);
+        assert!((first.1 - 6.0).abs() < 1e-9);
+        assert!((map.interpolate((5.0, 6.0, 7.0)).unwrap()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid() {
+        let values = vec![0; 96];
+        let error =
+            open_map_raw(&file(2, [1, 1, 3], &values, false)[..], Context::None).unwrap_err();
+        assert_eq!(error.short_description(), "Invalid map file");
+        let error =
+            open_map_raw(&file(4, [1, 2, 3], &values, false)[..], Context::None).unwrap_err();
+        assert_eq!(error.short_description(), "Unsupported map mode");
+        let error =
+            open_map_raw(&file(2, [1, 2, 3], &values[..50], false)[..], Context::None).unwrap_err();
+        assert_eq!(error.short_description(), "Invalid map file");
+        // 2^20 by 2^20 by 2^22 voxels of four bytes do not fit in memory
+        let mut huge = file(2, [1, 2, 3], &values, false);
+        for (index, power) in [20, 20, 22].iter().enumerate() {
+            huge[index * 4..(index + 1) * 4].copy_from_slice(&(1_i32 << power).to_le_bytes());
+        }
+        let error = open_map_raw(&huge[..], Context::None).unwrap_err();
+        assert_eq!(error.short_description(), "Invalid map file");
+        assert_eq!(error.level(), ErrorLevel::BreakingError);
+        let error = open_map_raw(&[0_u8; 100][..], Context::None).unwrap_err();
+        assert_eq!(error.short_description(), "Buffer could not be read");
+    }
+}
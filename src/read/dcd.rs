@@ -0,0 +1,419 @@
+use std::io::Read;
+
+use crate::error::*;
+use crate::structs::*;
+
+use super::trajectory::{read_or_end, Frame};
+
+/// The length of an AKMA time unit (as used for the time step in DCD files) in ps
+pub(crate) const AKMA_PS: f64 = 0.048_888_821;
+
+/// Read the frames of a CHARMM/NAMD DCD file.
+///
+/// A DCD file is a sequence of Fortran unformatted records: a header with the number of frames
+/// and the flags, a title, the number of atoms, and per frame an optional unit cell and the x, y,
+/// and z coordinates as single precision floats. Both byte orders are supported, as well as the
+/// X-PLOR variant of the header and fixed atoms (which are only stored in the first frame). The
+/// unit cell is stored as `A, gamma, B, beta, alpha, C` where newer versions of NAMD store the
+/// cosines of the angles instead of the angles.
+pub(crate) struct DcdReader<R> {
+    /// The input
+    input: R,
+    /// The context used for errors
+    context: Context,
+    /// If the file is big endian
+    big_endian: bool,
+    /// The number of atoms
+    atom_count: usize,
+    /// If every frame has a unit cell record
+    has_unit_cell: bool,
+    /// If every frame has a fourth dimension record
+    has_fourth_dimension: bool,
+    /// The step of the first frame
+    first_step: usize,
+    /// The number of steps between frames
+    step_interval: usize,
+    /// The time between steps in ps, if known
+    time_step: Option<f64>,
+    /// The indices of the atoms that are not fixed, if there are fixed atoms
+    free_atoms: Option<Vec<usize>>,
+    /// The coordinates of the previous frame, kept if there are fixed atoms
+    previous: Option<Vec<[f64; 3]>>,
+    /// The index of the next frame
+    index: usize,
+}
+
+impl<R: Read> DcdReader<R> {
+    /// Create a new reader and read the header of the file
+    pub(crate) fn new(input: R, context: Context) -> Result<Self, PDBError> {
+        let mut reader = DcdReader {
+            input,
+            context,
+            big_endian: false,
+            atom_count: 0,
+            has_unit_cell: false,
+            has_fourth_dimension: false,
+            first_step: 0,
+            step_interval: 1,
+            time_step: None,
+            free_atoms: None,
+            previous: None,
+            index: 0,
+        };
+        let mut marker = [0; 4];
+        if !read_or_end(&mut reader.input, &mut marker).map_err(|e| reader.io(&e))? {
+            return Err(reader.error("The file is empty."));
+        }
+        reader.big_endian = if u32::from_le_bytes(marker) == 84 {
+            false
+        } else if u32::from_be_bytes(marker) == 84 {
+            true
+        } else {
+            return Err(reader.error("The header record does not have the expected size of 84 bytes, note that DCD files with 64 bit record markers are not supported."));
+        };
+        let header = reader.record_data(84)?;
+        if &header[0..4] != b"CORD" {
+            return Err(reader.error("The header does not start with \"CORD\"."));
+        }
+        let control = (0..20)
+            .map(|index| reader.int(&header[4 + 4 * index..]))
+            .collect::<Vec<_>>();
+        let charmm = control[19] != 0;
+        let fixed = reader.count(control[8])?;
+        reader.first_step = usize::try_from(control[1]).unwrap_or_default();
+        reader.step_interval = usize::try_from(control[2]).unwrap_or(1).max(1);
+        let delta = if charmm {
+            f64::from(reader.float(&header[40..]))
+        } else {
+            reader.double(&header[40..])
+        };
+        reader.time_step = (delta > 0.0).then_some(delta * AKMA_PS);
+        reader.has_unit_cell = charmm && control[10] != 0;
+        reader.has_fourth_dimension = charmm && control[11] != 0;
+
+        let title = reader.record()?;
+        if title.len() < 4 {
+            return Err(reader.error("The title record is too short."));
+        }
+        let atoms = reader.record()?;
+        if atoms.len() != 4 {
+            return Err(reader.error("The record with the number of atoms should be 4 bytes."));
+        }
+        reader.atom_count = reader.count(reader.int(&atoms))?;
+        if fixed > 0 {
+            let free_count = reader.atom_count.checked_sub(fixed).ok_or_else(|| {
+                reader.error("The number of fixed atoms is larger than the number of atoms.")
+            })?;
+            let record = reader.record()?;
+            if record.len() != 4 * free_count {
+                return Err(reader.error("The record with the free atoms has an invalid size."));
+            }
+            let free = record
+                .chunks_exact(4)
+                .map(|bytes| {
+                    reader
+                        .count(reader.int(bytes))
+                        .ok()
+                        .filter(|index| (1..=reader.atom_count).contains(index))
+                        .map(|index| index - 1)
+                        .ok_or_else(|| reader.error("Invalid index of a free atom."))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            reader.free_atoms = Some(free);
+        }
+        Ok(reader)
+    }
+
+    /// Read the next frame, returns None if the file has ended
+    pub(crate) fn read_frame(&mut self) -> Result<Option<Frame>, PDBError> {
+        let mut marker = [0; 4];
+        if !read_or_end(&mut self.input, &mut marker).map_err(|e| self.io(&e))? {
+            return Ok(None);
+        }
+        let mut length = self.marker(marker)?;
+        let unit_cell = if self.has_unit_cell {
+            let record = self.record_data(length)?;
+            if record.len() != 48 {
+                return Err(self.error("The unit cell record should be 48 bytes."));
+            }
+            let values = record
+                .chunks_exact(8)
+                .map(|bytes| self.double(bytes))
+                .collect::<Vec<_>>();
+            let mut next = [0; 4];
+            self.read(&mut next)?;
+            length = self.marker(next)?;
+            unit_cell(&values)
+        } else {
+            None
+        };
+
+        let count = match (&self.free_atoms, &self.previous) {
+            (Some(free), Some(_)) => free.len(),
+            _ => self.atom_count,
+        };
+        let x = self.axis(length, count)?;
+        let y = self.axis_record(count)?;
+        let z = self.axis_record(count)?;
+        if self.has_fourth_dimension {
+            self.record()?;
+        }
+        let coordinates = match (&self.free_atoms, self.previous.take()) {
+            (Some(free), Some(mut previous)) => {
+                for (index, &atom) in free.iter().enumerate() {
+                    previous[atom] = [x[index], y[index], z[index]];
+                }
+                previous
+            }
+            _ => (0..count).map(|i| [x[i], y[i], z[i]]).collect(),
+        };
+        if self.free_atoms.is_some() {
+            self.previous = Some(coordinates.clone());
+        }
+
+        let step = self.first_step + self.index * self.step_interval;
+        #[allow(clippy::cast_precision_loss)]
+        let time = self.time_step.map(|time_step| step as f64 * time_step);
+        let frame = Frame::read(self.index, step, time, coordinates, unit_cell);
+        self.index += 1;
+        Ok(Some(frame))
+    }
+
+    /// Read the coordinates along a single axis, with the record length already read
+    fn axis(&mut self, length: usize, count: usize) -> Result<Vec<f64>, PDBError> {
+        if length != 4 * count {
+            return Err(self.error(format!(
+                "Frame {} should have {count} coordinates per axis.",
+                self.index
+            )));
+        }
+        let record = self.record_data(length)?;
+        Ok(record
+            .chunks_exact(4)
+            .map(|bytes| f64::from(self.float(bytes)))
+            .collect())
+    }
+
+    /// Read the coordinates along a single axis
+    fn axis_record(&mut self, count: usize) -> Result<Vec<f64>, PDBError> {
+        let mut marker = [0; 4];
+        self.read(&mut marker)?;
+        let length = self.marker(marker)?;
+        self.axis(length, count)
+    }
+
+    /// Read a full record
+    fn record(&mut self) -> Result<Vec<u8>, PDBError> {
+        let mut marker = [0; 4];
+        self.read(&mut marker)?;
+        let length = self.marker(marker)?;
+        self.record_data(length)
+    }
+
+    /// Read the data and the closing marker of a record with the given length
+    fn record_data(&mut self, length: usize) -> Result<Vec<u8>, PDBError> {
+        let mut data = Vec::new();
+        (&mut self.input)
+            .take(length as u64)
+            .read_to_end(&mut data)
+            .map_err(|e| self.io(&e))?;
+        if data.len() != length {
+            return Err(self.error("The file ended in the middle of a record."));
+        }
+        let mut marker = [0; 4];
+        self.read(&mut marker)?;
+        if self.marker(marker)? != length {
+            return Err(self.error("The markers around a record do not match."));
+        }
+        Ok(data)
+    }
+
+    /// Fill the buffer, the input is not allowed to end
+    fn read(&mut self, buffer: &mut [u8]) -> Result<(), PDBError> {
+        match read_or_end(&mut self.input, buffer) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(self.error("The file ended in the middle of a frame.")),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Err(self.error("The file ended in the middle of a frame."))
+            }
+            Err(e) => Err(self.io(&e)),
+        }
+    }
+
+    /// Interpret a record marker
+    fn marker(&self, bytes: [u8; 4]) -> Result<usize, PDBError> {
+        self.count(self.int(&bytes))
+    }
+
+    /// Interpret a number as a count, which should not be negative
+    fn count(&self, number: i32) -> Result<usize, PDBError> {
+        usize::try_from(number).map_err(|_| self.error(format!("Invalid count: {number}.")))
+    }
+
+    /// Interpret the first four bytes as an integer
+    fn int(&self, bytes: &[u8]) -> i32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            i32::from_be_bytes(bytes)
+        } else {
+            i32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Interpret the first four bytes as a single precision float
+    fn float(&self, bytes: &[u8]) -> f32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            f32::from_be_bytes(bytes)
+        } else {
+            f32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Interpret the first eight bytes as a double precision float
+    fn double(&self, bytes: &[u8]) -> f64 {
+        let mut array = [0; 8];
+        array.copy_from_slice(&bytes[..8]);
+        if self.big_endian {
+            f64::from_be_bytes(array)
+        } else {
+            f64::from_le_bytes(array)
+        }
+    }
+
+    /// Create an error for an invalid file
+    fn error(&self, message: impl ToString) -> PDBError {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid DCD file",
+            message,
+            self.context.clone(),
+        )
+    }
+
+    /// Create an error for a failed read
+    fn io(&self, error: &std::io::Error) -> PDBError {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Buffer could not be read",
+            format!("The DCD file could not be read: {error}."),
+            self.context.clone(),
+        )
+    }
+}
+
+/// Create the unit cell from the values `A, gamma, B, beta, alpha, C` in a DCD file. If all angles
+/// are within -1 and 1 they are the cosines of the angles. Returns None if all lengths are zero.
+fn unit_cell(values: &[f64]) -> Option<UnitCell> {
+    let (a, b, c) = (values[0], values[2], values[5]);
+    if a == 0.0 && b == 0.0 && c == 0.0 {
+        return None;
+    }
+    let mut angles = [values[4], values[3], values[1]];
+    if angles.iter().all(|angle| (-1.0..=1.0).contains(angle)) {
+        for angle in &mut angles {
+            *angle = angle.acos().to_degrees();
+        }
+    }
+    Some(UnitCell::new(a, b, c, angles[0], angles[1], angles[2]))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// Write a record with the given byte order
+    fn record(out: &mut Vec<u8>, data: &[u8], big_endian: bool) {
+        let length = if big_endian {
+            (data.len() as i32).to_be_bytes()
+        } else {
+            (data.len() as i32).to_le_bytes()
+        };
+        out.extend(length);
+        out.extend(data);
+        out.extend(length);
+    }
+
+    /// Write a small X-PLOR style big endian file with fixed atoms, the first atom is fixed
+    fn fixed_atoms() -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut header = b"CORD".to_vec();
+        for index in 0..20 {
+            let value: i32 = match index {
+                0 => 2,
+                2 => 5,
+                8 => 1,
+                _ => 0,
+            };
+            if index == 9 {
+                header.extend(2.0_f64.to_be_bytes());
+            } else if index != 10 {
+                header.extend(value.to_be_bytes());
+            }
+        }
+        record(&mut out, &header, true);
+        let mut title = 1_i32.to_be_bytes().to_vec();
+        title.extend([b' '; 80]);
+        record(&mut out, &title, true);
+        record(&mut out, &3_i32.to_be_bytes(), true);
+        let free: Vec<u8> = [2_i32, 3].iter().flat_map(|i| i.to_be_bytes()).collect();
+        record(&mut out, &free, true);
+        let floats =
+            |values: &[f32]| -> Vec<u8> { values.iter().flat_map(|v| v.to_be_bytes()).collect() };
+        for axis in [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]] {
+            record(&mut out, &floats(&axis), true);
+        }
+        for axis in [[-2.0, -3.0], [-5.0, -6.0], [-8.0, -9.0]] {
+            record(&mut out, &floats(&axis), true);
+        }
+        out
+    }
+
+    #[test]
+    fn fixed_atoms_big_endian() {
+        let bytes = fixed_atoms();
+        let mut reader = DcdReader::new(bytes.as_slice(), Context::None).unwrap();
+        let first = reader.read_frame().unwrap().unwrap();
+        assert_eq!(
+            first.coordinates(),
+            &[[1.0, 4.0, 7.0], [2.0, 5.0, 8.0], [3.0, 6.0, 9.0]]
+        );
+        assert_eq!(first.unit_cell(), None);
+        let second = reader.read_frame().unwrap().unwrap();
+        assert_eq!(
+            second.coordinates(),
+            &[[1.0, 4.0, 7.0], [-2.0, -5.0, -8.0], [-3.0, -6.0, -9.0]]
+        );
+        assert_eq!(second.step(), 5);
+        assert!((second.time().unwrap() - 5.0 * 2.0 * AKMA_PS).abs() < 1e-9);
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = fixed_atoms();
+        let mut reader = DcdReader::new(&bytes[..bytes.len() - 10], Context::None).unwrap();
+        assert!(reader.read_frame().unwrap().is_some());
+        let error = reader.read_frame().unwrap_err();
+        assert_eq!(error.short_description(), "Invalid DCD file");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(DcdReader::new(&b""[..], Context::None).is_err());
+        assert!(DcdReader::new(&b"\x54\0\0\0XXXX"[..], Context::None).is_err());
+        assert!(DcdReader::new(&[0_u8; 100][..], Context::None).is_err());
+    }
+
+    #[test]
+    fn unit_cell_angles() {
+        let cell = unit_cell(&[10.0, 0.5, 20.0, 0.0, 0.0, 30.0]).unwrap();
+        assert!((cell.gamma() - 60.0).abs() < 1e-9);
+        assert!((cell.beta() - 90.0).abs() < 1e-9);
+        let cell = unit_cell(&[10.0, 120.0, 20.0, 90.0, 90.0, 30.0]).unwrap();
+        assert!((cell.gamma() - 120.0).abs() < 1e-9);
+        assert_eq!(cell.c(), 30.0);
+        assert_eq!(unit_cell(&[0.0; 6]), None);
+    }
+}
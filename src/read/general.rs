@@ -57,7 +57,6 @@ mod tests {
     fn open_invalid() {
         assert!(open("file.png").is_err());
         assert!(open("file.mmcif").is_err());
-        assert!(open("file.pdbml").is_err());
         assert!(open("file.pd").is_err());
     }
 
@@ -67,5 +66,7 @@ mod tests {
         assert_eq!(pdb[0].short_description(), "Could not open file");
         let cif = open("file.cif").expect_err("This file should not exist.");
         assert_eq!(cif[0].short_description(), "Could not open file");
+        let pdbml = open("file.pdbml").expect_err("This file should not exist.");
+        assert_eq!(pdbml[0].short_description(), "Could not open file");
     }
 }
@@ -0,0 +1,362 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::error::*;
+use crate::reference_tables;
+use crate::structs::*;
+use crate::validate::*;
+use crate::ReadOptions;
+
+use super::general::ReadResult;
+use super::pqr::guess_element;
+
+/// Parse the given GROMACS GRO file into a PDB struct with [`ReadOptions`].
+/// Every frame in the file is read as a separate [`Model`].
+pub(crate) fn open_gro_with_options(
+    filename: impl AsRef<str>,
+    options: &ReadOptions,
+) -> ReadResult {
+    let filename = filename.as_ref();
+    let file = if let Ok(f) = File::open(filename) {
+        f
+    } else {
+        return Err(vec![PDBError::new(ErrorLevel::BreakingError, "Could not open file", "Could not open the specified file, make sure the path is correct, you have permission, and that it is not open in another program.", Context::show(filename))]);
+    };
+    open_gro_raw_with_options(BufReader::new(file), Context::show(filename), options)
+}
+
+/// Parse the input stream in the GROMACS GRO format into a PDB struct with [`ReadOptions`].
+///
+/// Each frame consists of a title line, the number of atoms, one fixed width line per atom,
+/// and the box vectors. The coordinates (nm) and velocities (nm/ps) are converted to Å and Å/ps.
+/// The box vectors of the first frame are stored as the [`UnitCell`]. As GRO files do not
+/// contain chains all atoms are placed in chain `A`, atoms in residues that are not amino acids
+/// are marked as hetero atoms, and the element is derived from the atom name.
+pub(crate) fn open_gro_raw_with_options<T>(
+    input: BufReader<T>,
+    context: Context,
+    options: &ReadOptions,
+) -> ReadResult
+where
+    T: std::io::Read,
+{
+    let mut errors = Vec::new();
+    let mut pdb = PDB::new();
+    let mut lines = input.lines().enumerate();
+    let mut next_line = || -> Result<Option<(usize, String)>, PDBError> {
+        match lines.next() {
+            Some((index, Ok(line))) => Ok(Some((index + 1, line))),
+            Some((index, Err(_))) => Err(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could read line",
+                format!(
+                    "Could not read line {} while parsing the input file.",
+                    index + 1
+                ),
+                context.clone(),
+            )),
+            None => Ok(None),
+        }
+    };
+    let end_of_file = |linenumber: usize, expected: &str| {
+        vec![PDBError::new(
+            ErrorLevel::BreakingError,
+            "Unexpected end of file",
+            format!("The file ended after line {linenumber} while {expected} was expected."),
+            context.clone(),
+        )]
+    };
+
+    // Every frame starts with a title line, a blank line at the end of the file is ignored
+    while let Some((mut linenumber, title)) = next_line().map_err(|e| vec![e])? {
+        let (n, count_line) = match next_line().map_err(|e| vec![e])? {
+            Some(line) => line,
+            None if title.trim().is_empty() => break,
+            None => return Err(end_of_file(linenumber, "the number of atoms")),
+        };
+        linenumber = n;
+        let count = if let Ok(count) = count_line.trim().parse::<usize>() {
+            count
+        } else {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Invalid atom count",
+                "The second line of a frame should contain the number of atoms.",
+                Context::full_line(linenumber, &count_line),
+            ));
+            return Err(errors);
+        };
+
+        let mut model = Model::new(pdb.model_count() + 1);
+        let mut last_atom_serial_number = 0;
+        let mut atom_serial_addition = 0;
+        let mut last_residue_serial_number = 0;
+        let mut residue_serial_addition = 0;
+        for _ in 0..count {
+            let (n, line) = match next_line().map_err(|e| vec![e])? {
+                Some(line) => line,
+                None => return Err(end_of_file(linenumber, "an atom line")),
+            };
+            linenumber = n;
+            match parse_atom_line(&line) {
+                Ok((residue_serial_number, residue_name, atom)) => {
+                    // Serial numbers are written modulo 100000, so they wrap around
+                    if atom.serial_number() == 0 && last_atom_serial_number == 99_999 {
+                        atom_serial_addition += 100_000;
+                    }
+                    if residue_serial_number == 0 && last_residue_serial_number == 99_999 {
+                        residue_serial_addition += 100_000;
+                    }
+                    last_atom_serial_number = atom.serial_number();
+                    last_residue_serial_number = residue_serial_number;
+                    if options.discard_hydrogens && atom.element() == Some(&Element::H) {
+                        continue;
+                    }
+                    let mut atom = atom;
+                    atom.set_serial_number(atom.serial_number() + atom_serial_addition);
+                    model.add_atom(
+                        atom,
+                        "A",
+                        (residue_serial_number + residue_serial_addition, None),
+                        (residue_name, None),
+                    );
+                }
+                Err(message) => errors.push(PDBError::new(
+                    ErrorLevel::InvalidatingError,
+                    "Invalid GRO atom",
+                    message,
+                    Context::full_line(linenumber, &line),
+                )),
+            }
+        }
+
+        let (n, box_line) = match next_line().map_err(|e| vec![e])? {
+            Some(line) => line,
+            None => return Err(end_of_file(linenumber, "the box vectors")),
+        };
+        linenumber = n;
+        match parse_box_line(&box_line) {
+            Ok(cell) => {
+                if pdb.unit_cell.is_none() {
+                    pdb.unit_cell = cell;
+                }
+            }
+            Err(message) => errors.push(PDBError::new(
+                ErrorLevel::InvalidatingError,
+                "Invalid GRO box vectors",
+                message,
+                Context::full_line(linenumber, &box_line),
+            )),
+        }
+
+        pdb.add_model(model);
+        if options.only_first_model {
+            break;
+        }
+    }
+
+    errors.extend(validate(&pdb));
+
+    if errors.iter().any(|e| e.fails(options.level)) {
+        Err(errors)
+    } else {
+        Ok((pdb, errors))
+    }
+}
+
+/// Parse a fixed width atom line of a GRO file into the residue serial number, residue name, and atom.
+/// The width of the coordinate fields is derived from the distance between the decimal points,
+/// as GROMACS allows for a variable precision.
+fn parse_atom_line(line: &str) -> Result<(isize, String, Atom), String> {
+    let field = |start: usize, end: usize| {
+        line.get(start..end.min(line.len())).ok_or_else(|| {
+            format!(
+                "The line is too short, it should contain a field at columns {}-{}.",
+                start + 1,
+                end
+            )
+        })
+    };
+    let number = |start: usize, width: usize| {
+        let text = field(start, start + width)?.trim();
+        text.parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("The value \"{text}\" is not a valid number."))
+    };
+
+    let residue_serial_number = field(0, 5)?
+        .trim()
+        .parse::<isize>()
+        .map_err(|_| "The residue number is not a valid number.".to_string())?;
+    let residue_name = field(5, 10)?.trim().to_string();
+    let name = field(10, 15)?.trim();
+    let serial_number = field(15, 20)?
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| "The atom number is not a valid number.".to_string())?;
+
+    let coordinates = field(20, line.len())?;
+    let width = coordinates.find('.').map_or(8, |first| {
+        coordinates[first + 1..]
+            .find('.')
+            .map_or(8, |second| second + 1)
+    });
+    let x = number(20, width)?;
+    let y = number(20 + width, width)?;
+    let z = number(20 + 2 * width, width)?;
+
+    let hetero = !reference_tables::is_amino_acid(&residue_name);
+    let mut atom = Atom::new(
+        hetero,
+        serial_number,
+        name,
+        x * 10.0,
+        y * 10.0,
+        z * 10.0,
+        1.0,
+        0.0,
+        guess_element(name, &residue_name, hetero),
+        0,
+    )
+    .ok_or_else(|| format!("The atom name \"{name}\" contains invalid characters."))?;
+    if line.trim_end().len() >= 20 + 6 * width {
+        atom.set_velocity((
+            number(20 + 3 * width, width)? * 10.0,
+            number(20 + 4 * width, width)? * 10.0,
+            number(20 + 5 * width, width)? * 10.0,
+        ))?;
+    }
+    Ok((residue_serial_number, residue_name, atom))
+}
+
+/// Parse the box vectors line of a GRO file, which contains either three numbers (a rectangular
+/// box) or nine numbers (a triclinic box) in nm. A box of only zeros gives no unit cell.
+fn parse_box_line(line: &str) -> Result<Option<UnitCell>, String> {
+    let values = line
+        .split_whitespace()
+        .map(|text| {
+            text.parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(|v| v * 10.0)
+                .ok_or_else(|| format!("The value \"{text}\" is not a valid number."))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let v = match values.len() {
+        3 => [
+            values[0], values[1], values[2], 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+        ],
+        9 => [
+            values[0], values[1], values[2], values[3], values[4], values[5], values[6], values[7],
+            values[8],
+        ],
+        n => {
+            return Err(format!(
+                "The box vectors should be given as 3 or 9 numbers but {n} numbers were found."
+            ))
+        }
+    };
+    if v.iter().all(|x| *x == 0.0) {
+        Ok(None)
+    } else {
+        // The order is v1(x) v2(y) v3(z) v1(y) v1(z) v2(x) v2(z) v3(x) v3(y)
+        Ok(Some(UnitCell::from_vectors(
+            [v[0], v[3], v[4]],
+            [v[5], v[1], v[6]],
+            [v[7], v[8], v[2]],
+        )))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::StrictnessLevel;
+
+    const GRO: &str = "A water and an ion t= 0.0
+    4
+    1SOL     OW    1   0.126   1.624   1.679  0.1227 -0.0580  0.0434
+    1SOL    HW1    2   0.190   1.661   1.747  0.8085  0.3191 -0.7791
+    1SOL    HW2    3   0.177   1.568   1.613 -0.9045 -2.6469  1.3180
+    2NA      NA    4   1.000   1.000   1.000  0.0000  0.0000  0.0000
+   1.82060   1.82060   1.82060
+A water and an ion t= 1.0
+    4
+    1SOL     OW    1   0.136   1.624   1.679
+    1SOL    HW1    2   0.200   1.661   1.747
+    1SOL    HW2    3   0.187   1.568   1.613
+    2NA      NA    4   1.010   1.000   1.000
+   2.00000   2.00000   2.00000   0.00000   0.00000   0.00000   0.00000   1.00000   0.00000
+";
+
+    fn open(text: &str, options: &ReadOptions) -> ReadResult {
+        open_gro_raw_with_options(BufReader::new(text.as_bytes()), Context::None, options)
+    }
+
+    #[test]
+    fn read_gro() {
+        let (pdb, errors) =
+            open(GRO, ReadOptions::new().set_level(StrictnessLevel::Loose)).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(pdb.model_count(), 2);
+        let model = pdb.model(0).unwrap();
+        assert_eq!(model.atom_count(), 4);
+        assert_eq!(model.residue_count(), 2);
+        let oxygen = model.atom(0).unwrap();
+        assert_eq!(oxygen.name(), "OW");
+        assert_eq!(oxygen.element(), Some(&Element::O));
+        assert!(oxygen.hetero());
+        assert!((oxygen.x() - 1.26).abs() < 1e-9);
+        assert!((oxygen.velocity().unwrap().0 - 1.227).abs() < 1e-9);
+        assert_eq!(model.atom(1).unwrap().element(), Some(&Element::H));
+        assert_eq!(model.atom(3).unwrap().element(), Some(&Element::Na));
+
+        let cell = pdb.unit_cell.as_ref().unwrap();
+        assert!((cell.a() - 18.206).abs() < 1e-9);
+        assert!((cell.gamma() - 90.0).abs() < 1e-9);
+
+        let second = pdb.model(1).unwrap();
+        assert_eq!(second.atom_count(), 4);
+        assert!((second.atom(0).unwrap().x() - 1.36).abs() < 1e-9);
+        assert_eq!(second.atom(0).unwrap().velocity(), None);
+    }
+
+    #[test]
+    fn options() {
+        let (pdb, _) = open(GRO, ReadOptions::new().set_only_first_model(true)).unwrap();
+        assert_eq!(pdb.model_count(), 1);
+        let (pdb, _) = open(GRO, ReadOptions::new().set_discard_hydrogens(true)).unwrap();
+        assert_eq!(pdb.model(0).unwrap().atom_count(), 2);
+    }
+
+    #[test]
+    fn triclinic_box() {
+        let cell = parse_box_line("2.0 2.0 2.0 0.0 0.0 0.0 0.0 0.0 1.0")
+            .unwrap()
+            .unwrap();
+        assert!((cell.alpha() - 63.434_948_822_922).abs() < 1e-9);
+        assert!((cell.beta() - 90.0).abs() < 1e-9);
+        assert!(parse_box_line("0.0 0.0 0.0").unwrap().is_none());
+        assert!(parse_box_line("1.0 1.0").is_err());
+    }
+
+    #[test]
+    fn variable_precision() {
+        let (_, _, atom) =
+            parse_atom_line("    1ALA      N    1   0.1000   0.2000   0.3000").unwrap();
+        assert!((atom.z() - 3.0).abs() < 1e-9);
+        assert!(!atom.hetero());
+    }
+
+    #[test]
+    fn truncated() {
+        let errors = open(
+            "Title\n    2\n    1SOL     OW    1   0.126   1.624   1.679\n",
+            &ReadOptions::new(),
+        )
+        .unwrap_err();
+        assert_eq!(errors[0].short_description(), "Unexpected end of file");
+    }
+}
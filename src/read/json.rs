@@ -0,0 +1,245 @@
+use crate::error::*;
+
+/// A decoded JSON value
+#[derive(Debug, PartialEq)]
+pub(crate) enum Json {
+    /// Null
+    Null,
+    /// A boolean
+    Bool(bool),
+    /// A number
+    Number(f64),
+    /// A string
+    Str(String),
+    /// An array
+    Array(Vec<Json>),
+    /// An object, in the original order
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Decode the given text, which should contain a single JSON value
+    pub(crate) fn parse(text: &str) -> Result<Self, PDBError> {
+        let mut input = text.as_bytes();
+        let value = decode_json(&mut input, 0)?;
+        skip_whitespace(&mut input);
+        if input.is_empty() {
+            Ok(value)
+        } else {
+            Err(invalid("There is trailing data after the JSON value."))
+        }
+    }
+
+    /// Get the value for the given key, if this is an object
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        if let Json::Object(object) = self {
+            object.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+        } else {
+            None
+        }
+    }
+
+    /// Get the number, if this is a number
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        if let Json::Number(n) = self {
+            Some(*n)
+        } else {
+            None
+        }
+    }
+
+    /// Get the items, if this is an array
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        if let Json::Array(a) = self {
+            Some(a)
+        } else {
+            None
+        }
+    }
+}
+
+/// The maximal nesting depth of JSON values, to prevent stack overflows on invalid files
+const MAX_DEPTH: usize = 64;
+
+/// Decode a single JSON value, advancing the input past it
+fn decode_json(input: &mut &[u8], depth: usize) -> Result<Json, PDBError> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("The JSON data is nested too deeply."));
+    }
+    skip_whitespace(input);
+    match input.first() {
+        Some(b'{') => {
+            *input = &input[1..];
+            let mut object = Vec::new();
+            if !take_if(input, b'}') {
+                loop {
+                    skip_whitespace(input);
+                    let key = decode_str(input)?;
+                    expect(input, b':')?;
+                    object.push((key, decode_json(input, depth + 1)?));
+                    if !take_if(input, b',') {
+                        expect(input, b'}')?;
+                        break;
+                    }
+                }
+            }
+            Ok(Json::Object(object))
+        }
+        Some(b'[') => {
+            *input = &input[1..];
+            let mut array = Vec::new();
+            if !take_if(input, b']') {
+                loop {
+                    array.push(decode_json(input, depth + 1)?);
+                    if !take_if(input, b',') {
+                        expect(input, b']')?;
+                        break;
+                    }
+                }
+            }
+            Ok(Json::Array(array))
+        }
+        Some(b'"') => decode_str(input).map(Json::Str),
+        Some(b't') => keyword(input, "true", Json::Bool(true)),
+        Some(b'f') => keyword(input, "false", Json::Bool(false)),
+        Some(b'n') => keyword(input, "null", Json::Null),
+        Some(b'-' | b'0'..=b'9') => {
+            let length = input
+                .iter()
+                .position(|c| !matches!(c, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+                .unwrap_or(input.len());
+            let (number, rest) = input.split_at(length);
+            *input = rest;
+            std::str::from_utf8(number)
+                .ok()
+                .and_then(|n| n.parse().ok())
+                .map(Json::Number)
+                .ok_or_else(|| invalid("A number is not valid."))
+        }
+        Some(_) => Err(invalid("An unexpected character was found.")),
+        None => Err(invalid("The file ended unexpectedly.")),
+    }
+}
+
+/// Skip any whitespace at the start of the input
+fn skip_whitespace(input: &mut &[u8]) {
+    let length = input
+        .iter()
+        .position(|c| !c.is_ascii_whitespace())
+        .unwrap_or(input.len());
+    *input = &input[length..];
+}
+
+/// Take the given character (after whitespace) from the input if it is the next one
+fn take_if(input: &mut &[u8], character: u8) -> bool {
+    skip_whitespace(input);
+    if input.first() == Some(&character) {
+        *input = &input[1..];
+        true
+    } else {
+        false
+    }
+}
+
+/// Take the given character (after whitespace) from the input, fails if it is not the next one
+fn expect(input: &mut &[u8], character: u8) -> Result<(), PDBError> {
+    if take_if(input, character) {
+        Ok(())
+    } else {
+        Err(invalid(&format!(
+            "Expected '{}' but found something else.",
+            char::from(character)
+        )))
+    }
+}
+
+/// Take the given keyword from the input and return the given value
+fn keyword(input: &mut &[u8], word: &str, value: Json) -> Result<Json, PDBError> {
+    if input.starts_with(word.as_bytes()) {
+        *input = &input[word.len()..];
+        Ok(value)
+    } else {
+        Err(invalid("An unknown keyword was found."))
+    }
+}
+
+/// Decode a string, including the quotes
+fn decode_str(input: &mut &[u8]) -> Result<String, PDBError> {
+    if input.first() != Some(&b'"') {
+        return Err(invalid("Expected a string."));
+    }
+    let mut bytes = Vec::new();
+    let mut position = 1;
+    loop {
+        match input.get(position) {
+            Some(b'"') => break,
+            Some(b'\\') => {
+                let escaped = input.get(position + 1).copied();
+                position += 2;
+                match escaped {
+                    Some(b'n') => bytes.push(b'\n'),
+                    Some(b't') => bytes.push(b'\t'),
+                    Some(b'r') => bytes.push(b'\r'),
+                    Some(b'b') => bytes.push(0x08),
+                    Some(b'f') => bytes.push(0x0c),
+                    Some(b'u') => {
+                        let character = input
+                            .get(position..position + 4)
+                            .and_then(|hex| std::str::from_utf8(hex).ok())
+                            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                            .map(|code| char::from_u32(code).unwrap_or('\u{fffd}'))
+                            .ok_or_else(|| invalid("A unicode escape is not valid."))?;
+                        let mut buffer = [0; 4];
+                        bytes.extend(character.encode_utf8(&mut buffer).as_bytes());
+                        position += 4;
+                    }
+                    Some(c) => bytes.push(c),
+                    None => return Err(invalid("The file ended unexpectedly.")),
+                }
+            }
+            Some(c) => {
+                bytes.push(*c);
+                position += 1;
+            }
+            None => return Err(invalid("The file ended unexpectedly.")),
+        }
+    }
+    *input = &input[position + 1..];
+    String::from_utf8(bytes).map_err(|_| invalid("A string is not valid UTF-8."))
+}
+
+/// Create an error for invalid JSON data
+fn invalid(message: &str) -> PDBError {
+    PDBError::new(
+        ErrorLevel::BreakingError,
+        "Invalid JSON",
+        message,
+        Context::none(),
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let value =
+            Json::parse(r#" {"a": [1, -2.5e1, true, null], "b!": "x\"y", "c": {}} "#).unwrap();
+        assert_eq!(
+            value.get("a").and_then(Json::as_array).unwrap(),
+            [
+                Json::Number(1.0),
+                Json::Number(-25.0),
+                Json::Bool(true),
+                Json::Null
+            ]
+        );
+        assert_eq!(value.get("b!"), Some(&Json::Str("x\"y".to_string())));
+        assert_eq!(value.get("c"), Some(&Json::Object(Vec::new())));
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("[1] 2").is_err());
+        assert!(Json::parse("{\"a\" 1}").is_err());
+    }
+}
@@ -0,0 +1,493 @@
+use super::lexer::parse_numeric;
+use super::lexitem::*;
+use crate::error::*;
+use crate::read::msgpack::MsgPack;
+
+/// Decode a BinaryCIF file into the CIF intermediate structure, so it can be read in the same
+/// way as the equivalent mmCIF file. Only the first data block is read.
+///
+/// A BinaryCIF file is a MessagePack encoded map with a list of data blocks, containing a list
+/// of categories, containing a list of columns. Every column is a binary array with the list of
+/// encodings that were applied to it, these are undone in reverse order. The supported
+/// encodings are `ByteArray`, `FixedPoint`, `IntervalQuantization`, `RunLength`, `Delta`,
+/// `IntegerPacking`, and `StringArray`. The optional mask of a column marks the values that are
+/// inapplicable (`.`) or unknown (`?`).
+pub fn lex_bcif(bytes: &[u8]) -> Result<DataBlock, PDBError> {
+    let file = MsgPack::parse(bytes)?;
+    let block = file
+        .get("dataBlocks")
+        .and_then(MsgPack::as_array)
+        .and_then(<[MsgPack<'_>]>::first)
+        .ok_or_else(|| invalid("The file does not contain any data blocks."))?;
+    let name = block
+        .get("header")
+        .and_then(MsgPack::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut items = Vec::new();
+    let categories = block
+        .get("categories")
+        .and_then(MsgPack::as_array)
+        .ok_or_else(|| invalid("A data block does not contain categories."))?;
+    for category in categories {
+        let category_name = category
+            .get("name")
+            .and_then(MsgPack::as_str)
+            .ok_or_else(|| invalid("A category does not have a name."))?;
+        let category_name = category_name.strip_prefix('_').unwrap_or(category_name);
+        let row_count = category
+            .get("rowCount")
+            .and_then(MsgPack::as_int)
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(|| invalid("A category does not have a row count."))?;
+        let mut header = Vec::new();
+        let mut columns = Vec::new();
+        for column in category
+            .get("columns")
+            .and_then(MsgPack::as_array)
+            .unwrap_or_default()
+        {
+            let column_name = column
+                .get("name")
+                .and_then(MsgPack::as_str)
+                .ok_or_else(|| invalid("A column does not have a name."))?;
+            let values = decode_column(column, row_count)
+                .map_err(|e| invalid(&format!("{e} In column \"{column_name}\".")))?;
+            header.push(format!("{category_name}.{column_name}"));
+            columns.push(values);
+        }
+        // The mmCIF parser only reads atoms from loops, so the atoms always are a loop
+        if row_count == 1 && category_name != "atom_site" {
+            for (name, mut values) in header.into_iter().zip(columns) {
+                items.push(Item::DataItem(DataItem::Single(Single {
+                    name,
+                    content: values.remove(0),
+                })));
+            }
+        } else if row_count > 0 {
+            let mut data: Vec<Vec<Value>> = (0..row_count)
+                .map(|_| Vec::with_capacity(columns.len()))
+                .collect();
+            for values in columns {
+                for (row, value) in data.iter_mut().zip(values) {
+                    row.push(value);
+                }
+            }
+            items.push(Item::DataItem(DataItem::Loop(Loop { header, data })));
+        }
+    }
+    Ok(DataBlock { name, items })
+}
+
+/// Create an error for an invalid BinaryCIF file
+fn invalid(message: &str) -> PDBError {
+    PDBError::new(
+        ErrorLevel::BreakingError,
+        "Invalid BinaryCIF",
+        message,
+        Context::none(),
+    )
+}
+
+/// Decode a single column into its values, applying the mask if present
+fn decode_column(column: &MsgPack<'_>, row_count: usize) -> Result<Vec<Value>, String> {
+    let data = decode_data(
+        column.get("data").ok_or("The column does not have data.")?,
+        row_count,
+    )?;
+    let mut values: Vec<Value> = match data {
+        Data::Int(values) => values
+            .into_iter()
+            .map(|value| {
+                #[allow(clippy::cast_precision_loss)]
+                Value::Numeric(value as f64)
+            })
+            .collect(),
+        Data::Float(values) => values.into_iter().map(Value::Numeric).collect(),
+        Data::Str(values) => values
+            .into_iter()
+            .map(|value| {
+                if value.is_empty() || value.contains(char::is_whitespace) {
+                    Value::Text(value)
+                } else {
+                    parse_numeric(&value).unwrap_or(Value::Text(value))
+                }
+            })
+            .collect(),
+        Data::Bytes(_) => return Err("The data is not decoded into values.".to_string()),
+    };
+    if values.len() != row_count {
+        return Err(format!(
+            "The column has {} values but the category has {row_count} rows.",
+            values.len()
+        ));
+    }
+    if let Some(mask) = column.get("mask").filter(|mask| **mask != MsgPack::Nil) {
+        if let Data::Int(mask) = decode_data(mask, row_count)? {
+            for (value, mask) in values.iter_mut().zip(mask) {
+                match mask {
+                    1 => *value = Value::Inapplicable,
+                    2 => *value = Value::Unknown,
+                    _ => (),
+                }
+            }
+        } else {
+            return Err("The mask is not an integer array.".to_string());
+        }
+    }
+    Ok(values)
+}
+
+/// The data of a column in the process of being decoded
+#[derive(Debug, PartialEq)]
+enum Data<'a> {
+    /// The raw bytes
+    Bytes(&'a [u8]),
+    /// An integer array
+    Int(Vec<i64>),
+    /// A floating point array
+    Float(Vec<f64>),
+    /// A string array
+    Str(Vec<String>),
+}
+
+/// Decode encoded data, a map with the binary data and the list of encodings, into at most the
+/// given number of values
+fn decode_data<'a>(encoded: &MsgPack<'a>, limit: usize) -> Result<Data<'a>, String> {
+    let data = encoded
+        .get("data")
+        .and_then(MsgPack::as_bin)
+        .ok_or("The encoded data is not binary.")?;
+    let encodings = encoded
+        .get("encoding")
+        .and_then(MsgPack::as_array)
+        .ok_or("The encoded data does not have encodings.")?;
+    decode(Data::Bytes(data), encodings, limit)
+}
+
+/// Undo the given encodings, in reverse order. The run length and integer packing encodings fail
+/// if they would give more than the given number of values.
+fn decode<'a>(
+    mut data: Data<'a>,
+    encodings: &[MsgPack<'a>],
+    limit: usize,
+) -> Result<Data<'a>, String> {
+    for encoding in encodings.iter().rev() {
+        let kind = encoding
+            .get("kind")
+            .and_then(MsgPack::as_str)
+            .ok_or("An encoding does not have a kind.")?;
+        let int = |name: &str| {
+            encoding
+                .get(name)
+                .and_then(MsgPack::as_int)
+                .ok_or(format!("The {kind} encoding does not have a valid {name}."))
+        };
+        let float = |name: &str| {
+            encoding
+                .get(name)
+                .and_then(MsgPack::as_float)
+                .ok_or(format!("The {kind} encoding does not have a valid {name}."))
+        };
+        let size = || int("srcSize").map(|size| usize::try_from(size).unwrap_or(0).min(limit));
+        let too_long = || format!("The {kind} encoding gives more than {limit} values.");
+        let overflow = || format!("The {kind} encoding could not be applied.");
+        data = match (kind, data) {
+            ("ByteArray", Data::Bytes(bytes)) => byte_array(bytes, int("type")?)?,
+            ("FixedPoint", Data::Int(values)) => {
+                let factor = float("factor")?;
+                #[allow(clippy::cast_precision_loss)]
+                Data::Float(values.into_iter().map(|v| v as f64 / factor).collect())
+            }
+            ("IntervalQuantization", Data::Int(values)) => {
+                let min = float("min")?;
+                let max = float("max")?;
+                #[allow(clippy::cast_precision_loss)]
+                let delta = (max - min) / (int("numSteps")? - 1).max(1) as f64;
+                #[allow(clippy::cast_precision_loss)]
+                Data::Float(values.into_iter().map(|v| min + delta * v as f64).collect())
+            }
+            ("RunLength", Data::Int(values)) => {
+                let mut output = Vec::with_capacity(size()?);
+                for pair in values.chunks(2) {
+                    if let [value, count] = pair {
+                        let count = usize::try_from(*count).unwrap_or(0);
+                        output
+                            .len()
+                            .checked_add(count)
+                            .filter(|total| *total <= limit)
+                            .ok_or_else(too_long)?;
+                        output.extend(std::iter::repeat(*value).take(count));
+                    } else {
+                        return Err("The RunLength encoded data has an odd length.".to_string());
+                    }
+                }
+                Data::Int(output)
+            }
+            ("Delta", Data::Int(mut values)) => {
+                let mut current = int("origin")?;
+                for value in &mut values {
+                    current = current.checked_add(*value).ok_or_else(overflow)?;
+                    *value = current;
+                }
+                Data::Int(values)
+            }
+            ("IntegerPacking", Data::Int(values)) => {
+                let unsigned = encoding
+                    .get("isUnsigned")
+                    .and_then(MsgPack::as_bool)
+                    .unwrap_or(false);
+                let (upper, lower) = match (int("byteCount")?, unsigned) {
+                    (1, true) => (0xFF, -1),
+                    (1, false) => (0x7F, -0x80),
+                    (2, true) => (0xFFFF, -1),
+                    (2, false) => (0x7FFF, -0x8000),
+                    _ => (i64::MAX, i64::MIN),
+                };
+                let mut output = Vec::with_capacity(size()?);
+                let mut current: i64 = 0;
+                for value in values {
+                    current = current.checked_add(value).ok_or_else(overflow)?;
+                    if value != upper && value != lower {
+                        if output.len() == limit {
+                            return Err(too_long());
+                        }
+                        output.push(current);
+                        current = 0;
+                    }
+                }
+                Data::Int(output)
+            }
+            ("StringArray", Data::Bytes(bytes)) => {
+                let array = |name: &str| {
+                    encoding
+                        .get(name)
+                        .and_then(MsgPack::as_array)
+                        .ok_or(format!("The StringArray encoding does not have {name}."))
+                };
+                let offsets = encoding
+                    .get("offsets")
+                    .and_then(MsgPack::as_bin)
+                    .ok_or("The StringArray encoding does not have offsets.")?;
+                // There is one more offset than there are unique strings
+                let offsets = match decode(
+                    Data::Bytes(offsets),
+                    array("offsetEncoding")?,
+                    limit.saturating_add(1),
+                )? {
+                    Data::Int(offsets) => offsets,
+                    _ => return Err("The string offsets are not integers.".to_string()),
+                };
+                let indices = match decode(Data::Bytes(bytes), array("dataEncoding")?, limit)? {
+                    Data::Int(indices) => indices,
+                    _ => return Err("The string indices are not integers.".to_string()),
+                };
+                let characters: Vec<char> = encoding
+                    .get("stringData")
+                    .and_then(MsgPack::as_str)
+                    .ok_or("The StringArray encoding does not have string data.")?
+                    .chars()
+                    .collect();
+                let strings = offsets
+                    .windows(2)
+                    .map(|window| {
+                        let start = usize::try_from(window[0]).ok()?;
+                        let end = usize::try_from(window[1]).ok()?;
+                        characters.get(start..end).map(|s| s.iter().collect())
+                    })
+                    .collect::<Option<Vec<String>>>()
+                    .ok_or("The string offsets are not valid.")?;
+                Data::Str(
+                    indices
+                        .into_iter()
+                        .map(|index| {
+                            usize::try_from(index)
+                                .map_or(Some(String::new()), |index| strings.get(index).cloned())
+                        })
+                        .collect::<Option<Vec<String>>>()
+                        .ok_or("A string index is out of range.")?,
+                )
+            }
+            (kind, _) => return Err(format!("The {kind} encoding could not be applied.")),
+        };
+    }
+    Ok(data)
+}
+
+/// Interpret the given little endian bytes as an array of the given type
+fn byte_array(bytes: &[u8], data_type: i64) -> Result<Data<'_>, String> {
+    /// Read the bytes into an array using the given conversion
+    fn convert<T, const N: usize>(bytes: &[u8], f: impl Fn([u8; N]) -> T) -> Vec<T> {
+        bytes
+            .chunks_exact(N)
+            .map(|chunk| {
+                let mut array = [0; N];
+                array.copy_from_slice(chunk);
+                f(array)
+            })
+            .collect()
+    }
+    let size = match data_type {
+        1 | 4 => 1,
+        2 | 5 => 2,
+        3 | 6 | 32 => 4,
+        33 => 8,
+        _ => return Err(format!("The ByteArray type {data_type} is not known.")),
+    };
+    if bytes.len() % size != 0 {
+        return Err("The length of the ByteArray does not fit its type.".to_string());
+    }
+    Ok(match data_type {
+        1 => Data::Int(convert(bytes, |b| i64::from(i8::from_le_bytes(b)))),
+        2 => Data::Int(convert(bytes, |b| i64::from(i16::from_le_bytes(b)))),
+        3 => Data::Int(convert(bytes, |b| i64::from(i32::from_le_bytes(b)))),
+        4 => Data::Int(convert(bytes, |b| i64::from(u8::from_le_bytes(b)))),
+        5 => Data::Int(convert(bytes, |b| i64::from(u16::from_le_bytes(b)))),
+        6 => Data::Int(convert(bytes, |b| i64::from(u32::from_le_bytes(b)))),
+        32 => Data::Float(convert(bytes, |b| f64::from(f32::from_le_bytes(b)))),
+        _ => Data::Float(convert(bytes, f64::from_le_bytes)),
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// Create an encoding with the given parameters
+    fn encoding<'a>(kind: &'a str, parameters: Vec<(&'a str, MsgPack<'a>)>) -> MsgPack<'a> {
+        let mut map = vec![(MsgPack::Str("kind"), MsgPack::Str(kind))];
+        map.extend(
+            parameters
+                .into_iter()
+                .map(|(key, value)| (MsgPack::Str(key), value)),
+        );
+        MsgPack::Map(map)
+    }
+
+    #[test]
+    fn integer_encodings() {
+        let encodings = [
+            encoding(
+                "Delta",
+                vec![("origin", MsgPack::Int(10)), ("srcType", MsgPack::Int(3))],
+            ),
+            encoding(
+                "RunLength",
+                vec![("srcType", MsgPack::Int(3)), ("srcSize", MsgPack::Int(4))],
+            ),
+            encoding(
+                "IntegerPacking",
+                vec![
+                    ("byteCount", MsgPack::Int(1)),
+                    ("isUnsigned", MsgPack::Bool(false)),
+                    ("srcSize", MsgPack::Int(4)),
+                ],
+            ),
+            encoding("ByteArray", vec![("type", MsgPack::Int(1))]),
+        ];
+        // 127 + 127 + 16, -128 - 16, and 5
+        let packed = [0x7f, 0x7f, 0x10, 0x80, 0xf0, 0x05];
+        assert_eq!(
+            decode(Data::Bytes(&packed), &encodings[2..], 3).unwrap(),
+            Data::Int(vec![270, -144, 5])
+        );
+        // The runs (0, 1) and (1, 3) give the differences [0, 1, 1, 1] from 10
+        assert_eq!(
+            decode(Data::Bytes(&[0, 1, 1, 3]), &encodings, 4).unwrap(),
+            Data::Int(vec![10, 11, 12, 13])
+        );
+        assert!(decode(Data::Bytes(&[0, 1, 1]), &encodings, 4).is_err());
+        // More values than rows, from the runs or the packing, and overflowing sums
+        assert!(decode(Data::Bytes(&[0, 1, 1, 0x7f]), &encodings[1..], 4).is_err());
+        assert!(decode(Data::Bytes(&packed), &encodings[2..], 2).is_err());
+        let packing = [
+            encoding("IntegerPacking", vec![("byteCount", MsgPack::Int(4))]),
+            encoding("Delta", vec![("origin", MsgPack::Int(i64::MAX))]),
+            encoding("ByteArray", vec![("type", MsgPack::Int(1))]),
+        ];
+        assert!(decode(Data::Bytes(&[1]), &packing[1..], 1).is_err());
+        assert!(decode(Data::Bytes(&[0, 0]), &packing, 2).is_err());
+    }
+
+    #[test]
+    fn float_and_string_encodings() {
+        let fixed = [
+            encoding(
+                "FixedPoint",
+                vec![
+                    ("factor", MsgPack::Float(100.0)),
+                    ("srcType", MsgPack::Int(33)),
+                ],
+            ),
+            encoding("ByteArray", vec![("type", MsgPack::Int(2))]),
+        ];
+        let bytes: Vec<u8> = [150_i16, -25]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert_eq!(
+            decode(Data::Bytes(&bytes), &fixed, 2).unwrap(),
+            Data::Float(vec![1.5, -0.25])
+        );
+
+        let offsets = [0, 2, 5];
+        let strings = encoding(
+            "StringArray",
+            vec![
+                (
+                    "dataEncoding",
+                    MsgPack::Array(vec![encoding("ByteArray", vec![("type", MsgPack::Int(1))])]),
+                ),
+                ("stringData", MsgPack::Str("CAGLY")),
+                (
+                    "offsetEncoding",
+                    MsgPack::Array(vec![encoding("ByteArray", vec![("type", MsgPack::Int(4))])]),
+                ),
+                ("offsets", MsgPack::Bin(&offsets)),
+            ],
+        );
+        assert_eq!(
+            decode(Data::Bytes(&[1, 0, 0xff]), &[strings], 3).unwrap(),
+            Data::Str(vec!["GLY".into(), "CA".into(), String::new()])
+        );
+    }
+
+    #[test]
+    fn mask() {
+        let byte_array =
+            || MsgPack::Array(vec![encoding("ByteArray", vec![("type", MsgPack::Int(4))])]);
+        let column = MsgPack::Map(vec![
+            (
+                MsgPack::Str("data"),
+                MsgPack::Map(vec![
+                    (MsgPack::Str("data"), MsgPack::Bin(&[7, 0, 0])),
+                    (MsgPack::Str("encoding"), byte_array()),
+                ]),
+            ),
+            (
+                MsgPack::Str("mask"),
+                MsgPack::Map(vec![
+                    (MsgPack::Str("data"), MsgPack::Bin(&[0, 1, 2])),
+                    (MsgPack::Str("encoding"), byte_array()),
+                ]),
+            ),
+        ]);
+        assert_eq!(
+            decode_column(&column, 3).unwrap(),
+            [Value::Numeric(7.0), Value::Inapplicable, Value::Unknown]
+        );
+        assert!(decode_column(&column, 2).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(lex_bcif(&[]).is_err());
+        assert!(lex_bcif(&[0x80]).is_err());
+        assert!(lex_bcif(&[
+            0x81, 0xaa, b'd', b'a', b't', b'a', b'B', b'l', b'o', b'c', b'k', b's', 0x90
+        ])
+        .is_err());
+    }
+}
@@ -11,6 +11,24 @@ pub fn lex_cif(text: &str) -> Result<DataBlock, PDBError> {
     })
 }
 
+/// Lex all values in the given piece of text, for example a (set of) line(s) from the body of
+/// a loop. The line number of the start of the text is used to give errors the correct context.
+pub fn lex_values(text: &str, line: usize) -> Result<Vec<Value>, PDBError> {
+    let mut input = Position {
+        text,
+        line,
+        column: 1,
+    };
+    let mut values = Vec::new();
+    loop {
+        trim_comments_and_whitespace(&mut input);
+        if input.text.is_empty() {
+            return Ok(values);
+        }
+        values.push(parse_value(&mut input)?);
+    }
+}
+
 /// Parse a CIF file
 fn parse_main(input: &mut Position<'_>) -> Result<DataBlock, PDBError> {
     trim_comments_and_whitespace(input);
@@ -78,7 +96,6 @@ fn parse_data_item(input: &mut Position<'_>) -> Result<DataItem, PDBError> {
             header: Vec::new(),
             data: Vec::new(),
         };
-        let mut values = Vec::new();
         trim_comments_and_whitespace(input);
 
         while let Some(()) = start_with(input, "_") {
@@ -87,9 +104,7 @@ fn parse_data_item(input: &mut Position<'_>) -> Result<DataItem, PDBError> {
             trim_comments_and_whitespace(input);
         }
 
-        while let Ok(value) = parse_value(input) {
-            values.push(value);
-        }
+        let values = parse_loop_values(input);
 
         let columns = loop_value.header.len();
         if values.len() % columns == 0 {
@@ -133,6 +148,117 @@ fn parse_data_item(input: &mut Position<'_>) -> Result<DataItem, PDBError> {
     }
 }
 
+/// The minimal number of lines in the body of a loop to lex it in parallel
+#[cfg(feature = "rayon")]
+const PARALLEL_LOOP_LINES: usize = 2_000;
+
+/// Parse all values in the body of a loop, until a value cannot be parsed.
+fn parse_loop_values(input: &mut Position<'_>) -> Vec<Value> {
+    #[cfg(feature = "rayon")]
+    if let Some(values) = parse_loop_values_parallel(input, PARALLEL_LOOP_LINES) {
+        return values;
+    }
+    let mut values = Vec::new();
+    while let Ok(value) = parse_value(input) {
+        values.push(value);
+    }
+    values
+}
+
+/// Parse all values in the body of a loop in parallel, by splitting the body in chunks of whole
+/// lines. This gives the exact same result as the sequential parsing. If the body contains
+/// constructs that could span multiple lines (multiline strings), if the body has fewer lines than
+/// `min_lines`, or if any value could not be parsed `None` is returned, and the body should be
+/// parsed sequentially.
+#[cfg(feature = "rayon")]
+fn parse_loop_values_parallel(input: &mut Position<'_>, min_lines: usize) -> Option<Vec<Value>> {
+    use rayon::prelude::*;
+
+    // Find the end of the loop body, which is the first line starting with a tag or reserved word
+    let text = input.text;
+    let mut line_starts = Vec::new();
+    let mut end = text.len();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('_')
+            || ["data_", "global_", "loop_", "save_", "stop_"]
+                .iter()
+                .any(|keyword| {
+                    start_with(
+                        &mut Position {
+                            text: trimmed,
+                            line: 0,
+                            column: 0,
+                        },
+                        keyword,
+                    )
+                    .is_some()
+                })
+        {
+            end = offset;
+            break;
+        }
+        if line.contains(';') {
+            return None;
+        }
+        line_starts.push(offset);
+        offset += line.len();
+    }
+    if line_starts.len() < min_lines.max(1) {
+        return None;
+    }
+
+    // Split the body in chunks of whole lines
+    let chunk_count = (rayon::current_num_threads() * 4)
+        .min(line_starts.len() / min_lines.max(1))
+        .max(1);
+    let chunk_lines = line_starts.len() / chunk_count;
+    let chunks: Vec<(usize, usize, usize)> = (0..chunk_count)
+        .map(|i| {
+            let start_line = i * chunk_lines;
+            let start = line_starts[start_line];
+            let stop = if i + 1 == chunk_count {
+                end
+            } else {
+                line_starts[(i + 1) * chunk_lines]
+            };
+            (start_line, start, stop)
+        })
+        .collect();
+
+    let results = chunks
+        .par_iter()
+        .map(|(start_line, start, stop)| {
+            let mut position = Position {
+                text: &text[*start..*stop],
+                line: input.line + start_line,
+                column: if *start_line == 0 { input.column } else { 1 },
+            };
+            let mut values = Vec::new();
+            loop {
+                trim_comments_and_whitespace(&mut position);
+                if position.text.is_empty() {
+                    return Some((values, position.line, position.column));
+                }
+                values.push(parse_value(&mut position).ok()?);
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut values = Vec::new();
+    let mut last = (input.line, input.column);
+    for (chunk, line, column) in results {
+        values.extend(chunk);
+        last = (line, column);
+    }
+    input.text = &text[end..];
+    input.line = last.0;
+    input.column = last.1;
+    trim_comments_and_whitespace(input);
+    Some(values)
+}
+
 /// Parse a value for a data item or inside a loop
 fn parse_value(input: &mut Position<'_>) -> Result<Value, PDBError> {
     let start = *input;
@@ -197,7 +323,7 @@ fn parse_value(input: &mut Position<'_>) -> Result<Value, PDBError> {
 }
 
 /// Parse a numeric value from a string which is expected to be of non zero length and not containing whitespace
-fn parse_numeric(text: &str) -> Option<Value> {
+pub(super) fn parse_numeric(text: &str) -> Option<Value> {
     let mut chars_to_remove = 0;
     let first_char = text.chars().next().unwrap();
     // Parse a possible sign
@@ -1283,6 +1409,44 @@ mod tests {
         )
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_loop_values() {
+        let mut parallel_loops = 0;
+        for name in ["1ubq.cif", "1yyf.cif", "rosetta_model.cif"] {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("example-pdbs")
+                .join(name);
+            let text = std::fs::read_to_string(path).unwrap();
+            let mut offset = 0;
+            while let Some(found) = text[offset..].find("\nloop_") {
+                offset += found + 1;
+                let mut sequential = Position {
+                    text: &text[offset..],
+                    line: 1,
+                    column: 1,
+                };
+                start_with(&mut sequential, "loop_").unwrap();
+                trim_comments_and_whitespace(&mut sequential);
+                while let Some(()) = start_with(&mut sequential, "_") {
+                    parse_identifier(&mut sequential);
+                    trim_comments_and_whitespace(&mut sequential);
+                }
+                let mut parallel = sequential;
+                let mut values = Vec::new();
+                while let Ok(value) = parse_value(&mut sequential) {
+                    values.push(value);
+                }
+                if let Some(parallel_values) = parse_loop_values_parallel(&mut parallel, 2) {
+                    parallel_loops += 1;
+                    assert_eq!(values, parallel_values);
+                    assert_eq!(sequential, parallel);
+                }
+            }
+        }
+        assert!(parallel_loops > 3);
+    }
+
     fn close(a: f64, b: f64) -> bool {
         let dif = a / b;
         (1.0 - dif) > -0.000000000000001 && (dif - 1.0) < 0.000000000000001
@@ -1,8 +1,17 @@
+/// Decode BinaryCIF files into the CIF intermediate structure
+mod bcif;
 /// Parse CIF files into intermediate structure
 mod lexer;
 /// Save the CIF intermediate structure
 mod lexitem;
+/// Parse ModelCIF quality assessment categories
+mod modelcif;
 /// Parse intermediate structure to PDB structure
 mod parser;
+/// Parse PDBML (XML) files into the CIF intermediate structure
+mod pdbml;
+/// Stream the atoms from a file without building the full structure
+mod stream;
 
 pub use parser::*;
+pub(crate) use stream::MmcifAtomStream;
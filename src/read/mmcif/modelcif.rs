@@ -0,0 +1,287 @@
+use super::lexitem::*;
+use super::parser::{get_f64, get_isize, get_text, get_usize};
+use crate::error::*;
+use crate::structs::*;
+use std::collections::HashMap;
+
+/// A category from a CIF data block, defined either as a loop or as single data items (which
+/// is read as a table with a single row)
+struct Table<'a> {
+    /// The names of the columns, without the category name
+    header: Vec<&'a str>,
+    /// The rows of values
+    rows: Vec<Vec<&'a Value>>,
+}
+
+impl<'a> Table<'a> {
+    /// Find the category with the given name (without leading underscore) in the data block
+    fn find(input: &'a DataBlock, category: &str) -> Option<Self> {
+        let prefix = format!("{category}.");
+        let mut single = Table {
+            header: Vec::new(),
+            rows: vec![Vec::new()],
+        };
+        for item in &input.items {
+            match item {
+                Item::DataItem(DataItem::Loop(multiple))
+                    if multiple.header.iter().any(|h| h.starts_with(&prefix)) =>
+                {
+                    return Some(Table {
+                        header: multiple
+                            .header
+                            .iter()
+                            .map(|h| h.strip_prefix(&prefix).unwrap_or(h))
+                            .collect(),
+                        rows: multiple.data.iter().map(|r| r.iter().collect()).collect(),
+                    });
+                }
+                Item::DataItem(DataItem::Single(item)) => {
+                    if let Some(name) = item.name.strip_prefix(&prefix) {
+                        single.header.push(name);
+                        single.rows[0].push(&item.content);
+                    }
+                }
+                _ => (),
+            }
+        }
+        (!single.header.is_empty()).then_some(single)
+    }
+
+    /// Get the value of the given column in the given row, if the column is present
+    fn value(&self, row: &[&'a Value], column: &str) -> Option<&'a Value> {
+        self.header
+            .iter()
+            .position(|h| *h == column)
+            .and_then(|i| row.get(i).copied())
+    }
+
+    /// Get the value of the given column as text, if it is present and not missing
+    fn text(&self, row: &[&'a Value], column: &str) -> Option<String> {
+        self.value(row, column)
+            .and_then(|value| get_text(value, &Context::none(), None).ok().flatten())
+    }
+}
+
+/// Parse the quality assessment categories of ModelCIF files, like the AlphaFold pLDDT. The
+/// local metric, preferably of type pLDDT, is stored as the confidence of the Residues and the
+/// global metrics are stored in the PDB.
+pub(super) fn parse_qa(input: &DataBlock, pdb: &mut PDB) -> Vec<PDBError> {
+    let mut errors = Vec::new();
+
+    // The name of every metric by id, and the id of the local metric to use
+    let mut names = HashMap::new();
+    let mut local_metric = None;
+    if let Some(metrics) = Table::find(input, "ma_qa_metric") {
+        for row in &metrics.rows {
+            if let Some(id) = metrics.text(row, "id") {
+                let kind = metrics.text(row, "type").unwrap_or_default();
+                let local = metrics
+                    .text(row, "mode")
+                    .map_or(true, |mode| mode == "local");
+                if local && (local_metric.is_none() || kind.eq_ignore_ascii_case("pLDDT")) {
+                    local_metric = Some(id.clone());
+                }
+                names.insert(id.clone(), metrics.text(row, "name").unwrap_or(id));
+            }
+        }
+    }
+
+    if let Some(global) = Table::find(input, "ma_qa_metric_global") {
+        for row in &global.rows {
+            let context = Context::show("_ma_qa_metric_global");
+            let id = global.text(row, "metric_id").unwrap_or_default();
+            match global
+                .value(row, "metric_value")
+                .map(|value| get_f64(value, &context, Some("metric_value")))
+            {
+                Some(Ok(Some(value))) => {
+                    pdb.add_confidence_metric(names.get(&id).unwrap_or(&id).clone(), value);
+                }
+                Some(Err(e)) => errors.push(e),
+                _ => (),
+            }
+        }
+    }
+
+    if let Some(local) = Table::find(input, "ma_qa_metric_local") {
+        let residues = residue_names(input);
+        for row in &local.rows {
+            let metric = local.text(row, "metric_id");
+            if local_metric.is_some() && metric != local_metric {
+                continue;
+            }
+            let context = Context::show(format!(
+                "_ma_qa_metric_local {}",
+                row.iter()
+                    .map(|value| get_text(value, &Context::none(), None)
+                        .ok()
+                        .flatten()
+                        .unwrap_or_else(|| "?".to_string()))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+            let value = match local
+                .value(row, "metric_value")
+                .map(|value| get_f64(value, &context, Some("metric_value")))
+            {
+                Some(Ok(Some(value))) => value,
+                Some(Err(e)) => {
+                    errors.push(e);
+                    continue;
+                }
+                _ => continue,
+            };
+            let model_id = match local
+                .value(row, "model_id")
+                .map(|value| get_usize(value, &context, Some("model_id")))
+            {
+                Some(Ok(id)) => id,
+                Some(Err(e)) => {
+                    errors.push(e);
+                    continue;
+                }
+                None => None,
+            };
+            let key = (
+                local.text(row, "label_asym_id").unwrap_or_default(),
+                local.text(row, "label_seq_id").unwrap_or_default(),
+            );
+            let found = residues.get(&key).map_or(false, |(chain, id)| {
+                let mut found = false;
+                for model in pdb
+                    .models_mut()
+                    .filter(|m| model_id.map_or(true, |id| m.serial_number() == id))
+                {
+                    for residue in model
+                        .chains_mut()
+                        .filter(|c| c.id() == chain)
+                        .flat_map(Chain::residues_mut)
+                        .filter(|r| r.id() == (id.0, id.1.as_deref()))
+                    {
+                        residue.set_confidence(value);
+                        found = true;
+                    }
+                }
+                found
+            });
+            if !found {
+                errors.push(PDBError::new(
+                    ErrorLevel::LooseWarning,
+                    "Quality metric for unknown residue",
+                    "The residue of this local quality metric could not be found in the atomic data, so its value is ignored.",
+                    context,
+                ));
+            }
+        }
+    }
+    errors
+}
+
+/// The chain name and residue id (serial number and insertion code) by `label_asym_id` and
+/// `label_seq_id`
+type ResidueNames = HashMap<(String, String), (String, (isize, Option<String>))>;
+
+/// Get the chain name and residue id for every combination of `label_asym_id` and
+/// `label_seq_id` in the atomic data, these are the names used when parsing the atoms
+fn residue_names(input: &DataBlock) -> ResidueNames {
+    let mut residues = HashMap::new();
+    if let Some(atoms) = Table::find(input, "atom_site") {
+        for row in &atoms.rows {
+            let asym = atoms.text(row, "label_asym_id").unwrap_or_default();
+            let seq = atoms.text(row, "label_seq_id").unwrap_or_default();
+            if residues.contains_key(&(asym.clone(), seq.clone())) {
+                continue;
+            }
+            let number = ["auth_seq_id", "label_seq_id"].iter().find_map(|column| {
+                atoms
+                    .value(row, column)
+                    .and_then(|value| get_isize(value, &Context::none(), None).ok().flatten())
+            });
+            if let Some(number) = number {
+                let chain = atoms
+                    .text(row, "auth_asym_id")
+                    .unwrap_or_else(|| asym.clone());
+                let insertion_code = atoms.text(row, "pdbx_PDB_ins_code");
+                residues.insert((asym, seq), (chain, (number, insertion_code)));
+            }
+        }
+    }
+    residues
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn qa_metrics() {
+        let cif = "data_model
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.label_atom_id
+_atom_site.label_comp_id
+_atom_site.label_asym_id
+_atom_site.label_seq_id
+_atom_site.auth_asym_id
+_atom_site.auth_seq_id
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+_atom_site.B_iso_or_equiv
+ATOM 1 C CA GLY A 1 B 10 0.0 0.0 0.0 91.5
+ATOM 2 C CA GLY A 2 B 11 3.8 0.0 0.0 45.0
+ATOM 3 C CA GLY A 3 B 12 7.6 0.0 0.0 12.0
+loop_
+_ma_qa_metric.id
+_ma_qa_metric.name
+_ma_qa_metric.type
+_ma_qa_metric.mode
+1 pLDDT pLDDT global
+2 pLDDT pLDDT local
+3 other other local
+_ma_qa_metric_global.ordinal_id 1
+_ma_qa_metric_global.model_id 1
+_ma_qa_metric_global.metric_id 1
+_ma_qa_metric_global.metric_value 49.5
+loop_
+_ma_qa_metric_local.ordinal_id
+_ma_qa_metric_local.model_id
+_ma_qa_metric_local.label_asym_id
+_ma_qa_metric_local.label_seq_id
+_ma_qa_metric_local.metric_id
+_ma_qa_metric_local.metric_value
+1 1 A 1 2 91.5
+2 1 A 2 2 45.0
+3 1 A 1 3 0.5
+4 1 A 9 2 50.0
+";
+        let (mut pdb, errors) = ReadOptions::new()
+            .set_format(Format::Mmcif)
+            .set_level(StrictnessLevel::Loose)
+            .read_raw(BufReader::new(cif.as_bytes()))
+            .unwrap();
+        assert_eq!(
+            errors
+                .iter()
+                .filter(|e| e.short_description() == "Quality metric for unknown residue")
+                .count(),
+            1
+        );
+        assert_eq!(pdb.confidence_metric("pLDDT"), Some(49.5));
+        let confidences: Vec<_> = pdb.residues().map(Residue::confidence).collect();
+        assert_eq!(confidences, [Some(91.5), Some(45.0), None]);
+        assert_eq!(pdb.residue(0).unwrap().serial_number(), 10);
+
+        pdb.remove_low_confidence_residues(70.0);
+        let numbers: Vec<_> = pdb.residues().map(Residue::serial_number).collect();
+        assert_eq!(numbers, [10, 12]);
+
+        pdb.set_confidence_from_b_factors();
+        let confidences: Vec<_> = pdb.residues().map(Residue::confidence).collect();
+        assert_eq!(confidences, [Some(91.5), Some(12.0)]);
+    }
+}
@@ -102,6 +102,87 @@ where
     }
 }
 
+/// Parse the given PDBML file into a PDB struct with [`ReadOptions`].
+pub(crate) fn open_pdbml_with_options(
+    filename: impl AsRef<str>,
+    options: &ReadOptions,
+) -> Result<(PDB, Vec<PDBError>), Vec<PDBError>> {
+    let filename = filename.as_ref();
+    let file = if let Ok(f) = File::open(filename) {
+        f
+    } else {
+        return Err(vec![PDBError::new(ErrorLevel::BreakingError, "Could not open file", "Could not open the specified file, make sure the path is correct, you have permission, and that it is not open in another program.", Context::show(filename))]);
+    };
+    let reader = std::io::BufReader::new(file);
+    open_pdbml_raw_with_options(reader, options)
+}
+
+/// Parse the given PDBML (XML) stream into a [`PDB`] struct. The categories are read in the same
+/// way as the categories of an mmCIF file, so the result is the same as for the equivalent mmCIF file.
+pub(crate) fn open_pdbml_raw_with_options<T>(
+    mut input: std::io::BufReader<T>,
+    options: &ReadOptions,
+) -> Result<(PDB, Vec<PDBError>), Vec<PDBError>>
+where
+    T: std::io::Read,
+{
+    let mut contents = String::new();
+    if input.read_to_string(&mut contents).is_ok() {
+        match super::pdbml::lex_pdbml(contents.as_str()) {
+            Ok(data_block) => parse_mmcif_with_options(&data_block, options),
+            Err(e) => Err(vec![e]),
+        }
+    } else {
+        Err(vec![PDBError::new(
+            crate::ErrorLevel::BreakingError,
+            "Buffer could not be read",
+            "The buffer provided to `open_raw` could not be read to end.",
+            Context::None,
+        )])
+    }
+}
+
+/// Parse the given BinaryCIF file into a PDB struct with [`ReadOptions`].
+pub(crate) fn open_bcif_with_options(
+    filename: impl AsRef<str>,
+    options: &ReadOptions,
+) -> Result<(PDB, Vec<PDBError>), Vec<PDBError>> {
+    let filename = filename.as_ref();
+    let file = if let Ok(f) = File::open(filename) {
+        f
+    } else {
+        return Err(vec![PDBError::new(ErrorLevel::BreakingError, "Could not open file", "Could not open the specified file, make sure the path is correct, you have permission, and that it is not open in another program.", Context::show(filename))]);
+    };
+    let reader = std::io::BufReader::new(file);
+    open_bcif_raw_with_options(reader, options)
+}
+
+/// Parse the given BinaryCIF stream into a [`PDB`] struct. The categories are decoded and read in
+/// the same way as the categories of an mmCIF file, so the result is the same as for the
+/// equivalent mmCIF file.
+pub(crate) fn open_bcif_raw_with_options<T>(
+    mut input: std::io::BufReader<T>,
+    options: &ReadOptions,
+) -> Result<(PDB, Vec<PDBError>), Vec<PDBError>>
+where
+    T: std::io::Read,
+{
+    let mut contents = Vec::new();
+    if input.read_to_end(&mut contents).is_ok() {
+        match super::bcif::lex_bcif(&contents) {
+            Ok(data_block) => parse_mmcif_with_options(&data_block, options),
+            Err(e) => Err(vec![e]),
+        }
+    } else {
+        Err(vec![PDBError::new(
+            crate::ErrorLevel::BreakingError,
+            "Buffer could not be read",
+            "The buffer provided to `open_raw` could not be read to end.",
+            Context::None,
+        )])
+    }
+}
+
 /// Parse a CIF intermediate structure into a PDB
 fn parse_mmcif(
     input: &DataBlock,
@@ -270,6 +351,7 @@ fn parse_mmcif_with_options(
         pdb.unit_cell = Some(unit_cell);
     }
 
+    errors.extend(super::modelcif::parse_qa(input, &mut pdb));
     reshuffle_conformers(&mut pdb);
     errors.extend(validate(&pdb));
     if errors.iter().any(|e| e.fails(options.level)) {
@@ -347,261 +429,365 @@ fn flatten_result<T, E>(value: Result<Result<T, E>, E>) -> Result<T, E> {
     }
 }
 
-/// Parse a loop containing atomic data
-fn parse_atoms(input: &Loop, pdb: &mut PDB, options: &ReadOptions) -> Option<Vec<PDBError>> {
-    #[derive(Eq, PartialEq)]
-    /// The mode of a column
-    enum Mode {
-        /// A required column (has to be defined)
-        Required,
-        /// An optional column, if undefined it will have a default value
-        Optional,
-    }
-    use Mode::{Optional, Required};
-
-    /// Easily define all columns
-    macro_rules! define_columns {
-        ($($i:expr, $name:ident, $label:expr, $req:expr);+;) => {
-            $(const $name: (usize, &str, Mode) = ($i, $label, $req);)+
-            const COLUMNS: &[(Mode, &str)] = &[
-                $(($req, $name.1)),+
-            ];
-        };
-    }
+/// The mode of a column
+#[derive(Eq, PartialEq)]
+enum Mode {
+    /// A required column (has to be defined)
+    Required,
+    /// An optional column, if undefined it will have a default value
+    Optional,
+}
+use Mode::{Optional, Required};
 
-    define_columns!(
-        0,  ATOM_ALT_ID, "atom_site.label_alt_id", Optional;
-        1,  ATOM_ANISOU_1_1, "_atom_site.aniso_U[1][1]", Optional;
-        2,  ATOM_ANISOU_1_2, "_atom_site.aniso_U[1][2]", Optional;
-        3,  ATOM_ANISOU_1_3, "_atom_site.aniso_U[1][3]", Optional;
-        4,  ATOM_ANISOU_2_1, "_atom_site.aniso_U[2][1]", Optional;
-        5,  ATOM_ANISOU_2_2, "_atom_site.aniso_U[2][2]", Optional;
-        6,  ATOM_ANISOU_2_3, "_atom_site.aniso_U[2][3]", Optional;
-        7,  ATOM_ANISOU_3_1, "_atom_site.aniso_U[3][1]", Optional;
-        8,  ATOM_ANISOU_3_2, "_atom_site.aniso_U[3][2]", Optional;
-        9,  ATOM_ANISOU_3_3, "_atom_site.aniso_U[3][3]", Optional;
-        10, ATOM_ASYM_ID, "atom_site.label_asym_id", Required;
-        11, ATOM_AUTH_ASYM_ID, "atom_site.auth_asym_id", Optional;
-        12, ATOM_B, "atom_site.B_iso_or_equiv", Optional;
-        13, ATOM_CHARGE, "atom_site.pdbx_formal_charge", Optional;
-        14, ATOM_COMP_ID, "atom_site.label_comp_id", Required;
-        15, ATOM_GROUP, "atom_site.group_PDB", Optional;
-        16, ATOM_ID, "atom_site.id", Required;
-        17, ATOM_INSERTION, "atom_site.pdbx_PDB_ins_code", Optional;
-        18, ATOM_MODEL, "atom_site.pdbx_PDB_model_num", Optional;
-        19, ATOM_NAME, "atom_site.label_atom_id", Required;
-        20, ATOM_OCCUPANCY, "atom_site.occupancy", Optional;
-        21, ATOM_SEQ_ID, "atom_site.label_seq_id", Required;
-        22, ATOM_AUTH_SEQ_ID, "atom_site.auth_seq_id", Optional;
-        23, ATOM_TYPE, "atom_site.type_symbol", Required;
-        24, ATOM_X, "atom_site.Cartn_x", Required;
-        25, ATOM_Y, "atom_site.Cartn_y", Required;
-        26, ATOM_Z, "atom_site.Cartn_z", Required;
-    );
-
-    let positions_: Vec<Result<Option<usize>, PDBError>> = COLUMNS
-        .iter()
-        .map(|tag| (input.header.iter().position(|t| t == tag.1), tag))
-        .map(|(pos, tag)| match pos {
-            Some(p) => Ok(Some(p)),
-            None if tag.0 == Required => Err(PDBError::new(
-                ErrorLevel::InvalidatingError,
-                "Missing column in coordinate atoms data loop",
-                "The above column is missing",
-                Context::show(tag.1),
-            )),
-            None => Ok(None),
-        })
-        .collect();
-
-    let mut errors = positions_
-        .iter()
-        .filter_map(|i| i.clone().err())
-        .collect::<Vec<_>>();
+/// Easily define all columns
+macro_rules! define_columns {
+    ($($i:expr, $name:ident, $label:expr, $req:expr);+;) => {
+        $(
+            #[doc = concat!("The `_", $label, "` column")]
+            const $name: (usize, &str, Mode) = ($i, $label, $req);
+        )+
+        /// All columns of the atomic data loop
+        const COLUMNS: &[(Mode, &str)] = &[
+            $(($req, $name.1)),+
+        ];
+    };
+}
 
-    if !errors.is_empty() {
-        return Some(errors);
+define_columns!(
+    0,  ATOM_ALT_ID, "atom_site.label_alt_id", Optional;
+    1,  ATOM_ANISOU_1_1, "_atom_site.aniso_U[1][1]", Optional;
+    2,  ATOM_ANISOU_1_2, "_atom_site.aniso_U[1][2]", Optional;
+    3,  ATOM_ANISOU_1_3, "_atom_site.aniso_U[1][3]", Optional;
+    4,  ATOM_ANISOU_2_1, "_atom_site.aniso_U[2][1]", Optional;
+    5,  ATOM_ANISOU_2_2, "_atom_site.aniso_U[2][2]", Optional;
+    6,  ATOM_ANISOU_2_3, "_atom_site.aniso_U[2][3]", Optional;
+    7,  ATOM_ANISOU_3_1, "_atom_site.aniso_U[3][1]", Optional;
+    8,  ATOM_ANISOU_3_2, "_atom_site.aniso_U[3][2]", Optional;
+    9,  ATOM_ANISOU_3_3, "_atom_site.aniso_U[3][3]", Optional;
+    10, ATOM_ASYM_ID, "atom_site.label_asym_id", Required;
+    11, ATOM_AUTH_ASYM_ID, "atom_site.auth_asym_id", Optional;
+    12, ATOM_B, "atom_site.B_iso_or_equiv", Optional;
+    13, ATOM_CHARGE, "atom_site.pdbx_formal_charge", Optional;
+    14, ATOM_COMP_ID, "atom_site.label_comp_id", Required;
+    15, ATOM_GROUP, "atom_site.group_PDB", Optional;
+    16, ATOM_ID, "atom_site.id", Required;
+    17, ATOM_INSERTION, "atom_site.pdbx_PDB_ins_code", Optional;
+    18, ATOM_MODEL, "atom_site.pdbx_PDB_model_num", Optional;
+    19, ATOM_NAME, "atom_site.label_atom_id", Required;
+    20, ATOM_OCCUPANCY, "atom_site.occupancy", Optional;
+    21, ATOM_SEQ_ID, "atom_site.label_seq_id", Required;
+    22, ATOM_AUTH_SEQ_ID, "atom_site.auth_seq_id", Optional;
+    23, ATOM_TYPE, "atom_site.type_symbol", Required;
+    24, ATOM_X, "atom_site.Cartn_x", Required;
+    25, ATOM_Y, "atom_site.Cartn_y", Required;
+    26, ATOM_Z, "atom_site.Cartn_z", Required;
+);
+
+/// The positions of all known columns in the header of an atomic data loop
+#[derive(Debug, Clone)]
+pub(crate) struct AtomSiteColumns {
+    /// The position of every column in [`COLUMNS`] in the loop header, if present
+    positions: Vec<Option<usize>>,
+}
+
+impl AtomSiteColumns {
+    /// Find all columns in the given loop header, fails if any required column is missing
+    pub(crate) fn new(header: &[String]) -> Result<Self, Vec<PDBError>> {
+        let positions_: Vec<Result<Option<usize>, PDBError>> = COLUMNS
+            .iter()
+            .map(|tag| (header.iter().position(|t| t == tag.1), tag))
+            .map(|(pos, tag)| match pos {
+                Some(p) => Ok(Some(p)),
+                None if tag.0 == Required => Err(PDBError::new(
+                    ErrorLevel::InvalidatingError,
+                    "Missing column in coordinate atoms data loop",
+                    "The above column is missing",
+                    Context::show(tag.1),
+                )),
+                None => Ok(None),
+            })
+            .collect();
+
+        let errors = positions_
+            .iter()
+            .filter_map(|i| i.clone().err())
+            .collect::<Vec<_>>();
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // The previous lines make sure that there is no error in the vector.
+        #[allow(clippy::unwrap_used)]
+        let positions = positions_.iter().map(|i| *i.as_ref().unwrap()).collect();
+        Ok(Self { positions })
     }
 
-    // The previous lines make sure that there is no error in the vector.
-    #[allow(clippy::unwrap_used)]
-    let positions: Vec<Option<usize>> = positions_.iter().map(|i| *i.as_ref().unwrap()).collect();
-    let mut first_model_number: usize = 0;
-    for (index, row) in input.data.iter().enumerate() {
-        let values: Vec<Option<&Value>> = positions.iter().map(|i| i.map(|x| &row[x])).collect();
-        let context = Context::show(format!("Main atomic data loop row: {index}"));
+    /// Select the values for all known columns from the given row
+    fn select<'a>(&self, row: &'a [Value]) -> Vec<Option<&'a Value>> {
+        self.positions.iter().map(|i| i.map(|x| &row[x])).collect()
+    }
+}
 
-        /// Parse a column given the function to use and the column index
-        macro_rules! parse_column {
-            ($type:tt, $index:tt) => {
-                if let Some(value) = values[$index.0] {
-                    match $type(value, &context, Some($index.1)) {
-                        Ok(t) => t,
-                        Err(e) => {
-                            errors.push(e);
-                            None
-                        }
-                    }
-                } else {
+/// Parse a column given the function to use and the column index
+macro_rules! parse_column {
+    ($values:ident, $context:ident, $errors:ident, $type:tt, $index:tt) => {
+        if let Some(value) = $values[$index.0] {
+            match $type(value, &$context, Some($index.1)) {
+                Ok(t) => t,
+                Err(e) => {
+                    $errors.push(e);
                     None
                 }
-            };
-        }
-
-        // Early return cases
-        let element = parse_column!(get_text, ATOM_TYPE).expect("Atom element should be provided");
-        if options.discard_hydrogens & (element == "H") {
-            continue;
-        }
-        let model_number = parse_column!(get_usize, ATOM_MODEL).unwrap_or(1);
-        if options.only_first_model {
-            if index == 0 {
-                first_model_number = model_number;
-            } else if model_number != first_model_number {
-                break;
             }
+        } else {
+            None
         }
+    };
+}
 
-        // Parse remaining fields in the order they appear in the line
-        let atom_type = parse_column!(get_text, ATOM_GROUP).unwrap_or_else(|| "ATOM".to_string());
-        let name = parse_column!(get_text, ATOM_NAME).expect("Atom name should be provided");
-        let serial_number =
-            parse_column!(get_usize, ATOM_ID).expect("Atom serial number should be provided");
-        let residue_name =
-            parse_column!(get_text, ATOM_COMP_ID).expect("Residue name should be provided");
-        #[allow(clippy::cast_possible_wrap)]
-        let residue_number = parse_column!(get_isize, ATOM_AUTH_SEQ_ID).unwrap_or_else(|| {
-            parse_column!(get_isize, ATOM_SEQ_ID)
-                .unwrap_or_else(|| pdb.total_residue_count() as isize)
-        });
-        let chain_name = parse_column!(get_text, ATOM_AUTH_ASYM_ID).unwrap_or_else(|| {
-            parse_column!(get_text, ATOM_ASYM_ID).expect("Chain name should be provided")
+/// A single row of an atomic data loop parsed into an atom and its location in the hierarchy
+#[derive(Debug, Clone)]
+pub(crate) struct AtomSiteRow {
+    /// The model serial number
+    pub(crate) model_number: usize,
+    /// The chain identifier
+    pub(crate) chain_name: String,
+    /// The residue serial number, if it was defined
+    pub(crate) residue_number: Option<isize>,
+    /// The insertion code of the residue
+    pub(crate) insertion_code: Option<String>,
+    /// The residue (conformer) name
+    pub(crate) residue_name: String,
+    /// The alternative location of the conformer
+    pub(crate) alt_loc: Option<String>,
+    /// The atom, if it could be constructed
+    pub(crate) atom: Option<Atom>,
+}
+
+/// Parse the element and model number of a row of the atomic data loop. These are needed to
+/// determine whether a row should be parsed at all. Returns `None` if the row should be
+/// skipped (because it is a hydrogen and hydrogens are discarded).
+pub(crate) fn parse_atom_row_filter(
+    columns: &AtomSiteColumns,
+    row: &[Value],
+    context: &Context,
+    options: &ReadOptions,
+    errors: &mut Vec<PDBError>,
+) -> Option<(String, usize)> {
+    let values = columns.select(row);
+    let element = parse_column!(values, context, errors, get_text, ATOM_TYPE)
+        .expect("Atom element should be provided");
+    if options.discard_hydrogens & (element == "H") {
+        return None;
+    }
+    let model_number = parse_column!(values, context, errors, get_usize, ATOM_MODEL).unwrap_or(1);
+    Some((element, model_number))
+}
+
+/// Parse the remaining fields of a row of the atomic data loop, with the element and model number
+/// as found by [`parse_atom_row_filter`].
+pub(crate) fn parse_atom_row(
+    columns: &AtomSiteColumns,
+    row: &[Value],
+    context: &Context,
+    (element, model_number): (String, usize),
+    errors: &mut Vec<PDBError>,
+) -> AtomSiteRow {
+    let values = columns.select(row);
+
+    // Parse remaining fields in the order they appear in the line
+    let atom_type = parse_column!(values, context, errors, get_text, ATOM_GROUP)
+        .unwrap_or_else(|| "ATOM".to_string());
+    let name = parse_column!(values, context, errors, get_text, ATOM_NAME)
+        .expect("Atom name should be provided");
+    let serial_number = parse_column!(values, context, errors, get_usize, ATOM_ID)
+        .expect("Atom serial number should be provided");
+    let residue_name = parse_column!(values, context, errors, get_text, ATOM_COMP_ID)
+        .expect("Residue name should be provided");
+    let residue_number = parse_column!(values, context, errors, get_isize, ATOM_AUTH_SEQ_ID)
+        .or_else(|| parse_column!(values, context, errors, get_isize, ATOM_SEQ_ID));
+    let chain_name = parse_column!(values, context, errors, get_text, ATOM_AUTH_ASYM_ID)
+        .unwrap_or_else(|| {
+            parse_column!(values, context, errors, get_text, ATOM_ASYM_ID)
+                .expect("Chain name should be provided")
         });
-        let pos_x = parse_column!(get_f64, ATOM_X).expect("Atom X position should be provided");
-        let pos_y = parse_column!(get_f64, ATOM_Y).expect("Atom Y position should be provided");
-        let pos_z = parse_column!(get_f64, ATOM_Z).expect("Atom Z position should be provided");
-        let occupancy = parse_column!(get_f64, ATOM_OCCUPANCY).unwrap_or(1.0);
-        let b_factor = parse_column!(get_f64, ATOM_B).unwrap_or(1.0);
-        let charge = parse_column!(get_isize, ATOM_CHARGE).unwrap_or(0);
-        let alt_loc = parse_column!(get_text, ATOM_ALT_ID);
-        let insertion_code = parse_column!(get_text, ATOM_INSERTION);
-        let aniso_temp = [
+    let pos_x = parse_column!(values, context, errors, get_f64, ATOM_X)
+        .expect("Atom X position should be provided");
+    let pos_y = parse_column!(values, context, errors, get_f64, ATOM_Y)
+        .expect("Atom Y position should be provided");
+    let pos_z = parse_column!(values, context, errors, get_f64, ATOM_Z)
+        .expect("Atom Z position should be provided");
+    let occupancy = parse_column!(values, context, errors, get_f64, ATOM_OCCUPANCY).unwrap_or(1.0);
+    let b_factor = parse_column!(values, context, errors, get_f64, ATOM_B).unwrap_or(1.0);
+    let charge = parse_column!(values, context, errors, get_isize, ATOM_CHARGE).unwrap_or(0);
+    let alt_loc = parse_column!(values, context, errors, get_text, ATOM_ALT_ID);
+    let insertion_code = parse_column!(values, context, errors, get_text, ATOM_INSERTION);
+    let aniso_temp = [
+        [
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_1_1),
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_1_2),
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_1_3),
+        ],
+        [
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_2_1),
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_2_2),
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_2_3),
+        ],
+        [
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_3_1),
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_3_2),
+            parse_column!(values, context, errors, get_f64, ATOM_ANISOU_3_3),
+        ],
+    ];
+
+    let aniso = if aniso_temp
+        .iter()
+        .flat_map(|l| l.iter())
+        .all(Option::is_some)
+    {
+        #[allow(clippy::unwrap_used)]
+        Some([
             [
-                parse_column!(get_f64, ATOM_ANISOU_1_1),
-                parse_column!(get_f64, ATOM_ANISOU_1_2),
-                parse_column!(get_f64, ATOM_ANISOU_1_3),
+                aniso_temp[0][0].unwrap(),
+                aniso_temp[0][1].unwrap(),
+                aniso_temp[0][2].unwrap(),
             ],
             [
-                parse_column!(get_f64, ATOM_ANISOU_2_1),
-                parse_column!(get_f64, ATOM_ANISOU_2_2),
-                parse_column!(get_f64, ATOM_ANISOU_2_3),
+                aniso_temp[1][0].unwrap(),
+                aniso_temp[1][1].unwrap(),
+                aniso_temp[1][2].unwrap(),
             ],
             [
-                parse_column!(get_f64, ATOM_ANISOU_3_1),
-                parse_column!(get_f64, ATOM_ANISOU_3_2),
-                parse_column!(get_f64, ATOM_ANISOU_3_3),
+                aniso_temp[2][0].unwrap(),
+                aniso_temp[2][1].unwrap(),
+                aniso_temp[2][2].unwrap(),
             ],
-        ];
+        ])
+    } else if aniso_temp
+        .iter()
+        .flat_map(|l| l.iter())
+        .any(Option::is_some)
+    {
+        errors.push(PDBError::new(
+            ErrorLevel::StrictWarning,
+            "Atom aniso U definition incomplete",
+            "For a valid anisotropic temperature factor definition all columns (1,1 up to and including 3,3) have to be defined.",
+            context.clone(),
+        ));
+        None
+    } else {
+        None
+    };
 
-        let aniso = if aniso_temp
-            .iter()
-            .flat_map(|l| l.iter())
-            .all(Option::is_some)
-        {
-            #[allow(clippy::unwrap_used)]
-            Some([
-                [
-                    aniso_temp[0][0].unwrap(),
-                    aniso_temp[0][1].unwrap(),
-                    aniso_temp[0][2].unwrap(),
-                ],
-                [
-                    aniso_temp[1][0].unwrap(),
-                    aniso_temp[1][1].unwrap(),
-                    aniso_temp[1][2].unwrap(),
-                ],
-                [
-                    aniso_temp[2][0].unwrap(),
-                    aniso_temp[2][1].unwrap(),
-                    aniso_temp[2][2].unwrap(),
-                ],
-            ])
-        } else if aniso_temp
-            .iter()
-            .flat_map(|l| l.iter())
-            .any(Option::is_some)
-        {
-            errors.push(PDBError::new(
-                ErrorLevel::StrictWarning,
-                "Atom aniso U definition incomplete",
-                "For a valid anisotropic temperature factor definition all columns (1,1 up to and including 3,3) have to be defined.",
-                context.clone(),
-            ));
-            None
-        } else {
-            None
-        };
+    let mut hetero = false;
+    if atom_type == "ATOM" {
+        hetero = false;
+    } else if atom_type == "HETATM" {
+        hetero = true;
+    } else {
+        errors.push(PDBError::new(
+            ErrorLevel::InvalidatingError,
+            "Atom type not correct",
+            "The atom type should be ATOM or HETATM",
+            context.clone(),
+        ))
+    }
+    let atom = if let Some(mut atom) = Atom::new(
+        hetero,
+        serial_number,
+        name,
+        pos_x,
+        pos_y,
+        pos_z,
+        occupancy,
+        b_factor,
+        element,
+        charge,
+    ) {
+        if let Some(matrix) = aniso {
+            atom.set_anisotropic_temperature_factors(matrix);
+        }
+        Some(atom)
+    } else {
+        errors.push(PDBError::new(
+            ErrorLevel::InvalidatingError,
+            "Atom definition incorrect",
+            "The atom name and element should only contain valid characters.",
+            context.clone(),
+        ));
+        None
+    };
 
-        let model = unsafe {
-            // I could not find a way to make the borrow checker happy, but if no item
-            // could be find the borrow should be ended and as such safe for mutating
-            // in the second branch.
-            let pdb_pointer: *mut PDB = pdb;
-            if let Some(m) = (*pdb_pointer)
-                .models_mut()
-                .find(|m| m.serial_number() == model_number)
-            {
-                m
-            } else {
-                (*pdb_pointer).add_model(Model::new(model_number));
-                #[allow(clippy::unwrap_used)]
-                (*pdb_pointer).models_mut().next_back().unwrap()
-            }
-        };
+    AtomSiteRow {
+        model_number,
+        chain_name,
+        residue_number,
+        insertion_code,
+        residue_name,
+        alt_loc,
+        atom,
+    }
+}
 
-        let mut hetero = false;
-        if atom_type == "ATOM" {
-            hetero = false;
-        } else if atom_type == "HETATM" {
-            hetero = true;
-        } else {
-            errors.push(PDBError::new(
-                ErrorLevel::InvalidatingError,
-                "Atom type not correct",
-                "The atom type should be ATOM or HETATM",
-                context.clone(),
-            ))
-        }
-        if let Some(mut atom) = Atom::new(
-            hetero,
-            serial_number,
-            name,
-            pos_x,
-            pos_y,
-            pos_z,
-            occupancy,
-            b_factor,
-            element,
-            charge,
-        ) {
-            if let Some(matrix) = aniso {
-                atom.set_anisotropic_temperature_factors(matrix);
-            }
+/// The minimal number of rows in the atomic data loop to parse them in parallel
+#[cfg(feature = "rayon")]
+const PARALLEL_ROWS: usize = 2_000;
 
-            model.add_atom(
-                atom,
-                chain_name,
-                (residue_number, insertion_code.as_deref()),
-                (residue_name, alt_loc.as_deref()),
-            );
-        } else {
-            errors.push(PDBError::new(
-                ErrorLevel::InvalidatingError,
-                "Atom definition incorrect",
-                "The atom name and element should only contain valid characters.",
-                context.clone(),
-            ))
+/// A parsed row of the atomic data loop, with the errors generated while determining if the row
+/// should be parsed, and the parsed row (if it should be parsed) with the errors generated while
+/// parsing the remainder of the row.
+type ParsedRow = (Vec<PDBError>, Option<(AtomSiteRow, Vec<PDBError>)>);
+
+/// Parse a loop containing atomic data
+fn parse_atoms(input: &Loop, pdb: &mut PDB, options: &ReadOptions) -> Option<Vec<PDBError>> {
+    #[cfg(feature = "rayon")]
+    let parallel = input.data.len() >= PARALLEL_ROWS;
+    #[cfg(not(feature = "rayon"))]
+    let parallel = false;
+    parse_atoms_in_mode(input, pdb, options, parallel)
+}
+
+/// Parse a loop containing atomic data, the rows are parsed in parallel if `parallel` is set
+/// and the `rayon` feature is enabled. The results are identical to the sequential parsing.
+fn parse_atoms_in_mode(
+    input: &Loop,
+    pdb: &mut PDB,
+    options: &ReadOptions,
+    parallel: bool,
+) -> Option<Vec<PDBError>> {
+    let columns = match AtomSiteColumns::new(&input.header) {
+        Ok(columns) => columns,
+        Err(errors) => return Some(errors),
+    };
+
+    let parse = |(index, row): (usize, &Vec<Value>)| -> ParsedRow {
+        let context = Context::show(format!("Main atomic data loop row: {index}"));
+        let mut filter_errors = Vec::new();
+        let filtered = parse_atom_row_filter(&columns, row, &context, options, &mut filter_errors);
+        let parsed = filtered.map(|filtered| {
+            let mut row_errors = Vec::new();
+            let row = parse_atom_row(&columns, row, &context, filtered, &mut row_errors);
+            (row, row_errors)
+        });
+        (filter_errors, parsed)
+    };
+
+    let mut errors = Vec::new();
+    if parallel {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            let parsed: Vec<ParsedRow> = input.data.par_iter().enumerate().map(parse).collect();
+            add_atom_rows(pdb, parsed.into_iter(), options, &mut errors);
         }
+    } else {
+        add_atom_rows(
+            pdb,
+            input.data.iter().enumerate().map(parse),
+            options,
+            &mut errors,
+        );
     }
     if !errors.is_empty() {
         Some(errors)
@@ -610,8 +796,69 @@ fn parse_atoms(input: &Loop, pdb: &mut PDB, options: &ReadOptions) -> Option<Vec
     }
 }
 
+/// Add all parsed rows of the atomic data loop to the given PDB, in order
+fn add_atom_rows(
+    pdb: &mut PDB,
+    rows: impl Iterator<Item = ParsedRow>,
+    options: &ReadOptions,
+    errors: &mut Vec<PDBError>,
+) {
+    let mut first_model_number: usize = 0;
+    for (index, (filter_errors, parsed)) in rows.enumerate() {
+        errors.extend(filter_errors);
+        // Early return cases
+        let (row, row_errors) = match parsed {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        if options.only_first_model {
+            if index == 0 {
+                first_model_number = row.model_number;
+            } else if row.model_number != first_model_number {
+                break;
+            }
+        }
+        errors.extend(row_errors);
+        add_atom_row(pdb, row);
+    }
+}
+
+/// Add a parsed row of the atomic data loop to the given PDB
+pub(crate) fn add_atom_row(pdb: &mut PDB, row: AtomSiteRow) {
+    #[allow(clippy::cast_possible_wrap)]
+    let residue_number = row
+        .residue_number
+        .unwrap_or_else(|| pdb.total_residue_count() as isize);
+
+    let model = unsafe {
+        // I could not find a way to make the borrow checker happy, but if no item
+        // could be find the borrow should be ended and as such safe for mutating
+        // in the second branch.
+        let pdb_pointer: *mut PDB = pdb;
+        if let Some(m) = (*pdb_pointer)
+            .models_mut()
+            .find(|m| m.serial_number() == row.model_number)
+        {
+            m
+        } else {
+            (*pdb_pointer).add_model(Model::new(row.model_number));
+            #[allow(clippy::unwrap_used)]
+            (*pdb_pointer).models_mut().next_back().unwrap()
+        }
+    };
+
+    if let Some(atom) = row.atom {
+        model.add_atom(
+            atom,
+            row.chain_name,
+            (residue_number, row.insertion_code.as_deref()),
+            (row.residue_name, row.alt_loc.as_deref()),
+        );
+    }
+}
+
 /// Get the Textual content of the value, if available
-fn get_text(
+pub(super) fn get_text(
     value: &Value,
     _context: &Context,
     _column: Option<&str>,
@@ -626,7 +873,7 @@ fn get_text(
 }
 
 /// Get the Numeric content of the value, if available, it also fails on NumericWithUncertainty
-fn get_f64(
+pub(super) fn get_f64(
     value: &Value,
     context: &Context,
     column: Option<&str>,
@@ -647,7 +894,7 @@ fn get_f64(
 }
 
 /// Get the Numeric content of the value, if available, as a usize
-fn get_usize(
+pub(super) fn get_usize(
     value: &Value,
     context: &Context,
     column: Option<&str>,
@@ -679,7 +926,7 @@ fn get_usize(
 }
 
 /// Get the Numeric content of the value, if available, as an isize
-fn get_isize(
+pub(super) fn get_isize(
     value: &Value,
     context: &Context,
     column: Option<&str>,
@@ -709,3 +956,43 @@ fn get_isize(
         }
     }))
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_atoms() {
+        for name in ["1ubq.cif", "1yyf.cif", "rosetta_model.cif"] {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("example-pdbs")
+                .join(name);
+            let text = std::fs::read_to_string(path).unwrap();
+            let block = super::super::lexer::lex_cif(&text).unwrap();
+            for item in &block.items {
+                if let Item::DataItem(DataItem::Loop(atoms)) = item {
+                    if !atoms.header.contains(&"atom_site.group_PDB".to_string()) {
+                        continue;
+                    }
+                    for (hydrogens, first_model) in [(false, false), (true, true)] {
+                        let mut options = ReadOptions::default();
+                        options
+                            .set_discard_hydrogens(hydrogens)
+                            .set_only_first_model(first_model);
+                        let mut sequential = PDB::new();
+                        let mut parallel = PDB::new();
+                        let sequential_errors =
+                            parse_atoms_in_mode(atoms, &mut sequential, &options, false);
+                        let parallel_errors =
+                            parse_atoms_in_mode(atoms, &mut parallel, &options, true);
+                        assert_eq!(sequential, parallel);
+                        assert_eq!(sequential_errors, parallel_errors);
+                        assert!(sequential.atom_count() > 0);
+                    }
+                }
+            }
+        }
+    }
+}
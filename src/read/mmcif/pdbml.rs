@@ -0,0 +1,465 @@
+use super::lexer::parse_numeric;
+use super::lexitem::*;
+use crate::error::*;
+
+/// Parse/lex a PDBML (XML) file into the CIF intermediate structure, so it can be read in the
+/// same way as the equivalent mmCIF file.
+///
+/// Every category element (`<PDBx:cellCategory>`) contains one element per row, with the key
+/// items as attributes and the other items as child elements. Categories with a single row
+/// are given as single data items, other categories as loops. Items that are missing in a row
+/// are inapplicable (`.`), items with `xsi:nil="true"` are unknown (`?`). The indices in item
+/// names are placed back in brackets (`origx11` becomes `origx[1][1]`).
+pub fn lex_pdbml(text: &str) -> Result<DataBlock, PDBError> {
+    let mut input = Position {
+        text,
+        line: 1,
+        column: 1,
+    };
+    skip_misc(&mut input)?;
+    let root = parse_element(&mut input, 0)?;
+    if root.name != "datablock" {
+        return Err(PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid PDBML root",
+            format!(
+                "The root element of a PDBML file should be a datablock but it is \"{}\".",
+                root.name
+            ),
+            Context::none(),
+        ));
+    }
+    let name = root
+        .attribute("datablockName")
+        .unwrap_or_default()
+        .to_string();
+
+    let mut items = Vec::new();
+    for category in root.children {
+        let category_name = category
+            .name
+            .strip_suffix("Category")
+            .unwrap_or(&category.name)
+            .to_string();
+        let mut header: Vec<String> = Vec::new();
+        let mut rows: Vec<Vec<(usize, Value)>> = Vec::new();
+        for row in category.children {
+            let mut values = Vec::new();
+            let attributes = row.attributes.into_iter().map(|(key, value)| {
+                let value = value_from_text(&value);
+                (key, value)
+            });
+            let children = row.children.into_iter().map(|item| {
+                let value = if item.attribute("nil") == Some("true") {
+                    Value::Unknown
+                } else {
+                    value_from_text(&item.text)
+                };
+                (item.name, value)
+            });
+            for (item, value) in attributes.chain(children) {
+                let tag = format!("{category_name}.{}", cif_item_name(&item));
+                let index = if let Some(index) = header.iter().position(|t| *t == tag) {
+                    index
+                } else {
+                    header.push(tag);
+                    header.len() - 1
+                };
+                values.push((index, value));
+            }
+            rows.push(values);
+        }
+        // The mmCIF parser only reads atoms from loops, so the atoms always are a loop
+        if rows.len() == 1 && category_name != "atom_site" {
+            for (index, value) in rows.remove(0) {
+                items.push(Item::DataItem(DataItem::Single(Single {
+                    name: header[index].clone(),
+                    content: value,
+                })));
+            }
+        } else if !rows.is_empty() {
+            let data = rows
+                .into_iter()
+                .map(|values| {
+                    let mut row: Vec<Value> = header.iter().map(|_| Value::Inapplicable).collect();
+                    for (index, value) in values {
+                        row[index] = value;
+                    }
+                    row
+                })
+                .collect();
+            items.push(Item::DataItem(DataItem::Loop(Loop { header, data })));
+        }
+    }
+    Ok(DataBlock { name, items })
+}
+
+/// An XML element, the namespace prefixes are removed from the names
+#[derive(Debug)]
+struct XmlElement {
+    /// The name
+    name: String,
+    /// The attributes, without the namespace declarations
+    attributes: Vec<(String, String)>,
+    /// The child elements
+    children: Vec<XmlElement>,
+    /// The text content, with the entities replaced
+    text: String,
+}
+
+impl XmlElement {
+    /// Get the value of the attribute with the given name
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Get the CIF value for the given text
+fn value_from_text(text: &str) -> Value {
+    let text = text.trim();
+    if text.is_empty() {
+        Value::Unknown
+    } else if text.contains(char::is_whitespace) {
+        Value::Text(text.to_string())
+    } else {
+        parse_numeric(text).unwrap_or_else(|| Value::Text(text.to_string()))
+    }
+}
+
+/// Get the CIF item name for the given PDBML item name, placing the indices of matrix and vector
+/// items in brackets (`Cartn_transf_matrix12` becomes `Cartn_transf_matrix[1][2]`)
+fn cif_item_name(name: &str) -> String {
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let indices = &name[base.len()..];
+    if (1..=2).contains(&indices.len())
+        && ["matrix", "vector", "origx", "aniso_U"]
+            .iter()
+            .any(|suffix| base.ends_with(suffix))
+    {
+        let mut name = base.to_string();
+        for index in indices.chars() {
+            name.push('[');
+            name.push(index);
+            name.push(']');
+        }
+        name
+    } else {
+        name.to_string()
+    }
+}
+
+/// Remove the namespace prefix from the given name
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Advance the position by the given number of bytes, keeping track of the line and column
+fn advance(input: &mut Position<'_>, length: usize) {
+    for c in input.text[..length].chars() {
+        if c == '\n' {
+            input.line += 1;
+            input.column = 1;
+        } else {
+            input.column += 1;
+        }
+    }
+    input.text = &input.text[length..];
+}
+
+/// Skip everything up to and including the given pattern
+fn skip_past(input: &mut Position<'_>, pattern: &str) -> Result<(), PDBError> {
+    if let Some(index) = input.text.find(pattern) {
+        advance(input, index + pattern.len());
+        Ok(())
+    } else {
+        Err(PDBError::new(
+            ErrorLevel::BreakingError,
+            "Unexpected end of file",
+            format!("The file ended while \"{pattern}\" was expected."),
+            Context::position(input),
+        ))
+    }
+}
+
+/// Skip whitespace, the XML declaration, processing instructions, comments, and doctype declarations
+fn skip_misc(input: &mut Position<'_>) -> Result<(), PDBError> {
+    loop {
+        let whitespace = input.text.len() - input.text.trim_start().len();
+        advance(input, whitespace);
+        if input.text.starts_with("<?") {
+            skip_past(input, "?>")?;
+        } else if input.text.starts_with("<!--") {
+            skip_past(input, "-->")?;
+        } else if input.text.starts_with("<!") {
+            skip_past(input, ">")?;
+        } else {
+            return Ok(());
+        }
+    }
+}
+
+/// The maximal nesting depth of XML elements, to prevent stack overflows on invalid files
+const MAX_DEPTH: usize = 64;
+
+/// Parse a single element, including all its children
+fn parse_element(input: &mut Position<'_>, depth: usize) -> Result<XmlElement, PDBError> {
+    let start = *input;
+    if depth > MAX_DEPTH {
+        return Err(PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid XML",
+            "The elements are nested too deeply.",
+            Context::position(&start),
+        ));
+    }
+    if !input.text.starts_with('<') {
+        return Err(PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid XML",
+            "An element was expected here.",
+            Context::position(&start),
+        ));
+    }
+    advance(input, 1);
+    let end = input
+        .text
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(input.text.len());
+    let full_name = &input.text[..end];
+    advance(input, end);
+    let mut element = XmlElement {
+        name: local_name(full_name).to_string(),
+        attributes: Vec::new(),
+        children: Vec::new(),
+        text: String::new(),
+    };
+
+    // The attributes
+    loop {
+        let whitespace = input.text.len() - input.text.trim_start().len();
+        advance(input, whitespace);
+        if input.text.starts_with("/>") {
+            advance(input, 2);
+            return Ok(element);
+        } else if input.text.starts_with('>') {
+            advance(input, 1);
+            break;
+        }
+        let (key, value) = parse_attribute(input)?;
+        if key != "xmlns" && !key.starts_with("xmlns:") {
+            element
+                .attributes
+                .push((local_name(&key).to_string(), value));
+        }
+    }
+
+    // The content
+    loop {
+        if input.text.starts_with("</") {
+            advance(input, 2);
+            let end = input.text.find('>').unwrap_or(input.text.len());
+            if input.text[..end].trim() != full_name {
+                return Err(PDBError::new(
+                    ErrorLevel::BreakingError,
+                    "Invalid XML",
+                    format!("The element \"{full_name}\" is closed by a different element."),
+                    Context::position(input),
+                ));
+            }
+            skip_past(input, ">")?;
+            return Ok(element);
+        } else if input.text.starts_with("<!--") {
+            skip_past(input, "-->")?;
+        } else if let Some(text) = input.text.strip_prefix("<![CDATA[") {
+            let end = text.find("]]>").unwrap_or(text.len());
+            element.text.push_str(&text[..end]);
+            skip_past(input, "]]>")?;
+        } else if input.text.starts_with('<') {
+            element.children.push(parse_element(input, depth + 1)?);
+        } else if input.text.is_empty() {
+            return Err(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Unexpected end of file",
+                format!("The file ended before the element \"{full_name}\" was closed."),
+                Context::position(&start),
+            ));
+        } else {
+            let end = input.text.find('<').unwrap_or(input.text.len());
+            let text = unescape(&input.text[..end], input)?;
+            element.text.push_str(&text);
+            advance(input, end);
+        }
+    }
+}
+
+/// Parse an attribute (`key="value"`)
+fn parse_attribute(input: &mut Position<'_>) -> Result<(String, String), PDBError> {
+    let error = |input: &Position<'_>| {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid XML",
+            "An attribute should be given as key=\"value\".",
+            Context::position(input),
+        )
+    };
+    let equals = input.text.find('=').ok_or_else(|| error(input))?;
+    let key = input.text[..equals].trim().to_string();
+    if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '>') {
+        return Err(error(input));
+    }
+    advance(input, equals + 1);
+    let whitespace = input.text.len() - input.text.trim_start().len();
+    advance(input, whitespace);
+    let quote = match input.text.chars().next() {
+        Some(quote @ ('"' | '\'')) => quote,
+        _ => return Err(error(input)),
+    };
+    advance(input, 1);
+    let end = input.text.find(quote).ok_or_else(|| error(input))?;
+    let value = unescape(&input.text[..end], input)?;
+    advance(input, end + 1);
+    Ok((key, value))
+}
+
+/// Replace the entities (`&lt;`, `&#65;`) in the given text
+fn unescape(text: &str, input: &Position<'_>) -> Result<String, PDBError> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(';').unwrap_or(0);
+        let entity = &rest[1..end.max(1)];
+        let character = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        if let (Some(character), true) = (character, end > 0) {
+            output.push(character);
+            rest = &rest[end + 1..];
+        } else {
+            return Err(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Invalid XML entity",
+                format!("The entity \"{}\" is not valid.", &rest[..end.max(1)]),
+                Context::position(input),
+            ));
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lex() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<PDBx:datablock datablockName="1ABC" xmlns:PDBx="http://pdbml.pdb.org/schema/pdbx-v50.xsd" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+   <!-- a comment -->
+   <PDBx:cellCategory>
+      <PDBx:cell entry_id="1ABC">
+         <PDBx:length_a>42.5</PDBx:length_a>
+         <PDBx:Z_PDB xsi:nil="true" />
+      </PDBx:cell>
+   </PDBx:cellCategory>
+   <PDBx:atom_sitesCategory>
+      <PDBx:atom_sites entry_id="1ABC">
+         <PDBx:Cartn_transf_matrix12>0.5</PDBx:Cartn_transf_matrix12>
+      </PDBx:atom_sites>
+   </PDBx:atom_sitesCategory>
+   <PDBx:structCategory>
+      <PDBx:struct entry_id="1ABC"><PDBx:title>A &amp; B &lt;&#67;&gt;</PDBx:title></PDBx:struct>
+   </PDBx:structCategory>
+   <PDBx:entityCategory>
+      <PDBx:entity id="1"><PDBx:type>polymer</PDBx:type></PDBx:entity>
+      <PDBx:entity id="2"><PDBx:pdbx_description>water</PDBx:pdbx_description></PDBx:entity>
+   </PDBx:entityCategory>
+</PDBx:datablock>
+"#;
+        let block = lex_pdbml(text).unwrap();
+        assert_eq!(block.name, "1ABC");
+        let single = |name: &str| {
+            block.items.iter().find_map(|item| match item {
+                Item::DataItem(DataItem::Single(single)) if single.name == name => {
+                    Some(&single.content)
+                }
+                _ => None,
+            })
+        };
+        assert_eq!(single("cell.entry_id"), Some(&Value::Text("1ABC".into())));
+        assert_eq!(single("cell.length_a"), Some(&Value::Numeric(42.5)));
+        assert_eq!(single("cell.Z_PDB"), Some(&Value::Unknown));
+        assert_eq!(
+            single("atom_sites.Cartn_transf_matrix[1][2]"),
+            Some(&Value::Numeric(0.5))
+        );
+        assert_eq!(
+            single("struct.title"),
+            Some(&Value::Text("A & B <C>".into()))
+        );
+        let entity = block
+            .items
+            .iter()
+            .find_map(|item| match item {
+                Item::DataItem(DataItem::Loop(entity)) => Some(entity),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            entity.header,
+            ["entity.id", "entity.type", "entity.pdbx_description"]
+        );
+        assert_eq!(
+            entity.data[1],
+            [
+                Value::Numeric(2.0),
+                Value::Inapplicable,
+                Value::Text("water".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(lex_pdbml("<PDBx:datablock><PDBx:cellCategory></PDBx:datablock>").is_err());
+        assert!(lex_pdbml("<PDBx:datablock>&unknown;</PDBx:datablock>").is_err());
+        assert!(lex_pdbml("<data_block/>").is_err());
+        assert!(lex_pdbml("<PDBx:datablock datablockName=1ABC/>").is_err());
+        // Deeply nested elements give an error instead of overflowing the stack
+        let nested = format!(
+            "<PDBx:datablock>{}{}</PDBx:datablock>",
+            "<a>".repeat(100_000),
+            "</a>".repeat(100_000)
+        );
+        let error = lex_pdbml(&nested).unwrap_err();
+        assert_eq!(
+            error.long_description(),
+            "The elements are nested too deeply."
+        );
+    }
+
+    #[test]
+    fn item_names() {
+        assert_eq!(cif_item_name("origx_vector3"), "origx_vector[3]");
+        assert_eq!(cif_item_name("aniso_U23"), "aniso_U[2][3]");
+        assert_eq!(cif_item_name("matrix11"), "matrix[1][1]");
+        assert_eq!(cif_item_name("label_seq_id"), "label_seq_id");
+        assert_eq!(cif_item_name("Cartn_x"), "Cartn_x");
+    }
+}
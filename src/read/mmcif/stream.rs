@@ -0,0 +1,275 @@
+use std::io::BufRead;
+
+use super::lexer::lex_values;
+use super::lexitem::Value;
+use super::parser::{parse_atom_row, parse_atom_row_filter, AtomSiteColumns};
+use crate::error::*;
+use crate::AtomRecord;
+use crate::ReadOptions;
+
+/// The state of the search through the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Looking for the start of a loop
+    Searching,
+    /// Reading the header of a loop
+    Header,
+    /// Reading the rows of the atomic data loop
+    Data,
+    /// The atomic data loop is read completely
+    Done,
+}
+
+/// Read the atoms of a mmCIF file one by one, see [`crate::AtomStream`].
+/// The file is read line by line, only the atomic data loop (`_atom_site`) is lexed.
+pub(crate) struct MmcifAtomStream<R> {
+    /// The input
+    input: R,
+    /// The options to read with
+    options: ReadOptions,
+    /// The buffer for the current line
+    line: String,
+    /// The current line number (1 based)
+    linenumber: usize,
+    /// The state of the reader
+    state: State,
+    /// The header of the current loop
+    header: Vec<String>,
+    /// The columns of the atomic data loop, once found
+    columns: Option<AtomSiteColumns>,
+    /// The values read but not yet parsed into atoms
+    values: Vec<Value>,
+    /// The index of the next row in the atomic data loop
+    row_index: usize,
+    /// The model number of the first row, used when only the first model is read
+    first_model_number: Option<usize>,
+    /// The number of residues seen, used for atoms without a residue number
+    residue_count: usize,
+    /// The identifier of the last residue seen
+    last_residue: Option<(usize, String, Option<isize>, Option<String>)>,
+}
+
+impl<R: BufRead> MmcifAtomStream<R> {
+    /// Create a new stream over the given input
+    pub(crate) fn new(input: R, options: &ReadOptions) -> Self {
+        MmcifAtomStream {
+            input,
+            options: options.clone(),
+            line: String::new(),
+            linenumber: 0,
+            state: State::Searching,
+            header: Vec::new(),
+            columns: None,
+            values: Vec::new(),
+            row_index: 0,
+            first_model_number: None,
+            residue_count: 0,
+            last_residue: None,
+        }
+    }
+
+    /// Read the next line and append it to the line buffer, returns false at the end of the input
+    fn read_line(&mut self) -> Result<bool, PDBError> {
+        self.linenumber += 1;
+        match self.input.read_line(&mut self.line) {
+            Ok(0) => Ok(false),
+            Ok(_) => Ok(true),
+            Err(_) => Err(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could read line",
+                format!(
+                    "Could not read line {} while parsing the input file.",
+                    self.linenumber
+                ),
+                Context::None,
+            )),
+        }
+    }
+
+    /// End the stream with the given error
+    fn fail(&mut self, error: PDBError) -> Option<Result<AtomRecord, PDBError>> {
+        self.state = State::Done;
+        Some(Err(error))
+    }
+
+    /// Read lines until the next atom is complete
+    pub(crate) fn next(&mut self) -> Option<Result<AtomRecord, PDBError>> {
+        loop {
+            if self.state == State::Done {
+                return None;
+            }
+            if let Some(columns) = &self.columns {
+                let width = self.header.len();
+                if self.values.len() >= width {
+                    let row: Vec<Value> = self.values.drain(..width).collect();
+                    let columns = columns.clone();
+                    match self.parse_row(&columns, &row) {
+                        Some(result) => return Some(result),
+                        None => continue,
+                    }
+                }
+            }
+
+            self.line.clear();
+            match self.read_line() {
+                Ok(true) => (),
+                Ok(false) => {
+                    if self.state == State::Data && !self.values.is_empty() {
+                        return self.fail(incomplete_loop_error(self.linenumber));
+                    }
+                    self.state = State::Done;
+                    return None;
+                }
+                Err(e) => return self.fail(e),
+            }
+            let trimmed = self.line.trim().to_string();
+            let trimmed = trimmed.as_str();
+            match self.state {
+                State::Searching => {
+                    if starts_with_keyword(trimmed, "loop_") {
+                        self.header.clear();
+                        self.state = State::Header;
+                    }
+                }
+                State::Header => {
+                    if let Some(tag) = trimmed.strip_prefix('_') {
+                        self.header
+                            .push(tag.split_whitespace().next().unwrap_or("").to_string());
+                    } else if trimmed.is_empty() || trimmed.starts_with('#') {
+                        continue;
+                    } else if self.header.iter().any(|h| h.starts_with("atom_site.")) {
+                        match AtomSiteColumns::new(&self.header) {
+                            Ok(columns) => self.columns = Some(columns),
+                            Err(errors) => {
+                                if let Some(e) = errors.into_iter().next() {
+                                    return self.fail(e);
+                                }
+                            }
+                        }
+                        self.state = State::Data;
+                        if let Err(e) = self.lex_data_line() {
+                            return self.fail(e);
+                        }
+                    } else {
+                        self.state = if starts_with_keyword(trimmed, "loop_") {
+                            self.header.clear();
+                            State::Header
+                        } else {
+                            State::Searching
+                        };
+                    }
+                }
+                State::Data => {
+                    if trimmed.starts_with('_')
+                        || ["data_", "global_", "loop_", "save_", "stop_"]
+                            .iter()
+                            .any(|keyword| starts_with_keyword(trimmed, keyword))
+                    {
+                        if !self.values.is_empty() {
+                            return self.fail(incomplete_loop_error(self.linenumber));
+                        }
+                        self.state = State::Done;
+                    } else if let Err(e) = self.lex_data_line() {
+                        return self.fail(e);
+                    }
+                }
+                State::Done => (),
+            }
+        }
+    }
+
+    /// Lex the values on the current line, if it starts a multiline string the full string is read
+    fn lex_data_line(&mut self) -> Result<(), PDBError> {
+        let start = self.linenumber;
+        if self.line.starts_with(';') {
+            // Read until the closing semicolon of the multiline string
+            loop {
+                let length = self.line.len();
+                if !self.read_line()? {
+                    break;
+                }
+                if self.line[length..].starts_with(';') {
+                    break;
+                }
+            }
+        }
+        self.values.extend(lex_values(&self.line, start)?);
+        Ok(())
+    }
+
+    /// Parse a single row of the atomic data loop. Returns `None` if the row should be skipped.
+    fn parse_row(
+        &mut self,
+        columns: &AtomSiteColumns,
+        row: &[Value],
+    ) -> Option<Result<AtomRecord, PDBError>> {
+        let context = Context::show(format!("Main atomic data loop row: {}", self.row_index));
+        self.row_index += 1;
+        let mut errors = Vec::new();
+
+        let filtered = parse_atom_row_filter(columns, row, &context, &self.options, &mut errors);
+        if let Some(e) = errors.iter().find(|e| e.fails(self.options.level)) {
+            return self.fail(e.clone());
+        }
+        let (element, model_number) = filtered?;
+        if self.options.only_first_model {
+            match self.first_model_number {
+                None => self.first_model_number = Some(model_number),
+                Some(first) if first != model_number => {
+                    self.state = State::Done;
+                    return None;
+                }
+                Some(_) => (),
+            }
+        }
+
+        let parsed = parse_atom_row(columns, row, &context, (element, model_number), &mut errors);
+        if let Some(e) = errors.into_iter().find(|e| e.fails(self.options.level)) {
+            return self.fail(e);
+        }
+
+        let residue = (
+            parsed.model_number,
+            parsed.chain_name.clone(),
+            parsed.residue_number,
+            parsed.insertion_code.clone(),
+        );
+        if self.last_residue.as_ref() != Some(&residue) {
+            self.residue_count += 1;
+            self.last_residue = Some(residue);
+        }
+        #[allow(clippy::cast_possible_wrap)]
+        let residue_number = parsed
+            .residue_number
+            .unwrap_or(self.residue_count as isize - 1);
+
+        parsed.atom.map(|atom| {
+            Ok(AtomRecord::new(
+                parsed.model_number,
+                parsed.chain_name,
+                (residue_number, parsed.insertion_code),
+                (parsed.residue_name, parsed.alt_loc),
+                atom,
+            ))
+        })
+    }
+}
+
+/// Check if the given text starts with the given (lowercase) keyword, case insensitive
+fn starts_with_keyword(text: &str, keyword: &str) -> bool {
+    text.len() >= keyword.len()
+        && text.is_char_boundary(keyword.len())
+        && text[..keyword.len()].eq_ignore_ascii_case(keyword)
+}
+
+/// The error for a loop that ends halfway through a row
+fn incomplete_loop_error(linenumber: usize) -> PDBError {
+    PDBError::new(
+        ErrorLevel::BreakingError,
+        "Loop has incorrect number of data items",
+        format!(
+            "The atomic data loop ended at line {linenumber} but the last row is not complete."
+        ),
+        Context::None,
+    )
+}
@@ -0,0 +1,775 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use crate::error::*;
+use crate::structs::*;
+use crate::validate::*;
+use crate::ReadOptions;
+
+use super::general::ReadResult;
+use super::msgpack::MsgPack;
+use super::pqr::guess_element;
+
+/// Parse the given MMTF file into a PDB struct with [`ReadOptions`].
+pub(crate) fn open_mmtf_with_options(
+    filename: impl AsRef<str>,
+    options: &ReadOptions,
+) -> ReadResult {
+    let filename = filename.as_ref();
+    let file = if let Ok(f) = File::open(filename) {
+        f
+    } else {
+        return Err(vec![PDBError::new(ErrorLevel::BreakingError, "Could not open file", "Could not open the specified file, make sure the path is correct, you have permission, and that it is not open in another program.", Context::show(filename))]);
+    };
+    open_mmtf_raw_with_options(BufReader::new(file), Context::show(filename), options)
+}
+
+/// Parse the input stream in the MMTF format into a PDB struct with [`ReadOptions`].
+///
+/// An MMTF file is a MessagePack encoded map, in which the lists per atom, group, and chain are
+/// binary arrays compressed with the codecs from the MMTF specification (version 1.0). Every
+/// model is read as a separate [`Model`]. The chains are named after their author chain name if
+/// present. The atoms in a group are defined by the group type, which also gives the bonds within
+/// the group. The bonds between groups are read from the global bond list. Groups with a chemical
+/// component type that is not a linking type (like `NON-POLYMER`) are read as hetero atoms.
+pub(crate) fn open_mmtf_raw_with_options<T>(
+    mut input: BufReader<T>,
+    context: Context,
+    options: &ReadOptions,
+) -> ReadResult
+where
+    T: std::io::Read,
+{
+    let mut bytes = Vec::new();
+    if input.read_to_end(&mut bytes).is_err() {
+        return Err(vec![PDBError::new(
+            ErrorLevel::BreakingError,
+            "Buffer could not be read",
+            "The buffer provided to `open_raw` could not be read to end.",
+            context,
+        )]);
+    }
+    let file = MsgPack::parse(&bytes).map_err(|e| vec![e])?;
+    let invalid = |message: String| {
+        vec![PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid MMTF",
+            message,
+            context.clone(),
+        )]
+    };
+    let required = |name: &str, list: Option<List>| {
+        list.ok_or_else(|| invalid(format!("The required field \"{name}\" is missing.")))
+    };
+    let field = |name: &str| list(&file, name).map_err(&invalid);
+
+    let group_types = file
+        .get("groupList")
+        .and_then(MsgPack::as_array)
+        .ok_or_else(|| invalid("The required field \"groupList\" is missing.".to_string()))?
+        .iter()
+        .map(GroupType::new)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(&invalid)?;
+    let x = required("xCoordList", field("xCoordList")?)?.floats();
+    let y = required("yCoordList", field("yCoordList")?)?.floats();
+    let z = required("zCoordList", field("zCoordList")?)?.floats();
+    let b_factors = field("bFactorList")?.map(List::floats);
+    let occupancies = field("occupancyList")?.map(List::floats);
+    let atom_ids = field("atomIdList")?.map(List::ints);
+    let alt_locs = field("altLocList")?.map(List::strings);
+    let group_ids = required("groupIdList", field("groupIdList")?)?.ints();
+    let group_type_list = required("groupTypeList", field("groupTypeList")?)?.ints();
+    let insertion_codes = field("insCodeList")?.map(List::strings);
+    let chain_ids = required("chainIdList", field("chainIdList")?)?.strings();
+    let chain_names = field("chainNameList")?.map(List::strings);
+    let groups_per_chain = required("groupsPerChain", field("groupsPerChain")?)?.ints();
+    let chains_per_model = required("chainsPerModel", field("chainsPerModel")?)?.ints();
+    let bond_atoms = field("bondAtomList")?.map_or_else(Vec::new, List::ints);
+    let bond_orders = field("bondOrderList")?.map(List::ints);
+
+    // Check the lengths of all lists, so the lists can be indexed freely when building the PDB
+    let group_types_used = group_type_list
+        .iter()
+        .map(|index| {
+            usize::try_from(*index)
+                .ok()
+                .and_then(|index| group_types.get(index))
+                .ok_or_else(|| invalid(format!("The group type {index} does not exist.")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let atom_count: usize = group_types_used.iter().map(|g| g.atom_names.len()).sum();
+    let chain_count = sum(&chains_per_model).map_err(&invalid)?;
+    let group_count = sum(&groups_per_chain).map_err(&invalid)?;
+    let lengths = [
+        ("xCoordList", Some(x.len()), atom_count),
+        ("yCoordList", Some(y.len()), atom_count),
+        ("zCoordList", Some(z.len()), atom_count),
+        ("bFactorList", b_factors.as_ref().map(Vec::len), atom_count),
+        (
+            "occupancyList",
+            occupancies.as_ref().map(Vec::len),
+            atom_count,
+        ),
+        ("atomIdList", atom_ids.as_ref().map(Vec::len), atom_count),
+        ("altLocList", alt_locs.as_ref().map(Vec::len), atom_count),
+        ("groupIdList", Some(group_ids.len()), group_count),
+        ("groupTypeList", Some(group_type_list.len()), group_count),
+        (
+            "insCodeList",
+            insertion_codes.as_ref().map(Vec::len),
+            group_count,
+        ),
+        ("groupsPerChain", Some(groups_per_chain.len()), chain_count),
+        ("chainIdList", Some(chain_ids.len()), chain_count),
+        (
+            "chainNameList",
+            chain_names.as_ref().map(Vec::len),
+            chain_count,
+        ),
+    ];
+    for (name, length, expected) in lengths {
+        if let Some(length) = length.filter(|length| *length != expected) {
+            return Err(invalid(format!(
+                "The field \"{name}\" has {length} items while {expected} items were expected."
+            )));
+        }
+    }
+
+    let mut errors = Vec::new();
+    let mut pdb = PDB::new();
+    pdb.identifier = file
+        .get("structureId")
+        .and_then(MsgPack::as_str)
+        .map(str::to_string);
+    if let Some(cell) = file.get("unitCell").and_then(MsgPack::as_array) {
+        if let [a, b, c, alpha, beta, gamma] = cell
+            .iter()
+            .filter_map(MsgPack::as_float)
+            .collect::<Vec<_>>()[..]
+        {
+            pdb.unit_cell = Some(UnitCell::new(a, b, c, alpha, beta, gamma));
+        }
+    }
+    if let Some(space_group) = file.get("spaceGroup").and_then(MsgPack::as_str) {
+        pdb.symmetry = Symmetry::new(space_group);
+        if pdb.symmetry.is_none() {
+            errors.push(PDBError::new(
+                ErrorLevel::InvalidatingError,
+                "Space group does not exist",
+                format!("The space group \"{space_group}\" could not be recognised."),
+                context.clone(),
+            ));
+        }
+    }
+
+    let mut counters: Vec<Option<usize>> = Vec::with_capacity(atom_count);
+    let mut bonds = Vec::new();
+    let mut chain_index = 0;
+    let mut group_index = 0;
+    for (model_index, model_chains) in chains_per_model.iter().enumerate() {
+        let mut model = Model::new(model_index + 1);
+        for _ in 0..*model_chains {
+            let chain_name = chain_names.as_ref().unwrap_or(&chain_ids)[chain_index].as_str();
+            for _ in 0..groups_per_chain[chain_index] {
+                let group = group_types_used[group_index];
+                let residue_id = (
+                    isize::try_from(group_ids[group_index]).unwrap_or_default(),
+                    insertion_codes
+                        .as_ref()
+                        .map(|codes| codes[group_index].as_str())
+                        .filter(|code| !code.is_empty()),
+                );
+                let first_atom = counters.len();
+                for (index, name) in group.atom_names.iter().enumerate() {
+                    let atom_index = counters.len();
+                    let element = if group.elements[index].is_empty() {
+                        guess_element(name, &group.name, group.hetero)
+                    } else {
+                        group.elements[index].clone()
+                    };
+                    let atom = Atom::new(
+                        group.hetero,
+                        atom_ids.as_ref().map_or(atom_index + 1, |ids| {
+                            usize::try_from(ids[atom_index]).unwrap_or_default()
+                        }),
+                        name,
+                        x[atom_index],
+                        y[atom_index],
+                        z[atom_index],
+                        occupancies.as_ref().map_or(1.0, |o| o[atom_index]),
+                        b_factors.as_ref().map_or(0.0, |b| b[atom_index]),
+                        element,
+                        group.charges[index],
+                    );
+                    let alt_loc = alt_locs
+                        .as_ref()
+                        .map(|alt_locs| alt_locs[atom_index].as_str())
+                        .filter(|alt_loc| !alt_loc.is_empty());
+                    let valid_names = prepare_identifier(chain_name).is_some()
+                        && prepare_identifier(&group.name).is_some()
+                        && alt_loc.map_or(true, valid_identifier)
+                        && residue_id.1.map_or(true, valid_identifier);
+                    match atom.filter(|_| valid_names) {
+                        Some(atom)
+                            if !(options.discard_hydrogens
+                                && atom.element() == Some(&Element::H)) =>
+                        {
+                            counters.push(Some(atom.counter()));
+                            model.add_atom(
+                                atom,
+                                chain_name,
+                                residue_id,
+                                (group.name.as_str(), alt_loc),
+                            );
+                        }
+                        Some(_) => counters.push(None),
+                        None => {
+                            counters.push(None);
+                            errors.push(PDBError::new(
+                                ErrorLevel::InvalidatingError,
+                                "Invalid atom",
+                                format!("The atom \"{name}\" (atom {}) in group \"{}\", or its chain, group, alternative location, or insertion code, contains invalid characters or values.", atom_index + 1, group.name),
+                                context.clone(),
+                            ));
+                        }
+                    }
+                }
+                bonds.extend(
+                    group
+                        .bonds
+                        .iter()
+                        .map(|(a, b, bond)| (first_atom + a, first_atom + b, *bond)),
+                );
+                group_index += 1;
+            }
+            chain_index += 1;
+        }
+        pdb.add_model(model);
+        if options.only_first_model {
+            break;
+        }
+    }
+
+    for (index, pair) in bond_atoms.chunks(2).enumerate() {
+        if let [a, b] = pair {
+            let order = bond_orders.as_ref().and_then(|o| o.get(index).copied());
+            if let (Ok(a), Ok(b)) = (usize::try_from(*a), usize::try_from(*b)) {
+                bonds.push((a, b, bond_from_order(order.unwrap_or(1))));
+            }
+        }
+    }
+    for (a, b, bond) in bonds {
+        // Atoms that are not read (discarded hydrogens or later models) do not have a counter
+        if let (Some(Some(a)), Some(Some(b))) = (counters.get(a), counters.get(b)) {
+            pdb.add_bond_counters(*a, *b, bond);
+        }
+    }
+
+    errors.extend(validate(&pdb));
+
+    if errors.iter().any(|e| e.fails(options.level)) {
+        Err(errors)
+    } else {
+        Ok((pdb, errors))
+    }
+}
+
+/// The definition of a group (residue) in an MMTF file
+struct GroupType {
+    /// The name of the group
+    name: String,
+    /// The names of the atoms
+    atom_names: Vec<String>,
+    /// The elements of the atoms
+    elements: Vec<String>,
+    /// The formal charges of the atoms
+    charges: Vec<isize>,
+    /// The bonds between the atoms, as indices into the atoms of the group
+    bonds: Vec<(usize, usize, Bond)>,
+    /// If the group is a hetero group, meaning that it is not a linking type
+    hetero: bool,
+}
+
+impl GroupType {
+    /// Read a group type from its MessagePack representation
+    fn new(value: &MsgPack<'_>) -> Result<Self, String> {
+        let array = |name: &str| {
+            value
+                .get(name)
+                .and_then(MsgPack::as_array)
+                .unwrap_or_default()
+                .iter()
+        };
+        let atom_names: Vec<String> = array("atomNameList")
+            .map(|n| n.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or("The atom names of a group type are not valid.")?;
+        let elements: Vec<String> = array("elementList")
+            .map(|n| n.as_str().map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or("The elements of a group type are not valid.")?;
+        let charges: Vec<isize> = array("formalChargeList")
+            .map(|c| c.as_int().and_then(|c| isize::try_from(c).ok()))
+            .collect::<Option<_>>()
+            .ok_or("The formal charges of a group type are not valid.")?;
+        let bond_atoms: Vec<usize> = array("bondAtomList")
+            .map(|a| a.as_int().and_then(|a| usize::try_from(a).ok()))
+            .collect::<Option<_>>()
+            .ok_or("The bonds of a group type are not valid.")?;
+        let bond_orders: Vec<i64> = array("bondOrderList").filter_map(MsgPack::as_int).collect();
+        let name = value
+            .get("groupName")
+            .and_then(MsgPack::as_str)
+            .unwrap_or_default()
+            .to_string();
+        if elements.len() != atom_names.len() || charges.len() != atom_names.len() {
+            return Err(format!(
+                "The group type \"{name}\" does not have an element and charge for every atom."
+            ));
+        }
+        let bonds = bond_atoms
+            .chunks(2)
+            .enumerate()
+            .filter_map(|(index, pair)| match pair {
+                [a, b] if *a < atom_names.len() && *b < atom_names.len() => Some((
+                    *a,
+                    *b,
+                    bond_from_order(bond_orders.get(index).copied().unwrap_or(1)),
+                )),
+                _ => None,
+            })
+            .collect();
+        let hetero = !value
+            .get("chemCompType")
+            .and_then(MsgPack::as_str)
+            .unwrap_or_default()
+            .to_ascii_uppercase()
+            .contains("LINKING");
+        Ok(Self {
+            name,
+            atom_names,
+            elements,
+            charges,
+            bonds,
+            hetero,
+        })
+    }
+}
+
+/// Get the bond for the given bond order, unknown bond orders are read as a covalent bond
+const fn bond_from_order(order: i64) -> Bond {
+    match order {
+        2 => Bond::Double,
+        3 => Bond::Triple,
+        _ => Bond::Covalent,
+    }
+}
+
+/// Sum the given counts, which should all be positive
+fn sum(counts: &[i64]) -> Result<usize, String> {
+    counts.iter().try_fold(0_usize, |acc, count| {
+        usize::try_from(*count)
+            .map(|count| acc + count)
+            .map_err(|_| format!("The count {count} is not valid."))
+    })
+}
+
+/// A decoded list from an MMTF file
+#[derive(Debug, PartialEq)]
+enum List {
+    /// A list of integers
+    Int(Vec<i64>),
+    /// A list of floating point numbers
+    Float(Vec<f64>),
+    /// A list of strings, in which missing characters are empty strings
+    Str(Vec<String>),
+}
+
+impl List {
+    /// Get the values as integers, floating point numbers are truncated and strings are zero
+    #[allow(clippy::cast_possible_truncation)]
+    fn ints(self) -> Vec<i64> {
+        match self {
+            List::Int(values) => values,
+            List::Float(values) => values.into_iter().map(|v| v as i64).collect(),
+            List::Str(values) => vec![0; values.len()],
+        }
+    }
+
+    /// Get the values as floating point numbers, strings are zero
+    fn floats(self) -> Vec<f64> {
+        match self {
+            #[allow(clippy::cast_precision_loss)]
+            List::Int(values) => values.into_iter().map(|v| v as f64).collect(),
+            List::Float(values) => values,
+            List::Str(values) => vec![0.0; values.len()],
+        }
+    }
+
+    /// Get the values as strings
+    fn strings(self) -> Vec<String> {
+        match self {
+            List::Int(values) => values.into_iter().map(|v| v.to_string()).collect(),
+            List::Float(values) => values.into_iter().map(|v| v.to_string()).collect(),
+            List::Str(values) => values,
+        }
+    }
+}
+
+/// Get the list with the given name from the file, decoding it if it is a binary array
+fn list(file: &MsgPack<'_>, name: &str) -> Result<Option<List>, String> {
+    match file.get(name) {
+        None | Some(MsgPack::Nil) => Ok(None),
+        Some(MsgPack::Bin(bytes)) => decode_binary(bytes)
+            .map(Some)
+            .map_err(|e| format!("{e} In field \"{name}\".")),
+        Some(MsgPack::Array(items)) => {
+            if let Some(ints) = items.iter().map(MsgPack::as_int).collect() {
+                Ok(Some(List::Int(ints)))
+            } else if let Some(floats) = items.iter().map(MsgPack::as_float).collect() {
+                Ok(Some(List::Float(floats)))
+            } else if let Some(strings) = items
+                .iter()
+                .map(|s| s.as_str().map(str::to_string))
+                .collect()
+            {
+                Ok(Some(List::Str(strings)))
+            } else {
+                Err(format!("The field \"{name}\" contains mixed values."))
+            }
+        }
+        Some(_) => Err(format!("The field \"{name}\" is not a list.")),
+    }
+}
+
+/// Decode a binary array, which starts with a header with the codec, the length of the decoded
+/// list, and a parameter, all as big endian 32 bit integers.
+fn decode_binary(bytes: &[u8]) -> Result<List, String> {
+    if bytes.len() < 12 {
+        return Err("The binary array is too short.".to_string());
+    }
+    let header = |index: usize| {
+        let mut value = [0; 4];
+        value.copy_from_slice(&bytes[index * 4..index * 4 + 4]);
+        i32::from_be_bytes(value)
+    };
+    let (codec, length, parameter) = (header(0), header(1), header(2));
+    let length = usize::try_from(length).map_err(|_| "The length is negative.".to_string())?;
+    let data = &bytes[12..];
+    let divide = |values: Vec<i64>| {
+        #[allow(clippy::cast_precision_loss)]
+        List::Float(
+            values
+                .into_iter()
+                .map(|v| v as f64 / f64::from(parameter))
+                .collect(),
+        )
+    };
+    let list = match codec {
+        1 => List::Float(convert(data, |b| f64::from(f32::from_be_bytes(b)))?),
+        2 => List::Int(convert(data, |b| i64::from(i8::from_be_bytes(b)))?),
+        3 => List::Int(convert(data, |b| i64::from(i16::from_be_bytes(b)))?),
+        4 => List::Int(int32(data)?),
+        5 => {
+            let size = usize::try_from(parameter)
+                .ok()
+                .filter(|size| *size > 0)
+                .ok_or("The string length is not valid.")?;
+            List::Str(
+                data.chunks(size)
+                    .map(|chunk| {
+                        String::from_utf8_lossy(chunk)
+                            .trim_end_matches('\0')
+                            .to_string()
+                    })
+                    .collect(),
+            )
+        }
+        6 => List::Str(
+            run_length(int32(data)?, length)?
+                .into_iter()
+                .map(|c| {
+                    u32::try_from(c)
+                        .ok()
+                        .filter(|c| *c != 0)
+                        .and_then(char::from_u32)
+                        .map_or_else(String::new, String::from)
+                })
+                .collect(),
+        ),
+        7 => List::Int(run_length(int32(data)?, length)?),
+        8 => List::Int(delta(run_length(int32(data)?, length)?)),
+        9 => divide(run_length(int32(data)?, length)?),
+        10 => divide(delta(recursive_index(
+            convert(data, |b| i64::from(i16::from_be_bytes(b)))?,
+            i64::from(i16::MAX),
+            i64::from(i16::MIN),
+        ))),
+        11 => divide(convert(data, |b| i64::from(i16::from_be_bytes(b)))?),
+        12 => divide(recursive_index(
+            convert(data, |b| i64::from(i16::from_be_bytes(b)))?,
+            i64::from(i16::MAX),
+            i64::from(i16::MIN),
+        )),
+        13 => divide(convert(data, |b| i64::from(i8::from_be_bytes(b)))?),
+        14 => List::Int(recursive_index(
+            convert(data, |b| i64::from(i16::from_be_bytes(b)))?,
+            i64::from(i16::MAX),
+            i64::from(i16::MIN),
+        )),
+        15 => List::Int(recursive_index(
+            convert(data, |b| i64::from(i8::from_be_bytes(b)))?,
+            i64::from(i8::MAX),
+            i64::from(i8::MIN),
+        )),
+        _ => return Err(format!("The codec {codec} is not known.")),
+    };
+    let decoded_length = match &list {
+        List::Int(values) => values.len(),
+        List::Float(values) => values.len(),
+        List::Str(values) => values.len(),
+    };
+    if decoded_length == length {
+        Ok(list)
+    } else {
+        Err(format!(
+            "The binary array decoded to {decoded_length} items while {length} items were expected."
+        ))
+    }
+}
+
+/// Read the big endian bytes into an array using the given conversion
+fn convert<T, const N: usize>(bytes: &[u8], f: impl Fn([u8; N]) -> T) -> Result<Vec<T>, String> {
+    if bytes.len() % N != 0 {
+        return Err("The length of the binary array does not fit its type.".to_string());
+    }
+    Ok(bytes
+        .chunks_exact(N)
+        .map(|chunk| {
+            let mut array = [0; N];
+            array.copy_from_slice(chunk);
+            f(array)
+        })
+        .collect())
+}
+
+/// Read big endian 32 bit integers
+fn int32(bytes: &[u8]) -> Result<Vec<i64>, String> {
+    convert(bytes, |b| i64::from(i32::from_be_bytes(b)))
+}
+
+/// Undo run length encoding, the values are pairs of a value and the number of repeats. It fails
+/// if this would give more than the expected number of values.
+fn run_length(values: Vec<i64>, length: usize) -> Result<Vec<i64>, String> {
+    if values.len() % 2 != 0 {
+        return Err("The run length encoded array has an odd length.".to_string());
+    }
+    let mut output = Vec::new();
+    for pair in values.chunks_exact(2) {
+        let count = usize::try_from(pair[1]).map_err(|_| "A run length is negative.")?;
+        output
+            .len()
+            .checked_add(count)
+            .filter(|total| *total <= length)
+            .ok_or_else(|| {
+                format!("The run length encoded array decodes to more than {length} items.")
+            })?;
+        output.extend(std::iter::repeat(pair[0]).take(count));
+    }
+    Ok(output)
+}
+
+/// Undo delta encoding, every value is the difference with the previous value
+fn delta(mut values: Vec<i64>) -> Vec<i64> {
+    let mut current = 0;
+    for value in &mut values {
+        current += *value;
+        *value = current;
+    }
+    values
+}
+
+/// Undo recursive index encoding, values equal to the given limits are added to the next value
+fn recursive_index(values: Vec<i64>, upper: i64, lower: i64) -> Vec<i64> {
+    let mut output = Vec::with_capacity(values.len());
+    let mut current = 0;
+    for value in values {
+        current += value;
+        if value != upper && value != lower {
+            output.push(current);
+            current = 0;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{Format, StrictnessLevel};
+
+    /// Read the given MMTF file and the PDB file it was converted from
+    fn open_both(name: &str) -> (PDB, PDB) {
+        let options = ReadOptions::new().set_level(StrictnessLevel::Loose).clone();
+        let (mmtf, errors) = options.read(format!("example-pdbs/{name}.mmtf")).unwrap();
+        assert!(errors
+            .iter()
+            .all(|e| e.level() != ErrorLevel::BreakingError));
+        let (pdb, _) = options.read(format!("example-pdbs/{name}.pdb")).unwrap();
+        (mmtf, pdb)
+    }
+
+    /// Check that the atoms and their place in the hierarchy are equal
+    fn assert_same_atoms(mmtf: &PDB, pdb: &PDB) {
+        assert_eq!(mmtf.model_count(), pdb.model_count());
+        assert_eq!(mmtf.chain_count(), pdb.chain_count());
+        assert_eq!(mmtf.residue_count(), pdb.residue_count());
+        assert_eq!(mmtf.atom_count(), pdb.atom_count());
+        for (a, b) in mmtf.atoms_with_hierarchy().zip(pdb.atoms_with_hierarchy()) {
+            assert_eq!(a.chain().id(), b.chain().id());
+            assert_eq!(a.residue().id(), b.residue().id());
+            assert_eq!(a.conformer().id(), b.conformer().id());
+            assert_eq!(a.atom().serial_number(), b.atom().serial_number());
+            assert_eq!(a.atom().name(), b.atom().name());
+            assert_eq!(a.atom().element(), b.atom().element());
+            assert_eq!(a.atom().hetero(), b.atom().hetero());
+            assert!(a.atom().distance(b.atom()) < 1e-6);
+            assert!((a.atom().b_factor() - b.atom().b_factor()).abs() < 1e-6);
+            assert!((a.atom().occupancy() - b.atom().occupancy()).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn read_1ubq() {
+        let (mmtf, pdb) = open_both("1ubq");
+        assert_same_atoms(&mmtf, &pdb);
+        assert_eq!(mmtf.identifier.as_deref(), Some("1UBQ"));
+        assert_eq!(mmtf.symmetry, pdb.symmetry);
+        let cell = mmtf.unit_cell.as_ref().unwrap();
+        assert!((cell.a() - 50.84).abs() < 1e-4);
+        assert!((cell.gamma() - 90.0).abs() < 1e-4);
+
+        // Backbone bonds from the group types and peptide bonds from the global bond list
+        let bonds: Vec<_> = mmtf.bonds().collect();
+        assert!(bonds
+            .iter()
+            .any(|(a, b, bond)| a.name() == "C" && b.name() == "O" && *bond == Bond::Double));
+        let peptide = bonds
+            .iter()
+            .filter(|(a, b, _)| a.name() == "C" && b.name() == "N")
+            .count();
+        assert_eq!(
+            peptide,
+            mmtf.chain(0)
+                .unwrap()
+                .residues()
+                .filter(|r| !r.atom(0).unwrap().hetero())
+                .count()
+                - 1
+        );
+    }
+
+    #[test]
+    fn read_models() {
+        let (mmtf, pdb) = open_both("models");
+        assert_same_atoms(&mmtf, &pdb);
+        assert_eq!(
+            mmtf.model(5).unwrap().residue(0).unwrap().name(),
+            Some("IL5")
+        );
+        let (first, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .set_only_first_model(true)
+            .read("example-pdbs/models.mmtf")
+            .unwrap();
+        assert_eq!(first.model_count(), 1);
+    }
+
+    #[test]
+    fn read_insertion_codes() {
+        let (mmtf, pdb) = open_both("insertion_codes");
+        assert_same_atoms(&mmtf, &pdb);
+        assert_eq!(mmtf.residue(0).unwrap().id(), (465, Some("A")));
+    }
+
+    #[test]
+    fn discard_hydrogens() {
+        let (mmtf, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .set_discard_hydrogens(true)
+            .read("example-pdbs/1ubq.mmtf")
+            .unwrap();
+        assert!(mmtf.atoms().all(|a| a.element() != Some(&Element::H)));
+    }
+
+    #[test]
+    fn codecs() {
+        let encode = |codec: i32, length: i32, parameter: i32, data: &[u8]| {
+            let mut bytes = Vec::new();
+            for value in [codec, length, parameter] {
+                bytes.extend(value.to_be_bytes());
+            }
+            bytes.extend(data);
+            bytes
+        };
+        let int32 =
+            |values: &[i32]| -> Vec<u8> { values.iter().flat_map(|v| v.to_be_bytes()).collect() };
+        // Run length and delta: [3, 2, 1, 2] -> [3, 3, 1, 1] -> [3, 6, 7, 8]
+        assert_eq!(
+            decode_binary(&encode(8, 4, 0, &int32(&[3, 2, 1, 2]))).unwrap(),
+            List::Int(vec![3, 6, 7, 8])
+        );
+        // Recursive index, delta, and integer: [32767, 1, -5] -> [32768, -5] -> [32768, 32763]
+        let data: Vec<u8> = [32767_i16, 1, -5]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+        assert_eq!(
+            decode_binary(&encode(10, 2, 1000, &data)).unwrap(),
+            List::Float(vec![32.768, 32.763])
+        );
+        // Characters with run length encoding
+        assert_eq!(
+            decode_binary(&encode(6, 3, 0, &int32(&[0, 2, 65, 1]))).unwrap(),
+            List::Str(vec![String::new(), String::new(), "A".to_string()])
+        );
+        // Fixed length strings
+        assert_eq!(
+            decode_binary(&encode(5, 2, 4, b"A\0\0\0BC\0\0")).unwrap(),
+            List::Str(vec!["A".to_string(), "BC".to_string()])
+        );
+        assert!(decode_binary(&encode(8, 5, 0, &int32(&[3, 2, 1, 2]))).is_err());
+        // Run lengths beyond the expected length are rejected before decoding them
+        assert!(decode_binary(&encode(7, 4, 0, &int32(&[3, 2, 1, i32::MAX]))).is_err());
+        assert!(decode_binary(&encode(6, 1, 0, &int32(&[65, 1, 66, 1]))).is_err());
+        assert!(decode_binary(&encode(99, 0, 0, &[])).is_err());
+        assert!(decode_binary(&[0, 0, 0, 4]).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        let read = |bytes: &[u8]| {
+            ReadOptions::new()
+                .set_format(Format::Mmtf)
+                .read_raw(BufReader::new(bytes))
+        };
+        assert!(read(&[]).is_err());
+        // An empty map misses all required fields
+        assert!(read(&[0x80]).is_err());
+        // Control characters in the chain name (the binary array of chainNameList starts with a
+        // two byte header and a twelve byte codec header) or the group name
+        let bytes = std::fs::read("example-pdbs/insertion_codes.mmtf").unwrap();
+        let find =
+            |key: &[u8]| bytes.windows(key.len()).position(|w| w == key).unwrap() + key.len();
+        for index in [find(b"chainNameList") + 14, find(b"groupName") + 1] {
+            let mut corrupted = bytes.clone();
+            corrupted[index] = 0x07;
+            let errors = read(&corrupted).map_or_else(|e| e, |(_, errors)| errors);
+            assert!(errors
+                .iter()
+                .any(|e| e.short_description() == "Invalid atom"));
+        }
+    }
+}
@@ -2,13 +2,50 @@ pub use general::{open, open_gz};
 pub use mmcif::{open_mmcif, open_mmcif_bufread, open_mmcif_raw};
 pub use pdb::{open_pdb, open_pdb_raw};
 pub use read_options::{Format, ReadOptions};
+pub use sdf::SdfResult;
+pub use stream::{AtomRecord, AtomStream, ModelStream};
+pub use trajectory::{Frame, Trajectory, TrajectoryFormat};
 
+pub(crate) use ccp4::open_map_raw;
+pub(crate) use dcd::AKMA_PS;
+pub(crate) use pae::open_pae_raw;
+pub(crate) use pdb::{POSE_ENERGIES_BEGIN, POSE_ENERGIES_END};
+
+/// Read CCP4/MRC density maps
+mod ccp4;
+/// Read CHARMM/NAMD DCD trajectories
+mod dcd;
 /// Give a high level interface for users
 mod general;
+/// Parse GROMACS GRO files
+mod gro;
+/// Decode JSON data, used by PAE files
+mod json;
 /// Parse mmCIF/PDBx files
 mod mmcif;
+/// Parse MMTF files
+mod mmtf;
+/// Parse Tripos MOL2 files
+mod mol2;
+/// Decode MessagePack data, used by binary formats
+mod msgpack;
 /// Read options
 mod read_options;
 
+/// Read predicted aligned error (PAE) JSON files
+mod pae;
 /// Parse PDB files
 mod pdb;
+/// Parse PQR files
+mod pqr;
+/// Parse MDL Molfile and SDF files
+mod sdf;
+/// Read GROMACS XTC trajectories
+mod xtc;
+/// Parse XYZ and extended XYZ files
+mod xyz;
+
+/// Stream atoms and models from files
+mod stream;
+/// Read frames from trajectory files
+mod trajectory;
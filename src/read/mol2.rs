@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::error::*;
+use crate::reference_tables;
+use crate::structs::*;
+use crate::validate::*;
+use crate::ReadOptions;
+
+use super::general::ReadResult;
+use super::pqr::guess_element;
+
+/// Parse the given Tripos MOL2 file into a PDB struct with [`ReadOptions`].
+/// Every molecule in the file is read as a separate [`Model`].
+pub(crate) fn open_mol2_with_options(
+    filename: impl AsRef<str>,
+    options: &ReadOptions,
+) -> ReadResult {
+    let filename = filename.as_ref();
+    let file = if let Ok(f) = File::open(filename) {
+        f
+    } else {
+        return Err(vec![PDBError::new(ErrorLevel::BreakingError, "Could not open file", "Could not open the specified file, make sure the path is correct, you have permission, and that it is not open in another program.", Context::show(filename))]);
+    };
+    open_mol2_raw_with_options(BufReader::new(file), Context::show(filename), options)
+}
+
+/// Parse the input stream in the Tripos MOL2 format into a PDB struct with [`ReadOptions`].
+///
+/// Every `MOLECULE` is read as a separate model. The substructures are read as residues, with
+/// the chain given in the `SUBSTRUCTURE` section (or `A` if there is none) and the residue name
+/// and number split from the substructure name (`ALA12` is residue 12 named `ALA`). The SYBYL
+/// atom types and partial charges are stored on the atoms, the element is derived from the atom
+/// type. The bonds are read with their bond order and the `CRYSIN` section is read as the
+/// [`UnitCell`] and [`Symmetry`]. Atoms in residues that are not amino acids are marked as
+/// hetero atoms.
+pub(crate) fn open_mol2_raw_with_options<T>(
+    input: BufReader<T>,
+    context: Context,
+    options: &ReadOptions,
+) -> ReadResult
+where
+    T: std::io::Read,
+{
+    let mut errors = Vec::new();
+    let mut pdb = PDB::new();
+    let mut molecule: Option<Molecule> = None;
+    let mut section = String::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = if let Ok(line) = line {
+            line
+        } else {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could read line",
+                format!(
+                    "Could not read line {} while parsing the input file.",
+                    index + 1
+                ),
+                context,
+            ));
+            return Err(errors);
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = trimmed.strip_prefix("@<TRIPOS>") {
+            section = name.trim().to_ascii_uppercase();
+            if section == "MOLECULE" {
+                if let Some(molecule) = molecule.take() {
+                    molecule.build(&mut pdb, options, &mut errors);
+                    if options.only_first_model {
+                        break;
+                    }
+                }
+                molecule = Some(Molecule::default());
+            }
+            continue;
+        }
+        if let Some(molecule) = &mut molecule {
+            molecule
+                .sections
+                .entry(section.clone())
+                .or_default()
+                .push((index + 1, line));
+        } else {
+            errors.push(PDBError::new(
+                ErrorLevel::GeneralWarning,
+                "Line outside molecule",
+                "This line is placed before the first MOLECULE record, it is ignored.",
+                Context::full_line(index + 1, &line),
+            ));
+        }
+    }
+    if let Some(molecule) = molecule {
+        molecule.build(&mut pdb, options, &mut errors);
+    }
+
+    errors.extend(validate(&pdb));
+
+    if errors.iter().any(|e| e.fails(options.level)) {
+        Err(errors)
+    } else {
+        Ok((pdb, errors))
+    }
+}
+
+/// The lines of a single MOL2 molecule, grouped by section name
+#[derive(Default)]
+struct Molecule {
+    /// The lines (with line numbers) of every section in this molecule
+    sections: HashMap<String, Vec<(usize, String)>>,
+}
+
+impl Molecule {
+    /// Get the lines of the given section
+    fn section(&self, name: &str) -> &[(usize, String)] {
+        self.sections.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Build a model from this molecule and add it to the PDB, together with its bonds
+    fn build(self, pdb: &mut PDB, options: &ReadOptions, errors: &mut Vec<PDBError>) {
+        let error = |level, short: &str, message: String, (linenumber, line): &(usize, String)| {
+            PDBError::new(level, short, message, Context::full_line(*linenumber, line))
+        };
+
+        // The substructures: id to (chain, residue serial number, residue name)
+        let mut substructures = HashMap::new();
+        for line in self.section("SUBSTRUCTURE") {
+            let fields: Vec<&str> = line.1.split_whitespace().collect();
+            if fields.len() < 3 {
+                errors.push(error(
+                    ErrorLevel::InvalidatingError,
+                    "Invalid MOL2 substructure",
+                    "A substructure line should contain at least the id, name, and root atom."
+                        .to_string(),
+                    line,
+                ));
+                continue;
+            }
+            let chain = fields
+                .get(5)
+                .filter(|chain| **chain != "****")
+                .unwrap_or(&"A");
+            let (name, serial_number) =
+                split_substructure_name(fields[1], fields.get(6).copied(), fields[0]);
+            substructures.insert(fields[0], (chain.to_string(), serial_number, name));
+        }
+
+        let mut model = Model::new(pdb.model_count() + 1);
+        let mut counters = HashMap::new();
+        let atom_lines = self.section("ATOM");
+        for line in atom_lines {
+            match parse_atom_line(&line.1) {
+                Ok((id, subst_id, subst_name, atom)) => {
+                    if options.discard_hydrogens && atom.element() == Some(&Element::H) {
+                        continue;
+                    }
+                    let (chain, serial_number, name) = substructures
+                        .get(subst_id.as_str())
+                        .cloned()
+                        .unwrap_or_else(|| {
+                            let (name, serial_number) =
+                                split_substructure_name(&subst_name, None, &subst_id);
+                            ("A".to_string(), serial_number, name)
+                        });
+                    counters.insert(id, atom.counter());
+                    model.add_atom(atom, chain, (serial_number, None), (name, None));
+                }
+                Err(message) => errors.push(error(
+                    ErrorLevel::InvalidatingError,
+                    "Invalid MOL2 atom",
+                    message,
+                    line,
+                )),
+            }
+        }
+
+        let header = self.section("MOLECULE");
+        if let Some(line) = header.get(1) {
+            if let Some(Ok(count)) = line.1.split_whitespace().next().map(str::parse::<usize>) {
+                if count != atom_lines.len() {
+                    errors.push(error(
+                        ErrorLevel::LooseWarning,
+                        "Atom count mismatch",
+                        format!(
+                            "The molecule should contain {count} atoms but {} atom lines were found.",
+                            atom_lines.len()
+                        ),
+                        line,
+                    ));
+                }
+            }
+        }
+
+        for line in self.section("BOND") {
+            let fields: Vec<&str> = line.1.split_whitespace().collect();
+            if fields.len() < 4 {
+                errors.push(error(
+                    ErrorLevel::InvalidatingError,
+                    "Invalid MOL2 bond",
+                    "A bond line should contain the id, origin atom, target atom, and bond type."
+                        .to_string(),
+                    line,
+                ));
+                continue;
+            }
+            let bond = match fields[3].to_ascii_lowercase().as_str() {
+                "1" | "am" | "du" | "un" => Bond::Covalent,
+                "2" => Bond::Double,
+                "3" => Bond::Triple,
+                "ar" => Bond::Aromatic,
+                "nc" => continue,
+                kind => {
+                    errors.push(error(
+                        ErrorLevel::InvalidatingError,
+                        "Invalid MOL2 bond",
+                        format!("The bond type \"{kind}\" is not a valid SYBYL bond type."),
+                        line,
+                    ));
+                    continue;
+                }
+            };
+            let atom = |id: &str| id.parse::<usize>().ok().and_then(|id| counters.get(&id));
+            match (atom(fields[1]), atom(fields[2])) {
+                (Some(a), Some(b)) => pdb.add_bond_counters(*a, *b, bond),
+                // Bonds to discarded hydrogens are ignored
+                _ if options.discard_hydrogens => (),
+                _ => errors.push(error(
+                    ErrorLevel::InvalidatingError,
+                    "Invalid MOL2 bond",
+                    "The bond refers to an atom that is not defined.".to_string(),
+                    line,
+                )),
+            }
+        }
+
+        if let Some(line) = self.section("CRYSIN").first() {
+            let values: Vec<f64> = line
+                .1
+                .split_whitespace()
+                .filter_map(|v| v.parse::<f64>().ok())
+                .collect();
+            if values.len() >= 6 && values.iter().all(|v| v.is_finite()) {
+                if pdb.unit_cell.is_none() {
+                    pdb.unit_cell = Some(UnitCell::new(
+                        values[0], values[1], values[2], values[3], values[4], values[5],
+                    ));
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let symmetry = values
+                        .get(6)
+                        .filter(|v| v.fract() == 0.0 && **v >= 1.0)
+                        .and_then(|v| Symmetry::from_index(*v as usize));
+                    pdb.symmetry = symmetry;
+                }
+            } else {
+                errors.push(error(
+                    ErrorLevel::InvalidatingError,
+                    "Invalid MOL2 crystal information",
+                    "The CRYSIN line should contain the cell lengths and angles, space group, and setting.".to_string(),
+                    line,
+                ));
+            }
+        }
+
+        pdb.add_model(model);
+    }
+}
+
+/// Parse a MOL2 atom line into the atom id, substructure id, substructure name, and atom.
+fn parse_atom_line(line: &str) -> Result<(usize, String, String, Atom), String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 6 {
+        return Err(format!(
+            "An atom line should contain at least the id, name, coordinates, and atom type but this line contains {} fields.",
+            fields.len()
+        ));
+    }
+    let number = |text: &str| {
+        text.parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("The value \"{text}\" is not a valid number."))
+    };
+    let id = fields[0]
+        .parse::<usize>()
+        .map_err(|_| "The atom id is not a valid number.".to_string())?;
+    let subst_id = fields.get(6).unwrap_or(&"1").to_string();
+    let subst_name = fields.get(7).unwrap_or(&"UNL").to_string();
+    let residue_name = split_substructure_name(&subst_name, None, &subst_id).0;
+    let hetero = !reference_tables::is_amino_acid(&residue_name);
+    let atom_type = fields[5];
+    // The element is the part of the atom type before the dot, dummy atoms and lone pairs
+    // (`Du`, `LP`) and generic types (`Any`, `Hal`) are not elements
+    let element = match atom_type.split('.').next() {
+        Some(symbol) if !matches!(symbol, "Du" | "LP" | "Lp") => Element::from_symbol(symbol),
+        _ => None,
+    }
+    .map_or_else(
+        || guess_element(fields[1], &residue_name, hetero),
+        |e| e.symbol().to_string(),
+    );
+    let mut atom = Atom::new(
+        hetero,
+        id,
+        fields[1],
+        number(fields[2])?,
+        number(fields[3])?,
+        number(fields[4])?,
+        1.0,
+        0.0,
+        element,
+        0,
+    )
+    .ok_or_else(|| "The atom name or element contains invalid characters.".to_string())?;
+    atom.set_atom_type(atom_type)?;
+    if let Some(charge) = fields.get(8) {
+        atom.set_partial_charge(number(charge)?)?;
+    }
+    Ok((id, subst_id, subst_name, atom))
+}
+
+/// Split a substructure name into a residue name and serial number. If the substructure type
+/// (`sub_type`) is given and the name starts with it the rest is the serial number, otherwise the
+/// trailing digits are used. If there are no trailing digits the substructure id is used.
+fn split_substructure_name(name: &str, sub_type: Option<&str>, id: &str) -> (String, isize) {
+    let fallback = id.parse::<isize>().unwrap_or(1);
+    if let Some(serial_number) = sub_type
+        .filter(|t| *t != "****")
+        .and_then(|t| name.strip_prefix(t))
+        .and_then(|rest| rest.parse::<isize>().ok())
+    {
+        return (sub_type.unwrap_or_default().to_string(), serial_number);
+    }
+    let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let prefix = prefix
+        .strip_suffix('-')
+        .filter(|p| !p.is_empty())
+        .unwrap_or(prefix);
+    match name[prefix.len()..].parse::<isize>() {
+        Ok(serial_number) if !prefix.is_empty() => (prefix.to_string(), serial_number),
+        _ => (name.to_string(), fallback),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::StrictnessLevel;
+
+    const MOL2: &str = "# Docked pose
+@<TRIPOS>MOLECULE
+complex
+ 6 5 2 0 0
+PROTEIN
+USER_CHARGES
+
+@<TRIPOS>ATOM
+      1 N         -1.2000    0.5000    0.1000 N.am      1 GLY12      -0.4157
+      2 CA        -0.1000    0.2000    0.0000 C.3       1 GLY12       0.0213
+      3 C1         3.0000    0.0000    0.0000 C.ar      2 LIG1        0.1000
+      4 C2         4.4000    0.0000    0.0000 C.ar      2 LIG1       -0.1000
+      5 CL1        5.1000    1.5000    0.0000 Cl        2 LIG1       -0.0500
+      6 H1         2.4000   -0.9000    0.0000 H         2 LIG1        0.0500
+@<TRIPOS>BOND
+     1     1     2 1
+     2     3     4 ar
+     3     4     5 1
+     4     3     6 1
+     5     2     3 nc
+@<TRIPOS>SUBSTRUCTURE
+     1 GLY12       1 RESIDUE           4 B     GLY     1 ROOT
+     2 LIG1        3 GROUP             0 ****  ****    0 ROOT
+@<TRIPOS>CRYSIN
+   40.0000   50.0000   60.0000   90.0000   90.0000   90.0000    19     1
+";
+
+    fn open(text: &str, options: &ReadOptions) -> ReadResult {
+        open_mol2_raw_with_options(BufReader::new(text.as_bytes()), Context::None, options)
+    }
+
+    #[test]
+    fn read_mol2() {
+        let (pdb, errors) = open(MOL2, &ReadOptions::new()).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(pdb.model_count(), 1);
+        assert_eq!(pdb.atom_count(), 6);
+        let chains: Vec<&str> = pdb.chains().map(Chain::id).collect();
+        assert_eq!(chains, ["B", "A"]);
+        let gly = pdb.residue(0).unwrap();
+        assert_eq!((gly.name(), gly.serial_number()), (Some("GLY"), 12));
+        let lig = pdb.residue(1).unwrap();
+        assert_eq!((lig.name(), lig.serial_number()), (Some("LIG"), 1));
+
+        let ca = pdb.atom(1).unwrap();
+        assert_eq!(ca.element(), Some(&Element::C));
+        assert!(!ca.hetero());
+        assert_eq!(ca.atom_type(), Some("C.3"));
+        assert_eq!(ca.partial_charge(), Some(0.0213));
+        let cl = pdb.atom(4).unwrap();
+        assert_eq!(cl.element(), Some(&Element::Cl));
+        assert!(cl.hetero());
+
+        let bonds: Vec<_> = pdb
+            .bonds()
+            .map(|(a, b, bond)| (a.serial_number(), b.serial_number(), bond))
+            .collect();
+        assert_eq!(
+            bonds,
+            [
+                (1, 2, Bond::Covalent),
+                (3, 4, Bond::Aromatic),
+                (4, 5, Bond::Covalent),
+                (3, 6, Bond::Covalent)
+            ]
+        );
+        assert_eq!(pdb.unit_cell.as_ref().unwrap().c(), 60.0);
+        assert_eq!(
+            pdb.symmetry.as_ref().unwrap().herman_mauguin_symbol(),
+            "P 21 21 21"
+        );
+    }
+
+    #[test]
+    fn options() {
+        let text = format!("{MOL2}{MOL2}");
+        let (pdb, _) = open(&text, &ReadOptions::new()).unwrap();
+        assert_eq!(pdb.model_count(), 2);
+        assert_eq!(pdb.bonds().count(), 8);
+        let (pdb, errors) = open(
+            &text,
+            ReadOptions::new()
+                .set_only_first_model(true)
+                .set_discard_hydrogens(true),
+        )
+        .unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(pdb.model_count(), 1);
+        assert_eq!(pdb.atom_count(), 5);
+        assert_eq!(pdb.bonds().count(), 3);
+    }
+
+    #[test]
+    fn invalid() {
+        let text = MOL2.replace("4.4000", "4.4.00");
+        let errors = open(&text, &ReadOptions::new()).unwrap_err();
+        assert_eq!(errors[0].short_description(), "Invalid MOL2 atom");
+        let text = MOL2.replace(" 6 5 2 0 0", " 7 5 2 0 0");
+        let (_, errors) =
+            open(&text, ReadOptions::new().set_level(StrictnessLevel::Loose)).unwrap();
+        assert_eq!(errors[0].short_description(), "Atom count mismatch");
+    }
+
+    #[test]
+    fn substructure_names() {
+        assert_eq!(
+            split_substructure_name("ALA12", Some("ALA"), "3"),
+            ("ALA".to_string(), 12)
+        );
+        assert_eq!(
+            split_substructure_name("LIG-1", None, "3"),
+            ("LIG".to_string(), -1)
+        );
+        assert_eq!(
+            split_substructure_name("<0>", None, "3"),
+            ("<0>".to_string(), 3)
+        );
+        assert_eq!(
+            split_substructure_name("HEM", Some("****"), "5"),
+            ("HEM".to_string(), 5)
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::error::*;
+
+/// A decoded MessagePack value, the strings and binary data borrow from the input
+#[derive(Debug, PartialEq)]
+pub(crate) enum MsgPack<'a> {
+    /// Nil
+    Nil,
+    /// A boolean
+    Bool(bool),
+    /// An integer, unsigned integers larger than `i64::MAX` are not supported
+    Int(i64),
+    /// A floating point number
+    Float(f64),
+    /// A string
+    Str(&'a str),
+    /// Binary data
+    Bin(&'a [u8]),
+    /// An array
+    Array(Vec<MsgPack<'a>>),
+    /// A map, in the original order
+    Map(Vec<(MsgPack<'a>, MsgPack<'a>)>),
+}
+
+impl<'a> MsgPack<'a> {
+    /// Decode the given bytes, which should contain a single MessagePack value
+    pub(crate) fn parse(bytes: &'a [u8]) -> Result<Self, PDBError> {
+        let mut input = bytes;
+        decode_msgpack(&mut input, 0)
+    }
+
+    /// Get the value for the given key, if this is a map
+    pub(crate) fn get(&self, key: &str) -> Option<&MsgPack<'a>> {
+        if let MsgPack::Map(map) = self {
+            map.iter()
+                .find(|(k, _)| *k == MsgPack::Str(key))
+                .map(|(_, v)| v)
+        } else {
+            None
+        }
+    }
+
+    /// Get the string, if this is a string
+    pub(crate) fn as_str(&self) -> Option<&'a str> {
+        if let MsgPack::Str(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    /// Get the binary data, if this is binary data
+    pub(crate) fn as_bin(&self) -> Option<&'a [u8]> {
+        if let MsgPack::Bin(b) = self {
+            Some(b)
+        } else {
+            None
+        }
+    }
+
+    /// Get the integer, if this is an integer
+    pub(crate) fn as_int(&self) -> Option<i64> {
+        if let MsgPack::Int(i) = self {
+            Some(*i)
+        } else {
+            None
+        }
+    }
+
+    /// Get the number as a float, if this is a number
+    pub(crate) fn as_float(&self) -> Option<f64> {
+        match self {
+            #[allow(clippy::cast_precision_loss)]
+            MsgPack::Int(i) => Some(*i as f64),
+            MsgPack::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Get the boolean, if this is a boolean
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        if let MsgPack::Bool(b) = self {
+            Some(*b)
+        } else {
+            None
+        }
+    }
+
+    /// Get the items, if this is an array
+    pub(crate) fn as_array(&self) -> Option<&[MsgPack<'a>]> {
+        if let MsgPack::Array(a) = self {
+            Some(a)
+        } else {
+            None
+        }
+    }
+}
+
+/// The maximal nesting depth of MessagePack values, to prevent stack overflows on invalid files
+const MAX_DEPTH: usize = 64;
+
+/// Decode a single MessagePack value, advancing the input past it
+fn decode_msgpack<'a>(input: &mut &'a [u8], depth: usize) -> Result<MsgPack<'a>, PDBError> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("The MessagePack data is nested too deeply."));
+    }
+    let marker = *take(input, 1)?.first().unwrap_or(&0);
+    Ok(match marker {
+        0x00..=0x7f => MsgPack::Int(i64::from(marker)),
+        0x80..=0x8f => decode_map(input, usize::from(marker & 0x0f), depth)?,
+        0x90..=0x9f => decode_array(input, usize::from(marker & 0x0f), depth)?,
+        0xa0..=0xbf => decode_str(input, usize::from(marker & 0x1f))?,
+        0xc0 => MsgPack::Nil,
+        0xc2 => MsgPack::Bool(false),
+        0xc3 => MsgPack::Bool(true),
+        0xc4..=0xc6 => {
+            let length = read_length(input, marker - 0xc4)?;
+            MsgPack::Bin(take(input, length)?)
+        }
+        0xca => MsgPack::Float(f64::from(f32::from_be_bytes(read_bytes(input)?))),
+        0xcb => MsgPack::Float(f64::from_be_bytes(read_bytes(input)?)),
+        0xcc => MsgPack::Int(i64::from(u8::from_be_bytes(read_bytes(input)?))),
+        0xcd => MsgPack::Int(i64::from(u16::from_be_bytes(read_bytes(input)?))),
+        0xce => MsgPack::Int(i64::from(u32::from_be_bytes(read_bytes(input)?))),
+        0xcf => MsgPack::Int(
+            i64::try_from(u64::from_be_bytes(read_bytes(input)?))
+                .map_err(|_| invalid("An integer is too big."))?,
+        ),
+        0xd0 => MsgPack::Int(i64::from(i8::from_be_bytes(read_bytes(input)?))),
+        0xd1 => MsgPack::Int(i64::from(i16::from_be_bytes(read_bytes(input)?))),
+        0xd2 => MsgPack::Int(i64::from(i32::from_be_bytes(read_bytes(input)?))),
+        0xd3 => MsgPack::Int(i64::from_be_bytes(read_bytes(input)?)),
+        0xd9..=0xdb => {
+            let length = read_length(input, marker - 0xd9)?;
+            decode_str(input, length)?
+        }
+        0xdc | 0xdd => {
+            let length = read_length(input, marker - 0xdc + 1)?;
+            decode_array(input, length, depth)?
+        }
+        0xde | 0xdf => {
+            let length = read_length(input, marker - 0xde + 1)?;
+            decode_map(input, length, depth)?
+        }
+        0xe0..=0xff => MsgPack::Int(i64::from(i8::from_be_bytes([marker]))),
+        _ => {
+            return Err(invalid(&format!(
+                "The MessagePack type 0x{marker:x} is not supported."
+            )))
+        }
+    })
+}
+
+/// Take the given number of bytes from the input
+fn take<'a>(input: &mut &'a [u8], length: usize) -> Result<&'a [u8], PDBError> {
+    if input.len() < length {
+        return Err(invalid("The file ended unexpectedly."));
+    }
+    let (bytes, rest) = input.split_at(length);
+    *input = rest;
+    Ok(bytes)
+}
+
+/// Take a fixed number of bytes from the input
+fn read_bytes<const N: usize>(input: &mut &[u8]) -> Result<[u8; N], PDBError> {
+    let mut array = [0; N];
+    array.copy_from_slice(take(input, N)?);
+    Ok(array)
+}
+
+/// Read a big endian length of 1, 2, or 4 bytes (`size` is 0, 1, or 2 respectively)
+fn read_length(input: &mut &[u8], size: u8) -> Result<usize, PDBError> {
+    Ok(match size {
+        0 => usize::from(read_bytes::<1>(input)?[0]),
+        1 => usize::from(u16::from_be_bytes(read_bytes(input)?)),
+        _ => u32::from_be_bytes(read_bytes(input)?) as usize,
+    })
+}
+
+/// Decode a string of the given length
+fn decode_str<'a>(input: &mut &'a [u8], length: usize) -> Result<MsgPack<'a>, PDBError> {
+    std::str::from_utf8(take(input, length)?)
+        .map(MsgPack::Str)
+        .map_err(|_| invalid("A string is not valid UTF-8."))
+}
+
+/// Decode an array with the given number of items
+fn decode_array<'a>(
+    input: &mut &'a [u8],
+    length: usize,
+    depth: usize,
+) -> Result<MsgPack<'a>, PDBError> {
+    let mut array = Vec::with_capacity(length.min(input.len()));
+    for _ in 0..length {
+        array.push(decode_msgpack(input, depth + 1)?);
+    }
+    Ok(MsgPack::Array(array))
+}
+
+/// Decode a map with the given number of key value pairs
+fn decode_map<'a>(
+    input: &mut &'a [u8],
+    length: usize,
+    depth: usize,
+) -> Result<MsgPack<'a>, PDBError> {
+    let mut map = Vec::with_capacity(length.min(input.len()));
+    for _ in 0..length {
+        let key = decode_msgpack(input, depth + 1)?;
+        let value = decode_msgpack(input, depth + 1)?;
+        map.push((key, value));
+    }
+    Ok(MsgPack::Map(map))
+}
+
+/// Create an error for invalid MessagePack data
+fn invalid(message: &str) -> PDBError {
+    PDBError::new(
+        ErrorLevel::BreakingError,
+        "Invalid MessagePack",
+        message,
+        Context::none(),
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msgpack() {
+        let bytes = [
+            0x82, 0xa1, b'a', 0x94, 0x01, 0xff, 0xcd, 0x01, 0x00, 0xc3, 0xa1, b'b', 0xc4, 0x02,
+            0x05, 0x06,
+        ];
+        let value = MsgPack::parse(&bytes).unwrap();
+        assert_eq!(
+            value.get("a").and_then(MsgPack::as_array).unwrap(),
+            [
+                MsgPack::Int(1),
+                MsgPack::Int(-1),
+                MsgPack::Int(256),
+                MsgPack::Bool(true)
+            ]
+        );
+        assert_eq!(value.get("b").and_then(MsgPack::as_bin), Some(&[5, 6][..]));
+        assert!(MsgPack::parse(&bytes[..8]).is_err());
+    }
+}
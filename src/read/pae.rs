@@ -0,0 +1,130 @@
+use std::io::Read;
+
+use super::json::Json;
+use crate::error::*;
+use crate::structs::*;
+
+/// Read a predicted aligned error (PAE) JSON file.
+///
+/// The following layouts are supported:
+/// * The current AlphaFold database and AlphaFold 2 layout, an object (optionally in an array
+///   with a single item) with the matrix as `predicted_aligned_error` and the maximum as
+///   `max_predicted_aligned_error`.
+/// * The AlphaFold 3 layout, with the matrix as `pae`.
+/// * The old AlphaFold database layout, an array with a single object with the flattened matrix
+///   as `distance` and the (one based) residue indices as `residue1` and `residue2`.
+pub(crate) fn open_pae_raw(
+    mut input: impl Read,
+    context: Context,
+) -> Result<PredictedAlignedError, PDBError> {
+    let error = |message: &str| {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid PAE file",
+            message,
+            context.clone(),
+        )
+    };
+    let mut text = String::new();
+    input.read_to_string(&mut text).map_err(|e| {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Buffer could not be read",
+            format!("The PAE file could not be read: {e}."),
+            context.clone(),
+        )
+    })?;
+    let json = Json::parse(&text).map_err(|e| error(e.long_description()))?;
+    let object = match &json {
+        Json::Array(items) if items.len() == 1 => &items[0],
+        _ => &json,
+    };
+    let numbers = |value: &Json| -> Result<Vec<f64>, PDBError> {
+        value
+            .as_array()
+            .and_then(|items| items.iter().map(Json::as_f64).collect())
+            .ok_or_else(|| error("The values should be an array of numbers."))
+    };
+    let max = object
+        .get("max_predicted_aligned_error")
+        .or_else(|| object.get("max_pae"))
+        .and_then(Json::as_f64);
+
+    if let Some(matrix) = object
+        .get("predicted_aligned_error")
+        .or_else(|| object.get("pae"))
+    {
+        let rows = matrix
+            .as_array()
+            .ok_or_else(|| error("The matrix should be an array of rows."))?
+            .iter()
+            .map(numbers)
+            .collect::<Result<Vec<_>, _>>()?;
+        PredictedAlignedError::new(rows, max).ok_or_else(|| error("The matrix is not square."))
+    } else if let (Some(first), Some(second), Some(distance)) = (
+        object.get("residue1"),
+        object.get("residue2"),
+        object.get("distance"),
+    ) {
+        let (first, second, distance) = (numbers(first)?, numbers(second)?, numbers(distance)?);
+        if first.len() != distance.len() || second.len() != distance.len() {
+            return Err(error("The residue and distance arrays differ in length."));
+        }
+        // The indices start at one, and no index can be larger than the number of pairs
+        #[allow(clippy::cast_precision_loss)]
+        let pairs = distance.len() as f64;
+        let indices = first
+            .iter()
+            .chain(&second)
+            .map(|index| {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                (index.fract() == 0.0 && *index >= 1.0 && *index <= pairs)
+                    .then(|| *index as usize - 1)
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| error("The residue indices should be whole numbers starting at one."))?;
+        let size = indices.iter().max().map_or(0, |max| max + 1);
+        if size.checked_mul(size) != Some(distance.len()) {
+            return Err(error("The residue pairs do not cover the full matrix."));
+        }
+        let mut rows = vec![vec![0.0; size]; size];
+        let (first, second) = indices.split_at(distance.len());
+        for ((a, b), value) in first.iter().zip(second).zip(distance) {
+            rows[*a][*b] = value;
+        }
+        PredictedAlignedError::new(rows, max).ok_or_else(|| error("The matrix is not square."))
+    } else {
+        Err(error("No predicted aligned error matrix could be found."))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts() {
+        let current = r#"[{"predicted_aligned_error": [[0, 1.5], [2, 0.25]], "max_predicted_aligned_error": 31.75}]"#;
+        let old = r#"[{"residue1": [1, 1, 2, 2], "residue2": [1, 2, 1, 2], "distance": [0, 1.5, 2, 0.25], "max_predicted_aligned_error": 31.75}]"#;
+        let af3 = r#"{"pae": [[0, 1.5], [2, 0.25]], "max_pae": 31.75}"#;
+        for text in [current, old, af3] {
+            let pae = open_pae_raw(text.as_bytes(), Context::None).unwrap();
+            assert_eq!(pae.size(), 2);
+            assert_eq!(pae.get(0, 1), Some(1.5));
+            assert_eq!(pae.get(1, 0), Some(2.0));
+            assert_eq!(pae.max(), Some(31.75));
+        }
+        assert!(open_pae_raw(&b"{\"pae\": [[0, 1]]}"[..], Context::None).is_err());
+        assert!(open_pae_raw(&b"{\"plddt\": [1]}"[..], Context::None).is_err());
+        for invalid in [
+            r#"[{"residue1": [1e18], "residue2": [1], "distance": [0]}]"#,
+            r#"[{"residue1": [1, 1, 2, 2], "residue2": [1, 2, 1, 2.5], "distance": [0, 1, 2, 3]}]"#,
+            r#"[{"residue1": [1, 1, 2, 0], "residue2": [1, 2, 1, 2], "distance": [0, 1, 2, 3]}]"#,
+            r#"[{"residue1": [1, 1, 2, -2], "residue2": [1, 2, 1, 2], "distance": [0, 1, 2, 3]}]"#,
+            r#"[{"residue1": [1, 1, 2], "residue2": [1, 2, 1], "distance": [0, 1, 2]}]"#,
+        ] {
+            assert!(open_pae_raw(invalid.as_bytes(), Context::None).is_err());
+        }
+    }
+}
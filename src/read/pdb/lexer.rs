@@ -9,6 +9,31 @@ use std::convert::TryFrom;
 use std::ops::Range;
 use std::str::FromStr;
 
+/// The minimal number of lines to lex them in parallel
+#[cfg(feature = "rayon")]
+const PARALLEL_LINES: usize = 1_000;
+
+/// Lex a set of lines, given as line number and line. The lines are lexed in parallel if the
+/// `rayon` feature is enabled and there are enough lines to make this worthwhile. The results
+/// are in the same order as the lines.
+pub fn lex_lines(
+    lines: &[(usize, String)],
+    options: &ReadOptions,
+) -> Vec<Result<(LexItem, Vec<PDBError>), PDBError>> {
+    #[cfg(feature = "rayon")]
+    if lines.len() >= PARALLEL_LINES {
+        use rayon::prelude::*;
+        return lines
+            .par_iter()
+            .map(|(linenumber, line)| lex_line(line, *linenumber, options))
+            .collect();
+    }
+    lines
+        .iter()
+        .map(|(linenumber, line)| lex_line(line, *linenumber, options))
+        .collect()
+}
+
 /// Lex a full line. It returns a lexed item with errors if it can lex something, otherwise it will only return an error.
 pub fn lex_line(
     line: &str,
@@ -281,11 +306,25 @@ fn lex_atom_basics(
     let serial_number = parse(linenumber, line, 6..11, &mut errors);
     let atom_name = parse(linenumber, line, 12..16, &mut errors);
     let alternate_location = parse_char(linenumber, line, 16, &mut errors);
-    let residue_name = parse(linenumber, line, 17..20, &mut errors);
+    // Molecular dynamics packages use the normally empty column 21 for 4 character residue names
+    let residue_name = if chars.get(20).map_or(false, |c| *c != ' ') {
+        parse(linenumber, line, 17..21, &mut errors)
+    } else {
+        parse(linenumber, line, 17..20, &mut errors)
+    };
     let chain_id = String::from(parse_char(linenumber, line, 21, &mut errors));
     let residue_serial_number = parse(linenumber, line, 22..26, &mut errors);
     let insertion = parse_char(linenumber, line, 26, &mut errors);
-    let segment_id = parse(linenumber, line, 72..76, &mut errors);
+    let mut segment_id: String = parse(linenumber, line, 72..76, &mut errors);
+    if !segment_id.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+        errors.push(PDBError::new(
+            ErrorLevel::LooseWarning,
+            "Invalid segment identifier",
+            "The segment identifier contains invalid characters, so it is ignored.",
+            Context::line(linenumber, line, 72, 4),
+        ));
+        segment_id = String::new();
+    }
     let element = parse(linenumber, line, 76..78, &mut errors);
 
     let mut charge = 0;
@@ -740,3 +779,33 @@ fn parse_char(linenumber: usize, line: &str, position: usize, errors: &mut Vec<P
         ' '
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lex_lines_in_order() {
+        for name in ["1ubq.pdb", "1yyf.pdb", "rosetta_model.pdb"] {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("example-pdbs")
+                .join(name);
+            let lines = std::fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .enumerate()
+                .map(|(index, line)| (index + 1, line.to_string()))
+                .collect::<Vec<_>>();
+            let options = ReadOptions::default();
+            let chunked = lex_lines(&lines, &options);
+            assert_eq!(chunked.len(), lines.len());
+            for ((linenumber, line), result) in lines.iter().zip(chunked) {
+                assert_eq!(
+                    format!("{result:?}"),
+                    format!("{:?}", lex_line(line, *linenumber, &options))
+                );
+            }
+        }
+    }
+}
@@ -4,9 +4,15 @@ mod lexer;
 mod lexitem;
 /// The parser and lexer
 mod parser;
+/// Parse Rosetta pose energies tables
+mod pose_energies;
+/// Stream the atoms from a file without building the full structure
+mod stream;
 /// Structs to help in building up the PDB struct
 mod temporary_structs;
 /// Methods to validate the parsed structure
 mod validate;
 
 pub use parser::*;
+pub(crate) use pose_energies::{POSE_ENERGIES_BEGIN, POSE_ENERGIES_END};
+pub(crate) use stream::PdbAtomStream;
@@ -0,0 +1,176 @@
+use crate::error::*;
+use crate::structs::*;
+
+/// The first line of a Rosetta pose energies table, followed by the name of the table
+pub(crate) const POSE_ENERGIES_BEGIN: &str = "#BEGIN_POSE_ENERGIES_TABLE";
+/// The last line of a Rosetta pose energies table, followed by the name of the table
+pub(crate) const POSE_ENERGIES_END: &str = "#END_POSE_ENERGIES_TABLE";
+
+/// Parse a Rosetta pose energies table, given as the lines from the begin line up to (but not
+/// including) the end line, and add it to the PDB.
+///
+/// The table has a `label` line with the score terms, a `weights` line with the weight of every
+/// term (`NA` if not applicable), a `pose` line with the totals for the whole pose, and a line
+/// for every residue. The residue lines are labelled with the residue name (with optional
+/// variants after a colon) and the index of the residue in the pose (starting at one), like
+/// `GLY:NtermProteinFull_1`. The residue energies are added to the Residues of the first Model in
+/// order, the residues in the table should match the residues in the model.
+pub(crate) fn parse_pose_energies(lines: &[(usize, String)], pdb: &mut PDB) -> Vec<PDBError> {
+    let mut errors = Vec::new();
+    let error = |message: String, (linenumber, line): &(usize, String)| {
+        PDBError::new(
+            ErrorLevel::LooseWarning,
+            "Invalid pose energies table",
+            message,
+            Context::FullLine {
+                linenumber: *linenumber,
+                line: line.clone(),
+            },
+        )
+    };
+    let name = lines.first().and_then(|(_, line)| {
+        let name = line[POSE_ENERGIES_BEGIN.len()..].trim();
+        (!name.is_empty()).then(|| name.to_string())
+    });
+    let mut terms: Vec<String> = Vec::new();
+    let mut weights = Vec::new();
+    let mut totals = Vec::new();
+    let mut residues: Vec<&mut Residue> = pdb
+        .models_mut()
+        .next()
+        .map(|model| model.residues_mut().collect())
+        .unwrap_or_default();
+
+    for line in lines.iter().skip(1) {
+        let mut fields = line.1.split_whitespace();
+        let label = if let Some(label) = fields.next() {
+            label
+        } else {
+            continue;
+        };
+        if label == "label" {
+            terms = fields.map(str::to_string).collect();
+            continue;
+        }
+        let values: Vec<&str> = fields.collect();
+        if values.len() != terms.len() {
+            errors.push(error(
+                format!(
+                    "This line has {} values while there are {} score terms.",
+                    values.len(),
+                    terms.len()
+                ),
+                line,
+            ));
+            continue;
+        }
+        if label == "weights" {
+            weights = values.iter().map(|value| value.parse().ok()).collect();
+            continue;
+        }
+        let values = if let Ok(values) = values
+            .iter()
+            .map(|value| value.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+        {
+            values
+        } else {
+            errors.push(error("Not all values are numbers.".to_string(), line));
+            continue;
+        };
+        if label == "pose" {
+            totals = values;
+            continue;
+        }
+
+        let (residue_name, index) = label
+            .rsplit_once('_')
+            .and_then(|(name, index)| Some((name, index.parse::<usize>().ok()?)))
+            .unwrap_or((label, 0));
+        let residue_name = residue_name
+            .split([':', '_'])
+            .next()
+            .unwrap_or(residue_name);
+        match index.checked_sub(1).and_then(|i| residues.get_mut(i)) {
+            Some(residue) if residue.name() == Some(residue_name) => {
+                residue.remove_energies();
+                for (term, value) in terms.iter().zip(values) {
+                    if !residue.set_energy(term.as_str(), value) {
+                        errors.push(error(format!("The value for '{term}' is not finite."), line));
+                    }
+                }
+            }
+            Some(residue) => errors.push(error(
+                format!(
+                    "The residue name '{residue_name}' does not match the name of residue {index} in the first model ({}).",
+                    residue.name().unwrap_or("?")
+                ),
+                line,
+            )),
+            None => errors.push(error(
+                format!("There is no residue {index} in the first model."),
+                line,
+            )),
+        }
+    }
+
+    if weights.len() != terms.len() {
+        weights = vec![None; terms.len()];
+    }
+    if totals.len() == terms.len() {
+        if let Some(energies) =
+            PoseEnergies::new(name, terms.into_iter().zip(weights).collect(), totals)
+        {
+            pdb.set_pose_energies(energies);
+        }
+    } else if let Some(first) = lines.first() {
+        errors.push(error(
+            "The totals for the pose are missing.".to_string(),
+            first,
+        ));
+    }
+    errors
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::*;
+    use std::io::{BufReader, BufWriter};
+
+    #[test]
+    fn rosetta_model() {
+        let (pdb, errors) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/rosetta_model.pdb")
+            .unwrap();
+        assert!(errors
+            .iter()
+            .all(|e| e.short_description() != "Invalid pose energies table"));
+        let energies = pdb.pose_energies().unwrap();
+        assert_eq!(energies.name(), Some("r2x3_bp4_7_0001"));
+        assert_eq!(energies.terms().count(), 20);
+        assert_eq!(energies.weight("fa_rep"), Some(0.55));
+        assert_eq!(energies.weight("total"), None);
+        assert_eq!(energies.total("total"), Some(-317.493));
+        let residue = pdb.residue(1).unwrap();
+        assert_eq!(residue.energy("fa_rep"), Some(0.802558));
+        assert_eq!(residue.energies().count(), 20);
+        assert!(pdb.residues().all(|r| r.energies().count() == 20));
+
+        let mut bytes = Vec::new();
+        save_pdb_raw(&pdb, BufWriter::new(&mut bytes), StrictnessLevel::Loose);
+        let (saved, _) = ReadOptions::new()
+            .set_format(Format::Pdb)
+            .set_level(StrictnessLevel::Loose)
+            .read_raw(BufReader::new(&bytes[..]))
+            .unwrap();
+        assert_eq!(saved.pose_energies(), pdb.pose_energies());
+        for (a, b) in saved.residues().zip(pdb.residues()) {
+            assert_eq!(
+                a.energies().collect::<Vec<_>>(),
+                b.energies().collect::<Vec<_>>()
+            );
+        }
+    }
+}
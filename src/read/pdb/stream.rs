@@ -0,0 +1,222 @@
+use std::io::BufRead;
+use std::iter::Cycle;
+use std::ops::RangeInclusive;
+
+use crate::error::*;
+use crate::structs::*;
+use crate::AtomRecord;
+use crate::ReadOptions;
+
+use super::lexer::*;
+use super::lexitem::*;
+
+/// Read the atoms of a PDB file one by one, see [`crate::AtomStream`].
+pub(crate) struct PdbAtomStream<R> {
+    /// The input
+    input: R,
+    /// The options to read with
+    options: ReadOptions,
+    /// The buffer for the current line
+    line: String,
+    /// The current line number (1 based)
+    linenumber: usize,
+    /// The serial number of the current model
+    model_number: usize,
+    /// If an atom was read in the current model
+    model_has_atoms: bool,
+    /// The last atom read, kept to add an ANISOU record if it follows
+    pending: Option<AtomRecord>,
+    /// The serial number of the last atom read (as found in the file)
+    last_atom_serial_number: usize,
+    /// The number to add to the atom serial numbers, to handle wrapping serial numbers
+    atom_serial_addition: usize,
+    /// The serial number of the last residue read (as found in the file)
+    last_residue_serial_number: isize,
+    /// The number to add to the residue serial numbers, to handle wrapping serial numbers
+    residue_serial_addition: isize,
+    /// Source for the chain identifiers of atoms with blank chain identifiers
+    chain_iter: Cycle<RangeInclusive<char>>,
+    /// The chain identifier for atoms with blank chain identifiers
+    chain_id_new: Option<char>,
+    /// An error found while the last atom was still pending, to be returned after that atom
+    error: Option<PDBError>,
+    /// If the end of the stream is reached
+    done: bool,
+}
+
+impl<R: BufRead> PdbAtomStream<R> {
+    /// Create a new stream over the given input
+    pub(crate) fn new(input: R, options: &ReadOptions) -> Self {
+        let mut chain_iter = ('A'..='Z').cycle();
+        let chain_id_new = chain_iter.next();
+        PdbAtomStream {
+            input,
+            options: options.clone(),
+            line: String::new(),
+            linenumber: 0,
+            model_number: 0,
+            model_has_atoms: false,
+            pending: None,
+            last_atom_serial_number: 0,
+            atom_serial_addition: 0,
+            last_residue_serial_number: 0,
+            residue_serial_addition: 0,
+            chain_iter,
+            chain_id_new,
+            error: None,
+            done: false,
+        }
+    }
+
+    /// Read the next line into the line buffer, returns false at the end of the input
+    fn read_line(&mut self) -> Result<bool, PDBError> {
+        self.line.clear();
+        self.linenumber += 1;
+        match self.input.read_line(&mut self.line) {
+            Ok(0) => Ok(false),
+            Ok(_) => {
+                let trimmed = self.line.trim_end_matches(&['\n', '\r'][..]).len();
+                self.line.truncate(trimmed);
+                Ok(true)
+            }
+            Err(_) => Err(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could read line",
+                format!(
+                    "Could not read line {} while parsing the input file.",
+                    self.linenumber
+                ),
+                Context::None,
+            )),
+        }
+    }
+
+    /// End the stream with the given error, the pending atom (if any) is returned first
+    fn fail(&mut self, error: PDBError) -> Option<Result<AtomRecord, PDBError>> {
+        self.done = true;
+        if let Some(record) = self.pending.take() {
+            self.error = Some(error);
+            Some(Ok(record))
+        } else {
+            Some(Err(error))
+        }
+    }
+
+    /// Read lines until the next atom is complete
+    pub(crate) fn next(&mut self) -> Option<Result<AtomRecord, PDBError>> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        while !self.done {
+            match self.read_line() {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.done = true;
+                    break;
+                }
+                Err(e) => return self.fail(e),
+            }
+            let (item, errors) = match lex_line(&self.line, self.linenumber, &self.options) {
+                Ok(result) => result,
+                Err(e) if e.fails(self.options.level) => return self.fail(e),
+                Err(_) => continue,
+            };
+            if let Some(e) = errors.into_iter().find(|e| e.fails(self.options.level)) {
+                return self.fail(e);
+            }
+            match item {
+                LexItem::Atom(
+                    hetero,
+                    serial_number,
+                    name,
+                    alt_loc,
+                    residue_name,
+                    mut chain_id,
+                    residue_serial_number,
+                    insertion_code,
+                    x,
+                    y,
+                    z,
+                    occ,
+                    b,
+                    segment_id,
+                    element,
+                    charge,
+                ) => {
+                    if self.options.discard_hydrogens & (element == "H") {
+                        continue;
+                    }
+                    if self.options.chains_from_segments && !segment_id.is_empty() {
+                        chain_id = segment_id.clone();
+                    }
+                    if serial_number == 0 && self.last_atom_serial_number == 99_999 {
+                        self.atom_serial_addition += 100_000;
+                    }
+                    if residue_serial_number == 0 && self.last_residue_serial_number == 9999 {
+                        self.residue_serial_addition += 10000;
+                    }
+                    if chain_id.trim().is_empty() {
+                        chain_id = self
+                            .chain_id_new
+                            .expect("Chain ID iterator is exhausted")
+                            .to_string();
+                    }
+                    self.last_atom_serial_number = serial_number;
+                    self.last_residue_serial_number = residue_serial_number;
+                    self.model_has_atoms = true;
+
+                    let mut atom = Atom::new(
+                        hetero,
+                        serial_number + self.atom_serial_addition,
+                        name,
+                        x,
+                        y,
+                        z,
+                        occ,
+                        b,
+                        element,
+                        charge,
+                    )
+                    .expect("Invalid characters in atom creation");
+                    // Invalid segment identifiers are reported and removed by the lexer
+                    atom.set_segment_id(segment_id).ok();
+                    let record = AtomRecord::new(
+                        self.model_number,
+                        chain_id,
+                        (
+                            residue_serial_number + self.residue_serial_addition,
+                            insertion_code,
+                        ),
+                        (residue_name, alt_loc),
+                        atom,
+                    );
+                    if let Some(previous) = self.pending.replace(record) {
+                        return Some(Ok(previous));
+                    }
+                }
+                LexItem::Anisou(serial_number, _, _, _, _, _, _, factors, _, _, _) => {
+                    if let Some(record) = &mut self.pending {
+                        if record.atom().serial_number()
+                            == serial_number + self.atom_serial_addition
+                        {
+                            record
+                                .atom_mut()
+                                .set_anisotropic_temperature_factors(factors);
+                        }
+                    }
+                }
+                LexItem::Model(number) => {
+                    if self.model_has_atoms && self.options.only_first_model {
+                        self.done = true;
+                        break;
+                    }
+                    self.model_number = number;
+                    self.model_has_atoms = false;
+                }
+                LexItem::TER() => self.chain_id_new = self.chain_iter.next(),
+                _ => (),
+            }
+        }
+        self.pending.take().map(Ok)
+    }
+}
@@ -0,0 +1,277 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::error::*;
+use crate::structs::*;
+use crate::validate::*;
+use crate::ReadOptions;
+
+use super::general::ReadResult;
+
+/// Parse the given PQR file into a PDB struct with [`ReadOptions`].
+/// The partial charge and radius of each atom are stored on the atom, see
+/// [`Atom::partial_charge`] and [`Atom::radius`].
+pub(crate) fn open_pqr_with_options(
+    filename: impl AsRef<str>,
+    options: &ReadOptions,
+) -> ReadResult {
+    let filename = filename.as_ref();
+    let file = if let Ok(f) = File::open(filename) {
+        f
+    } else {
+        return Err(vec![PDBError::new(ErrorLevel::BreakingError, "Could not open file", "Could not open the specified file, make sure the path is correct, you have permission, and that it is not open in another program.", Context::show(filename))]);
+    };
+    open_pqr_raw_with_options(BufReader::new(file), Context::show(filename), options)
+}
+
+/// Parse the input stream in the PQR format into a PDB struct with [`ReadOptions`].
+///
+/// PQR files are whitespace delimited, each `ATOM`/`HETATM` line contains the serial number,
+/// atom name, residue name, an optional chain identifier, residue number (optionally followed by
+/// an insertion code), the coordinates, the partial charge and the radius. As PQR files do not
+/// define an element column the element is derived from the atom name.
+pub(crate) fn open_pqr_raw_with_options<T>(
+    input: BufReader<T>,
+    context: Context,
+    options: &ReadOptions,
+) -> ReadResult
+where
+    T: std::io::Read,
+{
+    let mut errors = Vec::new();
+    let mut pdb = PDB::new();
+    let mut current_model = Model::new(0);
+    let mut chain_iter = ('A'..='Z').cycle();
+    let mut chain_id_new = chain_iter.next().unwrap_or('A');
+
+    for (index, line) in input.lines().enumerate() {
+        let linenumber = index + 1;
+        let line = if let Ok(line) = line {
+            line
+        } else {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could read line",
+                format!("Could not read line {linenumber} while parsing the input file."),
+                context,
+            ));
+            return Err(errors);
+        };
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.first().map(|s| s.to_ascii_uppercase()).as_deref() {
+            Some("ATOM") | Some("HETATM") => match parse_atom_line(&fields, chain_id_new) {
+                Ok((chain_id, residue_id, residue_name, atom)) => {
+                    if options.discard_hydrogens && atom.element() == Some(&Element::H) {
+                        continue;
+                    }
+                    current_model.add_atom(
+                        atom,
+                        chain_id,
+                        (residue_id.0, residue_id.1.as_deref()),
+                        (residue_name, None),
+                    );
+                }
+                Err(message) => errors.push(PDBError::new(
+                    ErrorLevel::InvalidatingError,
+                    "Invalid PQR atom",
+                    message,
+                    Context::full_line(linenumber, &line),
+                )),
+            },
+            Some("MODEL") => {
+                if current_model.atom_count() > 0 {
+                    if options.only_first_model {
+                        break;
+                    }
+                    pdb.add_model(current_model);
+                }
+                let serial_number = fields
+                    .get(1)
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .unwrap_or(pdb.model_count() + 1);
+                current_model = Model::new(serial_number);
+            }
+            Some("ENDMDL") if current_model.atom_count() > 0 => {
+                if options.only_first_model {
+                    break;
+                }
+                let serial_number = current_model.serial_number();
+                pdb.add_model(std::mem::replace(
+                    &mut current_model,
+                    Model::new(serial_number + 1),
+                ));
+            }
+            Some("TER") => chain_id_new = chain_iter.next().unwrap_or('A'),
+            _ => (),
+        }
+    }
+    if current_model.atom_count() > 0 {
+        pdb.add_model(current_model);
+    }
+
+    errors.extend(validate(&pdb));
+
+    if errors.iter().any(|e| e.fails(options.level)) {
+        Err(errors)
+    } else {
+        Ok((pdb, errors))
+    }
+}
+
+/// The result of parsing a single atom line: the chain, residue id, residue name, and atom
+type PqrAtom = (String, (isize, Option<String>), String, Atom);
+
+/// Parse the whitespace delimited fields of an `ATOM`/`HETATM` line in a PQR file.
+/// The chain identifier is optional, if it is missing `chain_id_new` is used.
+fn parse_atom_line(fields: &[&str], chain_id_new: char) -> Result<PqrAtom, String> {
+    let (chain_id, residue_field, numbers) = match fields.len() {
+        10 => (chain_id_new.to_string(), fields[4], &fields[5..]),
+        11 => (fields[4].to_string(), fields[5], &fields[6..]),
+        n => {
+            return Err(format!(
+                "An atom line should contain 10 or 11 fields (with a chain identifier) but this line contains {n} fields."
+            ))
+        }
+    };
+    let hetero = fields[0].eq_ignore_ascii_case("HETATM");
+    let serial_number = fields[1]
+        .parse::<usize>()
+        .map_err(|_| format!("The serial number \"{}\" is not a valid number.", fields[1]))?;
+    let name = fields[2];
+    let residue_name = fields[3];
+
+    let split = residue_field
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(residue_field.len());
+    let residue_number = residue_field[..split]
+        .parse::<isize>()
+        .map_err(|_| format!("The residue number \"{residue_field}\" is not a valid number."))?;
+    let insertion_code = Some(&residue_field[split..])
+        .filter(|code| !code.is_empty())
+        .map(ToString::to_string);
+
+    let mut values = [0.0; 5];
+    for (value, text) in values.iter_mut().zip(numbers) {
+        *value = text
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("The value \"{text}\" is not a valid number."))?;
+    }
+    let [x, y, z, partial_charge, radius] = values;
+
+    let mut atom = Atom::new(
+        hetero,
+        serial_number,
+        name,
+        x,
+        y,
+        z,
+        1.0,
+        0.0,
+        guess_element(name, residue_name, hetero),
+        0,
+    )
+    .ok_or_else(|| format!("The atom name \"{name}\" contains invalid characters."))?;
+    atom.set_partial_charge(partial_charge)?;
+    atom.set_radius(radius)?;
+
+    Ok((
+        chain_id,
+        (residue_number, insertion_code),
+        residue_name.to_string(),
+        atom,
+    ))
+}
+
+/// Guess the element of an atom from its name, as PQR files do not contain an element column.
+/// Single atom hetero residues (like ions) use the full name, otherwise the first letter
+/// of the name is used (ignoring leading digits as in `1HB`).
+pub(super) fn guess_element(name: &str, residue_name: &str, hetero: bool) -> String {
+    if hetero && name.eq_ignore_ascii_case(residue_name) {
+        name.to_string()
+    } else {
+        name.trim_start_matches(|c: char| c.is_ascii_digit())
+            .chars()
+            .next()
+            .map_or_else(String::new, |c| c.to_string())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::StrictnessLevel;
+
+    const PQR: &str = "REMARK   1 PQR file generated by PDB2PQR
+ATOM      1  N   MET A   1     -10.234   4.123   2.345 -0.3000 1.8240
+ATOM      2  CA  MET A   1      -9.123   3.456   2.100  0.0221 1.9080
+ATOM      3  HA  MET A   1      -9.000   3.000   1.100  0.0880 1.1000
+HETATM    4 ZN    ZN Z   2A      1.000   2.000   3.000  2.0000 1.3900
+TER
+ATOM      5  O   HOH     3       4.000   5.000   6.000 -0.8340 1.6612
+END
+";
+
+    fn open(text: &str, options: &ReadOptions) -> ReadResult {
+        open_pqr_raw_with_options(BufReader::new(text.as_bytes()), Context::None, options)
+    }
+
+    fn read(options: &mut ReadOptions) -> PDB {
+        let (pdb, errors) = open(PQR, options.set_level(StrictnessLevel::Loose)).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        pdb
+    }
+
+    #[test]
+    fn read_pqr() {
+        let pdb = read(&mut ReadOptions::new());
+        assert_eq!(pdb.model_count(), 1);
+        assert_eq!(pdb.chain_count(), 3);
+        assert_eq!(pdb.atom_count(), 5);
+        let first = pdb.atom(0).unwrap();
+        assert_eq!(first.name(), "N");
+        assert_eq!(first.element(), Some(&Element::N));
+        assert_eq!(first.pos(), (-10.234, 4.123, 2.345));
+        assert_eq!(first.partial_charge(), Some(-0.3));
+        assert_eq!(first.radius(), Some(1.824));
+        assert_eq!(pdb.atom(1).unwrap().element(), Some(&Element::C));
+
+        let zinc = pdb.chains().nth(1).unwrap();
+        assert_eq!(zinc.id(), "Z");
+        let residue = zinc.residue(0).unwrap();
+        assert_eq!(residue.id(), (2, Some("A")));
+        assert!(residue.atom(0).unwrap().hetero());
+        assert_eq!(residue.atom(0).unwrap().element(), Some(&Element::Zn));
+
+        // The water does not have a chain identifier, it is placed in the next chain after TER
+        assert_eq!(pdb.chains().nth(2).unwrap().id(), "B");
+        assert_eq!(
+            pdb.chains().nth(2).unwrap().residue(0).unwrap().name(),
+            Some("HOH")
+        );
+    }
+
+    #[test]
+    fn discard_hydrogens() {
+        let pdb = read(ReadOptions::new().set_discard_hydrogens(true));
+        assert_eq!(pdb.atom_count(), 4);
+    }
+
+    #[test]
+    fn invalid_line() {
+        let errors = open(
+            "ATOM      1  N   MET A   1     -10.234   4.123 -0.3000 1.8240\n",
+            ReadOptions::new().set_level(StrictnessLevel::Loose),
+        )
+        .unwrap_err();
+        assert_eq!(errors[0].short_description(), "Invalid PQR atom");
+        let errors = open(
+            "ATOM      1  N   MET A   1     -10.234   4.123   X -0.3000 1.8240\n",
+            &ReadOptions::new(),
+        )
+        .unwrap_err();
+        assert_eq!(errors[0].short_description(), "Invalid PQR atom");
+    }
+}
@@ -3,6 +3,8 @@ use std::{ffi::OsStr, path::Path};
 use crate::{Context, PDBError, StrictnessLevel};
 
 use super::general::ReadResult;
+use super::sdf::SdfResult;
+use super::{open_map_raw, open_pae_raw, AtomStream, ModelStream, Trajectory, TrajectoryFormat};
 
 /// Used to set which format to read the file in.
 #[derive(Debug, Clone, Copy, Default)]
@@ -11,6 +13,20 @@ pub enum Format {
     Pdb,
     /// Load mmCIF files
     Mmcif,
+    /// Load PDBML (XML) files
+    Pdbml,
+    /// Load BinaryCIF files
+    BinaryCif,
+    /// Load MMTF files
+    Mmtf,
+    /// Load PQR files
+    Pqr,
+    /// Load GROMACS GRO files
+    Gro,
+    /// Load Tripos MOL2 files
+    Mol2,
+    /// Load XYZ and extended XYZ files
+    Xyz,
     /// Automatically detect the format
     #[default]
     Auto,
@@ -21,6 +37,13 @@ impl From<&str> for Format {
         match s {
             "pdb" => Self::Pdb,
             "mmcif" => Self::Mmcif,
+            "pdbml" | "xml" => Self::Pdbml,
+            "bcif" | "binarycif" => Self::BinaryCif,
+            "mmtf" => Self::Mmtf,
+            "pqr" => Self::Pqr,
+            "gro" => Self::Gro,
+            "mol2" => Self::Mol2,
+            "xyz" | "extxyz" => Self::Xyz,
             _ => panic!("Unknown format: {}", s),
         }
     }
@@ -51,7 +74,7 @@ impl From<&str> for Format {
 ///
 /// The format of the file is inferred by [`ReadOptions::guess_format`]
 /// when it is not set explicitly with [`ReadOptions::set_format`].
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ReadOptions {
     /// The format to read the file in.
     pub(crate) format: Format,
@@ -74,6 +97,8 @@ pub struct ReadOptions {
 
     /// Only read atomic coordinates
     pub(crate) only_atomic_coords: bool,
+    /// Use the segment identifiers as chain identifiers
+    pub(crate) chains_from_segments: bool,
 }
 
 impl ReadOptions {
@@ -134,7 +159,15 @@ impl ReadOptions {
         self
     }
 
-    /// Open an atomic data file, either PDB or mmCIF/PDBx, into a [`PDB`] structure.
+    /// Sets whether to use the segment identifiers (columns 73-76) of PDB files as chain
+    /// identifiers, as is common for files from molecular dynamics packages like CHARMM and NAMD.
+    /// Atoms without a segment identifier keep their chain identifier.
+    pub fn set_chains_from_segments(&mut self, chains_from_segments: bool) -> &mut Self {
+        self.chains_from_segments = chains_from_segments;
+        self
+    }
+
+    /// Open an atomic data file, either PDB, mmCIF/PDBx, PDBML, BinaryCIF, MMTF, PQR, GRO, MOL2, or XYZ, into a [`PDB`] structure.
     /// The correct type will be determined based on the file extension.
     ///
     /// # Errors
@@ -153,6 +186,13 @@ impl ReadOptions {
             match self.format {
                 Format::Pdb => super::pdb::open_pdb_with_options(path, self),
                 Format::Mmcif => super::mmcif::open_mmcif_with_options(path, self),
+                Format::Pdbml => super::mmcif::open_pdbml_with_options(path, self),
+                Format::BinaryCif => super::mmcif::open_bcif_with_options(path, self),
+                Format::Mmtf => super::mmtf::open_mmtf_with_options(path, self),
+                Format::Pqr => super::pqr::open_pqr_with_options(path, self),
+                Format::Gro => super::gro::open_gro_with_options(path, self),
+                Format::Mol2 => super::mol2::open_mol2_with_options(path, self),
+                Format::Xyz => super::xyz::open_xyz_with_options(path, self),
                 Format::Auto => self.read_auto(path),
             }
         }
@@ -179,10 +219,27 @@ impl ReadOptions {
                         super::pdb::open_pdb_raw_with_options(reader, Context::None, self)
                     }
                     Format::Mmcif => super::mmcif::open_mmcif_raw_with_options(reader, self),
+                    Format::Pdbml => super::mmcif::open_pdbml_raw_with_options(reader, self),
+                    Format::BinaryCif => super::mmcif::open_bcif_raw_with_options(reader, self),
+                    Format::Mmtf => {
+                        super::mmtf::open_mmtf_raw_with_options(reader, Context::None, self)
+                    }
+                    Format::Pqr => {
+                        super::pqr::open_pqr_raw_with_options(reader, Context::None, self)
+                    }
+                    Format::Gro => {
+                        super::gro::open_gro_raw_with_options(reader, Context::None, self)
+                    }
+                    Format::Mol2 => {
+                        super::mol2::open_mol2_raw_with_options(reader, Context::None, self)
+                    }
+                    Format::Xyz => {
+                        super::xyz::open_xyz_raw_with_options(reader, Context::None, self)
+                    }
                     Format::Auto => Err(vec![PDBError::new(
                         crate::ErrorLevel::BreakingError,
                         "Could not determine file type",
-                        "Could not determine the type of the gzipped file, use .pdb.gz or .cif.gz",
+                        "Could not determine the type of the gzipped file, use .pdb.gz, .cif.gz, .xml.gz, .bcif.gz, .mmtf.gz, .pqr.gz, .gro.gz, .mol2.gz, or .xyz.gz",
                         Context::show(filename),
                     )]),
                 }
@@ -190,10 +247,17 @@ impl ReadOptions {
                 match file_format {
                     Format::Pdb => super::pdb::open_pdb_with_options(path, self),
                     Format::Mmcif => super::mmcif::open_mmcif_with_options(path, self),
+                    Format::Pdbml => super::mmcif::open_pdbml_with_options(path, self),
+                    Format::BinaryCif => super::mmcif::open_bcif_with_options(path, self),
+                    Format::Mmtf => super::mmtf::open_mmtf_with_options(path, self),
+                    Format::Pqr => super::pqr::open_pqr_with_options(path, self),
+                    Format::Gro => super::gro::open_gro_with_options(path, self),
+                    Format::Mol2 => super::mol2::open_mol2_with_options(path, self),
+                    Format::Xyz => super::xyz::open_xyz_with_options(path, self),
                     _ => Err(vec![PDBError::new(
                         crate::ErrorLevel::BreakingError,
                         "Incorrect extension",
-                        "Could not determine the type of the given file extension, make it .pdb or .cif",
+                        "Could not determine the type of the given file extension, make it .pdb, .cif, .xml, .bcif, .mmtf, .pqr, .gro, .mol2, or .xyz",
                         Context::show(path.as_ref()),
                     )])
                 }
@@ -202,7 +266,7 @@ impl ReadOptions {
             Err(vec![PDBError::new(
                 crate::ErrorLevel::BreakingError,
                 "Missing extension",
-                "The given file does not have an extension, make it .pdb or .cif",
+                "The given file does not have an extension, make it .pdb, .cif, .xml, .bcif, .mmtf, .pqr, .gro, .mol2, or .xyz",
                 Context::show(path.as_ref()),
             )])
         }
@@ -221,6 +285,13 @@ impl ReadOptions {
         match self.format {
             Format::Pdb => super::pdb::open_pdb_raw_with_options(input, Context::None, self),
             Format::Mmcif => super::mmcif::open_mmcif_raw_with_options(input, self),
+            Format::Pdbml => super::mmcif::open_pdbml_raw_with_options(input, self),
+            Format::BinaryCif => super::mmcif::open_bcif_raw_with_options(input, self),
+            Format::Mmtf => super::mmtf::open_mmtf_raw_with_options(input, Context::None, self),
+            Format::Pqr => super::pqr::open_pqr_raw_with_options(input, Context::None, self),
+            Format::Gro => super::gro::open_gro_raw_with_options(input, Context::None, self),
+            Format::Mol2 => super::mol2::open_mol2_raw_with_options(input, Context::None, self),
+            Format::Xyz => super::xyz::open_xyz_raw_with_options(input, Context::None, self),
             Format::Auto => Err(vec![PDBError::new(
                 crate::ErrorLevel::BreakingError,
                 "Could not determine file type",
@@ -229,6 +300,369 @@ impl ReadOptions {
             )]),
         }
     }
+
+    /// Read the molecules in an MDL Molfile or SDF file (V2000 or V3000) into [`crate::Ligand`]s.
+    /// Every record in the file results in a single ligand with a hetero residue containing the
+    /// atoms, the bonds with their bond order, and the data items. A ligand can be placed in a
+    /// structure with [`crate::PDB::add_ligand`]. Gzipped files (`.sdf.gz`) are decompressed on the fly.
+    ///
+    /// The options to discard hydrogens and to only read the first model (here the first
+    /// molecule) are honoured. The format set with [`ReadOptions::set_format`] is ignored.
+    ///
+    /// ```no_run
+    /// use pdbtbx::*;
+    /// let (mut pdb, _errors) = ReadOptions::new().read("example-pdbs/1ubq.pdb").unwrap();
+    /// let (ligands, _errors) = ReadOptions::new().read_sdf("ligand.sdf").unwrap();
+    /// for ligand in ligands {
+    ///     pdb.add_ligand(ligand, "A");
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the file could not be opened or if any error fails with the set
+    /// level. Otherwise it returns the ligands with all errors/warnings found while parsing them.
+    ///
+    /// # Related
+    /// If you want to open a file from memory, see [`ReadOptions::read_sdf_raw`].
+    /// To save residues as SDF see [`crate::save_sdf`].
+    pub fn read_sdf(&self, path: impl AsRef<str>) -> SdfResult {
+        let filename = path.as_ref();
+        if self.decompress || filename.to_lowercase().ends_with(".gz") {
+            let file = std::fs::File::open(filename).map_err(|_| {
+                vec![PDBError::new(
+                    crate::ErrorLevel::BreakingError,
+                    "Could not open file",
+                    "Could not open the given file, make sure it exists and you have the correct permissions",
+                    Context::show(filename),
+                )]
+            })?;
+            let reader = std::io::BufReader::new(flate2::read::GzDecoder::new(file));
+            super::sdf::open_sdf_raw_with_options(reader, Context::show(filename), self)
+        } else {
+            super::sdf::open_sdf_with_options(filename, self)
+        }
+    }
+
+    /// Read the molecules in an MDL Molfile or SDF input stream into [`crate::Ligand`]s.
+    /// See [`ReadOptions::read_sdf`] for details.
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if any error fails with the set level.
+    pub fn read_sdf_raw<T>(&self, input: std::io::BufReader<T>) -> SdfResult
+    where
+        T: std::io::Read,
+    {
+        super::sdf::open_sdf_raw_with_options(input, Context::None, self)
+    }
+
+    /// Stream the atoms of a PDB or mmCIF file one by one, without building the full [`PDB`]
+    /// structure. The file is read line by line, so the memory use does not depend on the size
+    /// of the file. The format is determined in the same way as for [`ReadOptions::read`], and
+    /// gzipped files are decompressed on the fly.
+    ///
+    /// The options to discard hydrogens, to only read the first model, and to only read atomic
+    /// coordinates are honoured. See [`AtomStream`] for details on the handling of errors.
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the file could not be opened or the format could not be determined.
+    ///
+    /// # Related
+    /// If you want to read whole models at a time, see [`ReadOptions::stream_models`].
+    pub fn stream_atoms(
+        &self,
+        path: impl AsRef<str>,
+    ) -> Result<AtomStream<Box<dyn std::io::BufRead>>, PDBError> {
+        let filename = path.as_ref();
+        let (format, compressed) = match (self.format, guess_format(filename)) {
+            (Format::Auto, Some(guess)) => guess,
+            (Format::Auto, None) => {
+                return Err(PDBError::new(
+                    crate::ErrorLevel::BreakingError,
+                    "Could not determine file type",
+                    "Could not determine the type of the given file, make it .pdb or .cif",
+                    Context::show(filename),
+                ))
+            }
+            (format, guess) => (format, self.decompress || guess.map_or(false, |g| g.1)),
+        };
+        let file = std::fs::File::open(filename).map_err(|_| {
+            PDBError::new(
+                crate::ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the given file, make sure it exists and you have the correct permissions",
+                Context::show(filename),
+            )
+        })?;
+        let reader: Box<dyn std::io::BufRead> = if compressed {
+            Box::new(std::io::BufReader::new(flate2::read::GzDecoder::new(file)))
+        } else {
+            Box::new(std::io::BufReader::new(file))
+        };
+        self.stream_atoms_with_format(reader, format)
+    }
+
+    /// Stream the atoms of a PDB or mmCIF input stream one by one, without building the full
+    /// [`PDB`] structure. The file format **must** be set explicitly with [`ReadOptions::set_format`].
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the format is not set.
+    ///
+    /// # Related
+    /// If you want to stream a file, see [`ReadOptions::stream_atoms`].
+    pub fn stream_atoms_raw<T>(
+        &self,
+        input: std::io::BufReader<T>,
+    ) -> Result<AtomStream<std::io::BufReader<T>>, PDBError>
+    where
+        T: std::io::Read,
+    {
+        self.stream_atoms_with_format(input, self.format)
+    }
+
+    /// Stream the models of a PDB or mmCIF file one by one. Only a single model is kept in memory
+    /// at a time. See [`ReadOptions::stream_atoms`] for details. Information given after the
+    /// models, like the Rosetta pose energies table, is not read.
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the file could not be opened or the format could not be determined.
+    pub fn stream_models(
+        &self,
+        path: impl AsRef<str>,
+    ) -> Result<ModelStream<Box<dyn std::io::BufRead>>, PDBError> {
+        self.stream_atoms(path).map(ModelStream::new)
+    }
+
+    /// Stream the models of a PDB or mmCIF input stream one by one. Only a single model is kept
+    /// in memory at a time. The file format **must** be set explicitly with [`ReadOptions::set_format`].
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the format is not set.
+    pub fn stream_models_raw<T>(
+        &self,
+        input: std::io::BufReader<T>,
+    ) -> Result<ModelStream<std::io::BufReader<T>>, PDBError>
+    where
+        T: std::io::Read,
+    {
+        self.stream_atoms_raw(input).map(ModelStream::new)
+    }
+
+    /// Read the frames of a CHARMM/NAMD DCD or GROMACS XTC trajectory one by one. Only a single
+    /// frame is kept in memory at a time. The format is determined from the extension (`.dcd` or
+    /// `.xtc`), and gzipped files are decompressed on the fly. Every [`crate::Frame`] contains the
+    /// coordinates (in Å) of all atoms and the unit cell, if present, combine it with a topology
+    /// with [`crate::Frame::to_model`] or [`crate::Frame::to_pdb`].
+    ///
+    /// If the option to only read the first model is set only the first frame is read, the other
+    /// options are ignored.
+    ///
+    /// ```no_run
+    /// use pdbtbx::*;
+    /// let (topology, _errors) = ReadOptions::new().read("topology.pdb").unwrap();
+    /// for frame in ReadOptions::new().read_trajectory("trajectory.xtc").unwrap() {
+    ///     let model = frame.unwrap().to_model(&topology).unwrap();
+    ///     println!("{}", model.atoms().next().unwrap().x());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the file could not be opened, the format could not be determined,
+    /// or the header of the file is invalid.
+    ///
+    /// # Related
+    /// If you want to read a trajectory from memory, see [`ReadOptions::read_trajectory_raw`].
+    pub fn read_trajectory(
+        &self,
+        path: impl AsRef<str>,
+    ) -> Result<Trajectory<Box<dyn std::io::BufRead>>, PDBError> {
+        let filename = path.as_ref();
+        let (format, compressed) = TrajectoryFormat::guess(filename).ok_or_else(|| {
+            PDBError::new(
+                crate::ErrorLevel::BreakingError,
+                "Could not determine file type",
+                "Could not determine the type of the given trajectory, make it .dcd or .xtc",
+                Context::show(filename),
+            )
+        })?;
+        let file = std::fs::File::open(filename).map_err(|_| {
+            PDBError::new(
+                crate::ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the given file, make sure it exists and you have the correct permissions",
+                Context::show(filename),
+            )
+        })?;
+        let reader: Box<dyn std::io::BufRead> = if self.decompress || compressed {
+            Box::new(std::io::BufReader::new(flate2::read::GzDecoder::new(file)))
+        } else {
+            Box::new(std::io::BufReader::new(file))
+        };
+        self.trajectory_with_format(reader, format, Context::show(filename))
+    }
+
+    /// Read the frames of a DCD or XTC input stream one by one. See [`ReadOptions::read_trajectory`]
+    /// for details.
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the header of the input is invalid.
+    pub fn read_trajectory_raw<T>(
+        &self,
+        input: std::io::BufReader<T>,
+        format: TrajectoryFormat,
+    ) -> Result<Trajectory<std::io::BufReader<T>>, PDBError>
+    where
+        T: std::io::Read,
+    {
+        self.trajectory_with_format(input, format, Context::None)
+    }
+
+    /// Read a CCP4/MRC density map (`.map`, `.mrc`, or `.ccp4`, optionally gzipped). The values
+    /// can be sampled at arbitrary positions with [`crate::DensityMap::interpolate`], and compared
+    /// to a model with [`crate::DensityMap::atom_values`] and [`crate::DensityMap::residue_values`]
+    /// (or the Q-score variants with the `rstar` feature). The other options are ignored.
+    ///
+    /// ```no_run
+    /// use pdbtbx::*;
+    /// let (pdb, _errors) = ReadOptions::new().read("model.pdb").unwrap();
+    /// let map = ReadOptions::new().read_map("emd_1234.map.gz").unwrap();
+    /// for residue in map.residue_values(&pdb) {
+    ///     if let Some(summary) = residue.summary() {
+    ///         println!("{} {:.3}", residue.residue().serial_number(), summary.mean());
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the file could not be opened or if it is not a valid map.
+    ///
+    /// # Related
+    /// If you want to read a map from memory, see [`ReadOptions::read_map_raw`].
+    pub fn read_map(&self, path: impl AsRef<str>) -> Result<crate::DensityMap, PDBError> {
+        let filename = path.as_ref();
+        let file = std::fs::File::open(filename).map_err(|_| {
+            PDBError::new(
+                crate::ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the given file, make sure it exists and you have the correct permissions",
+                Context::show(filename),
+            )
+        })?;
+        if self.decompress || filename.to_lowercase().ends_with(".gz") {
+            open_map_raw(flate2::read::GzDecoder::new(file), Context::show(filename))
+        } else {
+            open_map_raw(std::io::BufReader::new(file), Context::show(filename))
+        }
+    }
+
+    /// Read a CCP4/MRC density map from an input stream. See [`ReadOptions::read_map`] for details.
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the input is not a valid map.
+    pub fn read_map_raw<T>(
+        &self,
+        input: std::io::BufReader<T>,
+    ) -> Result<crate::DensityMap, PDBError>
+    where
+        T: std::io::Read,
+    {
+        open_map_raw(input, Context::None)
+    }
+
+    /// Read a predicted aligned error (PAE) JSON file (optionally gzipped), as given by AlphaFold
+    /// next to the predicted structure. The residues are numbered by their index in the
+    /// structure, and can be split in confidently placed domains with
+    /// [`crate::PredictedAlignedError::domains`]. The other options are ignored.
+    ///
+    /// ```no_run
+    /// use pdbtbx::*;
+    /// let (pdb, _errors) = ReadOptions::new().read("AF-P69905-F1-model_v4.cif").unwrap();
+    /// let pae = ReadOptions::new()
+    ///     .read_pae("AF-P69905-F1-predicted_aligned_error_v4.json")
+    ///     .unwrap();
+    /// let residues: Vec<&Residue> = pdb.model(0).unwrap().residues().collect();
+    /// for domain in pae.domains(5.0, 20) {
+    ///     println!("Domain from {} to {}", residues[domain[0]].serial_number(), residues[domain[domain.len() - 1]].serial_number());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the file could not be opened or if it is not a valid PAE file.
+    ///
+    /// # Related
+    /// If you want to read a PAE file from memory, see [`ReadOptions::read_pae_raw`].
+    pub fn read_pae(
+        &self,
+        path: impl AsRef<str>,
+    ) -> Result<crate::PredictedAlignedError, PDBError> {
+        let filename = path.as_ref();
+        let file = std::fs::File::open(filename).map_err(|_| {
+            PDBError::new(
+                crate::ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the given file, make sure it exists and you have the correct permissions",
+                Context::show(filename),
+            )
+        })?;
+        if self.decompress || filename.to_lowercase().ends_with(".gz") {
+            open_pae_raw(flate2::read::GzDecoder::new(file), Context::show(filename))
+        } else {
+            open_pae_raw(std::io::BufReader::new(file), Context::show(filename))
+        }
+    }
+
+    /// Read a predicted aligned error (PAE) JSON file from an input stream. See
+    /// [`ReadOptions::read_pae`] for details.
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the input is not a valid PAE file.
+    pub fn read_pae_raw<T>(
+        &self,
+        input: std::io::BufReader<T>,
+    ) -> Result<crate::PredictedAlignedError, PDBError>
+    where
+        T: std::io::Read,
+    {
+        open_pae_raw(input, Context::None)
+    }
+
+    /// Create the trajectory for the given format
+    fn trajectory_with_format<R: std::io::Read>(
+        &self,
+        input: R,
+        format: TrajectoryFormat,
+        context: Context,
+    ) -> Result<Trajectory<R>, PDBError> {
+        match format {
+            TrajectoryFormat::Dcd => Trajectory::dcd(input, context, self.only_first_model),
+            TrajectoryFormat::Xtc => Ok(Trajectory::xtc(input, context, self.only_first_model)),
+        }
+    }
+
+    /// Create the atom stream for the given format
+    fn stream_atoms_with_format<R: std::io::BufRead>(
+        &self,
+        input: R,
+        format: Format,
+    ) -> Result<AtomStream<R>, PDBError> {
+        match format {
+            Format::Pdb => Ok(AtomStream::pdb(super::pdb::PdbAtomStream::new(input, self))),
+            Format::Mmcif => Ok(AtomStream::mmcif(super::mmcif::MmcifAtomStream::new(
+                input, self,
+            ))),
+            Format::Pdbml | Format::BinaryCif | Format::Mmtf | Format::Pqr | Format::Gro | Format::Mol2 | Format::Xyz => Err(PDBError::new(
+                crate::ErrorLevel::BreakingError,
+                "Streaming not supported",
+                "Streaming is only supported for PDB and mmCIF files, use ReadOptions::read instead",
+                Context::None,
+            )),
+            Format::Auto => Err(PDBError::new(
+                crate::ErrorLevel::BreakingError,
+                "Could not determine file type",
+                "Could not determine the type of the input stream, set self.format",
+                Context::None,
+            )),
+        }
+    }
 }
 
 /// Guess the file format based on the file name extensions.
@@ -238,11 +672,25 @@ fn guess_format(filename: &str) -> Option<(Format, bool)> {
     match path.extension().and_then(OsStr::to_str) {
         Some("pdb") | Some("pdb1") => Some((Format::Pdb, false)),
         Some("cif") | Some("mmcif") => Some((Format::Mmcif, false)),
+        Some("xml") | Some("pdbml") => Some((Format::Pdbml, false)),
+        Some("bcif") => Some((Format::BinaryCif, false)),
+        Some("mmtf") => Some((Format::Mmtf, false)),
+        Some("pqr") => Some((Format::Pqr, false)),
+        Some("gro") => Some((Format::Gro, false)),
+        Some("mol2") => Some((Format::Mol2, false)),
+        Some("xyz") | Some("extxyz") => Some((Format::Xyz, false)),
         Some("gz") => {
             let path_ext = Path::new(path.file_stem().and_then(OsStr::to_str).unwrap_or(""));
             match path_ext.extension().and_then(OsStr::to_str) {
                 Some("pdb") | Some("pdb1") => Some((Format::Pdb, true)),
                 Some("cif") | Some("mmcif") => Some((Format::Mmcif, true)),
+                Some("xml") | Some("pdbml") => Some((Format::Pdbml, true)),
+                Some("bcif") => Some((Format::BinaryCif, true)),
+                Some("mmtf") => Some((Format::Mmtf, true)),
+                Some("pqr") => Some((Format::Pqr, true)),
+                Some("gro") => Some((Format::Gro, true)),
+                Some("mol2") => Some((Format::Mol2, true)),
+                Some("xyz") | Some("extxyz") => Some((Format::Xyz, true)),
                 _ => None,
             }
         }
@@ -0,0 +1,601 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::error::*;
+use crate::structs::*;
+use crate::ReadOptions;
+
+/// The result of reading an SDF file, the molecules and the warnings found while reading
+pub type SdfResult = Result<(Vec<Ligand>, Vec<PDBError>), Vec<PDBError>>;
+
+/// The residue name given to the molecules read from SDF files
+const RESIDUE_NAME: &str = "LIG";
+
+/// Parse the given MDL Molfile or SDF file into ligands with [`ReadOptions`].
+pub(crate) fn open_sdf_with_options(filename: impl AsRef<str>, options: &ReadOptions) -> SdfResult {
+    let filename = filename.as_ref();
+    let file = if let Ok(f) = File::open(filename) {
+        f
+    } else {
+        return Err(vec![PDBError::new(ErrorLevel::BreakingError, "Could not open file", "Could not open the specified file, make sure the path is correct, you have permission, and that it is not open in another program.", Context::show(filename))]);
+    };
+    open_sdf_raw_with_options(BufReader::new(file), Context::show(filename), options)
+}
+
+/// Parse the input stream in the MDL Molfile or SDF format into ligands with [`ReadOptions`].
+///
+/// Both the V2000 and V3000 connection tables are supported. Every record results in a single
+/// [`Ligand`], consisting of a hetero residue named `LIG` with the atoms named by their element
+/// and index (`C1`, `C2`, `O1`), the bonds with their bond order, and the data items. Records
+/// that cannot be read are skipped with an `InvalidatingError`. If only the first model should be
+/// read, only the first record is read.
+pub(crate) fn open_sdf_raw_with_options<T>(
+    input: BufReader<T>,
+    context: Context,
+    options: &ReadOptions,
+) -> SdfResult
+where
+    T: std::io::Read,
+{
+    let mut errors = Vec::new();
+    let mut ligands = Vec::new();
+    let mut record = Vec::new();
+    let mut lines = input.lines().enumerate().peekable();
+    while let Some((index, line)) = lines.next() {
+        let line = if let Ok(line) = line {
+            line
+        } else {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could read line",
+                format!(
+                    "Could not read line {} while parsing the input file.",
+                    index + 1
+                ),
+                context,
+            ));
+            return Err(errors);
+        };
+        let last = lines.peek().is_none();
+        let end = line.starts_with("$$$$");
+        if !end {
+            record.push((index + 1, line));
+        }
+        if end || last {
+            if record.iter().any(|(_, line)| !line.trim().is_empty()) {
+                if let Some(ligand) = parse_record(&record, options, &mut errors) {
+                    ligands.push(ligand);
+                }
+                if options.only_first_model {
+                    break;
+                }
+            }
+            record.clear();
+        }
+    }
+
+    if errors.iter().any(|e| e.fails(options.level)) {
+        Err(errors)
+    } else {
+        Ok((ligands, errors))
+    }
+}
+
+/// An atom as read from the connection table: element, position, and charge
+type TableAtom = (Element, (f64, f64, f64), isize);
+
+/// A bond as read from the connection table: the indices of the atoms and the bond
+type TableBond = (usize, usize, Bond);
+
+/// Parse a single molfile record (without the `$$$$` line), returns `None` if it is invalid
+fn parse_record(
+    record: &[(usize, String)],
+    options: &ReadOptions,
+    errors: &mut Vec<PDBError>,
+) -> Option<Ligand> {
+    let error = |(linenumber, line): &(usize, String), message: String| {
+        PDBError::new(
+            ErrorLevel::InvalidatingError,
+            "Invalid molfile",
+            format!("{message} This molecule is skipped."),
+            Context::full_line(*linenumber, line),
+        )
+    };
+    if record.len() < 4 {
+        errors.push(error(
+            record.last()?,
+            "A molfile should start with three header lines and a counts line.".to_string(),
+        ));
+        return None;
+    }
+    let name = record[0].1.trim().to_string();
+    let result = if record[3].1.contains("V3000") {
+        parse_v3000(&record[4..], errors)
+    } else {
+        parse_v2000(&record[3..], errors)
+    };
+    let (atoms, bonds, data_start) = match result {
+        Ok(table) => table,
+        Err((line, message)) => {
+            errors.push(error(line, message));
+            return None;
+        }
+    };
+
+    // Build the residue, keeping track of the new index of every atom
+    let mut conformer =
+        Conformer::new(RESIDUE_NAME, None, None).expect("Invalid characters in conformer creation");
+    let mut indices = Vec::with_capacity(atoms.len());
+    let mut element_counts: HashMap<Element, usize> = HashMap::new();
+    for (serial_number, (element, (x, y, z), charge)) in atoms.into_iter().enumerate() {
+        if options.discard_hydrogens && element == Element::H {
+            indices.push(None);
+            continue;
+        }
+        let count = element_counts.entry(element).or_insert(0);
+        *count += 1;
+        let atom = Atom::new(
+            true,
+            serial_number + 1,
+            format!("{}{}", element.symbol(), count),
+            x,
+            y,
+            z,
+            1.0,
+            0.0,
+            element.symbol(),
+            charge,
+        )
+        .expect("Invalid characters in atom creation");
+        indices.push(Some(conformer.atom_count()));
+        conformer.add_atom(atom);
+    }
+    let bonds: Vec<_> = bonds
+        .into_iter()
+        .filter_map(|(a, b, bond)| Some((indices[a]?, indices[b]?, bond)))
+        .collect();
+    let residue =
+        Residue::new(1, None, Some(conformer)).expect("Invalid characters in residue creation");
+
+    Some(Ligand::new(
+        name,
+        residue,
+        &bonds,
+        parse_data_items(&record[data_start.min(record.len())..]),
+    ))
+}
+
+/// The error type of the connection table parsers, the line and the message
+type TableError<'a> = (&'a (usize, String), String);
+
+/// Parse a V2000 connection table starting at the counts line. Returns the atoms, bonds, and the
+/// index in the record of the first line after the properties block (`M  END`).
+fn parse_v2000<'a>(
+    lines: &'a [(usize, String)],
+    errors: &mut Vec<PDBError>,
+) -> Result<(Vec<TableAtom>, Vec<TableBond>, usize), TableError<'a>> {
+    let counts = &lines[0];
+    let field = |line: &'a (usize, String), start: usize, end: usize| {
+        line.1
+            .get(start..end.min(line.1.len()))
+            .map(str::trim)
+            .ok_or_else(|| {
+                (
+                    line,
+                    format!(
+                        "The line is too short, it should contain a field at columns {}-{}.",
+                        start + 1,
+                        end
+                    ),
+                )
+            })
+    };
+    let integer = |line: &'a (usize, String), start: usize, end: usize| {
+        let text = field(line, start, end)?;
+        text.parse::<isize>()
+            .map_err(|_| (line, format!("The value \"{text}\" is not a valid number.")))
+    };
+    let number = |line: &'a (usize, String), start: usize, end: usize| {
+        let text = field(line, start, end)?;
+        text.parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| (line, format!("The value \"{text}\" is not a valid number.")))
+    };
+
+    let atom_count = usize::try_from(integer(counts, 0, 3)?)
+        .map_err(|_| (counts, "The atom count is negative.".to_string()))?;
+    let bond_count = usize::try_from(integer(counts, 3, 6)?)
+        .map_err(|_| (counts, "The bond count is negative.".to_string()))?;
+    if lines.len() < 1 + atom_count + bond_count {
+        return Err((
+            lines.last().unwrap_or(counts),
+            format!(
+                "The molfile ended before all {atom_count} atoms and {bond_count} bonds were read."
+            ),
+        ));
+    }
+
+    let mut atoms = Vec::with_capacity(atom_count);
+    for line in &lines[1..=atom_count] {
+        let symbol = field(line, 31, 34)?;
+        let element = parse_element(symbol).ok_or_else(|| {
+            (
+                line,
+                format!("The element \"{symbol}\" is not a valid element."),
+            )
+        })?;
+        let charge = if line.1.len() > 36 {
+            match integer(line, 36, 39)? {
+                0 | 4 => 0,
+                code @ 1..=7 => 4 - code,
+                code => {
+                    return Err((line, format!("The charge code \"{code}\" is not valid.")));
+                }
+            }
+        } else {
+            0
+        };
+        atoms.push((
+            element,
+            (
+                number(line, 0, 10)?,
+                number(line, 10, 20)?,
+                number(line, 20, 30)?,
+            ),
+            charge,
+        ));
+    }
+
+    let mut bonds = Vec::with_capacity(bond_count);
+    for line in &lines[1 + atom_count..1 + atom_count + bond_count] {
+        let a = atom_index(integer(line, 0, 3)?, atom_count, line)?;
+        let b = atom_index(integer(line, 3, 6)?, atom_count, line)?;
+        bonds.push((a, b, bond_type(integer(line, 6, 9)?, line, errors)));
+    }
+
+    // The properties block, only the charges are used
+    let mut charges = Vec::new();
+    let mut end = lines.len();
+    for (index, line) in lines.iter().enumerate().skip(1 + atom_count + bond_count) {
+        if line.1.starts_with("M  END") {
+            end = index + 1;
+            break;
+        } else if line.1.starts_with("M  CHG") {
+            let values: Vec<&str> = line.1[6..].split_whitespace().collect();
+            for pair in values.get(1..).unwrap_or(&[]).chunks(2) {
+                if let [index, charge] = pair {
+                    let index = index.parse::<isize>().map_err(|_| {
+                        (
+                            line,
+                            format!("The value \"{index}\" is not a valid number."),
+                        )
+                    })?;
+                    let charge = charge.parse::<isize>().map_err(|_| {
+                        (
+                            line,
+                            format!("The value \"{charge}\" is not a valid number."),
+                        )
+                    })?;
+                    charges.push((atom_index(index, atom_count, line)?, charge));
+                }
+            }
+        }
+    }
+    // Charges in the properties block supersede all charges in the atom block
+    if !charges.is_empty() {
+        for atom in &mut atoms {
+            atom.2 = 0;
+        }
+        for (index, charge) in charges {
+            atoms[index].2 = charge;
+        }
+    }
+    // The counts line is the fourth line of the record
+    Ok((atoms, bonds, end + 3))
+}
+
+/// Parse a V3000 connection table starting after the counts line. Returns the atoms, bonds, and
+/// the index in the record of the first line after the connection table (`M  END`).
+fn parse_v3000<'a>(
+    lines: &'a [(usize, String)],
+    errors: &mut Vec<PDBError>,
+) -> Result<(Vec<TableAtom>, Vec<TableBond>, usize), TableError<'a>> {
+    // Join the continuation lines, keeping the first line for the error messages
+    let mut entries: Vec<(&'a (usize, String), String)> = Vec::new();
+    let mut continued = false;
+    let mut end = lines.len();
+    for (index, line) in lines.iter().enumerate() {
+        if line.1.starts_with("M  END") {
+            end = index + 1;
+            break;
+        }
+        if let Some(text) = line.1.strip_prefix("M  V30 ") {
+            let text = text.trim_end();
+            let (text, next_continues) = match text.strip_suffix('-') {
+                Some(text) => (text, true),
+                None => (text, false),
+            };
+            match entries.last_mut() {
+                Some(last) if continued => last.1.push_str(text),
+                _ => entries.push((line, text.to_string())),
+            }
+            continued = next_continues;
+        }
+    }
+
+    let mut atoms = Vec::new();
+    let mut atom_indices = HashMap::new();
+    let mut bond_entries = Vec::new();
+    let mut section = "";
+    for (line, text) in &entries {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        match fields.as_slice() {
+            ["BEGIN", name, ..] => section = name,
+            ["END", ..] => section = "",
+            [index, symbol, x, y, z, rest @ ..] if section == "ATOM" => {
+                let number = |text: &str| {
+                    text.parse::<f64>()
+                        .ok()
+                        .filter(|v| v.is_finite())
+                        .ok_or_else(|| {
+                            (
+                                *line,
+                                format!("The value \"{text}\" is not a valid number."),
+                            )
+                        })
+                };
+                let element = parse_element(symbol).ok_or_else(|| {
+                    (
+                        *line,
+                        format!("The element \"{symbol}\" is not a valid element."),
+                    )
+                })?;
+                let charge = rest
+                    .iter()
+                    .find_map(|field| field.strip_prefix("CHG="))
+                    .map_or(Ok(0), |charge| {
+                        charge.parse::<isize>().map_err(|_| {
+                            (
+                                *line,
+                                format!("The charge \"{charge}\" is not a valid number."),
+                            )
+                        })
+                    })?;
+                atom_indices.insert(index.to_string(), atoms.len());
+                atoms.push((element, (number(x)?, number(y)?, number(z)?), charge));
+            }
+            [_, kind, a, b, ..] if section == "BOND" => bond_entries.push((*line, *kind, *a, *b)),
+            _ => (),
+        }
+    }
+
+    let mut bonds = Vec::with_capacity(bond_entries.len());
+    for (line, kind, a, b) in bond_entries {
+        let index = |text: &str| {
+            atom_indices.get(text).copied().ok_or_else(|| {
+                (
+                    line,
+                    format!("The bond refers to a non existing atom \"{text}\"."),
+                )
+            })
+        };
+        let kind = kind.parse::<isize>().map_err(|_| {
+            (
+                line,
+                format!("The bond type \"{kind}\" is not a valid number."),
+            )
+        })?;
+        bonds.push((index(a)?, index(b)?, bond_type(kind, line, errors)));
+    }
+    // The counts line is the fourth line of the record
+    Ok((atoms, bonds, end + 4))
+}
+
+/// Get the zero based index of an atom from the one based index in the file
+fn atom_index(
+    index: isize,
+    atom_count: usize,
+    line: &(usize, String),
+) -> Result<usize, TableError<'_>> {
+    usize::try_from(index)
+        .ok()
+        .filter(|i| (1..=atom_count).contains(i))
+        .map(|i| i - 1)
+        .ok_or_else(|| {
+            (
+                line,
+                format!("The bond refers to a non existing atom \"{index}\"."),
+            )
+        })
+}
+
+/// Get the element for an atom symbol, deuterium and tritium are read as hydrogen
+fn parse_element(symbol: &str) -> Option<Element> {
+    match symbol {
+        "D" | "T" => Some(Element::H),
+        _ => Element::from_symbol(symbol),
+    }
+}
+
+/// Get the bond for the given bond type, query bond types are read as covalent bonds with a warning
+fn bond_type(kind: isize, line: &(usize, String), errors: &mut Vec<PDBError>) -> Bond {
+    match kind {
+        1 => Bond::Covalent,
+        2 => Bond::Double,
+        3 => Bond::Triple,
+        4 => Bond::Aromatic,
+        9 => Bond::MetalCoordination,
+        10 => Bond::Hydrogen,
+        _ => {
+            errors.push(PDBError::new(
+                ErrorLevel::LooseWarning,
+                "Unsupported bond type",
+                format!(
+                    "The bond type \"{kind}\" is a query bond type, it is read as a covalent bond."
+                ),
+                Context::full_line(line.0, &line.1),
+            ));
+            Bond::Covalent
+        }
+    }
+}
+
+/// Parse the data items of an SDF record (`> <name>` followed by the value lines)
+fn parse_data_items(lines: &[(usize, String)]) -> Vec<(String, String)> {
+    let mut items = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+    for (_, line) in lines {
+        if line.starts_with('>') {
+            if let Some((name, value)) = current.take() {
+                items.push((name, value.join("\n")));
+            }
+            let name = line
+                .find('<')
+                .and_then(|start| {
+                    line[start + 1..]
+                        .find('>')
+                        .map(|end| &line[start + 1..start + 1 + end])
+                })
+                .unwrap_or("");
+            current = Some((name.to_string(), Vec::new()));
+        } else if line.trim().is_empty() {
+            if let Some((name, value)) = current.take() {
+                items.push((name, value.join("\n")));
+            }
+        } else if let Some((_, value)) = &mut current {
+            value.push(line);
+        }
+    }
+    if let Some((name, value)) = current {
+        items.push((name, value.join("\n")));
+    }
+    items
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::StrictnessLevel;
+
+    const ACETATE: &str = "acetate
+  pdbtbx          3D
+
+  4  3  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.5000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    2.1000    1.0500    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+    2.1000   -1.0500    0.0000 O   0  5  0  0  0  0  0  0  0  0  0  0
+  1  2  1  0  0  0  0
+  2  3  2  0  0  0  0
+  2  4  1  0  0  0  0
+M  CHG  1   4  -1
+M  END
+> <ID>
+CHEBI:30089
+
+> <NOTE>
+first line
+second line
+
+$$$$
+";
+
+    const ETHYNE_V3000: &str = "ethyne
+  pdbtbx          3D
+
+  0  0  0     0  0            999 V3000
+M  V30 BEGIN CTAB
+M  V30 COUNTS 4 3 0 0 0
+M  V30 BEGIN ATOM
+M  V30 1 C 0.0 0.0 0.0 0
+M  V30 2 C 1.2 0.0 0.0 0
+M  V30 3 H -1.06 0.0 0.0 0
+M  V30 4 H 2.26 0.0 -
+M  V30 0.0 0
+M  V30 END ATOM
+M  V30 BEGIN BOND
+M  V30 1 3 1 2
+M  V30 2 1 1 3
+M  V30 3 1 2 4
+M  V30 END BOND
+M  V30 END CTAB
+M  END
+$$$$
+";
+
+    fn open(text: &str, options: &ReadOptions) -> SdfResult {
+        open_sdf_raw_with_options(BufReader::new(text.as_bytes()), Context::None, options)
+    }
+
+    #[test]
+    fn read_v2000() {
+        let (ligands, errors) = open(ACETATE, &ReadOptions::new()).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(ligands.len(), 1);
+        let ligand = &ligands[0];
+        assert_eq!(ligand.name(), "acetate");
+        assert_eq!(ligand.residue().name(), Some("LIG"));
+        let names: Vec<&str> = ligand.residue().atoms().map(Atom::name).collect();
+        assert_eq!(names, ["C1", "C2", "O1", "O2"]);
+        assert!(ligand.residue().atoms().all(Atom::hetero));
+        assert_eq!(ligand.residue().atom(3).unwrap().charge(), -1);
+        assert_eq!(ligand.residue().atom(1).unwrap().pos(), (1.5, 0.0, 0.0));
+        let bonds: Vec<_> = ligand
+            .bonds()
+            .map(|(a, b, bond)| (a.name(), b.name(), bond))
+            .collect();
+        assert_eq!(
+            bonds,
+            [
+                ("C1", "C2", Bond::Covalent),
+                ("C2", "O1", Bond::Double),
+                ("C2", "O2", Bond::Covalent)
+            ]
+        );
+        assert_eq!(ligand.property("ID"), Some("CHEBI:30089"));
+        assert_eq!(ligand.property("NOTE"), Some("first line\nsecond line"));
+    }
+
+    #[test]
+    fn read_v3000() {
+        let (ligands, errors) = open(ETHYNE_V3000, &ReadOptions::new()).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        let ligand = &ligands[0];
+        assert_eq!(ligand.residue().atom_count(), 4);
+        assert_eq!(ligand.residue().atom(3).unwrap().pos(), (2.26, 0.0, 0.0));
+        assert_eq!(ligand.bonds().next().unwrap().2, Bond::Triple);
+        assert_eq!(ligand.bonds().count(), 3);
+
+        let (ligands, _) =
+            open(ETHYNE_V3000, ReadOptions::new().set_discard_hydrogens(true)).unwrap();
+        assert_eq!(ligands[0].residue().atom_count(), 2);
+        assert_eq!(ligands[0].bonds().count(), 1);
+    }
+
+    #[test]
+    fn multiple_records() {
+        let text = format!("{ACETATE}{ETHYNE_V3000}");
+        let (ligands, _) = open(&text, &ReadOptions::new()).unwrap();
+        assert_eq!(ligands.len(), 2);
+        assert_eq!(ligands[1].name(), "ethyne");
+        let (ligands, _) = open(&text, ReadOptions::new().set_only_first_model(true)).unwrap();
+        assert_eq!(ligands.len(), 1);
+        // A molfile without the record separator
+        let (ligands, _) = open(ACETATE.trim_end_matches("$$$$\n"), &ReadOptions::new()).unwrap();
+        assert_eq!(ligands.len(), 1);
+    }
+
+    #[test]
+    fn invalid() {
+        let text = ACETATE.replace("  2  4  1", "  2  9  1");
+        let errors = open(&text, &ReadOptions::new()).unwrap_err();
+        assert_eq!(errors[0].short_description(), "Invalid molfile");
+        let text = ACETATE.replace("  2  3  2", "  2  3  8");
+        let (_, errors) =
+            open(&text, ReadOptions::new().set_level(StrictnessLevel::Loose)).unwrap();
+        assert_eq!(errors[0].short_description(), "Unsupported bond type");
+    }
+}
@@ -0,0 +1,427 @@
+use std::io::BufRead;
+
+use crate::error::*;
+use crate::structs::*;
+use crate::validate::reshuffle_residue_conformers;
+
+use super::mmcif::MmcifAtomStream;
+use super::pdb::PdbAtomStream;
+
+/// A single atom read from a stream, together with all identifiers needed to place it in the
+/// PDB hierarchy. These are the same identifiers as used by [`Model::add_atom`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomRecord {
+    /// The serial number of the model this atom is part of
+    model_serial_number: usize,
+    /// The identifier of the chain this atom is part of
+    chain_id: String,
+    /// The serial number of the residue this atom is part of
+    residue_serial_number: isize,
+    /// The insertion code of the residue this atom is part of
+    insertion_code: Option<String>,
+    /// The name of the conformer this atom is part of
+    conformer_name: String,
+    /// The alternative location of the conformer this atom is part of
+    alternative_location: Option<String>,
+    /// The atom itself
+    atom: Atom,
+}
+
+impl AtomRecord {
+    /// Create a new AtomRecord
+    pub(crate) fn new(
+        model_serial_number: usize,
+        chain_id: impl Into<String>,
+        residue_id: (isize, Option<String>),
+        conformer_id: (impl Into<String>, Option<String>),
+        atom: Atom,
+    ) -> Self {
+        AtomRecord {
+            model_serial_number,
+            chain_id: chain_id.into(),
+            residue_serial_number: residue_id.0,
+            insertion_code: residue_id.1,
+            conformer_name: conformer_id.0.into(),
+            alternative_location: conformer_id.1,
+            atom,
+        }
+    }
+
+    /// The serial number of the model this atom is part of
+    pub const fn model_serial_number(&self) -> usize {
+        self.model_serial_number
+    }
+
+    /// The identifier of the chain this atom is part of
+    pub fn chain_id(&self) -> &str {
+        &self.chain_id
+    }
+
+    /// The serial number of the residue this atom is part of
+    pub const fn residue_serial_number(&self) -> isize {
+        self.residue_serial_number
+    }
+
+    /// The insertion code of the residue this atom is part of
+    pub fn insertion_code(&self) -> Option<&str> {
+        self.insertion_code.as_deref()
+    }
+
+    /// The identifier of the residue this atom is part of, as used by [`Residue::id`]
+    pub fn residue_id(&self) -> (isize, Option<&str>) {
+        (self.residue_serial_number, self.insertion_code())
+    }
+
+    /// The name of the conformer (the residue name) this atom is part of
+    pub fn conformer_name(&self) -> &str {
+        &self.conformer_name
+    }
+
+    /// The alternative location of the conformer this atom is part of
+    pub fn alternative_location(&self) -> Option<&str> {
+        self.alternative_location.as_deref()
+    }
+
+    /// The identifier of the conformer this atom is part of, as used by [`Conformer::id`]
+    pub fn conformer_id(&self) -> (&str, Option<&str>) {
+        (&self.conformer_name, self.alternative_location())
+    }
+
+    /// The atom
+    pub const fn atom(&self) -> &Atom {
+        &self.atom
+    }
+
+    /// The atom, mutable
+    pub fn atom_mut(&mut self) -> &mut Atom {
+        &mut self.atom
+    }
+
+    /// Get the atom, consuming this record
+    pub fn into_atom(self) -> Atom {
+        self.atom
+    }
+
+    /// Add the atom to the given model, creating the chain, residue, and conformer if needed
+    fn add_to_model(self, model: &mut Model) {
+        model.add_atom(
+            self.atom,
+            self.chain_id,
+            (self.residue_serial_number, self.insertion_code.as_deref()),
+            (self.conformer_name, self.alternative_location.as_deref()),
+        );
+    }
+}
+
+/// The underlying format specific streams
+enum AtomStreamKind<R> {
+    /// A PDB file
+    Pdb(Box<PdbAtomStream<R>>),
+    /// A mmCIF file
+    Mmcif(Box<MmcifAtomStream<R>>),
+}
+
+/// An iterator over all atoms in a structure file, reading the file line by line without
+/// building the full [`PDB`] structure. This keeps the memory use bounded, independent of the
+/// size of the file. Create one with [`ReadOptions::stream_atoms`] or [`ReadOptions::stream_atoms_raw`].
+///
+/// Only errors that fail at the strictness level of the [`ReadOptions`] are reported, any
+/// warnings are discarded. After an error is returned the stream ends. Because the full structure
+/// is never built no structure wide validation is done, and records other than the atomic
+/// coordinates (and anisotropic temperature factors) are skipped.
+pub struct AtomStream<R> {
+    /// The format specific stream
+    inner: AtomStreamKind<R>,
+    /// If the stream has ended
+    done: bool,
+}
+
+impl<R: BufRead> AtomStream<R> {
+    /// Create a stream over a PDB file
+    pub(crate) fn pdb(stream: PdbAtomStream<R>) -> Self {
+        AtomStream {
+            inner: AtomStreamKind::Pdb(Box::new(stream)),
+            done: false,
+        }
+    }
+
+    /// Create a stream over a mmCIF file
+    pub(crate) fn mmcif(stream: MmcifAtomStream<R>) -> Self {
+        AtomStream {
+            inner: AtomStreamKind::Mmcif(Box::new(stream)),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for AtomStream<R> {
+    type Item = Result<AtomRecord, PDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = match &mut self.inner {
+            AtomStreamKind::Pdb(stream) => stream.next(),
+            AtomStreamKind::Mmcif(stream) => stream.next(),
+        };
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+impl<R> std::fmt::Debug for AtomStream<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = match self.inner {
+            AtomStreamKind::Pdb(_) => "PDB",
+            AtomStreamKind::Mmcif(_) => "mmCIF",
+        };
+        write!(f, "AtomStream {{ format: {format}, done: {} }}", self.done)
+    }
+}
+
+/// An iterator over all models in a structure file, reading the file line by line and only
+/// keeping a single model in memory at a time. Create one with [`ReadOptions::stream_models`]
+/// or [`ReadOptions::stream_models_raw`]. See [`AtomStream`] for the details on error handling.
+#[derive(Debug)]
+pub struct ModelStream<R> {
+    /// The stream of atoms
+    atoms: AtomStream<R>,
+    /// The first atom of the next model, if already read
+    next_atom: Option<AtomRecord>,
+}
+
+impl<R: BufRead> ModelStream<R> {
+    /// Create a stream over all models in the given atom stream
+    pub(crate) const fn new(atoms: AtomStream<R>) -> Self {
+        ModelStream {
+            atoms,
+            next_atom: None,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ModelStream<R> {
+    type Item = Result<Model, PDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = match self.next_atom.take() {
+            Some(record) => record,
+            None => match self.atoms.next()? {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            },
+        };
+        let mut model = Model::new(first.model_serial_number());
+        first.add_to_model(&mut model);
+
+        for record in &mut self.atoms {
+            match record {
+                Ok(record) if record.model_serial_number() == model.serial_number() => {
+                    record.add_to_model(&mut model);
+                }
+                Ok(record) => {
+                    self.next_atom = Some(record);
+                    break;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        for residue in model.residues_mut() {
+            reshuffle_residue_conformers(residue);
+        }
+        Some(Ok(model))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{Format, ReadOptions, StrictnessLevel};
+    use std::path::Path;
+
+    fn example(name: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("example-pdbs")
+            .join(name)
+            .into_os_string()
+            .into_string()
+            .unwrap()
+    }
+
+    #[test]
+    fn stream_atoms_matches_read() {
+        for name in ["1ubq.pdb", "1ubq.cif", "1yyf.pdb", "1yyf.cif"] {
+            let mut options = ReadOptions::default();
+            options.set_level(StrictnessLevel::Loose);
+            let (pdb, _) = options.read(example(name)).unwrap();
+            let records = options
+                .stream_atoms(example(name))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(records.len(), pdb.total_atom_count(), "{name}");
+            // The stream is in file order, the hierarchy is grouped by chain
+            let hierarchies = pdb
+                .atoms_with_hierarchy()
+                .map(|h| ((h.model().serial_number(), h.atom().serial_number()), h))
+                .collect::<std::collections::HashMap<_, _>>();
+            for record in &records {
+                let hierarchy =
+                    &hierarchies[&(record.model_serial_number(), record.atom().serial_number())];
+                assert_eq!(record.atom(), hierarchy.atom());
+                assert_eq!(
+                    record.model_serial_number(),
+                    hierarchy.model().serial_number()
+                );
+                assert_eq!(record.chain_id(), hierarchy.chain().id());
+                assert_eq!(record.residue_id(), hierarchy.residue().id());
+                assert_eq!(record.conformer_id(), hierarchy.conformer().id());
+            }
+        }
+    }
+
+    #[test]
+    fn stream_models_matches_read() {
+        for name in ["models.pdb", "1ubq.cif", "rosetta_model.pdb"] {
+            let mut options = ReadOptions::default();
+            options.set_level(StrictnessLevel::Loose);
+            let (mut pdb, _) = options.read(example(name)).unwrap();
+            // The pose energies table of Rosetta models is not read when streaming
+            pdb.residues_mut().for_each(Residue::remove_energies);
+            let models = options
+                .stream_models(example(name))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(models.len(), pdb.model_count(), "{name}");
+            for (streamed, read) in models.iter().zip(pdb.models()) {
+                assert_eq!(streamed, read, "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn stream_options() {
+        let mut options = ReadOptions::default();
+        options
+            .set_level(StrictnessLevel::Loose)
+            .set_discard_hydrogens(true)
+            .set_only_first_model(true);
+        let (pdb, _) = options.read(example("models.pdb")).unwrap();
+        let models = options
+            .stream_models(example("models.pdb"))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(&models[0], pdb.model(0).unwrap());
+        assert!(models[0].atoms().all(|a| a.element() != Some(&Element::H)));
+    }
+
+    #[test]
+    fn stream_raw() {
+        let input = "ATOM      1  N   MET A   1      27.340  24.430   2.614  1.00  9.67           N\n\
+                     ATOM      2  CA  MET A   1      26.266  25.413   2.842  1.00 10.38           C\n\
+                     TER\n\
+                     HETATM    3  O   HOH     2      26.913  26.639   3.531  1.00  8.61           O\n";
+        let records = ReadOptions::default()
+            .set_format(Format::Pdb)
+            .stream_atoms_raw(std::io::BufReader::new(input.as_bytes()))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].chain_id(), "A");
+        assert_eq!(records[1].atom().name(), "CA");
+        assert_eq!(records[2].chain_id(), "B");
+        assert!(records[2].atom().hetero());
+        assert!(ReadOptions::default()
+            .stream_atoms_raw(std::io::BufReader::new(input.as_bytes()))
+            .is_err());
+    }
+
+    #[test]
+    fn stream_mmcif_split_rows() {
+        let input = "data_test
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.label_atom_id
+_atom_site.label_comp_id
+_atom_site.label_asym_id
+_atom_site.label_seq_id
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+ATOM 1 N N MET A 1 1.0 2.0 3.0
+ATOM 2 C CA
+'MET'
+A 1 4.0 5.0 6.0
+# a comment
+ATOM 3 C C MET A 1 7.0 8.0 9.0 ATOM 4 O O MET A 1 1.0 1.0 1.0
+#
+loop_
+_other.item
+1
+";
+        let records = ReadOptions::default()
+            .set_format(Format::Mmcif)
+            .stream_atoms_raw(std::io::BufReader::new(input.as_bytes()))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1].conformer_name(), "MET");
+        assert_eq!(records[3].atom().name(), "O");
+        assert_eq!(records[1].atom().pos(), (4.0, 5.0, 6.0));
+    }
+
+    #[test]
+    fn stream_mmcif_without_group() {
+        let input = "data_test
+loop_
+_atom_site.id
+_atom_site.type_symbol
+_atom_site.label_atom_id
+_atom_site.label_comp_id
+_atom_site.label_asym_id
+_atom_site.label_seq_id
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+1 N N MET A 1 1.0 2.0 3.0
+2 C CA MET A 1 4.0 5.0 6.0
+#
+";
+        let records = ReadOptions::default()
+            .set_format(Format::Mmcif)
+            .stream_atoms_raw(std::io::BufReader::new(input.as_bytes()))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].atom().name(), "CA");
+        assert_eq!(records[1].atom().pos(), (4.0, 5.0, 6.0));
+    }
+
+    #[test]
+    fn stream_error_ends_stream() {
+        let input = "ATOM      1  N   MET A   1      27.340  24.430   2.614  1.00  9.67           N\n\
+                     ATOM      2  CA  MET A   1      2x.266  25.413   2.842  1.00 10.38           C\n\
+                     ATOM      3  C   MET A   1      26.266  25.413   2.842  1.00 10.38           C\n";
+        let results = ReadOptions::default()
+            .set_format(Format::Pdb)
+            .stream_atoms_raw(std::io::BufReader::new(input.as_bytes()))
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,404 @@
+use std::io::Read;
+
+use crate::error::*;
+use crate::structs::*;
+
+use super::dcd::DcdReader;
+use super::xtc::XtcReader;
+
+/// The trajectory formats that can be read with [`crate::ReadOptions::read_trajectory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrajectoryFormat {
+    /// CHARMM/NAMD DCD files
+    Dcd,
+    /// GROMACS XTC files
+    Xtc,
+}
+
+impl TrajectoryFormat {
+    /// Guess the trajectory format based on the file name extensions, also returns if the file is gzipped
+    pub(crate) fn guess(filename: &str) -> Option<(Self, bool)> {
+        let lowercase = filename.to_lowercase();
+        let (name, compressed) = match lowercase.strip_suffix(".gz") {
+            Some(name) => (name, true),
+            None => (lowercase.as_str(), false),
+        };
+        if name.ends_with(".dcd") {
+            Some((Self::Dcd, compressed))
+        } else if name.ends_with(".xtc") {
+            Some((Self::Xtc, compressed))
+        } else {
+            None
+        }
+    }
+}
+
+/// A single frame of a trajectory: the coordinates of all atoms at a certain time, together with
+/// the unit cell at that time (if the trajectory contains one). The coordinates are in Å and are in
+/// the same order as the atoms of the topology, use [`Frame::to_model`] or [`Frame::to_pdb`] to
+/// combine the frame with a topology.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The index of this frame in the trajectory, starting at 0
+    index: usize,
+    /// The simulation step of this frame
+    step: usize,
+    /// The simulation time of this frame in ps, if known
+    time: Option<f64>,
+    /// The coordinates of all atoms in Å
+    coordinates: Vec<[f64; 3]>,
+    /// The unit cell (periodic box) of this frame
+    unit_cell: Option<UnitCell>,
+}
+
+impl Frame {
+    /// Create a new frame with the given coordinates in Å, without a unit cell, at step 0
+    pub fn new(coordinates: Vec<[f64; 3]>) -> Self {
+        Frame {
+            index: 0,
+            step: 0,
+            time: None,
+            coordinates,
+            unit_cell: None,
+        }
+    }
+
+    /// Create a new frame with the coordinates of all atoms in the given model, in the order of
+    /// [`Model::atoms`]
+    pub fn from_model(model: &Model) -> Self {
+        Self::new(
+            model
+                .atoms()
+                .map(|atom| {
+                    let position = atom.pos();
+                    [position.0, position.1, position.2]
+                })
+                .collect(),
+        )
+    }
+
+    /// Create a new frame as read from a trajectory
+    pub(crate) fn read(
+        index: usize,
+        step: usize,
+        time: Option<f64>,
+        coordinates: Vec<[f64; 3]>,
+        unit_cell: Option<UnitCell>,
+    ) -> Self {
+        Frame {
+            index,
+            step,
+            time,
+            coordinates,
+            unit_cell,
+        }
+    }
+
+    /// The index of this frame in the trajectory, starting at 0
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The simulation step of this frame
+    pub const fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Set the simulation step of this frame
+    pub fn set_step(&mut self, new_step: usize) {
+        self.step = new_step;
+    }
+
+    /// The simulation time of this frame in ps, if known
+    pub const fn time(&self) -> Option<f64> {
+        self.time
+    }
+
+    /// Set the simulation time of this frame in ps
+    pub fn set_time(&mut self, new_time: Option<f64>) {
+        self.time = new_time;
+    }
+
+    /// The coordinates of all atoms in Å
+    pub fn coordinates(&self) -> &[[f64; 3]] {
+        &self.coordinates
+    }
+
+    /// Get the coordinates of all atoms in Å, consuming this frame
+    pub fn into_coordinates(self) -> Vec<[f64; 3]> {
+        self.coordinates
+    }
+
+    /// The number of atoms in this frame
+    pub fn atom_count(&self) -> usize {
+        self.coordinates.len()
+    }
+
+    /// The unit cell (periodic box) of this frame, if the trajectory contains one
+    pub const fn unit_cell(&self) -> Option<&UnitCell> {
+        self.unit_cell.as_ref()
+    }
+
+    /// Set the unit cell (periodic box) of this frame
+    pub fn set_unit_cell(&mut self, new_unit_cell: Option<UnitCell>) {
+        self.unit_cell = new_unit_cell;
+    }
+
+    /// Set the positions of the atoms in the given model to the coordinates of this frame. The atoms
+    /// are matched in the order of [`Model::atoms_mut`].
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the number of atoms in the model is not the same as in this frame.
+    pub fn apply(&self, model: &mut Model) -> Result<(), PDBError> {
+        if model.atom_count() != self.coordinates.len() {
+            return Err(PDBError::new(
+                ErrorLevel::InvalidatingError,
+                "Atom count mismatch",
+                format!(
+                    "Frame {} of the trajectory has {} atoms but the model has {} atoms.",
+                    self.index,
+                    self.coordinates.len(),
+                    model.atom_count()
+                ),
+                Context::None,
+            ));
+        }
+        for (atom, position) in model.atoms_mut().zip(&self.coordinates) {
+            atom.set_pos((position[0], position[1], position[2]))
+                .map_err(|message| {
+                    PDBError::new(
+                        ErrorLevel::InvalidatingError,
+                        "Invalid coordinates",
+                        message,
+                        Context::None,
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Create a new model from the first model of the topology with the atoms placed at the
+    /// coordinates of this frame. The serial number of the model is the frame index plus one.
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the topology has no models or if the number of atoms in its first
+    /// model is not the same as in this frame.
+    pub fn to_model(&self, topology: &PDB) -> Result<Model, PDBError> {
+        let mut model = topology.model(0).cloned().ok_or_else(|| {
+            PDBError::new(
+                ErrorLevel::InvalidatingError,
+                "Empty topology",
+                "The topology for the trajectory does not contain any models.",
+                Context::None,
+            )
+        })?;
+        self.apply(&mut model)?;
+        model.set_serial_number(self.index + 1);
+        Ok(model)
+    }
+
+    /// Create a new structure from the topology with a single model with the atoms placed at the
+    /// coordinates of this frame, see [`Frame::to_model`]. The unit cell of the structure is set to
+    /// the unit cell of this frame, if the trajectory contains one.
+    ///
+    /// # Errors
+    /// Returns a `PDBError` if the topology has no models or if the number of atoms in its first
+    /// model is not the same as in this frame.
+    pub fn to_pdb(&self, topology: &PDB) -> Result<PDB, PDBError> {
+        let model = self.to_model(topology)?;
+        let mut pdb = topology.clone();
+        pdb.remove_models_by(|_| true);
+        pdb.add_model(model);
+        if self.unit_cell.is_some() {
+            pdb.unit_cell = self.unit_cell.clone();
+        }
+        Ok(pdb)
+    }
+}
+
+/// The underlying format specific readers
+enum TrajectoryKind<R> {
+    /// A DCD file
+    Dcd(Box<DcdReader<R>>),
+    /// A XTC file
+    Xtc(Box<XtcReader<R>>),
+}
+
+/// An iterator over all frames in a trajectory, reading a single frame at a time. Create one with
+/// [`crate::ReadOptions::read_trajectory`] or [`crate::ReadOptions::read_trajectory_raw`].
+///
+/// After an error is returned the iterator ends.
+pub struct Trajectory<R> {
+    /// The format specific reader
+    inner: TrajectoryKind<R>,
+    /// Only read the first frame
+    only_first_frame: bool,
+    /// If the trajectory has ended
+    done: bool,
+}
+
+impl<R: Read> Trajectory<R> {
+    /// Create an iterator over a DCD file, this reads the header of the file
+    pub(crate) fn dcd(
+        input: R,
+        context: Context,
+        only_first_frame: bool,
+    ) -> Result<Self, PDBError> {
+        Ok(Trajectory {
+            inner: TrajectoryKind::Dcd(Box::new(DcdReader::new(input, context)?)),
+            only_first_frame,
+            done: false,
+        })
+    }
+
+    /// Create an iterator over a XTC file
+    pub(crate) fn xtc(input: R, context: Context, only_first_frame: bool) -> Self {
+        Trajectory {
+            inner: TrajectoryKind::Xtc(Box::new(XtcReader::new(input, context))),
+            only_first_frame,
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for Trajectory<R> {
+    type Item = Result<Frame, PDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = match &mut self.inner {
+            TrajectoryKind::Dcd(reader) => reader.read_frame(),
+            TrajectoryKind::Xtc(reader) => reader.read_frame(),
+        }
+        .transpose();
+        if self.only_first_frame || !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+impl<R> std::fmt::Debug for Trajectory<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = match self.inner {
+            TrajectoryKind::Dcd(_) => "DCD",
+            TrajectoryKind::Xtc(_) => "XTC",
+        };
+        write!(f, "Trajectory {{ format: {format}, done: {} }}", self.done)
+    }
+}
+
+/// Fill the buffer from the input. Returns false if the input ended before any byte was read,
+/// and an error if the input ended halfway.
+pub(super) fn read_or_end(input: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::ReadOptions;
+
+    /// The frames in the fixtures are translated by this vector times the frame index
+    const SHIFT: [f64; 3] = [0.5, -0.25, 1.0];
+
+    fn check(path: &str, tolerance: f64) {
+        let (topology, _) = ReadOptions::new()
+            .set_level(crate::StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let frames = ReadOptions::new()
+            .read_trajectory(path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        for (index, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.index(), index);
+            assert_eq!(frame.step(), 100 + 10 * index);
+            assert_eq!(frame.atom_count(), topology.atom_count());
+            for (atom, position) in topology.atoms().zip(frame.coordinates()) {
+                let expected = atom.pos();
+                let expected = [expected.0, expected.1, expected.2];
+                for axis in 0..3 {
+                    let shifted = expected[axis] + SHIFT[axis] * index as f64;
+                    assert!((position[axis] - shifted).abs() < tolerance);
+                }
+            }
+            let cell = frame.unit_cell().unwrap();
+            let scale = 1.0 + 0.01 * index as f64;
+            assert!((cell.a() - 50.84 * scale).abs() < 1e-3);
+            assert!((cell.b() - 42.77 * scale).abs() < 1e-3);
+            assert!((cell.c() - 28.95 * scale).abs() < 1e-3);
+            assert!((cell.alpha() - 90.0).abs() < 1e-3);
+            assert!((cell.gamma() - 90.0).abs() < 1e-3);
+
+            let pdb = frame.to_pdb(&topology).unwrap();
+            assert_eq!(pdb.model_count(), 1);
+            assert_eq!(pdb.model(0).unwrap().serial_number(), index + 1);
+            assert_eq!(pdb.unit_cell.as_ref(), Some(cell));
+            let first = pdb.atoms().next().unwrap().pos();
+            assert_eq!(first.0, frame.coordinates()[0][0]);
+        }
+    }
+
+    #[test]
+    fn read_dcd() {
+        check("example-pdbs/trajectories/1ubq.dcd", 1e-4);
+    }
+
+    #[test]
+    fn read_xtc() {
+        check("example-pdbs/trajectories/1ubq.xtc", 6e-3);
+    }
+
+    #[test]
+    fn only_first_frame() {
+        let frames = ReadOptions::new()
+            .set_only_first_model(true)
+            .read_trajectory("example-pdbs/trajectories/1ubq.xtc")
+            .unwrap()
+            .count();
+        assert_eq!(frames, 1);
+    }
+
+    #[test]
+    fn atom_count_mismatch() {
+        let (topology, _) = ReadOptions::new()
+            .set_level(crate::StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let frame = Frame::new(vec![[0.0; 3]; 10]);
+        let error = frame.to_model(&topology).unwrap_err();
+        assert_eq!(error.short_description(), "Atom count mismatch");
+        assert!(frame.to_model(&PDB::new()).is_err());
+    }
+
+    #[test]
+    fn guess_format() {
+        assert_eq!(
+            TrajectoryFormat::guess("run.dcd"),
+            Some((TrajectoryFormat::Dcd, false))
+        );
+        assert_eq!(
+            TrajectoryFormat::guess("run.XTC.gz"),
+            Some((TrajectoryFormat::Xtc, true))
+        );
+        assert_eq!(TrajectoryFormat::guess("run.pdb"), None);
+        let error = ReadOptions::new().read_trajectory("run.trr").unwrap_err();
+        assert_eq!(error.short_description(), "Could not determine file type");
+    }
+}
@@ -0,0 +1,423 @@
+use std::io::Read;
+
+use crate::error::*;
+use crate::structs::*;
+
+use super::trajectory::{read_or_end, Frame};
+
+/// The sizes used for the small (relative) coordinates in the XTC compression, every entry is
+/// about 2^(1/3) times larger than the previous so that three integers fit in `index` bits
+const MAGIC_INTS: [u32; 73] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 10, 12, 16, 20, 25, 32, 40, 50, 64, 80, 101, 128, 161, 203, 256,
+    322, 406, 512, 645, 812, 1024, 1290, 1625, 2048, 2580, 3250, 4096, 5060, 6501, 8192, 10321,
+    13003, 16384, 20642, 26007, 32768, 41285, 52015, 65536, 82570, 104_031, 131_072, 165_140,
+    208_063, 262_144, 330_280, 416_127, 524_287, 660_561, 832_255, 1_048_576, 1_321_122, 1_664_510,
+    2_097_152, 2_642_245, 3_329_021, 4_194_304, 5_284_491, 6_658_042, 8_388_607, 10_568_983,
+    13_316_085, 16_777_216,
+];
+
+/// The first usable index in [`MAGIC_INTS`]
+const FIRST_INDEX: usize = 9;
+
+/// Read the frames of a GROMACS XTC file.
+///
+/// Every frame consists of a header with the number of atoms, the step, the time in ps, and the
+/// box vectors in nm, followed by the coordinates in nm. Frames with more than 9 atoms use the
+/// lossy `xdr3dfcoord` compression: all coordinates are stored as integers at a given precision,
+/// and runs of atoms close to the previous atom are stored as small differences. The coordinates
+/// and box are converted to Å.
+pub(crate) struct XtcReader<R> {
+    /// The input
+    input: R,
+    /// The context used for errors
+    context: Context,
+    /// The index of the next frame
+    index: usize,
+}
+
+impl<R: Read> XtcReader<R> {
+    /// Create a new reader
+    pub(crate) const fn new(input: R, context: Context) -> Self {
+        XtcReader {
+            input,
+            context,
+            index: 0,
+        }
+    }
+
+    /// Read the next frame, returns None if the file has ended
+    pub(crate) fn read_frame(&mut self) -> Result<Option<Frame>, PDBError> {
+        let mut magic = [0; 4];
+        if !read_or_end(&mut self.input, &mut magic).map_err(|e| self.io(&e))? {
+            return Ok(None);
+        }
+        let magic = i32::from_be_bytes(magic);
+        if magic != 1995 && magic != 2023 {
+            return Err(self.error(format!("Frame {} has an invalid magic number.", self.index)));
+        }
+        let atom_count = self.count()?;
+        let step = usize::try_from(self.int()?).unwrap_or_default();
+        let time = f64::from(self.float()?);
+        let mut vectors = [[0.0; 3]; 3];
+        for vector in &mut vectors {
+            for value in vector.iter_mut() {
+                *value = f64::from(self.float()?) * 10.0;
+            }
+        }
+        if self.count()? != atom_count {
+            return Err(self.error("The number of atoms in the header and the coordinates differ."));
+        }
+
+        let coordinates = if atom_count <= 9 {
+            (0..atom_count)
+                .map(|_| -> Result<_, PDBError> {
+                    Ok([
+                        f64::from(self.float()?) * 10.0,
+                        f64::from(self.float()?) * 10.0,
+                        f64::from(self.float()?) * 10.0,
+                    ])
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            self.compressed(atom_count, magic == 2023)?
+        };
+        let unit_cell = if vectors.iter().flatten().all(|value| *value == 0.0) {
+            None
+        } else {
+            Some(UnitCell::from_vectors(vectors[0], vectors[1], vectors[2]))
+        };
+        let frame = Frame::read(self.index, step, Some(time), coordinates, unit_cell);
+        self.index += 1;
+        Ok(Some(frame))
+    }
+
+    /// Read and decompress the coordinates of a frame, in Å
+    fn compressed(&mut self, atom_count: usize, long: bool) -> Result<Vec<[f64; 3]>, PDBError> {
+        let precision = f64::from(self.float()?);
+        if precision <= 0.0 {
+            return Err(self.error("The precision should be positive."));
+        }
+        let minimum = [self.int()?, self.int()?, self.int()?];
+        let maximum = [self.int()?, self.int()?, self.int()?];
+        let mut sizes = [0; 3];
+        for axis in 0..3 {
+            sizes[axis] = u32::try_from(i64::from(maximum[axis]) - i64::from(minimum[axis]) + 1)
+                .map_err(|_| self.error("The coordinate range is invalid."))?;
+        }
+        let large = sizes.iter().any(|size| *size > 0x00FF_FFFF);
+        let bit_sizes = sizes.map(|size| 32 - size.leading_zeros());
+        let bit_size = {
+            let product = sizes.iter().map(|size| u128::from(*size)).product::<u128>();
+            128 - product.leading_zeros()
+        };
+        let mut small_index = usize::try_from(self.int()?)
+            .ok()
+            .filter(|index| (FIRST_INDEX..MAGIC_INTS.len()).contains(index))
+            .ok_or_else(|| self.error("The size of the small coordinates is invalid."))?;
+        let length = if long {
+            let mut bytes = [0; 8];
+            self.read(&mut bytes)?;
+            usize::try_from(u64::from_be_bytes(bytes)).unwrap_or(usize::MAX)
+        } else {
+            self.count()?
+        };
+        let mut data = Vec::new();
+        (&mut self.input)
+            .take(length as u64)
+            .read_to_end(&mut data)
+            .map_err(|e| self.io(&e))?;
+        if data.len() != length {
+            return Err(self.error("The file ended in the middle of a frame."));
+        }
+        let mut padding = [0; 3];
+        self.read(&mut padding[..(4 - length % 4) % 4])?;
+
+        let invalid = || {
+            self.error(format!(
+                "The coordinates of frame {} are invalid.",
+                self.index
+            ))
+        };
+        let mut bits = BitReader::new(&data);
+        let mut smaller = MAGIC_INTS[FIRST_INDEX.max(small_index - 1)] / 2;
+        let mut small_number = MAGIC_INTS[small_index] / 2;
+        let mut coordinates = Vec::with_capacity(atom_count);
+        let mut run = 0;
+        while coordinates.len() < atom_count {
+            let raw = if large {
+                [
+                    bits.bits(bit_sizes[0]),
+                    bits.bits(bit_sizes[1]),
+                    bits.bits(bit_sizes[2]),
+                ]
+            } else {
+                bits.ints(bit_size, sizes).ok_or_else(invalid)?.map(Some)
+            };
+            let mut previous = [0; 3];
+            for axis in 0..3 {
+                previous[axis] =
+                    i64::from(raw[axis].ok_or_else(invalid)?) + i64::from(minimum[axis]);
+            }
+
+            let mut is_smaller = 0;
+            if bits.bits(1).ok_or_else(invalid)? == 1 {
+                let value = bits.bits(5).ok_or_else(invalid)?;
+                is_smaller = i64::from(value % 3) - 1;
+                run = (value - value % 3) as usize;
+            }
+            if run > 0 {
+                if coordinates.len() + 1 + run / 3 > atom_count {
+                    return Err(invalid());
+                }
+                let size = MAGIC_INTS[small_index];
+                let small_bits = u32::try_from(small_index).unwrap_or_default();
+                for k in (0..run).step_by(3) {
+                    let small = bits.ints(small_bits, [size; 3]).ok_or_else(invalid)?;
+                    let mut current = [0; 3];
+                    for axis in 0..3 {
+                        current[axis] =
+                            i64::from(small[axis]) + previous[axis] - i64::from(small_number);
+                    }
+                    // The first two atoms are interchanged for better compression of water molecules
+                    coordinates.push(current);
+                    if k == 0 {
+                        coordinates.push(previous);
+                    }
+                    previous = current;
+                }
+            } else {
+                coordinates.push(previous);
+            }
+
+            small_index = match is_smaller {
+                -1 => small_index - 1,
+                1 => small_index + 1,
+                _ => small_index,
+            };
+            if !(FIRST_INDEX..MAGIC_INTS.len()).contains(&small_index) {
+                return Err(invalid());
+            }
+            if is_smaller < 0 {
+                small_number = smaller;
+                smaller = if small_index > FIRST_INDEX {
+                    MAGIC_INTS[small_index - 1] / 2
+                } else {
+                    0
+                };
+            } else if is_smaller > 0 {
+                smaller = small_number;
+                small_number = MAGIC_INTS[small_index] / 2;
+            }
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let coordinates = coordinates
+            .into_iter()
+            .map(|position| position.map(|value| value as f64 / precision * 10.0))
+            .collect();
+        Ok(coordinates)
+    }
+
+    /// Read a big endian integer
+    fn int(&mut self) -> Result<i32, PDBError> {
+        let mut bytes = [0; 4];
+        self.read(&mut bytes)?;
+        Ok(i32::from_be_bytes(bytes))
+    }
+
+    /// Read a big endian integer that should not be negative
+    fn count(&mut self) -> Result<usize, PDBError> {
+        let number = self.int()?;
+        usize::try_from(number).map_err(|_| self.error(format!("Invalid count: {number}.")))
+    }
+
+    /// Read a big endian single precision float
+    fn float(&mut self) -> Result<f32, PDBError> {
+        let mut bytes = [0; 4];
+        self.read(&mut bytes)?;
+        Ok(f32::from_be_bytes(bytes))
+    }
+
+    /// Fill the buffer, the input is not allowed to end
+    fn read(&mut self, buffer: &mut [u8]) -> Result<(), PDBError> {
+        match read_or_end(&mut self.input, buffer) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(self.error("The file ended in the middle of a frame.")),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Err(self.error("The file ended in the middle of a frame."))
+            }
+            Err(e) => Err(self.io(&e)),
+        }
+    }
+
+    /// Create an error for an invalid file
+    fn error(&self, message: impl ToString) -> PDBError {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Invalid XTC file",
+            message,
+            self.context.clone(),
+        )
+    }
+
+    /// Create an error for a failed read
+    fn io(&self, error: &std::io::Error) -> PDBError {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Buffer could not be read",
+            format!("The XTC file could not be read: {error}."),
+            self.context.clone(),
+        )
+    }
+}
+
+/// Read bits from a byte buffer, most significant bit first
+struct BitReader<'a> {
+    /// The data
+    data: &'a [u8],
+    /// The index of the next byte
+    position: usize,
+    /// The number of bits in `last_byte` that are not read yet
+    last_bits: u32,
+    /// The last bytes read
+    last_byte: u32,
+}
+
+impl<'a> BitReader<'a> {
+    /// Create a new reader
+    const fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            position: 0,
+            last_bits: 0,
+            last_byte: 0,
+        }
+    }
+
+    /// Read the next byte into `last_byte`
+    fn next_byte(&mut self) -> Option<()> {
+        let byte = self.data.get(self.position)?;
+        self.position += 1;
+        self.last_byte = (self.last_byte << 8) | u32::from(*byte);
+        Some(())
+    }
+
+    /// Read a number with the given number of bits (at most 32)
+    fn bits(&mut self, mut bits: u32) -> Option<u32> {
+        let mask = u32::MAX.checked_shr(32 - bits).unwrap_or(0);
+        let mut number = 0;
+        while bits >= 8 {
+            self.next_byte()?;
+            number |= (self.last_byte >> self.last_bits) << (bits - 8);
+            bits -= 8;
+        }
+        if bits > 0 {
+            if self.last_bits < bits {
+                self.last_bits += 8;
+                self.next_byte()?;
+            }
+            self.last_bits -= bits;
+            number |= (self.last_byte >> self.last_bits) & ((1 << bits) - 1);
+        }
+        Some(number & mask)
+    }
+
+    /// Read three numbers that are packed together into the given number of bits, the numbers
+    /// are smaller than the given sizes
+    fn ints(&mut self, mut bits: u32, sizes: [u32; 3]) -> Option<[u32; 3]> {
+        if bits > 32 * 8 {
+            return None;
+        }
+        let mut bytes = [0; 32];
+        let mut count = 0;
+        while bits > 8 {
+            bytes[count] = self.bits(8)?;
+            count += 1;
+            bits -= 8;
+        }
+        if bits > 0 {
+            bytes[count] = self.bits(bits)?;
+            count += 1;
+        }
+        let mut numbers = [0; 3];
+        for index in (1..3).rev() {
+            let mut number: u64 = 0;
+            for byte in bytes[..count].iter_mut().rev() {
+                number = (number << 8) | u64::from(*byte);
+                let quotient = number / u64::from(sizes[index]);
+                #[allow(clippy::cast_possible_truncation)]
+                {
+                    *byte = quotient as u32;
+                }
+                number -= quotient * u64::from(sizes[index]);
+            }
+            numbers[index] = u32::try_from(number).ok()?;
+        }
+        numbers[0] = bytes[0] | (bytes[1] << 8) | (bytes[2] << 16) | (bytes[3] << 24);
+        Some(numbers)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// Write an uncompressed frame with the given coordinates in nm
+    fn frame(out: &mut Vec<u8>, step: i32, coordinates: &[[f32; 3]]) {
+        out.extend(1995_i32.to_be_bytes());
+        out.extend((coordinates.len() as i32).to_be_bytes());
+        out.extend(step.to_be_bytes());
+        out.extend(0.5_f32.to_be_bytes());
+        for value in [3.0_f32, 0.0, 0.0, 0.0, 3.0, 0.0, -1.5, 0.0, 3.0] {
+            out.extend(value.to_be_bytes());
+        }
+        out.extend((coordinates.len() as i32).to_be_bytes());
+        for value in coordinates.iter().flatten() {
+            out.extend(value.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn uncompressed() {
+        let mut bytes = Vec::new();
+        frame(&mut bytes, 10, &[[0.1, 0.2, 0.3], [1.0, 2.0, 3.0]]);
+        frame(&mut bytes, 20, &[[0.5, 0.5, 0.5], [1.5, 2.5, 3.5]]);
+        let mut reader = XtcReader::new(bytes.as_slice(), Context::None);
+        let first = reader.read_frame().unwrap().unwrap();
+        assert_eq!(first.step(), 10);
+        assert_eq!(first.time(), Some(0.5));
+        assert!((first.coordinates()[1][2] - 30.0).abs() < 1e-5);
+        let cell = first.unit_cell().unwrap();
+        assert!((cell.a() - 30.0).abs() < 1e-5);
+        assert!((cell.c() - 15.0 * 5.0_f64.sqrt()).abs() < 1e-5);
+        assert!((cell.beta() - (-1.0 / 5.0_f64.sqrt()).acos().to_degrees()).abs() < 1e-5);
+        let second = reader.read_frame().unwrap().unwrap();
+        assert_eq!(second.index(), 1);
+        assert!((second.coordinates()[0][0] - 5.0).abs() < 1e-5);
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn bits() {
+        let data = [0b1011_0011, 0b0101_1100, 0xFF, 0x01];
+        let mut reader = BitReader::new(&data);
+        assert_eq!(reader.bits(1), Some(1));
+        assert_eq!(reader.bits(3), Some(0b011));
+        assert_eq!(reader.bits(8), Some(0b0011_0101));
+        assert_eq!(reader.bits(12), Some(0b1100_1111_1111));
+        assert_eq!(reader.bits(8), Some(1));
+        assert_eq!(reader.bits(1), None);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut bytes = Vec::new();
+        frame(&mut bytes, 10, &[[0.1, 0.2, 0.3]]);
+        let mut reader = XtcReader::new(&bytes[..bytes.len() - 2], Context::None);
+        let error = reader.read_frame().unwrap_err();
+        assert_eq!(error.short_description(), "Invalid XTC file");
+        bytes[3] = 0;
+        let mut reader = XtcReader::new(bytes.as_slice(), Context::None);
+        assert!(reader.read_frame().is_err());
+    }
+}
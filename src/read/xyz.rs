@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::error::*;
+use crate::structs::*;
+use crate::validate::*;
+use crate::ReadOptions;
+
+use super::general::ReadResult;
+
+/// The name of the residue containing all atoms read from an XYZ file
+const RESIDUE_NAME: &str = "MOL";
+
+/// Parse the given XYZ or extended XYZ file into a PDB struct with [`ReadOptions`].
+/// Every frame in the file is read as a separate [`Model`].
+pub(crate) fn open_xyz_with_options(
+    filename: impl AsRef<str>,
+    options: &ReadOptions,
+) -> ReadResult {
+    let filename = filename.as_ref();
+    let file = if let Ok(f) = File::open(filename) {
+        f
+    } else {
+        return Err(vec![PDBError::new(ErrorLevel::BreakingError, "Could not open file", "Could not open the specified file, make sure the path is correct, you have permission, and that it is not open in another program.", Context::show(filename))]);
+    };
+    open_xyz_raw_with_options(BufReader::new(file), Context::show(filename), options)
+}
+
+/// Parse the input stream in the XYZ or extended XYZ format into a PDB struct with [`ReadOptions`].
+///
+/// Each frame consists of the number of atoms, a comment line, and one line per atom with the
+/// element and the coordinates (Å). All atoms of a frame are placed in a single hetero residue
+/// (`MOL` 1) in chain `A`, with the element symbol as atom name. The serial numbers are the
+/// indices in the frame (starting at 1).
+///
+/// In extended XYZ files the comment line contains `key=value` pairs. The `Lattice` of the first
+/// frame is stored as the [`UnitCell`]. The `Properties` key defines the columns of the atom
+/// lines, besides `species` (or `Z`) and `pos` the per-atom properties `charge` (or `charges`
+/// and `initial_charges`) and `radius` (or `radii`) are stored on the atoms. Other properties are
+/// ignored with a warning.
+pub(crate) fn open_xyz_raw_with_options<T>(
+    input: BufReader<T>,
+    context: Context,
+    options: &ReadOptions,
+) -> ReadResult
+where
+    T: std::io::Read,
+{
+    let mut errors = Vec::new();
+    let mut pdb = PDB::new();
+    let mut lines = input.lines().enumerate();
+    let mut next_line = || -> Result<Option<(usize, String)>, PDBError> {
+        match lines.next() {
+            Some((index, Ok(line))) => Ok(Some((index + 1, line))),
+            Some((index, Err(_))) => Err(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could read line",
+                format!(
+                    "Could not read line {} while parsing the input file.",
+                    index + 1
+                ),
+                context.clone(),
+            )),
+            None => Ok(None),
+        }
+    };
+    let end_of_file = |linenumber: usize, expected: &str| {
+        vec![PDBError::new(
+            ErrorLevel::BreakingError,
+            "Unexpected end of file",
+            format!("The file ended after line {linenumber} while {expected} was expected."),
+            context.clone(),
+        )]
+    };
+    let mut ignored_properties = Vec::new();
+
+    while let Some((mut linenumber, count_line)) = next_line().map_err(|e| vec![e])? {
+        // Blank lines between or after the frames are ignored
+        if count_line.trim().is_empty() {
+            continue;
+        }
+        let count = if let Ok(count) = count_line.trim().parse::<usize>() {
+            count
+        } else {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Invalid atom count",
+                "The first line of a frame should contain the number of atoms.",
+                Context::full_line(linenumber, &count_line),
+            ));
+            return Err(errors);
+        };
+        let (n, comment) = match next_line().map_err(|e| vec![e])? {
+            Some(line) => line,
+            None => return Err(end_of_file(linenumber, "the comment line")),
+        };
+        linenumber = n;
+        let info = parse_comment(&comment);
+        let columns = match parse_properties(
+            info.get("properties")
+                .and_then(Option::as_deref)
+                .unwrap_or("species:S:1:pos:R:3"),
+        ) {
+            Ok(columns) => columns,
+            Err(message) => {
+                errors.push(PDBError::new(
+                    ErrorLevel::BreakingError,
+                    "Invalid extended XYZ properties",
+                    message,
+                    Context::full_line(linenumber, &comment),
+                ));
+                return Err(errors);
+            }
+        };
+        for column in &columns {
+            if column.kind == Property::Other && !ignored_properties.contains(&column.name) {
+                errors.push(PDBError::new(
+                    ErrorLevel::GeneralWarning,
+                    "Ignored extended XYZ property",
+                    format!(
+                        "The per-atom property \"{}\" is not supported and is ignored.",
+                        column.name
+                    ),
+                    Context::full_line(linenumber, &comment),
+                ));
+                ignored_properties.push(column.name.clone());
+            }
+        }
+        if pdb.unit_cell.is_none() {
+            if let Some(lattice) = info.get("lattice").and_then(Option::as_deref) {
+                match parse_lattice(lattice) {
+                    Ok(cell) => pdb.unit_cell = Some(cell),
+                    Err(message) => errors.push(PDBError::new(
+                        ErrorLevel::InvalidatingError,
+                        "Invalid extended XYZ lattice",
+                        message,
+                        Context::full_line(linenumber, &comment),
+                    )),
+                }
+            }
+        }
+
+        let mut model = Model::new(pdb.model_count() + 1);
+        for index in 0..count {
+            let (n, line) = match next_line().map_err(|e| vec![e])? {
+                Some(line) => line,
+                None => return Err(end_of_file(linenumber, "an atom line")),
+            };
+            linenumber = n;
+            match parse_atom_line(&line, &columns, index + 1) {
+                Ok(atom) => {
+                    if options.discard_hydrogens && atom.element() == Some(&Element::H) {
+                        continue;
+                    }
+                    model.add_atom(atom, "A", (1, None), (RESIDUE_NAME, None));
+                }
+                Err(message) => errors.push(PDBError::new(
+                    ErrorLevel::InvalidatingError,
+                    "Invalid XYZ atom",
+                    message,
+                    Context::full_line(linenumber, &line),
+                )),
+            }
+        }
+
+        pdb.add_model(model);
+        if options.only_first_model {
+            break;
+        }
+    }
+
+    errors.extend(validate(&pdb));
+
+    if errors.iter().any(|e| e.fails(options.level)) {
+        Err(errors)
+    } else {
+        Ok((pdb, errors))
+    }
+}
+
+/// The per-atom properties of extended XYZ files that are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Property {
+    /// The element symbol
+    Species,
+    /// The atomic number
+    AtomicNumber,
+    /// The position (Å)
+    Position,
+    /// The partial charge
+    Charge,
+    /// The radius (Å)
+    Radius,
+    /// Any other property, which is ignored
+    Other,
+}
+
+/// A property column (or set of columns) in an atom line
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Column {
+    /// The name of the property
+    name: String,
+    /// The kind of the property
+    kind: Property,
+    /// The index of the first column
+    start: usize,
+    /// The number of columns
+    count: usize,
+}
+
+/// Parse the comment line of an extended XYZ file into `key=value` pairs, keys are lowercased.
+/// Values can be quoted with double quotes, keys without a value (like the words of a plain XYZ
+/// comment) get the value `None`, so only explicit values are used.
+fn parse_comment(line: &str) -> HashMap<String, Option<String>> {
+    let mut pairs = HashMap::new();
+    let mut chars = line.trim().chars().peekable();
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+        let mut key = String::new();
+        while let Some(c) = chars.peek().copied() {
+            if c.is_whitespace() || c == '=' {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+        let mut value = None;
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            let value = value.get_or_insert_with(String::new);
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    value.push(c);
+                }
+            } else {
+                while let Some(c) = chars.peek().copied() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+            }
+        }
+        let key = key.to_ascii_lowercase();
+        if value.is_some() || !pairs.contains_key(&key) {
+            pairs.insert(key, value);
+        }
+    }
+    pairs
+}
+
+/// Parse the `Properties` value of an extended XYZ file (`name:type:count` triples).
+fn parse_properties(text: &str) -> Result<Vec<Column>, String> {
+    let parts: Vec<&str> = text.split(':').collect();
+    if parts.len() % 3 != 0 {
+        return Err(format!(
+            "The properties \"{text}\" should consist of name:type:count triples."
+        ));
+    }
+    let mut start = 0;
+    let mut columns = Vec::new();
+    for triple in parts.chunks(3) {
+        let count = triple[2]
+            .parse::<usize>()
+            .map_err(|_| format!("The column count \"{}\" is not a valid number.", triple[2]))?;
+        let kind = match (triple[0].to_ascii_lowercase().as_str(), count) {
+            ("species" | "element", 1) => Property::Species,
+            ("z", 1) => Property::AtomicNumber,
+            ("pos", 3) => Property::Position,
+            ("charge" | "charges" | "initial_charges", 1) => Property::Charge,
+            ("radius" | "radii", 1) => Property::Radius,
+            _ => Property::Other,
+        };
+        columns.push(Column {
+            name: triple[0].to_string(),
+            kind,
+            start,
+            count,
+        });
+        start += count;
+    }
+    if !columns.iter().any(|c| c.kind == Property::Position) {
+        return Err("The properties do not contain the positions (pos:R:3).".to_string());
+    }
+    if !columns
+        .iter()
+        .any(|c| matches!(c.kind, Property::Species | Property::AtomicNumber))
+    {
+        return Err(
+            "The properties do not contain the elements (species:S:1 or Z:I:1).".to_string(),
+        );
+    }
+    Ok(columns)
+}
+
+/// Parse the `Lattice` value of an extended XYZ file (nine numbers, the three cell vectors).
+fn parse_lattice(text: &str) -> Result<UnitCell, String> {
+    let values = text
+        .split_whitespace()
+        .map(|value| {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("The value \"{value}\" is not a valid number."))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.len() == 9 {
+        Ok(UnitCell::from_vectors(
+            [values[0], values[1], values[2]],
+            [values[3], values[4], values[5]],
+            [values[6], values[7], values[8]],
+        ))
+    } else {
+        Err(format!(
+            "The lattice should contain 9 numbers but {} numbers were found.",
+            values.len()
+        ))
+    }
+}
+
+/// Parse an atom line using the given property columns. In plain XYZ files the element can also
+/// be given as atomic number.
+fn parse_atom_line(line: &str, columns: &[Column], serial_number: usize) -> Result<Atom, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let needed = columns.iter().map(|c| c.start + c.count).max().unwrap_or(0);
+    if fields.len() < needed {
+        return Err(format!(
+            "An atom line should contain {needed} fields but this line contains {} fields.",
+            fields.len()
+        ));
+    }
+    let number = |index: usize| {
+        fields[index]
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("The value \"{}\" is not a valid number.", fields[index]))
+    };
+
+    let mut element = None;
+    let mut position = (0.0, 0.0, 0.0);
+    let mut charge = None;
+    let mut radius = None;
+    for column in columns {
+        let field = fields[column.start];
+        match column.kind {
+            Property::Species => {
+                element = Element::from_symbol(field)
+                    .or_else(|| field.parse::<usize>().ok().and_then(Element::new));
+                if element.is_none() {
+                    return Err(format!("The element \"{field}\" is not a valid element."));
+                }
+            }
+            Property::AtomicNumber => {
+                element = element.or_else(|| field.parse::<usize>().ok().and_then(Element::new));
+                if element.is_none() {
+                    return Err(format!(
+                        "The atomic number \"{field}\" is not a valid element."
+                    ));
+                }
+            }
+            Property::Position => {
+                position = (
+                    number(column.start)?,
+                    number(column.start + 1)?,
+                    number(column.start + 2)?,
+                );
+            }
+            Property::Charge => charge = Some(number(column.start)?),
+            Property::Radius => radius = Some(number(column.start)?),
+            Property::Other => (),
+        }
+    }
+    let symbol = element.map_or("", |e| e.symbol());
+    let mut atom = Atom::new(
+        true,
+        serial_number,
+        symbol,
+        position.0,
+        position.1,
+        position.2,
+        1.0,
+        0.0,
+        symbol,
+        0,
+    )
+    .ok_or_else(|| "The atom could not be created.".to_string())?;
+    if let Some(charge) = charge {
+        atom.set_partial_charge(charge)?;
+    }
+    if let Some(radius) = radius {
+        atom.set_radius(radius)?;
+    }
+    Ok(atom)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::StrictnessLevel;
+
+    fn open(text: &str, options: &ReadOptions) -> ReadResult {
+        open_xyz_raw_with_options(BufReader::new(text.as_bytes()), Context::None, options)
+    }
+
+    #[test]
+    fn read_xyz() {
+        let text = "3
+water, energy= -76.4
+O   0.000000   0.000000   0.117300
+H   0.000000   0.757200  -0.469200
+1   0.000000  -0.757200  -0.469200
+3
+water frame 2
+O   0.000000   0.000000   0.127300
+H   0.000000   0.757200  -0.469200
+H   0.000000  -0.757200  -0.469200
+
+";
+        let (pdb, errors) = open(text, &ReadOptions::new()).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(pdb.model_count(), 2);
+        assert_eq!(pdb.chain_count(), 1);
+        assert_eq!(pdb.residue_count(), 1);
+        assert_eq!(pdb.atom_count(), 3);
+        let oxygen = pdb.atom(0).unwrap();
+        assert_eq!(oxygen.name(), "O");
+        assert_eq!(oxygen.element(), Some(&Element::O));
+        assert!(oxygen.hetero());
+        assert_eq!(pdb.atom(2).unwrap().element(), Some(&Element::H));
+        assert_eq!(pdb.atom(2).unwrap().serial_number(), 3);
+        assert!((pdb.model(1).unwrap().atom(0).unwrap().z() - 0.1273).abs() < 1e-9);
+
+        let (pdb, _) = open(
+            text,
+            ReadOptions::new()
+                .set_only_first_model(true)
+                .set_discard_hydrogens(true),
+        )
+        .unwrap();
+        assert_eq!(pdb.model_count(), 1);
+        assert_eq!(pdb.atom_count(), 1);
+    }
+
+    #[test]
+    fn read_extended_xyz() {
+        let text = "2
+Lattice=\"10.0 0.0 0.0 0.0 10.0 0.0 0.0 0.0 12.0\" Properties=species:S:1:pos:R:3:charge:R:1:forces:R:3 energy=-1.5 pbc=\"T T T\"
+Na 1.0 2.0 3.0 1.0 0.1 0.2 0.3
+Cl 4.0 5.0 6.0 -1.0 0.1 0.2 0.3
+";
+        let (pdb, errors) =
+            open(text, ReadOptions::new().set_level(StrictnessLevel::Loose)).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].short_description(),
+            "Ignored extended XYZ property"
+        );
+        let cell = pdb.unit_cell.as_ref().unwrap();
+        assert_eq!(cell.size(), (10.0, 10.0, 12.0));
+        assert_eq!(cell.gamma(), 90.0);
+        let chlorine = pdb.atom(1).unwrap();
+        assert_eq!(chlorine.element(), Some(&Element::Cl));
+        assert_eq!(chlorine.pos(), (4.0, 5.0, 6.0));
+        assert_eq!(chlorine.partial_charge(), Some(-1.0));
+    }
+
+    #[test]
+    fn comment() {
+        let info =
+            parse_comment("Properties=species:S:1:pos:R:3 Lattice=\"1 0 0 0 1 0 0 0 1\" pbc");
+        assert_eq!(info["properties"].as_deref(), Some("species:S:1:pos:R:3"));
+        assert_eq!(info["lattice"].as_deref(), Some("1 0 0 0 1 0 0 0 1"));
+        assert_eq!(info["pbc"], None);
+        assert!(parse_properties("species:S:1").is_err());
+        assert!(parse_properties("species:S").is_err());
+    }
+
+    #[test]
+    fn plain_comment_with_keywords() {
+        let text = "1\nProperties of water, Lattice unknown\nO 0.0 0.0 0.1\n";
+        let (pdb, errors) = open(text, &ReadOptions::new()).unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(pdb.atom_count(), 1);
+        assert_eq!(pdb.atom(0).unwrap().pos(), (0.0, 0.0, 0.1));
+        assert!(pdb.unit_cell.is_none());
+    }
+
+    #[test]
+    fn invalid() {
+        let errors = open("2\n\nO 0.0 0.0 0.0\n", &ReadOptions::new()).unwrap_err();
+        assert_eq!(errors[0].short_description(), "Unexpected end of file");
+        let errors = open("1\n\nXx 0.0 0.0 0.0\n", &ReadOptions::new()).unwrap_err();
+        assert_eq!(errors[0].short_description(), "Invalid XYZ atom");
+        let errors = open("one\n", &ReadOptions::new()).unwrap_err();
+        assert_eq!(errors[0].short_description(), "Invalid atom count");
+    }
+}
@@ -58,6 +58,18 @@ pub fn is_backbone(name: impl AsRef<str>) -> bool {
     BACKBONE_NAMES.contains(&name.as_ref())
 }
 
+/// Gets the theoretical maximal solvent accessible surface area in Å² of the given amino acid
+/// (three letter code) in a Gly-X-Gly tripeptide, used to calculate the relative accessibility.
+/// Source: Tien, M. Z., Meyer, A. G., Sydykova, D. K., Spielman, S. J., & Wilke, C. O. (2013).
+/// Maximum allowed solvent accessibilites of residues in proteins. PLoS ONE, 8(11), e80635.
+/// <https://doi.org/10.1371/journal.pone.0080635>
+pub fn max_accessibility(aa: impl AsRef<str>) -> Option<f64> {
+    MAX_ACCESSIBILITY
+        .iter()
+        .find(|(name, _)| *name == aa.as_ref())
+        .map(|(_, area)| *area)
+}
+
 /// Returns if the given number is a valid remark-type-number (according to wwPDB v 3.30)
 pub fn valid_remark_type_number(number: usize) -> bool {
     REMARK_TYPES.contains(&number)
@@ -70,6 +82,30 @@ const REMARK_TYPES: [usize; 42] = [
     900, 999,
 ];
 
+/// The theoretical maximal solvent accessible surface area in Å² of the common amino acids
+const MAX_ACCESSIBILITY: &[(&str, f64)] = &[
+    ("ALA", 129.0),
+    ("ARG", 274.0),
+    ("ASN", 195.0),
+    ("ASP", 193.0),
+    ("CYS", 167.0),
+    ("GLN", 225.0),
+    ("GLU", 223.0),
+    ("GLY", 104.0),
+    ("HIS", 224.0),
+    ("ILE", 197.0),
+    ("LEU", 201.0),
+    ("LYS", 236.0),
+    ("MET", 224.0),
+    ("PHE", 240.0),
+    ("PRO", 159.0),
+    ("SER", 155.0),
+    ("THR", 172.0),
+    ("TRP", 285.0),
+    ("TYR", 263.0),
+    ("VAL", 174.0),
+];
+
 /// All amino acids. Includes Amber-specific naming conventions for (de-)protonated versions, CYS involved in
 /// disulfide bonding and the like.
 const AMINO_ACIDS: &[&str] = &[
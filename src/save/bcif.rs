@@ -0,0 +1,829 @@
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+
+use crate::error::*;
+use crate::structs::*;
+use crate::validate;
+use crate::StrictnessLevel;
+use crate::TransformationMatrix;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
+
+/// Save the given PDB struct to the given file as BinaryCIF.
+/// # Errors
+/// It validates the PDB. It fails if the validation fails with the given `level`, or if the file could not be opened.
+/// If validation gives rise to problems, use the `save_bcif_raw` function.
+pub fn save_bcif(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+) -> Result<(), Vec<PDBError>> {
+    save_bcif_(pdb, filename, level, BufWriter::new)
+}
+
+/// Save the given PDB struct to the given file as BinaryCIF and compresses to .gz
+/// # Errors
+/// It validates the PDB. It fails if the validation fails with the given `level`, or if the file could not be opened.
+/// If validation gives rise to problems, use the `save_bcif_raw` function.
+#[cfg(feature = "compression")]
+pub fn save_bcif_gz(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    compression_level: Option<Compression>,
+) -> Result<(), Vec<PDBError>> {
+    save_bcif_(pdb, filename, level, |file| {
+        BufWriter::new(GzEncoder::new(file, compression_level.unwrap_or_default()))
+    })
+}
+
+/// Generic function to save the given PDB struct to the given file, validating it beforehand.
+fn save_bcif_<T, W>(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    writer: W,
+) -> Result<(), Vec<PDBError>>
+where
+    T: Write,
+    W: FnOnce(File) -> BufWriter<T>,
+{
+    let filename = filename.as_ref();
+    let mut errors = validate(pdb);
+    if errors.iter().any(|e| e.fails(level)) {
+        return Err(errors);
+    }
+
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(_e) => {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+                Context::show(filename)
+            ));
+            return Err(errors);
+        }
+    };
+
+    save_bcif_raw(pdb, writer(file));
+    Ok(())
+}
+
+/// Save the given PDB struct to the given BufWriter as BinaryCIF.
+/// It does not validate or renumber the PDB, so if that is needed that needs to be done in preparation.
+///
+/// The same categories are saved as in [`crate::save_mmcif_raw`]: the entry, unit cell, symmetry,
+/// scale, origx, MtriX, and the atoms. Every column is encoded with the smallest combination of
+/// the `Delta`, `RunLength`, and `IntegerPacking` encodings. Floating point columns are first
+/// encoded with `FixedPoint` using the smallest factor that keeps the values intact, and string
+/// columns with `StringArray`. Missing values are saved in the mask of the column.
+#[allow(clippy::unwrap_used)]
+pub fn save_bcif_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>) {
+    let name = pdb.identifier.as_deref().unwrap_or("?");
+    let mut categories = Vec::new();
+
+    categories.push(Category::new(
+        "entry",
+        1,
+        vec![Column::text("id", vec![name.to_string()])],
+    ));
+
+    if let Some(unit_cell) = &pdb.unit_cell {
+        categories.push(Category::new(
+            "cell",
+            1,
+            vec![
+                Column::text("entry_id", vec![name.to_string()]),
+                Column::float("length_a", vec![Some(unit_cell.a())]),
+                Column::float("length_b", vec![Some(unit_cell.b())]),
+                Column::float("length_c", vec![Some(unit_cell.c())]),
+                Column::float("angle_alpha", vec![Some(unit_cell.alpha())]),
+                Column::float("angle_beta", vec![Some(unit_cell.beta())]),
+                Column::float("angle_gamma", vec![Some(unit_cell.gamma())]),
+                Column::int(
+                    "Z_PDB",
+                    vec![pdb
+                        .symmetry
+                        .as_ref()
+                        .and_then(|s| i64::try_from(s.z()).ok())],
+                    MISSING,
+                ),
+            ],
+        ));
+    }
+
+    if let Some(scale) = &pdb.scale {
+        let mut columns = vec![Column::text("entry_id", vec![name.to_string()])];
+        columns.extend(matrix_columns(
+            "Cartn_transf_matrix",
+            "Cartn_transf_vector",
+            &[scale],
+        ));
+        categories.push(Category::new("atom_sites", 1, columns));
+    }
+
+    if let Some(origx) = &pdb.origx {
+        let mut columns = vec![Column::text("entry_id", vec![name.to_string()])];
+        columns.extend(matrix_columns("origx", "origx_vector", &[origx]));
+        categories.push(Category::new("database_PDB_matrix", 1, columns));
+    }
+
+    let mtrix: Vec<_> = pdb.mtrix().collect();
+    if !mtrix.is_empty() {
+        let mut columns = vec![
+            Column::int(
+                "id",
+                mtrix
+                    .iter()
+                    .map(|m| i64::try_from(m.serial_number).ok())
+                    .collect(),
+                MISSING,
+            ),
+            Column::text(
+                "code",
+                mtrix
+                    .iter()
+                    .map(|m| if m.contained { "given" } else { "generated" }.to_string())
+                    .collect(),
+            ),
+        ];
+        let transformations: Vec<_> = mtrix.iter().map(|m| &m.transformation).collect();
+        columns.extend(matrix_columns("matrix", "vector", &transformations));
+        categories.push(Category::new("struct_ncs_oper", mtrix.len(), columns));
+    }
+
+    if let Some(symmetry) = &pdb.symmetry {
+        categories.push(Category::new(
+            "symmetry",
+            1,
+            vec![
+                Column::text("entry_id", vec![name.to_string()]),
+                Column::text(
+                    "space_group_name_H-M",
+                    vec![symmetry.herman_mauguin_symbol().to_string()],
+                ),
+                Column::text(
+                    "pdbx_full_space_group_name_H-M",
+                    vec![symmetry.herman_mauguin_symbol().to_string()],
+                ),
+                Column::int(
+                    "Int_Tables_number",
+                    vec![Some(i64::try_from(symmetry.index()).unwrap_or_default())],
+                    MISSING,
+                ),
+            ],
+        ));
+    }
+
+    let mut sites = Vec::new();
+    for model in pdb.models() {
+        for (chain_index, chain) in model.chains().enumerate() {
+            for (residue_index, residue) in chain.residues().enumerate() {
+                for conformer in residue.conformers() {
+                    for atom in conformer.atoms() {
+                        sites.push(AtomSite {
+                            model,
+                            chain_index: chain_index + 1,
+                            chain,
+                            residue_index: residue_index + 1,
+                            residue,
+                            conformer,
+                            atom,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    if !sites.is_empty() {
+        let text = |name: &str, f: &dyn Fn(&AtomSite<'_>) -> String| {
+            Column::text(name, sites.iter().map(f).collect())
+        };
+        let optional_text = |name: &str, f: &dyn Fn(&AtomSite<'_>) -> Option<String>| {
+            Column::optional_text(name, sites.iter().map(f).collect(), INAPPLICABLE)
+        };
+        let int = |name: &str, f: &dyn Fn(&AtomSite<'_>) -> Option<i64>| {
+            Column::int(name, sites.iter().map(f).collect(), MISSING)
+        };
+        let float = |name: &str, f: &dyn Fn(&Atom) -> f64| {
+            Column::float(name, sites.iter().map(|s| Some(f(s.atom))).collect())
+        };
+        let mut columns = vec![
+            text("group_PDB", &|s| {
+                if s.atom.hetero() { "HETATM" } else { "ATOM" }.to_string()
+            }),
+            int("id", &|s| i64::try_from(s.atom.serial_number()).ok()),
+            text("type_symbol", &|s| {
+                s.atom.element().map_or("", Element::symbol).to_string()
+            }),
+            text("label_atom_id", &|s| s.atom.name().to_string()),
+            optional_text("label_alt_id", &|s| {
+                s.conformer.alternative_location().map(str::to_string)
+            }),
+            text("label_comp_id", &|s| s.conformer.name().to_string()),
+            text("label_asym_id", &|s| number_to_base26(s.chain_index)),
+            text("auth_asym_id", &|s| s.chain.id().to_string()),
+            int("label_entity_id", &|s| i64::try_from(s.chain_index).ok()),
+            int("label_seq_id", &|s| i64::try_from(s.residue_index).ok()),
+            int("auth_seq_id", &|s| Some(s.residue.serial_number() as i64)),
+            optional_text("pdbx_PDB_ins_code", &|s| {
+                s.residue.insertion_code().map(str::to_string)
+            }),
+            float("Cartn_x", &Atom::x),
+            float("Cartn_y", &Atom::y),
+            float("Cartn_z", &Atom::z),
+            float("occupancy", &Atom::occupancy),
+            float("B_iso_or_equiv", &Atom::b_factor),
+            int("pdbx_formal_charge", &|s| {
+                i64::try_from(s.atom.charge()).ok()
+            }),
+            int("pdbx_PDB_model_num", &|s| {
+                i64::try_from(s.model.serial_number()).ok()
+            }),
+        ];
+        if sites
+            .iter()
+            .any(|s| s.atom.anisotropic_temperature_factors().is_some())
+        {
+            for r in 0..3 {
+                for c in 0..3 {
+                    columns.push(Column::float(
+                        format!("aniso_U[{}][{}]", r + 1, c + 1),
+                        sites
+                            .iter()
+                            .map(|s| s.atom.anisotropic_temperature_factors().map(|m| m[r][c]))
+                            .collect(),
+                    ));
+                }
+            }
+        }
+        categories.push(Category::new("atom_site", sites.len(), columns));
+    }
+
+    let file = MsgPack::Map(vec![
+        ("version", MsgPack::Str("0.3.0".to_string())),
+        ("encoder", MsgPack::Str("pdbtbx".to_string())),
+        (
+            "dataBlocks",
+            MsgPack::Array(vec![MsgPack::Map(vec![
+                ("header", MsgPack::Str(name.to_string())),
+                (
+                    "categories",
+                    MsgPack::Array(categories.into_iter().map(Category::encode).collect()),
+                ),
+            ])]),
+        ),
+    ]);
+    let mut bytes = Vec::new();
+    file.write(&mut bytes);
+    sink.write_all(&bytes).unwrap();
+    sink.flush().unwrap();
+}
+
+/// The mask value for an inapplicable value (`.`)
+const INAPPLICABLE: i64 = 1;
+/// The mask value for an unknown value (`?`)
+const MISSING: i64 = 2;
+
+/// Create the columns for the given transformations, the matrix columns are followed by the vector columns
+fn matrix_columns(
+    matrix_name: &str,
+    vector_name: &str,
+    transformations: &[&TransformationMatrix],
+) -> Vec<Column> {
+    let mut columns = Vec::new();
+    for r in 0..3 {
+        for c in 0..3 {
+            columns.push(Column::float(
+                format!("{matrix_name}[{}][{}]", r + 1, c + 1),
+                transformations
+                    .iter()
+                    .map(|t| Some(t.matrix()[r][c]))
+                    .collect(),
+            ));
+        }
+    }
+    for r in 0..3 {
+        columns.push(Column::float(
+            format!("{vector_name}[{}]", r + 1),
+            transformations
+                .iter()
+                .map(|t| Some(t.matrix()[r][3]))
+                .collect(),
+        ));
+    }
+    columns
+}
+
+/// An atom with its place in the hierarchy
+struct AtomSite<'a> {
+    /// The model
+    model: &'a Model,
+    /// The index of the chain, starting at one
+    chain_index: usize,
+    /// The chain
+    chain: &'a Chain,
+    /// The index of the residue in the chain, starting at one
+    residue_index: usize,
+    /// The residue
+    residue: &'a Residue,
+    /// The conformer
+    conformer: &'a Conformer,
+    /// The atom
+    atom: &'a Atom,
+}
+
+/// A category with its columns
+struct Category {
+    /// The name, without the leading underscore
+    name: &'static str,
+    /// The number of rows
+    row_count: usize,
+    /// The columns
+    columns: Vec<Column>,
+}
+
+impl Category {
+    /// Create a new category
+    const fn new(name: &'static str, row_count: usize, columns: Vec<Column>) -> Self {
+        Self {
+            name,
+            row_count,
+            columns,
+        }
+    }
+
+    /// Encode this category into its MessagePack representation
+    fn encode(self) -> MsgPack {
+        MsgPack::Map(vec![
+            ("name", MsgPack::Str(format!("_{}", self.name))),
+            (
+                "columns",
+                MsgPack::Array(self.columns.into_iter().map(Column::encode).collect()),
+            ),
+            ("rowCount", MsgPack::size(self.row_count)),
+        ])
+    }
+}
+
+/// A column with its values
+struct Column {
+    /// The name
+    name: String,
+    /// The values
+    data: ColumnData,
+    /// The mask, if any value is missing
+    mask: Option<Vec<i64>>,
+}
+
+/// The values of a column
+enum ColumnData {
+    /// Integer values
+    Int(Vec<i64>),
+    /// Floating point values
+    Float(Vec<f64>),
+    /// Textual values
+    Text(Vec<String>),
+}
+
+impl Column {
+    /// Create the mask for the given values, with the given mask value for missing values
+    fn mask<T>(values: &[Option<T>], missing: i64) -> Option<Vec<i64>> {
+        values.iter().any(Option::is_none).then(|| {
+            values
+                .iter()
+                .map(|v| if v.is_some() { 0 } else { missing })
+                .collect()
+        })
+    }
+
+    /// Create an integer column
+    fn int(name: impl Into<String>, values: Vec<Option<i64>>, missing: i64) -> Self {
+        Self {
+            name: name.into(),
+            mask: Self::mask(&values, missing),
+            data: ColumnData::Int(values.into_iter().map(Option::unwrap_or_default).collect()),
+        }
+    }
+
+    /// Create a floating point column, missing values are inapplicable
+    fn float(name: impl Into<String>, values: Vec<Option<f64>>) -> Self {
+        Self {
+            name: name.into(),
+            mask: Self::mask(&values, INAPPLICABLE),
+            data: ColumnData::Float(values.into_iter().map(Option::unwrap_or_default).collect()),
+        }
+    }
+
+    /// Create a textual column
+    fn text(name: impl Into<String>, values: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            mask: None,
+            data: ColumnData::Text(values),
+        }
+    }
+
+    /// Create a textual column with missing values
+    fn optional_text(name: impl Into<String>, values: Vec<Option<String>>, missing: i64) -> Self {
+        Self {
+            name: name.into(),
+            mask: Self::mask(&values, missing),
+            data: ColumnData::Text(values.into_iter().map(Option::unwrap_or_default).collect()),
+        }
+    }
+
+    /// Encode this column into its MessagePack representation
+    fn encode(self) -> MsgPack {
+        let data = match &self.data {
+            ColumnData::Int(values) => encode_int(values),
+            ColumnData::Float(values) => encode_float(values),
+            ColumnData::Text(values) => encode_text(values),
+        };
+        MsgPack::Map(vec![
+            ("name", MsgPack::Str(self.name)),
+            ("data", data.into_msgpack()),
+            (
+                "mask",
+                self.mask
+                    .map_or(MsgPack::Nil, |mask| encode_int(&mask).into_msgpack()),
+            ),
+        ])
+    }
+}
+
+/// Encoded data, the bytes with the list of applied encodings
+struct Encoded {
+    /// The bytes
+    data: Vec<u8>,
+    /// The encodings, in the order they were applied
+    encoding: Vec<MsgPack>,
+}
+
+impl Encoded {
+    /// Get the MessagePack representation
+    fn into_msgpack(self) -> MsgPack {
+        MsgPack::Map(vec![
+            ("data", MsgPack::Bin(self.data)),
+            ("encoding", MsgPack::Array(self.encoding)),
+        ])
+    }
+}
+
+/// Create the MessagePack representation of an encoding
+fn encoding(kind: &str, parameters: Vec<(&'static str, MsgPack)>) -> MsgPack {
+    let mut map = vec![("kind", MsgPack::Str(kind.to_string()))];
+    map.extend(parameters);
+    MsgPack::Map(map)
+}
+
+/// Encode integers, using the combination of `Delta`, `RunLength`, and `IntegerPacking` that
+/// results in the smallest data
+fn encode_int(values: &[i64]) -> Encoded {
+    let mut best: Option<Encoded> = None;
+    for (delta, run_length) in [(false, false), (true, false), (false, true), (true, true)] {
+        let mut data = values.to_vec();
+        let mut encodings = Vec::new();
+        if delta {
+            let origin = data.first().copied().unwrap_or_default();
+            let mut previous = origin;
+            for value in &mut data {
+                let current = *value;
+                *value -= previous;
+                previous = current;
+            }
+            encodings.push(encoding(
+                "Delta",
+                vec![
+                    ("origin", MsgPack::Int(origin)),
+                    ("srcType", MsgPack::Int(3)),
+                ],
+            ));
+        }
+        if run_length {
+            let size = data.len();
+            let mut runs: Vec<i64> = Vec::new();
+            for value in data {
+                match runs.as_mut_slice() {
+                    [.., last, count] if *last == value => *count += 1,
+                    _ => runs.extend([value, 1]),
+                }
+            }
+            data = runs;
+            encodings.push(encoding(
+                "RunLength",
+                vec![
+                    ("srcType", MsgPack::Int(3)),
+                    ("srcSize", MsgPack::size(size)),
+                ],
+            ));
+        }
+        let encoded = pack(&data, encodings);
+        if best
+            .as_ref()
+            .map_or(true, |best| encoded.data.len() < best.data.len())
+        {
+            best = Some(encoded);
+        }
+    }
+    best.unwrap_or_else(|| pack(&[], Vec::new()))
+}
+
+/// Save the integers as bytes, using `IntegerPacking` into one or two bytes if that is smaller
+fn pack(values: &[i64], mut encodings: Vec<MsgPack>) -> Encoded {
+    let unsigned = values.iter().all(|v| *v >= 0);
+    let mut best = (4, values.len() * 4, Vec::new());
+    for byte_count in [1, 2] {
+        let (upper, lower) = match (byte_count, unsigned) {
+            (1, true) => (0xFF, 0),
+            (1, false) => (0x7F, -0x80),
+            (_, true) => (0xFFFF, 0),
+            (_, false) => (0x7FFF, -0x8000),
+        };
+        let mut packed = Vec::new();
+        for value in values {
+            let mut value = *value;
+            while value >= upper {
+                packed.push(upper);
+                value -= upper;
+            }
+            while value <= lower && !unsigned {
+                packed.push(lower);
+                value -= lower;
+            }
+            packed.push(value);
+        }
+        if packed.len() * byte_count < best.1 {
+            best = (byte_count, packed.len() * byte_count, packed);
+        }
+    }
+    let (byte_count, _, packed) = best;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let data = match (byte_count, unsigned) {
+        (1, true) => packed.iter().map(|v| *v as u8).collect(),
+        (1, false) => packed
+            .iter()
+            .flat_map(|v| (*v as i8).to_le_bytes())
+            .collect(),
+        (2, true) => packed
+            .iter()
+            .flat_map(|v| (*v as u16).to_le_bytes())
+            .collect(),
+        (2, false) => packed
+            .iter()
+            .flat_map(|v| (*v as i16).to_le_bytes())
+            .collect(),
+        _ => values
+            .iter()
+            .flat_map(|v| (*v as i32).to_le_bytes())
+            .collect(),
+    };
+    let data_type = match (byte_count, unsigned) {
+        (1, true) => 4,
+        (1, false) => 1,
+        (2, true) => 5,
+        (2, false) => 2,
+        _ => 3,
+    };
+    if byte_count < 4 {
+        encodings.push(encoding(
+            "IntegerPacking",
+            vec![
+                ("byteCount", MsgPack::size(byte_count)),
+                ("isUnsigned", MsgPack::Bool(unsigned)),
+                ("srcSize", MsgPack::size(values.len())),
+            ],
+        ));
+    }
+    encodings.push(encoding(
+        "ByteArray",
+        vec![("type", MsgPack::Int(data_type))],
+    ));
+    Encoded {
+        data,
+        encoding: encodings,
+    }
+}
+
+/// Encode floating point values with `FixedPoint`, using the smallest factor (up to 10⁶) that
+/// keeps the values intact, or save them as 64 bit floating point values otherwise
+fn encode_float(values: &[f64]) -> Encoded {
+    for exponent in 0..=6 {
+        let factor = 10_f64.powi(exponent);
+        let fits = values.iter().all(|v| {
+            let scaled = (v * factor).round();
+            scaled.abs() < f64::from(i32::MAX) && (scaled / factor - v).abs() <= 1e-12 * v.abs()
+        });
+        if fits {
+            #[allow(clippy::cast_possible_truncation)]
+            let integers: Vec<i64> = values.iter().map(|v| (v * factor).round() as i64).collect();
+            let mut encoded = encode_int(&integers);
+            encoded.encoding.insert(
+                0,
+                encoding(
+                    "FixedPoint",
+                    vec![
+                        ("factor", MsgPack::Float(factor)),
+                        ("srcType", MsgPack::Int(33)),
+                    ],
+                ),
+            );
+            return encoded;
+        }
+    }
+    Encoded {
+        data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        encoding: vec![encoding("ByteArray", vec![("type", MsgPack::Int(33))])],
+    }
+}
+
+/// Encode textual values with `StringArray`, every distinct string is only saved once
+#[allow(clippy::cast_possible_wrap)]
+fn encode_text(values: &[String]) -> Encoded {
+    let mut strings: HashMap<&str, i64> = HashMap::new();
+    let mut string_data = String::new();
+    let mut offsets = vec![0];
+    let mut length = 0;
+    let indices: Vec<i64> = values
+        .iter()
+        .map(|value| {
+            let next = strings.len() as i64;
+            *strings.entry(value.as_str()).or_insert_with(|| {
+                string_data.push_str(value);
+                length += value.chars().count() as i64;
+                offsets.push(length);
+                next
+            })
+        })
+        .collect();
+    let data = encode_int(&indices);
+    let offsets = encode_int(&offsets);
+    Encoded {
+        data: data.data,
+        encoding: vec![encoding(
+            "StringArray",
+            vec![
+                ("dataEncoding", MsgPack::Array(data.encoding)),
+                ("stringData", MsgPack::Str(string_data)),
+                ("offsetEncoding", MsgPack::Array(offsets.encoding)),
+                ("offsets", MsgPack::Bin(offsets.data)),
+            ],
+        )],
+    }
+}
+
+/// A MessagePack value to be written
+enum MsgPack {
+    /// Nil
+    Nil,
+    /// A boolean
+    Bool(bool),
+    /// An integer
+    Int(i64),
+    /// A floating point number
+    Float(f64),
+    /// A string
+    Str(String),
+    /// Binary data
+    Bin(Vec<u8>),
+    /// An array
+    Array(Vec<MsgPack>),
+    /// A map with string keys
+    Map(Vec<(&'static str, MsgPack)>),
+}
+
+impl MsgPack {
+    /// Create an integer for the given size
+    fn size(size: usize) -> Self {
+        MsgPack::Int(i64::try_from(size).unwrap_or(i64::MAX))
+    }
+
+    /// Write this value to the given buffer
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn write(&self, output: &mut Vec<u8>) {
+        /// Write the marker and the length for a value with the given length
+        fn header(output: &mut Vec<u8>, length: usize, fix: Option<(u8, usize)>, markers: [u8; 3]) {
+            match fix {
+                Some((marker, max)) if length <= max => output.push(marker | length as u8),
+                _ if length <= 0xFF && markers[0] != 0 => output.extend([markers[0], length as u8]),
+                _ if length <= 0xFFFF => {
+                    output.push(markers[1]);
+                    output.extend((length as u16).to_be_bytes());
+                }
+                _ => {
+                    output.push(markers[2]);
+                    output.extend((length as u32).to_be_bytes());
+                }
+            }
+        }
+        match self {
+            MsgPack::Nil => output.push(0xc0),
+            MsgPack::Bool(value) => output.push(if *value { 0xc3 } else { 0xc2 }),
+            MsgPack::Int(value) => {
+                if (0..=0x7f).contains(value) || (-32..0).contains(value) {
+                    output.push(*value as u8);
+                } else if let Ok(value) = i32::try_from(*value) {
+                    output.push(0xd2);
+                    output.extend(value.to_be_bytes());
+                } else {
+                    output.push(0xd3);
+                    output.extend(value.to_be_bytes());
+                }
+            }
+            MsgPack::Float(value) => {
+                output.push(0xcb);
+                output.extend(value.to_be_bytes());
+            }
+            MsgPack::Str(value) => {
+                header(output, value.len(), Some((0xa0, 31)), [0xd9, 0xda, 0xdb]);
+                output.extend(value.as_bytes());
+            }
+            MsgPack::Bin(value) => {
+                header(output, value.len(), None, [0xc4, 0xc5, 0xc6]);
+                output.extend(value);
+            }
+            MsgPack::Array(values) => {
+                header(output, values.len(), Some((0x90, 15)), [0, 0xdc, 0xdd]);
+                for value in values {
+                    value.write(output);
+                }
+            }
+            MsgPack::Map(values) => {
+                header(output, values.len(), Some((0x80, 15)), [0, 0xde, 0xdf]);
+                for (key, value) in values {
+                    MsgPack::Str((*key).to_string()).write(output);
+                    value.write(output);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{Format, ReadOptions};
+    use std::io::BufReader;
+
+    #[test]
+    fn round_trip() {
+        let (pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.cif")
+            .unwrap();
+        let mut buffer = Vec::new();
+        save_bcif_raw(&pdb, BufWriter::new(&mut buffer));
+        let mut text = Vec::new();
+        crate::save_mmcif_raw(&pdb, BufWriter::new(&mut text));
+        assert!(buffer.len() * 2 < text.len());
+        let (read, _) = ReadOptions::new()
+            .set_format(Format::BinaryCif)
+            .set_level(StrictnessLevel::Loose)
+            .read_raw(BufReader::new(buffer.as_slice()))
+            .unwrap();
+        assert_eq!(read.identifier, pdb.identifier);
+        assert_eq!(read.unit_cell, pdb.unit_cell);
+        assert_eq!(read.symmetry, pdb.symmetry);
+        assert_eq!(read.scale, pdb.scale);
+        assert_eq!(read.origx, pdb.origx);
+        assert_eq!(read.chain_count(), pdb.chain_count());
+        assert_eq!(read.residue_count(), pdb.residue_count());
+        assert_eq!(read.atom_count(), pdb.atom_count());
+        for (a, b) in pdb.atoms_with_hierarchy().zip(read.atoms_with_hierarchy()) {
+            assert_eq!(a.atom().serial_number(), b.atom().serial_number());
+            assert_eq!(a.atom().name(), b.atom().name());
+            assert_eq!(a.atom().element(), b.atom().element());
+            assert!(a.atom().distance(b.atom()) < 1e-9);
+            assert!((a.atom().b_factor() - b.atom().b_factor()).abs() < 1e-9);
+            assert!((a.atom().occupancy() - b.atom().occupancy()).abs() < 1e-9);
+            assert_eq!(a.chain().id(), b.chain().id());
+            assert_eq!(a.residue().id(), b.residue().id());
+            assert_eq!(a.conformer().id(), b.conformer().id());
+        }
+    }
+
+    #[test]
+    fn integer_encoding() {
+        let ids: Vec<i64> = (1..=1000).collect();
+        let encoded = encode_int(&ids);
+        // Delta and run length reduce this to [0, 1, 1, 999], packed into seven unsigned bytes
+        assert_eq!(encoded.data.len(), 7);
+        assert_eq!(encoded.encoding.len(), 4);
+        let packed = pack(&[300, -200, 5], Vec::new());
+        assert_eq!(packed.data.len(), 6);
+    }
+
+    #[test]
+    fn float_encoding() {
+        let encoded = encode_float(&[1.5, -2.25, 3.0]);
+        assert_eq!(encoded.encoding.len(), 3);
+        let encoded = encode_float(&[std::f64::consts::PI]);
+        assert_eq!(encoded.data.len(), 8);
+    }
+}
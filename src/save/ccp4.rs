@@ -0,0 +1,159 @@
+use crate::structs::*;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use crate::{Context, ErrorLevel, PDBError};
+
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+
+/// Save the given density map to the given file in the CCP4/MRC format, see [`save_map_raw`] for
+/// details.
+///
+/// ```no_run
+/// use pdbtbx::*;
+/// let (pdb, _errors) = ReadOptions::new().read("example-pdbs/1ubq.pdb").unwrap();
+/// let map = DensityMap::calculate_in_box(&pdb, 0.5, 3.0).unwrap();
+/// save_map(&map, "dump/1ubq.map").unwrap();
+/// ```
+///
+/// # Errors
+/// It fails if the file could not be created.
+pub fn save_map(map: &DensityMap, filename: impl AsRef<str>) -> Result<(), PDBError> {
+    save_map_(map, filename, BufWriter::new)
+}
+
+/// Save the given density map to the given file in the CCP4/MRC format and use gzip compression.
+///
+/// # Errors
+/// It fails if the file could not be created.
+#[cfg(feature = "compression")]
+pub fn save_map_gz(
+    map: &DensityMap,
+    filename: impl AsRef<str>,
+    compression_level: Option<Compression>,
+) -> Result<(), PDBError> {
+    save_map_(map, filename, |file| {
+        BufWriter::new(GzEncoder::new(file, compression_level.unwrap_or_default()))
+    })
+}
+
+/// Generic function to save the given density map to the given file.
+fn save_map_<T, W>(map: &DensityMap, filename: impl AsRef<str>, writer: W) -> Result<(), PDBError>
+where
+    T: Write,
+    W: FnOnce(File) -> BufWriter<T>,
+{
+    let filename = filename.as_ref();
+    let file = File::create(filename).map_err(|_| {
+        PDBError::new(
+            ErrorLevel::BreakingError,
+            "Could not open file",
+            "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+            Context::show(filename),
+        )
+    })?;
+    save_map_raw(map, writer(file));
+    Ok(())
+}
+
+/// Save the given density map to the given BufWriter in the CCP4/MRC format.
+///
+/// The map is saved in mode 2 (32-bit floats) in little endian byte order, with the x axis as
+/// columns, the y axis as rows, and the z axis as sections. If the map has an origin the position
+/// of its first grid point is saved as `ORIGIN`, as is common for electron microscopy maps. The
+/// space group is set to P 1.
+#[allow(clippy::unwrap_used, clippy::cast_possible_truncation)]
+pub fn save_map_raw<T: Write>(map: &DensityMap, mut sink: BufWriter<T>) {
+    let int = |value: usize| i32::try_from(value).unwrap_or(i32::MAX).to_le_bytes();
+    let float = |value: f64| (value as f32).to_le_bytes();
+    let mut header = Vec::with_capacity(1024);
+    for value in map.size() {
+        header.extend(int(value));
+    }
+    header.extend(2_i32.to_le_bytes());
+    for value in map.start() {
+        header.extend(i32::try_from(value).unwrap_or_default().to_le_bytes());
+    }
+    for value in map.sampling() {
+        header.extend(int(value));
+    }
+    let cell = map.unit_cell();
+    for value in [
+        cell.a(),
+        cell.b(),
+        cell.c(),
+        cell.alpha(),
+        cell.beta(),
+        cell.gamma(),
+    ] {
+        header.extend(float(value));
+    }
+    for axis in 1..=3 {
+        header.extend(int(axis));
+    }
+    for value in [map.min(), map.max(), map.mean()] {
+        header.extend(float(value));
+    }
+    header.extend(int(1)); // Space group
+    header.extend(int(0)); // No extended header
+    header.resize(27 * 4, 0);
+    header.extend(20140_i32.to_le_bytes()); // Version of the MRC format
+    header.resize(49 * 4, 0);
+    #[allow(clippy::cast_precision_loss)]
+    let origin = if map.origin() == [0.0; 3] {
+        (0.0, 0.0, 0.0)
+    } else {
+        map.grid_position(map.start().map(|value| value as f64))
+    };
+    for value in [origin.0, origin.1, origin.2] {
+        header.extend(float(value));
+    }
+    header.extend(b"MAP ");
+    header.extend([0x44, 0x44, 0, 0]);
+    header.extend(float(map.rms()));
+    header.extend(int(1)); // Number of labels
+    header.extend(format!("{:80}", "Created by pdbtbx").as_bytes());
+    header.resize(1024, 0);
+    sink.write_all(&header).unwrap();
+
+    for value in map.data() {
+        sink.write_all(&value.to_le_bytes()).unwrap();
+    }
+    sink.flush().unwrap();
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::ReadOptions;
+    use std::io::BufReader;
+
+    #[test]
+    fn round_trip() {
+        let cell = UnitCell::new(10.0, 12.0, 14.0, 80.0, 95.0, 110.0);
+        let data: Vec<f32> = (0..60).map(|v| v as f32 / 7.0).collect();
+        let mut map =
+            DensityMap::new([3, 4, 5], [-1, 2, 0], [10, 12, 14], cell, [0.0; 3], data).unwrap();
+        for origin in [[0.0; 3], [1.5, -2.0, 3.25]] {
+            map.set_origin(origin);
+            let mut bytes = Vec::new();
+            save_map_raw(&map, BufWriter::new(&mut bytes));
+            assert_eq!(bytes.len(), 1024 + 60 * 4);
+            let read = ReadOptions::new()
+                .read_map_raw(BufReader::new(&bytes[..]))
+                .unwrap();
+            assert_eq!(read.size(), map.size());
+            assert_eq!(read.start(), map.start());
+            assert_eq!(read.sampling(), map.sampling());
+            assert_eq!(read.data(), map.data());
+            for (value, expected) in read.origin().iter().zip(map.origin()) {
+                assert!((value - expected).abs() < 1e-4);
+            }
+            assert!((read.unit_cell().beta() - 95.0).abs() < 1e-4);
+        }
+    }
+}
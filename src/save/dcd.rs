@@ -0,0 +1,288 @@
+use crate::Frame;
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+use crate::read::AKMA_PS;
+use crate::{Context, ErrorLevel, PDBError};
+
+/// Save the frames of a trajectory one by one to a CHARMM/NAMD DCD file. Only a single frame has
+/// to be kept in memory at a time. The file is written in the CHARMM variant with little endian
+/// byte order and single precision coordinates. If the first frame has a unit cell every frame is
+/// saved with a unit cell record (with the cosines of the angles, as NAMD does), frames without a
+/// unit cell then get an empty unit cell. The header contains the number of frames, so it is
+/// updated by [`DcdTrajectoryWriter::finish`], which should be called after the last frame.
+///
+/// The step of the first frame and the difference in step between the first two frames are saved,
+/// together with the time step if the first two frames have a time. The steps of the frames are
+/// assumed to be evenly spaced.
+///
+/// ```no_run
+/// use pdbtbx::*;
+/// let (pdb, _errors) = ReadOptions::new().read("ensemble.pdb").unwrap();
+/// let mut writer = DcdTrajectoryWriter::create("dump/ensemble.dcd").unwrap();
+/// for model in pdb.models() {
+///     let mut frame = Frame::from_model(model);
+///     frame.set_unit_cell(pdb.unit_cell.clone());
+///     writer.write_frame(&frame).unwrap();
+/// }
+/// writer.finish().unwrap();
+/// ```
+pub struct DcdTrajectoryWriter<T: Write + Seek> {
+    /// The output
+    sink: BufWriter<T>,
+    /// The number of atoms, known after the first frame
+    atom_count: Option<usize>,
+    /// If every frame has a unit cell record
+    has_unit_cell: bool,
+    /// The number of frames written
+    frames: usize,
+    /// The step and time of the first two frames
+    first_frames: Vec<(usize, Option<f64>)>,
+}
+
+impl DcdTrajectoryWriter<File> {
+    /// Create a writer that saves the frames to the given file.
+    ///
+    /// # Errors
+    /// It fails if the file could not be created.
+    pub fn create(filename: impl AsRef<str>) -> Result<Self, PDBError> {
+        let filename = filename.as_ref();
+        let file = File::create(filename).map_err(|_| {
+            PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+                Context::show(filename),
+            )
+        })?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<T: Write + Seek> DcdTrajectoryWriter<T> {
+    /// Create a writer that saves the frames to the given sink, the sink should be empty.
+    pub const fn new(sink: BufWriter<T>) -> Self {
+        DcdTrajectoryWriter {
+            sink,
+            atom_count: None,
+            has_unit_cell: false,
+            frames: 0,
+            first_frames: Vec::new(),
+        }
+    }
+
+    /// Write the given frame.
+    ///
+    /// # Errors
+    /// It fails if the number of atoms differs from the first frame, if the frame has a unit cell
+    /// while the first frame did not, or if the output could not be written.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), PDBError> {
+        if self.atom_count.is_none() {
+            self.atom_count = Some(frame.atom_count());
+            self.has_unit_cell = frame.unit_cell().is_some();
+            self.write_header()?;
+        }
+        if self.atom_count != Some(frame.atom_count()) {
+            return Err(PDBError::new(
+                ErrorLevel::InvalidatingError,
+                "Atom count mismatch",
+                format!(
+                    "The frame has {} atoms but the first frame of the trajectory has {} atoms.",
+                    frame.atom_count(),
+                    self.atom_count.unwrap_or_default()
+                ),
+                Context::None,
+            ));
+        }
+        if !self.has_unit_cell && frame.unit_cell().is_some() {
+            return Err(PDBError::new(
+                ErrorLevel::InvalidatingError,
+                "Inconsistent unit cells",
+                "The frame has a unit cell but the first frame of the trajectory has none, so the file has no space for unit cells.",
+                Context::None,
+            ));
+        }
+
+        let mut data = Vec::with_capacity(12 * frame.atom_count() + 80);
+        if self.has_unit_cell {
+            let values = frame.unit_cell().map_or([0.0; 6], |cell| {
+                let cosine = |angle: f64| angle.to_radians().cos();
+                [
+                    cell.a(),
+                    cosine(cell.gamma()),
+                    cell.b(),
+                    cosine(cell.beta()),
+                    cosine(cell.alpha()),
+                    cell.c(),
+                ]
+            });
+            let values: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+            record(&mut data, &values);
+        }
+        for axis in 0..3 {
+            #[allow(clippy::cast_possible_truncation)]
+            let values: Vec<u8> = frame
+                .coordinates()
+                .iter()
+                .flat_map(|position| (position[axis] as f32).to_le_bytes())
+                .collect();
+            record(&mut data, &values);
+        }
+        self.sink.write_all(&data).map_err(|e| write_error(&e))?;
+        if self.first_frames.len() < 2 {
+            self.first_frames.push((frame.step(), frame.time()));
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Update the header with the number of frames and flush the output. This should be called
+    /// after the last frame.
+    ///
+    /// # Errors
+    /// It fails if the output could not be written.
+    pub fn finish(mut self) -> Result<(), PDBError> {
+        if self.atom_count.is_none() {
+            self.atom_count = Some(0);
+            self.write_header()?;
+        } else {
+            self.sink
+                .seek(SeekFrom::Start(0))
+                .map_err(|e| write_error(&e))?;
+            self.write_header()?;
+            self.sink
+                .seek(SeekFrom::End(0))
+                .map_err(|e| write_error(&e))?;
+        }
+        self.sink.flush().map_err(|e| write_error(&e))
+    }
+
+    /// Write the header, title, and number of atoms
+    fn write_header(&mut self) -> Result<(), PDBError> {
+        let first_step = self.first_frames.first().map_or(0, |frame| frame.0);
+        let (step_interval, time_step) = match self.first_frames.as_slice() {
+            [(first, first_time), (second, second_time)] if second > first => {
+                #[allow(clippy::cast_precision_loss)]
+                let time_step = first_time
+                    .zip(*second_time)
+                    .map(|(a, b)| (b - a) / (second - first) as f64);
+                (second - first, time_step)
+            }
+            _ => (1, None),
+        };
+        let int = |value: usize| i32::try_from(value).unwrap_or(i32::MAX).to_le_bytes();
+        let mut header = b"CORD".to_vec();
+        header.extend(int(self.frames));
+        header.extend(int(first_step));
+        header.extend(int(step_interval));
+        header.extend(int(
+            first_step + self.frames.saturating_sub(1) * step_interval
+        ));
+        header.extend([0; 20]);
+        #[allow(clippy::cast_possible_truncation)]
+        header.extend(((time_step.unwrap_or(0.0) / AKMA_PS) as f32).to_le_bytes());
+        header.extend(i32::from(self.has_unit_cell).to_le_bytes());
+        header.extend([0; 32]);
+        header.extend(24_i32.to_le_bytes());
+
+        let mut title = 1_i32.to_le_bytes().to_vec();
+        title.extend(format!("{:80}", "REMARKS Created by pdbtbx").as_bytes());
+
+        let mut data = Vec::with_capacity(200);
+        record(&mut data, &header);
+        record(&mut data, &title);
+        record(&mut data, &int(self.atom_count.unwrap_or_default()));
+        self.sink.write_all(&data).map_err(|e| write_error(&e))
+    }
+}
+
+impl<T: Write + Seek> std::fmt::Debug for DcdTrajectoryWriter<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DcdTrajectoryWriter {{ frames: {} }}", self.frames)
+    }
+}
+
+/// Add a Fortran unformatted record with the given data
+fn record(out: &mut Vec<u8>, data: &[u8]) {
+    let length = u32::try_from(data.len()).unwrap_or(u32::MAX).to_le_bytes();
+    out.extend(length);
+    out.extend(data);
+    out.extend(length);
+}
+
+/// Create an error for a failed write
+fn write_error(error: &std::io::Error) -> PDBError {
+    PDBError::new(
+        ErrorLevel::BreakingError,
+        "Could not write file",
+        format!("The DCD file could not be written: {error}."),
+        Context::None,
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{ReadOptions, TrajectoryFormat, UnitCell};
+    use std::io::{BufReader, Cursor};
+
+    fn write(frames: &[Frame]) -> Vec<u8> {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = DcdTrajectoryWriter::new(BufWriter::new(&mut cursor));
+        for frame in frames {
+            writer.write_frame(frame).unwrap();
+        }
+        writer.finish().unwrap();
+        cursor.into_inner()
+    }
+
+    fn read(bytes: &[u8]) -> Vec<Frame> {
+        ReadOptions::new()
+            .read_trajectory_raw(BufReader::new(bytes), TrajectoryFormat::Dcd)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn fixture_round_trip() {
+        let frames = read(&std::fs::read("example-pdbs/trajectories/1ubq.dcd").unwrap());
+        let written = read(&write(&frames));
+        assert_eq!(written.len(), 3);
+        for (frame, expected) in written.iter().zip(&frames) {
+            assert_eq!(frame.coordinates(), expected.coordinates());
+            assert_eq!(frame.step(), expected.step());
+            assert!((frame.time().unwrap() - expected.time().unwrap()).abs() < 1e-4);
+            let (cell, expected) = (frame.unit_cell().unwrap(), expected.unit_cell().unwrap());
+            assert!((cell.a() - expected.a()).abs() < 1e-9);
+            assert!((cell.gamma() - expected.gamma()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn unit_cells() {
+        let mut first = Frame::new(vec![[1.0, 2.0, 3.0]]);
+        first.set_unit_cell(Some(UnitCell::new(10.0, 20.0, 30.0, 90.0, 100.0, 120.0)));
+        let second = Frame::new(vec![[4.0, 5.0, 6.0]]);
+        let frames = read(&write(&[first.clone(), second.clone()]));
+        assert_eq!(frames[0].coordinates(), first.coordinates());
+        let cell = frames[0].unit_cell().unwrap();
+        assert!((cell.beta() - 100.0).abs() < 1e-9);
+        assert!((cell.gamma() - 120.0).abs() < 1e-9);
+        assert_eq!(frames[1].unit_cell(), None);
+        assert_eq!(frames[1].step(), 1);
+
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = DcdTrajectoryWriter::new(BufWriter::new(&mut cursor));
+        writer.write_frame(&second).unwrap();
+        let error = writer.write_frame(&first).unwrap_err();
+        assert_eq!(error.short_description(), "Inconsistent unit cells");
+    }
+
+    #[test]
+    fn empty() {
+        assert!(read(&write(&[])).is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::StrictnessLevel;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use crate::PDB;
+use crate::{validate, Context, ErrorLevel, PDBError};
+
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+
+/// Save the given PDB struct to the given file in the GROMACS GRO format, validating it beforehand.
+/// Every model is saved as a separate frame, see [`save_gro_raw`] for details.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_gro_raw` function.
+pub fn save_gro(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+) -> Result<(), Vec<PDBError>> {
+    save_gro_(pdb, filename, level, BufWriter::new)
+}
+
+/// Save the given PDB struct to the given file in the GROMACS GRO format, validating it
+/// beforehand, and use gzip compression.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_gro_raw` function.
+#[cfg(feature = "compression")]
+pub fn save_gro_gz(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    compression_level: Option<Compression>,
+) -> Result<(), Vec<PDBError>> {
+    save_gro_(pdb, filename, level, |file| {
+        BufWriter::new(GzEncoder::new(file, compression_level.unwrap_or_default()))
+    })
+}
+
+/// Generic function to save the given PDB struct to the given file, validating it beforehand.
+fn save_gro_<T, W>(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    writer: W,
+) -> Result<(), Vec<PDBError>>
+where
+    T: Write,
+    W: FnOnce(File) -> BufWriter<T>,
+{
+    let filename = filename.as_ref();
+    let mut errors = validate(pdb);
+    if errors.iter().any(|e| e.fails(level)) {
+        return Err(errors);
+    }
+
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(_e) => {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+                Context::show(filename)
+            ));
+            return Err(errors);
+        }
+    };
+
+    save_gro_raw(pdb, writer(file));
+    Ok(())
+}
+
+/// Save the given PDB struct to the given BufWriter in the GROMACS GRO format.
+/// It does not validate or renumber the PDB, so if that is needed, that needs to be done in preparation.
+///
+/// Every model is saved as a frame, with the identifier of the PDB (if any) as title. The
+/// coordinates are converted from Å to nm. Velocities are only saved if all atoms in a model
+/// have a velocity. The unit cell is saved as the box vectors of every frame. As GRO files do not
+/// contain chains, the chain information is lost. Serial numbers are saved modulo 100000 and
+/// residue and atom names are truncated to five characters.
+#[allow(clippy::unwrap_used)]
+pub fn save_gro_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>) {
+    let title = pdb.identifier.as_deref().unwrap_or("Generated by pdbtbx");
+    let box_line = pdb.unit_cell.as_ref().map_or_else(
+        || format!("{:10.5}{:10.5}{:10.5}", 0.0, 0.0, 0.0),
+        |cell| {
+            let [a, b, c] = cell.vectors();
+            let mut line = format!(
+                "{:10.5}{:10.5}{:10.5}",
+                a[0] / 10.0,
+                b[1] / 10.0,
+                c[2] / 10.0
+            );
+            if [a[1], a[2], b[0], b[2], c[0], c[1]]
+                .iter()
+                .any(|v| v.abs() > 1e-6)
+            {
+                for value in [a[1], a[2], b[0], b[2], c[0], c[1]] {
+                    line += &format!("{:10.5}", value / 10.0);
+                }
+            }
+            line
+        },
+    );
+    let truncate = |text: &str| text.chars().take(5).collect::<String>();
+
+    for model in pdb.models() {
+        let velocities = model.atoms().all(|a| a.velocity().is_some());
+        if pdb.model_count() > 1 {
+            writeln!(sink, "{title} model {}", model.serial_number()).unwrap();
+        } else {
+            writeln!(sink, "{title}").unwrap();
+        }
+        writeln!(sink, "{:5}", model.atom_count()).unwrap();
+        for residue in model.residues() {
+            for conformer in residue.conformers() {
+                for atom in conformer.atoms() {
+                    write!(
+                        sink,
+                        "{:>5}{:<5}{:>5}{:>5}{:8.3}{:8.3}{:8.3}",
+                        residue.serial_number() % 100_000,
+                        truncate(conformer.name()),
+                        truncate(atom.name()),
+                        atom.serial_number() % 100_000,
+                        atom.x() / 10.0,
+                        atom.y() / 10.0,
+                        atom.z() / 10.0,
+                    )
+                    .unwrap();
+                    if let (true, Some((vx, vy, vz))) = (velocities, atom.velocity()) {
+                        write!(sink, "{:8.4}{:8.4}{:8.4}", vx / 10.0, vy / 10.0, vz / 10.0)
+                            .unwrap();
+                    }
+                    writeln!(sink).unwrap();
+                }
+            }
+        }
+        writeln!(sink, "{box_line}").unwrap();
+    }
+    sink.flush().unwrap();
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::structs::*;
+    use crate::{Format, ReadOptions};
+    use std::io::BufReader;
+
+    #[test]
+    fn round_trip() {
+        let mut pdb = PDB::new();
+        for serial_number in 1..=2 {
+            let mut model = Model::new(serial_number);
+            let mut atom = Atom::new(true, 1, "OW", 1.26, 16.24, 16.79, 1.0, 0.0, "O", 0).unwrap();
+            atom.set_velocity((1.227, -0.58, 0.434)).unwrap();
+            model.add_atom(atom, "A", (1, None), ("SOL", None));
+            let mut atom = Atom::new(true, 2, "HW1", 1.9, 16.61, 17.47, 1.0, 0.0, "H", 0).unwrap();
+            atom.set_velocity((8.085, 3.191, -7.791)).unwrap();
+            model.add_atom(atom, "A", (1, None), ("SOL", None));
+            pdb.add_model(model);
+        }
+        pdb.unit_cell = Some(UnitCell::new(20.0, 20.0, 20.0, 90.0, 90.0, 60.0));
+
+        let mut buffer = Vec::new();
+        save_gro_raw(&pdb, BufWriter::new(&mut buffer));
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(
+            text.contains("    1SOL     OW    1   0.126   1.624   1.679  0.1227 -0.0580  0.0434\n")
+        );
+
+        let (read, errors) = ReadOptions::new()
+            .set_format(Format::Gro)
+            .read_raw(BufReader::new(text.as_bytes()))
+            .unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(read.model_count(), 2);
+        for (a, b) in pdb.atoms().zip(read.atoms()) {
+            assert_eq!(a.name(), b.name());
+            assert_eq!(a.element(), b.element());
+            assert!(a.distance(b) < 1e-9);
+            let (va, vb) = (a.velocity().unwrap(), b.velocity().unwrap());
+            assert!((va.0 - vb.0).abs() < 1e-9);
+        }
+        let cell = read.unit_cell.unwrap();
+        assert!((cell.gamma() - 60.0).abs() < 1e-3);
+        assert!((cell.b() - 20.0).abs() < 1e-3);
+    }
+}
@@ -402,7 +402,7 @@ _atom_site.aniso_U[3][3]"
 
 /// Print a floating point with at least 1 decimal place and at max 5 decimals
 #[allow(clippy::cast_possible_truncation)]
-fn print_float(num: f64) -> String {
+pub(super) fn print_float(num: f64) -> String {
     let rounded = (num * 100000.).round() / 100000.;
     if (rounded.round() - rounded).abs() < std::f64::EPSILON {
         format!("{}.0", rounded.trunc() as isize)
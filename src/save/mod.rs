@@ -1,10 +1,37 @@
+/// Save BinaryCIF files
+mod bcif;
+/// Save CCP4/MRC density maps
+mod ccp4;
+/// Save CHARMM/NAMD DCD trajectories
+mod dcd;
 /// Give a high level interface for users
 mod general;
+/// Save GROMACS GRO files
+mod gro;
 /// Save mmCIF/PDBx files
 mod mmcif;
+/// Save Tripos MOL2 files
+mod mol2;
 /// Save PDB files
 mod pdb;
+/// Save PDBML (XML) files
+mod pdbml;
+/// Save PQR files
+mod pqr;
+/// Save SDF files
+mod sdf;
+/// Save XYZ and extended XYZ files
+mod xyz;
 
+pub use bcif::{save_bcif, save_bcif_gz, save_bcif_raw};
+pub use ccp4::{save_map, save_map_gz, save_map_raw};
+pub use dcd::DcdTrajectoryWriter;
 pub use general::{save, save_gz};
+pub use gro::{save_gro, save_gro_gz, save_gro_raw};
 pub use mmcif::{save_mmcif, save_mmcif_gz, save_mmcif_raw};
-pub use pdb::{save_pdb, save_pdb_gz, save_pdb_raw};
+pub use mol2::{save_mol2, save_mol2_gz, save_mol2_raw};
+pub use pdb::{save_pdb, save_pdb_gz, save_pdb_raw, PdbTrajectoryWriter};
+pub use pdbml::{save_pdbml, save_pdbml_gz, save_pdbml_raw};
+pub use pqr::{save_pqr, save_pqr_gz, save_pqr_raw};
+pub use sdf::{save_sdf, save_sdf_gz, save_sdf_raw, save_sdf_residues_raw};
+pub use xyz::{save_xyz, save_xyz_gz, save_xyz_raw, save_xyz_selection, save_xyz_selection_raw};
@@ -0,0 +1,304 @@
+use crate::reference_tables;
+use crate::structs::*;
+use crate::StrictnessLevel;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use crate::{validate, Context, ErrorLevel, PDBError};
+
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+
+/// Save the given PDB struct to the given file in the Tripos MOL2 format, validating it beforehand.
+/// Every model is saved as a separate molecule, see [`save_mol2_raw`] for details.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_mol2_raw` function.
+pub fn save_mol2(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+) -> Result<(), Vec<PDBError>> {
+    save_mol2_(pdb, filename, level, BufWriter::new)
+}
+
+/// Save the given PDB struct to the given file in the Tripos MOL2 format, validating it
+/// beforehand, and use gzip compression.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_mol2_raw` function.
+#[cfg(feature = "compression")]
+pub fn save_mol2_gz(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    compression_level: Option<Compression>,
+) -> Result<(), Vec<PDBError>> {
+    save_mol2_(pdb, filename, level, |file| {
+        BufWriter::new(GzEncoder::new(file, compression_level.unwrap_or_default()))
+    })
+}
+
+/// Generic function to save the given PDB struct to the given file, validating it beforehand.
+fn save_mol2_<T, W>(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    writer: W,
+) -> Result<(), Vec<PDBError>>
+where
+    T: Write,
+    W: FnOnce(File) -> BufWriter<T>,
+{
+    let filename = filename.as_ref();
+    let mut errors = validate(pdb);
+    if errors.iter().any(|e| e.fails(level)) {
+        return Err(errors);
+    }
+
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(_e) => {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+                Context::show(filename)
+            ));
+            return Err(errors);
+        }
+    };
+
+    save_mol2_raw(pdb, writer(file));
+    Ok(())
+}
+
+/// Save the given PDB struct to the given BufWriter in the Tripos MOL2 format.
+/// It does not validate or renumber the PDB, so if that is needed, that needs to be done in preparation.
+///
+/// Every model is saved as a molecule named after the identifier of the PDB (if any). Every
+/// residue is saved as a substructure named after the residue name and serial number (`ALA12`),
+/// including the chain. The atoms are numbered from one in every molecule. The atom type of an
+/// atom is used if available, otherwise the element symbol is used (or `Du` if the element is
+/// unknown). The partial charges are saved if all atoms in a molecule have one. The bonds of the
+/// PDB are saved with their bond order, bonds without a bond order (like hydrogen bonds) are not
+/// saved. The unit cell and symmetry are saved in the `CRYSIN` section.
+#[allow(clippy::unwrap_used)]
+pub fn save_mol2_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>) {
+    let name = pdb.identifier.as_deref().unwrap_or("Generated by pdbtbx");
+    let bonds: Vec<_> = pdb.bonds().collect();
+
+    for model in pdb.models() {
+        let hierarchy: Vec<_> = model.atoms_with_hierarchy().collect();
+        let ids: HashMap<usize, usize> = hierarchy
+            .iter()
+            .enumerate()
+            .map(|(index, h)| (h.atom().counter(), index + 1))
+            .collect();
+        let model_bonds: Vec<(usize, usize, &str)> = bonds
+            .iter()
+            .filter_map(|(a, b, bond)| {
+                let kind = match bond {
+                    Bond::Double => "2",
+                    Bond::Triple => "3",
+                    Bond::Aromatic => "ar",
+                    _ => bond.order().map(|_| "1")?,
+                };
+                Some((*ids.get(&a.counter())?, *ids.get(&b.counter())?, kind))
+            })
+            .collect();
+        let charges = model.atoms().all(|a| a.partial_charge().is_some());
+        let protein = model
+            .residues()
+            .any(|r| r.name().map_or(false, reference_tables::is_amino_acid));
+
+        writeln!(sink, "@<TRIPOS>MOLECULE").unwrap();
+        if pdb.model_count() > 1 {
+            writeln!(sink, "{name} model {}", model.serial_number()).unwrap();
+        } else {
+            writeln!(sink, "{name}").unwrap();
+        }
+        writeln!(
+            sink,
+            "{:>5} {:>5} {:>5} 0 0",
+            hierarchy.len(),
+            model_bonds.len(),
+            model.residue_count()
+        )
+        .unwrap();
+        writeln!(sink, "{}", if protein { "PROTEIN" } else { "SMALL" }).unwrap();
+        writeln!(
+            sink,
+            "{}",
+            if charges {
+                "USER_CHARGES"
+            } else {
+                "NO_CHARGES"
+            }
+        )
+        .unwrap();
+        writeln!(sink).unwrap();
+
+        writeln!(sink, "@<TRIPOS>ATOM").unwrap();
+        let mut substructures = Vec::new();
+        for (index, h) in hierarchy.iter().enumerate() {
+            let residue = h.residue();
+            let substructure = format!(
+                "{}{}",
+                residue.name().unwrap_or("UNL"),
+                residue.serial_number()
+            );
+            if substructures
+                .last()
+                .map_or(true, |(_, _, r): &(_, _, &Residue)| {
+                    !std::ptr::eq(*r, residue)
+                })
+            {
+                substructures.push((index + 1, h.chain().id(), residue));
+            }
+            let atom = h.atom();
+            writeln!(
+                sink,
+                "{:>7} {:<8} {:>10.4} {:>10.4} {:>10.4} {:<8} {:>5} {:<8} {:>10.4}",
+                index + 1,
+                atom.name(),
+                atom.x(),
+                atom.y(),
+                atom.z(),
+                atom.atom_type()
+                    .unwrap_or_else(|| atom.element().map_or("Du", Element::symbol)),
+                substructures.len(),
+                substructure,
+                atom.partial_charge().filter(|_| charges).unwrap_or(0.0)
+            )
+            .unwrap();
+        }
+
+        writeln!(sink, "@<TRIPOS>BOND").unwrap();
+        for (index, (a, b, kind)) in model_bonds.iter().enumerate() {
+            writeln!(sink, "{:>6} {a:>5} {b:>5} {kind}", index + 1).unwrap();
+        }
+
+        writeln!(sink, "@<TRIPOS>SUBSTRUCTURE").unwrap();
+        for (index, (root, chain, residue)) in substructures.iter().enumerate() {
+            let residue_name = residue.name().unwrap_or("UNL");
+            let amino_acid = reference_tables::is_amino_acid(residue_name);
+            writeln!(
+                sink,
+                "{:>6} {:<8} {:>5} {:<8} {} {:<4} {:<8}",
+                index + 1,
+                format!("{residue_name}{}", residue.serial_number()),
+                root,
+                if amino_acid { "RESIDUE" } else { "GROUP" },
+                if amino_acid { 1 } else { 0 },
+                chain,
+                residue_name
+            )
+            .unwrap();
+        }
+
+        if let Some(cell) = &pdb.unit_cell {
+            writeln!(sink, "@<TRIPOS>CRYSIN").unwrap();
+            writeln!(
+                sink,
+                "{:10.4} {:10.4} {:10.4} {:10.4} {:10.4} {:10.4} {:>5} {:>5}",
+                cell.a(),
+                cell.b(),
+                cell.c(),
+                cell.alpha(),
+                cell.beta(),
+                cell.gamma(),
+                pdb.symmetry.as_ref().map_or(1, Symmetry::index),
+                1
+            )
+            .unwrap();
+        }
+        writeln!(sink).unwrap();
+    }
+    sink.flush().unwrap();
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{Format, ReadOptions};
+    use std::io::BufReader;
+
+    #[test]
+    fn round_trip() {
+        let mut model = Model::new(1);
+        for (serial_number, (name, atom_type, chain, residue)) in [
+            ("N", "N.am", "A", (5, "GLY")),
+            ("CA", "C.3", "A", (5, "GLY")),
+            ("C1", "C.ar", "B", (1, "LIG")),
+            ("C2", "C.ar", "B", (1, "LIG")),
+        ]
+        .iter()
+        .enumerate()
+        {
+            #[allow(clippy::cast_precision_loss)]
+            let x = serial_number as f64 * 1.4;
+            let mut atom = Atom::new(
+                residue.1 == "LIG",
+                serial_number + 1,
+                *name,
+                x,
+                0.5,
+                -1.0,
+                1.0,
+                0.0,
+                "C",
+                0,
+            )
+            .unwrap();
+            atom.set_atom_type(*atom_type).unwrap();
+            atom.set_partial_charge(0.25 - x / 10.0).unwrap();
+            model.add_atom(atom, chain, (residue.0, None), (residue.1, None));
+        }
+        let mut pdb = PDB::new();
+        pdb.add_model(model);
+        let counters: Vec<usize> = pdb.atoms().map(Atom::counter).collect();
+        pdb.add_bond_counters(counters[0], counters[1], Bond::Covalent);
+        pdb.add_bond_counters(counters[2], counters[3], Bond::Aromatic);
+        pdb.add_bond_counters(counters[1], counters[2], Bond::Hydrogen);
+        pdb.unit_cell = Some(UnitCell::new(10.0, 20.0, 30.0, 90.0, 90.0, 90.0));
+        pdb.symmetry = Symmetry::new("P 1 21 1");
+
+        let mut buffer = Vec::new();
+        save_mol2_raw(&pdb, BufWriter::new(&mut buffer));
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("USER_CHARGES"));
+        assert!(text.contains("     2 LIG1"));
+
+        let (read, errors) = ReadOptions::new()
+            .set_format(Format::Mol2)
+            .read_raw(BufReader::new(text.as_bytes()))
+            .unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(read.atom_count(), 4);
+        for (a, b) in pdb.atoms().zip(read.atoms()) {
+            assert_eq!(a.name(), b.name());
+            assert_eq!(a.atom_type(), b.atom_type());
+            assert!((a.partial_charge().unwrap() - b.partial_charge().unwrap()).abs() < 1e-4);
+            assert_eq!(a.hetero(), b.hetero());
+            assert!(a.distance(b) < 1e-4);
+        }
+        let residues: Vec<_> = read
+            .chains()
+            .flat_map(|c| {
+                c.residues()
+                    .map(move |r| (c.id(), r.serial_number(), r.name()))
+            })
+            .collect();
+        assert_eq!(residues, [("A", 5, Some("GLY")), ("B", 1, Some("LIG"))]);
+        let bonds: Vec<_> = read.bonds().map(|(_, _, bond)| bond).collect();
+        assert_eq!(bonds, [Bond::Covalent, Bond::Aromatic]);
+        assert_eq!(read.symmetry.unwrap().index(), 4);
+    }
+}
@@ -1,4 +1,6 @@
+use crate::read::{POSE_ENERGIES_BEGIN, POSE_ENERGIES_END};
 use crate::structs::*;
+use crate::Frame;
 use crate::StrictnessLevel;
 use crate::TransformationMatrix;
 
@@ -111,40 +113,108 @@ where
 /// * Does not save the MASTER record
 #[allow(clippy::unwrap_used)]
 pub fn save_pdb_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>, level: StrictnessLevel) {
-    let get_line = |fields: Vec<(usize, &str)>| {
-        let mut line = String::with_capacity(70);
-        for (length, text) in fields {
-            if length > 0 {
-                let cell = &text[text.len() - cmp::min(length, text.len())..];
-                let trimmed = cell.trim_start_matches('0');
-                if !cell.is_empty() && trimmed.is_empty() {
-                    std::fmt::write(&mut line, format_args!("{0:1$}", "0", length)).unwrap();
-                } else {
-                    std::fmt::write(&mut line, format_args!("{trimmed:length$}")).unwrap();
-                }
+    let mut print_line = |fields: Vec<(usize, &str)>| write_line(&mut sink, level, fields).unwrap();
+    write_header(pdb, pdb.unit_cell.as_ref(), level, &mut print_line);
+    let multiple_models = pdb.models().size_hint().0 > 1;
+    for model in pdb.models() {
+        write_model(
+            model,
+            multiple_models.then(|| model.serial_number()),
+            None,
+            &mut print_line,
+        );
+    }
+    write_pose_energies(pdb, &mut print_line);
+    write_footer(
+        pdb,
+        pdb.unit_cell.as_ref(),
+        (pdb.model_count(), pdb.total_atom_count()),
+        level,
+        &mut print_line,
+    );
+
+    sink.flush().unwrap();
+}
+
+/// Get the inner str of an Option<&str> or "" if the option is None
+macro_rules! get_option {
+    ($option:expr) => {
+        $option.as_deref().unwrap_or("")
+    };
+}
+
+/// Create a line from the given fields, every field is right aligned to the given length, or
+/// added as is if the length is zero
+#[allow(clippy::unwrap_used)]
+fn get_line(fields: Vec<(usize, &str)>) -> String {
+    let mut line = String::with_capacity(70);
+    for (length, text) in fields {
+        if length > 0 {
+            let cell = &text[text.len() - cmp::min(length, text.len())..];
+            let trimmed = cell.trim_start_matches('0');
+            if !cell.is_empty() && trimmed.is_empty() {
+                std::fmt::write(&mut line, format_args!("{0:1$}", "0", length)).unwrap();
             } else {
-                line += text;
+                std::fmt::write(&mut line, format_args!("{trimmed:length$}")).unwrap();
             }
+        } else {
+            line += text;
         }
-        line
-    };
-    let mut print_line = |fields: Vec<(usize, &str)>| {
-        let mut line = get_line(fields);
-        if level != StrictnessLevel::Loose && line.len() < 70 {
-            let dif = 70 - line.len();
-            line.reserve(dif);
-            line.extend(iter::repeat(" ").take(dif));
-        }
-        sink.write_all(line.as_bytes()).unwrap();
-        sink.write_all(b"\n").unwrap();
-    };
-    /// Get the inner str of an Option<&str> or "" if the option is None
-    macro_rules! get_option {
-        ($option:expr) => {
-            $option.as_deref().unwrap_or("")
-        };
     }
+    line
+}
+
+/// Write a line with the given fields to the sink, padded to 70 characters if the level is not loose
+fn write_line<T: Write>(
+    sink: &mut BufWriter<T>,
+    level: StrictnessLevel,
+    fields: Vec<(usize, &str)>,
+) -> std::io::Result<()> {
+    let mut line = get_line(fields);
+    if level != StrictnessLevel::Loose && line.len() < 70 {
+        let dif = 70 - line.len();
+        line.reserve(dif);
+        line.extend(iter::repeat(" ").take(dif));
+    }
+    sink.write_all(line.as_bytes())?;
+    sink.write_all(b"\n")
+}
 
+/// Write the lines given by `write` to the sink, stopping at the first line that could not be
+/// written
+fn write_lines<T: Write>(
+    sink: &mut BufWriter<T>,
+    level: StrictnessLevel,
+    write: impl FnOnce(&mut dyn FnMut(Vec<(usize, &str)>)),
+) -> Result<(), PDBError> {
+    let mut result = Ok(());
+    write(&mut |fields| {
+        if result.is_ok() {
+            result = write_line(sink, level, fields);
+        }
+    });
+    result.map_err(|e| write_error(&e))
+}
+
+/// Create an error for output that could not be written
+fn write_error(error: &std::io::Error) -> PDBError {
+    PDBError::new(
+        ErrorLevel::BreakingError,
+        "Could not write file",
+        format!("The PDB file could not be written: {error}."),
+        Context::None,
+    )
+}
+
+/// Write all records before the models. The unit cell is given separately so that it can be
+/// overridden when saving trajectories.
+#[allow(clippy::unwrap_used)]
+fn write_header(
+    pdb: &PDB,
+    unit_cell: Option<&UnitCell>,
+    level: StrictnessLevel,
+    print_line: &mut impl FnMut(Vec<(usize, &str)>),
+) {
     if let Some(name) = &pdb.identifier {
         print_line(vec![
             (
@@ -387,7 +457,7 @@ pub fn save_pdb_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>, level: Strictne
         }
     }
     // Cryst
-    if let Some(unit_cell) = &pdb.unit_cell {
+    if let Some(unit_cell) = unit_cell {
         let sym = if let Some(symmetry) = &pdb.symmetry {
             format!("{:10}{:3}", symmetry.herman_mauguin_symbol(), symmetry.z(),)
         } else {
@@ -450,7 +520,7 @@ pub fn save_pdb_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>, level: Strictne
     if let Some(scale) = &pdb.scale {
         write_matrix("SCALE", scale.matrix());
     } else if level == StrictnessLevel::Strict {
-        if let Some(unit_cell) = &pdb.unit_cell {
+        if let Some(unit_cell) = unit_cell {
             write_matrix(
                 "SCALE",
                 TransformationMatrix::scale(
@@ -503,92 +573,185 @@ pub fn save_pdb_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>, level: Strictne
             (0, if mtrix.contained { "1" } else { " " }),
         ]);
     }
+}
 
-    // Models
-    let multiple_models = pdb.models().size_hint().0 > 1;
-    for model in pdb.models() {
-        if multiple_models {
-            print_line(vec![
-                (0, "MODEL        "),
-                (0, model.serial_number().to_string().as_str()),
-            ]);
-        }
+/// Write the atoms of a single model, with the MODEL and ENDMDL records if a serial number is given.
+/// If coordinates are given they are used instead of the positions of the atoms, in the order of
+/// [`Model::atoms`].
+#[allow(clippy::unwrap_used)]
+fn write_model(
+    model: &Model,
+    serial_number: Option<usize>,
+    coordinates: Option<&[[f64; 3]]>,
+    print_line: &mut impl FnMut(Vec<(usize, &str)>),
+) {
+    let mut index = 0;
+    if let Some(serial_number) = serial_number {
+        print_line(vec![
+            (0, "MODEL        "),
+            (0, serial_number.to_string().as_str()),
+        ]);
+    }
 
-        let atom_line = |atom: &Atom, conformer: &Conformer, residue: &Residue, chain: &Chain| {
-            get_line(vec![
-                (5, atom.serial_number().to_string().as_str()),
-                (0, " "),
-                (4, atom.name()),
-                (1, conformer.alternative_location().unwrap_or(" ")),
-                (4, conformer.name()),
-                (1, chain.id()),
-                (4, residue.serial_number().to_string().as_str()),
-                (1, residue.insertion_code().unwrap_or(" ")),
-            ])
-        };
+    let atom_line = |atom: &Atom, conformer: &Conformer, residue: &Residue, chain: &Chain| {
+        get_line(vec![
+            (5, atom.serial_number().to_string().as_str()),
+            (0, " "),
+            (4, atom.name()),
+            (1, conformer.alternative_location().unwrap_or(" ")),
+            (4, conformer.name()),
+            (1, chain.id()),
+            (4, residue.serial_number().to_string().as_str()),
+            (1, residue.insertion_code().unwrap_or(" ")),
+        ])
+    };
 
-        for chain in model.chains().filter(|c| c.atoms().next().is_some()) {
-            for residue in chain.residues() {
-                for conformer in residue.conformers() {
-                    for atom in conformer.atoms() {
-                        let element = atom.element().map_or_else(|| "", Element::symbol);
+    for chain in model.chains().filter(|c| c.atoms().next().is_some()) {
+        for residue in chain.residues() {
+            for conformer in residue.conformers() {
+                for atom in conformer.atoms() {
+                    let element = atom.element().map_or_else(|| "", Element::symbol);
+                    let segment_id = format!("{:4}", atom.segment_id().unwrap_or(""));
+                    let position = coordinates.map_or_else(
+                        || atom.pos(),
+                        |coordinates| {
+                            let position = coordinates[index];
+                            (position[0], position[1], position[2])
+                        },
+                    );
+                    index += 1;
+                    print_line(vec![
+                        (6, if atom.hetero() { "HETATM" } else { "ATOM  " }),
+                        (0, &atom_line(atom, conformer, residue, chain)),
+                        (0, "   "),
+                        (8, &format!("{:8.3}", position.0)),
+                        (8, &format!("{:8.3}", position.1)),
+                        (8, &format!("{:8.3}", position.2)),
+                        (6, &format!("{:6.2}", atom.occupancy())),
+                        (6, &format!("{:6.2}", atom.b_factor())),
+                        (0, "      "),
+                        (0, &segment_id[..4]),
+                        (2, element),
+                        (0, &atom.pdb_charge()),
+                    ]);
+                    #[allow(clippy::cast_possible_truncation)]
+                    if atom.anisotropic_temperature_factors().is_some() {
+                        let f = atom.anisotropic_temperature_factors().unwrap();
                         print_line(vec![
-                            (6, if atom.hetero() { "HETATM" } else { "ATOM  " }),
+                            (6, "ANISOU"),
                             (0, &atom_line(atom, conformer, residue, chain)),
-                            (0, "   "),
-                            (8, &format!("{:8.3}", atom.pos().0)),
-                            (8, &format!("{:8.3}", atom.pos().1)),
-                            (8, &format!("{:8.3}", atom.pos().2)),
-                            (6, &format!("{:6.2}", atom.occupancy())),
-                            (6, &format!("{:6.2}", atom.b_factor())),
-                            (0, "          "),
+                            (0, " "),
+                            (7, &format!("{:8.3}", (f[0][0] * 10000.0) as isize)),
+                            (7, &format!("{:8.3}", (f[1][1] * 10000.0) as isize)),
+                            (7, &format!("{:8.3}", (f[2][2] * 10000.0) as isize)),
+                            (7, &format!("{:8.3}", (f[0][1] * 10000.0) as isize)),
+                            (7, &format!("{:8.3}", (f[0][2] * 10000.0) as isize)),
+                            (7, &format!("{:8.3}", (f[1][2] * 10000.0) as isize)),
+                            (0, "  "),
+                            (0, &segment_id[..4]),
                             (2, element),
                             (0, &atom.pdb_charge()),
                         ]);
-                        #[allow(clippy::cast_possible_truncation)]
-                        if atom.anisotropic_temperature_factors().is_some() {
-                            let f = atom.anisotropic_temperature_factors().unwrap();
-                            print_line(vec![
-                                (6, "ANISOU"),
-                                (0, &atom_line(atom, conformer, residue, chain)),
-                                (0, " "),
-                                (7, &format!("{:8.3}", (f[0][0] * 10000.0) as isize)),
-                                (7, &format!("{:8.3}", (f[1][1] * 10000.0) as isize)),
-                                (7, &format!("{:8.3}", (f[2][2] * 10000.0) as isize)),
-                                (7, &format!("{:8.3}", (f[0][1] * 10000.0) as isize)),
-                                (7, &format!("{:8.3}", (f[0][2] * 10000.0) as isize)),
-                                (7, &format!("{:8.3}", (f[1][2] * 10000.0) as isize)),
-                                (0, "      "),
-                                (2, element),
-                                (0, &atom.pdb_charge()),
-                            ]);
-                        }
                     }
                 }
             }
-            let last_atom = chain.atoms().nth_back(0).unwrap();
-            let last_residue = chain.residues().nth_back(0).unwrap();
-            let last_conformer = chain.conformers().nth_back(0).unwrap();
-            print_line(vec![
-                (0, "TER"),
-                (5, last_atom.serial_number().to_string().as_str()),
-                (0, "      "),
-                (3, last_conformer.name()),
-                (0, " "),
-                (1, chain.id()),
-                (4, last_residue.serial_number().to_string().as_str()),
-            ]);
-        }
-        if multiple_models {
-            print_line(vec![(0, "ENDMDL")]);
         }
+        let last_atom = chain.atoms().nth_back(0).unwrap();
+        let last_residue = chain.residues().nth_back(0).unwrap();
+        let last_conformer = chain.conformers().nth_back(0).unwrap();
+        print_line(vec![
+            (0, "TER"),
+            (5, last_atom.serial_number().to_string().as_str()),
+            (0, "      "),
+            (3, last_conformer.name()),
+            (0, " "),
+            (1, chain.id()),
+            (4, last_residue.serial_number().to_string().as_str()),
+        ]);
+    }
+    if serial_number.is_some() {
+        print_line(vec![(0, "ENDMDL")]);
+    }
+}
+
+/// Write the Rosetta pose energies table, if present, with the energies of the Residues of the
+/// first model. The residues are labelled with their name and index, variants are not saved.
+fn write_pose_energies(pdb: &PDB, print_line: &mut impl FnMut(Vec<(usize, &str)>)) {
+    let energies = if let Some(energies) = pdb.pose_energies() {
+        energies
+    } else {
+        return;
+    };
+    let row = |label: &str, values: &mut dyn Iterator<Item = String>| {
+        iter::once(label.to_string())
+            .chain(values)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let name = energies.name().unwrap_or("");
+    print_line(vec![(
+        0,
+        "# All scores below are weighted scores, not raw scores.",
+    )]);
+    print_line(vec![(0, POSE_ENERGIES_BEGIN), (0, " "), (0, name)]);
+    print_line(vec![(
+        0,
+        &row("label", &mut energies.terms().map(str::to_string)),
+    )]);
+    print_line(vec![(
+        0,
+        &row(
+            "weights",
+            &mut energies.terms().map(|term| {
+                energies
+                    .weight(term)
+                    .map_or_else(|| "NA".to_string(), |weight| weight.to_string())
+            }),
+        ),
+    )]);
+    print_line(vec![(
+        0,
+        &row(
+            "pose",
+            &mut energies.totals().map(|(_, total)| total.to_string()),
+        ),
+    )]);
+    for (index, residue) in pdb
+        .models()
+        .next()
+        .into_iter()
+        .flat_map(Model::residues)
+        .enumerate()
+        .filter(|(_, residue)| residue.energies().next().is_some())
+    {
+        let label = format!("{}_{}", residue.name().unwrap_or("UNK"), index + 1);
+        print_line(vec![(
+            0,
+            &row(
+                &label,
+                &mut energies
+                    .terms()
+                    .map(|term| residue.energy(term).unwrap_or_default().to_string()),
+            ),
+        )]);
     }
+    print_line(vec![(0, POSE_ENERGIES_END), (0, " "), (0, name)]);
+}
+
+/// Write the MASTER (if the level is not loose) and END records
+fn write_footer(
+    pdb: &PDB,
+    unit_cell: Option<&UnitCell>,
+    (model_count, atom_count): (usize, usize),
+    level: StrictnessLevel,
+    print_line: &mut impl FnMut(Vec<(usize, &str)>),
+) {
     if level != StrictnessLevel::Loose {
         let mut xform = 0;
         if pdb.origx.is_some() || level == StrictnessLevel::Strict {
             xform += 3;
         }
-        if pdb.scale.is_some() || (level == StrictnessLevel::Strict && pdb.unit_cell.is_some()) {
+        if pdb.scale.is_some() || (level == StrictnessLevel::Strict && unit_cell.is_some()) {
             xform += 3;
         }
         for _ in pdb.mtrix() {
@@ -604,13 +767,332 @@ pub fn save_pdb_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>, level: Strictne
             (5, "0"), //numTurn (deprecated)
             (5, "0"), //numSite
             (5, xform.to_string().as_str()),
-            (5, pdb.total_atom_count().to_string().as_str()),
-            (5, pdb.model_count().to_string().as_str()),
+            (5, atom_count.to_string().as_str()),
+            (5, model_count.to_string().as_str()),
             (5, "0"), //numConnect
             (5, "0"), //numSeq
         ]);
     }
     print_line(vec![(0, "END")]);
+}
 
-    sink.flush().unwrap();
+/// Save the frames of a trajectory one by one as models in a PDB file. Every frame is written as
+/// the first model of the topology with the coordinates of the frame, so only a single frame has to
+/// be kept in memory at a time. The header of the topology is written before the first frame, with
+/// the unit cell of the first frame if it has one. Call [`PdbTrajectoryWriter::finish`] after the
+/// last frame to close the file.
+///
+/// ```no_run
+/// use pdbtbx::*;
+/// let (topology, _errors) = ReadOptions::new().read("topology.pdb").unwrap();
+/// let mut writer =
+///     PdbTrajectoryWriter::create(&topology, "dump/trajectory.pdb", StrictnessLevel::Loose)
+///         .unwrap();
+/// for frame in ReadOptions::new().read_trajectory("trajectory.xtc").unwrap() {
+///     writer.write_frame(&frame.unwrap()).unwrap();
+/// }
+/// writer.finish().unwrap();
+/// ```
+pub struct PdbTrajectoryWriter<'a, T: Write> {
+    /// The topology
+    topology: &'a PDB,
+    /// The output
+    sink: BufWriter<T>,
+    /// The level used for the output format
+    level: StrictnessLevel,
+    /// The unit cell written in the header, if the header is written
+    header: Option<Option<UnitCell>>,
+    /// The number of frames written
+    frames: usize,
+}
+
+impl<'a> PdbTrajectoryWriter<'a, File> {
+    /// Create a writer that saves the frames to the given file, validating the topology beforehand.
+    ///
+    /// # Errors
+    /// It fails if the validation fails with the given `level` or if the file could not be created.
+    pub fn create(
+        topology: &'a PDB,
+        filename: impl AsRef<str>,
+        level: StrictnessLevel,
+    ) -> Result<Self, Vec<PDBError>> {
+        create_trajectory_file(topology, filename.as_ref(), level)
+            .map(|file| Self::new(topology, BufWriter::new(file), level))
+    }
+}
+
+#[cfg(feature = "compression")]
+impl<'a> PdbTrajectoryWriter<'a, GzEncoder<File>> {
+    /// Create a writer that saves the frames to the given file with gzip compression, validating
+    /// the topology beforehand.
+    ///
+    /// # Errors
+    /// It fails if the validation fails with the given `level` or if the file could not be created.
+    pub fn create_gz(
+        topology: &'a PDB,
+        filename: impl AsRef<str>,
+        level: StrictnessLevel,
+        compression_level: Option<Compression>,
+    ) -> Result<Self, Vec<PDBError>> {
+        create_trajectory_file(topology, filename.as_ref(), level).map(|file| {
+            let encoder = GzEncoder::new(file, compression_level.unwrap_or_default());
+            Self::new(topology, BufWriter::new(encoder), level)
+        })
+    }
+}
+
+impl<'a, T: Write> PdbTrajectoryWriter<'a, T> {
+    /// Create a writer that saves the frames to the given sink. It does not validate the topology.
+    pub fn new(topology: &'a PDB, sink: BufWriter<T>, level: StrictnessLevel) -> Self {
+        PdbTrajectoryWriter {
+            topology,
+            sink,
+            level,
+            header: None,
+            frames: 0,
+        }
+    }
+
+    /// Write the given frame as the next model, the models are numbered from 1 in the order they
+    /// are written.
+    ///
+    /// # Errors
+    /// It fails if the topology has no models, if the number of atoms in its first model is not
+    /// the same as in the frame, or if the output could not be written.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), PDBError> {
+        let model = self.topology.model(0).ok_or_else(|| {
+            PDBError::new(
+                ErrorLevel::InvalidatingError,
+                "Empty topology",
+                "The topology for the trajectory does not contain any models.",
+                Context::None,
+            )
+        })?;
+        if model.atom_count() != frame.atom_count() {
+            return Err(PDBError::new(
+                ErrorLevel::InvalidatingError,
+                "Atom count mismatch",
+                format!(
+                    "The frame has {} atoms but the topology has {} atoms.",
+                    frame.atom_count(),
+                    model.atom_count()
+                ),
+                Context::None,
+            ));
+        }
+        self.write_header(frame.unit_cell())?;
+        self.frames += 1;
+        let frames = self.frames;
+        write_lines(&mut self.sink, self.level, |mut print_line| {
+            write_model(
+                model,
+                Some(frames),
+                Some(frame.coordinates()),
+                &mut print_line,
+            );
+        })
+    }
+
+    /// Write the end of the file and flush the output. This should be called after the last frame.
+    ///
+    /// # Errors
+    /// It fails if the output could not be written.
+    pub fn finish(mut self) -> Result<(), PDBError> {
+        self.write_header(None)?;
+        let atom_count = self.topology.model(0).map_or(0, Model::atom_count);
+        let unit_cell = self.header.take().flatten();
+        let (topology, frames, level) = (self.topology, self.frames, self.level);
+        write_lines(&mut self.sink, level, |mut print_line| {
+            write_footer(
+                topology,
+                unit_cell.as_ref(),
+                (frames, frames * atom_count),
+                level,
+                &mut print_line,
+            );
+        })?;
+        self.sink.flush().map_err(|e| write_error(&e))
+    }
+
+    /// Write the header if it is not written yet, with the given unit cell or the unit cell of the topology
+    fn write_header(&mut self, unit_cell: Option<&UnitCell>) -> Result<(), PDBError> {
+        if self.header.is_none() {
+            let unit_cell = unit_cell.or(self.topology.unit_cell.as_ref()).cloned();
+            let (topology, level) = (self.topology, self.level);
+            write_lines(&mut self.sink, level, |mut print_line| {
+                write_header(topology, unit_cell.as_ref(), level, &mut print_line);
+            })?;
+            self.header = Some(unit_cell);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Write> std::fmt::Debug for PdbTrajectoryWriter<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PdbTrajectoryWriter {{ frames: {} }}", self.frames)
+    }
+}
+
+/// Validate the topology and create the file for a trajectory
+fn create_trajectory_file(
+    topology: &PDB,
+    filename: &str,
+    level: StrictnessLevel,
+) -> Result<File, Vec<PDBError>> {
+    let mut errors = validate(topology);
+    errors.extend(validate_pdb(topology));
+    if errors.iter().any(|error| error.fails(level)) {
+        return Err(errors);
+    }
+    File::create(filename).map_err(|_| {
+        vec![PDBError::new(
+            ErrorLevel::BreakingError,
+            "Could not open file",
+            "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+            Context::show(filename),
+        )]
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{Format, ReadOptions};
+
+    #[test]
+    fn trajectory() {
+        let (topology, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let mut writer = PdbTrajectoryWriter::new(
+            &topology,
+            BufWriter::new(Vec::new()),
+            StrictnessLevel::Loose,
+        );
+        let mut frames = Vec::new();
+        for frame in ReadOptions::new()
+            .read_trajectory("example-pdbs/trajectories/1ubq.xtc")
+            .unwrap()
+        {
+            let frame = frame.unwrap();
+            writer.write_frame(&frame).unwrap();
+            frames.push(frame);
+        }
+        let error = writer.write_frame(&Frame::new(vec![[0.0; 3]])).unwrap_err();
+        assert_eq!(error.short_description(), "Atom count mismatch");
+        let bytes = writer.sink.into_inner().unwrap();
+        assert_eq!(String::from_utf8_lossy(&bytes).matches("ENDMDL").count(), 3);
+
+        // A full output gives an error instead of a panic
+        let mut full = [0; 100];
+        let mut writer = PdbTrajectoryWriter::new(
+            &topology,
+            BufWriter::with_capacity(10, &mut full[..]),
+            StrictnessLevel::Loose,
+        );
+        let error = writer.write_frame(&frames[0]).unwrap_err();
+        assert_eq!(error.short_description(), "Could not write file");
+        assert!(writer.finish().is_err());
+
+        let (pdb, _) = ReadOptions::new()
+            .set_format(Format::Pdb)
+            .set_level(StrictnessLevel::Loose)
+            .read_raw(std::io::BufReader::new(bytes.as_slice()))
+            .unwrap();
+        assert_eq!(pdb.model_count(), 3);
+        let cell = pdb.unit_cell.as_ref().unwrap();
+        assert!((cell.a() - 50.84).abs() < 1e-3);
+        for (model, frame) in pdb.models().zip(&frames) {
+            for (atom, position) in model.atoms().zip(frame.coordinates()) {
+                assert!((atom.x() - position[0]).abs() < 1e-3);
+                assert!((atom.z() - position[2]).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn segment_ids() {
+        let text = "\
+ATOM      1  N   ALA A   1       0.000   0.000   0.000  1.00  0.00      PROA N
+ATOM      2  CA  ALA A   1       1.500   0.000   0.000  1.00  0.00      PROA C
+ATOM      3  OH2 TIP3W   1       5.000   5.000   5.000  1.00  0.00      WT1  O
+ATOM      4  OH2 TIP3W   2       8.000   5.000   5.000  1.00  0.00      WT2  O
+END
+";
+        let read = |text: &[u8], segments: bool| {
+            ReadOptions::new()
+                .set_format(Format::Pdb)
+                .set_level(StrictnessLevel::Loose)
+                .set_chains_from_segments(segments)
+                .read_raw(std::io::BufReader::new(text))
+                .unwrap()
+                .0
+        };
+        let pdb = read(text.as_bytes(), false);
+        let chains: Vec<_> = pdb.chains().map(Chain::id).collect();
+        assert_eq!(chains, ["A", "W"]);
+        let segments: Vec<_> = pdb.atoms().map(Atom::segment_id).collect();
+        assert_eq!(
+            segments,
+            [Some("PROA"), Some("PROA"), Some("WT1"), Some("WT2")]
+        );
+        assert_eq!(pdb.residue(1).unwrap().name(), Some("TIP3"));
+
+        let pdb = read(text.as_bytes(), true);
+        let chains: Vec<_> = pdb.chains().map(Chain::id).collect();
+        assert_eq!(chains, ["PROA", "WT1", "WT2"]);
+
+        let mut bytes = Vec::new();
+        save_pdb_raw(&pdb, BufWriter::new(&mut bytes), StrictnessLevel::Loose);
+        let saved = String::from_utf8(bytes).unwrap();
+        let water = saved.lines().find(|l| l.contains("OH2")).unwrap();
+        assert_eq!(&water[17..21], "TIP3");
+        assert_eq!(&water[72..76], "WT1 ");
+        let saved = read(saved.as_bytes(), false);
+        for (a, b) in saved.atoms().zip(pdb.atoms()) {
+            assert_eq!(a.segment_id(), b.segment_id());
+        }
+
+        let too_long = |pdb: &PDB| {
+            validate_pdb(pdb)
+                .iter()
+                .filter(|e| e.short_description() == "Segment id too long")
+                .count()
+        };
+        let mut pdb = pdb;
+        assert_eq!(too_long(&pdb), 0);
+        pdb.atom_mut(0).unwrap().set_segment_id("PROTEIN").unwrap();
+        assert_eq!(too_long(&pdb), 1);
+    }
+
+    #[test]
+    fn invalid_segment_ids() {
+        let text = "\
+ATOM      1  N   ALA A   1       0.000   0.000   0.000  1.00  0.00      P\tOA N
+ATOM      2  CA  ALA A   1       1.500   0.000   0.000  1.00  0.00      PROA C
+END
+";
+        let options = ReadOptions::new()
+            .set_format(Format::Pdb)
+            .set_level(StrictnessLevel::Loose)
+            .clone();
+        let (pdb, errors) = options
+            .read_raw(std::io::BufReader::new(text.as_bytes()))
+            .unwrap();
+        assert!(errors
+            .iter()
+            .any(|e| e.short_description() == "Invalid segment identifier"));
+        let segments: Vec<_> = pdb.atoms().map(Atom::segment_id).collect();
+        assert_eq!(segments, [None, Some("PROA")]);
+        let records = options
+            .stream_atoms_raw(std::io::BufReader::new(text.as_bytes()))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records[0].atom().segment_id(), None);
+        assert_eq!(records[1].atom().segment_id(), Some("PROA"));
+    }
 }
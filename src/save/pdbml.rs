@@ -0,0 +1,388 @@
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+
+use super::mmcif::print_float;
+use crate::error::*;
+use crate::structs::*;
+use crate::validate;
+use crate::StrictnessLevel;
+use crate::TransformationMatrix;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
+
+/// Save the given PDB struct to the given file as PDBML (XML).
+/// # Errors
+/// It validates the PDB. It fails if the validation fails with the given `level`, or if the file could not be opened.
+/// If validation gives rise to problems, use the `save_pdbml_raw` function.
+pub fn save_pdbml(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+) -> Result<(), Vec<PDBError>> {
+    save_pdbml_(pdb, filename, level, BufWriter::new)
+}
+
+/// Save the given PDB struct to the given file as PDBML (XML) and compress it with gzip.
+/// # Errors
+/// It validates the PDB. It fails if the validation fails with the given `level`, or if the file could not be opened.
+/// If validation gives rise to problems, use the `save_pdbml_raw` function.
+#[cfg(feature = "compression")]
+pub fn save_pdbml_gz(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    compression_level: Option<Compression>,
+) -> Result<(), Vec<PDBError>> {
+    save_pdbml_(pdb, filename, level, |file| {
+        BufWriter::new(GzEncoder::new(file, compression_level.unwrap_or_default()))
+    })
+}
+
+/// Generic function to save the given PDB struct to the given file, validating it beforehand.
+fn save_pdbml_<T, W>(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    writer: W,
+) -> Result<(), Vec<PDBError>>
+where
+    T: Write,
+    W: FnOnce(File) -> BufWriter<T>,
+{
+    let filename = filename.as_ref();
+    let mut errors = validate(pdb);
+    if errors.iter().any(|e| e.fails(level)) {
+        return Err(errors);
+    }
+
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(_e) => {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+                Context::show(filename)
+            ));
+            return Err(errors);
+        }
+    };
+
+    save_pdbml_raw(pdb, writer(file));
+    Ok(())
+}
+
+/// Save the given PDB struct to the given BufWriter as PDBML (XML).
+/// It does not validate or renumber the PDB, so if that is needed that needs to be done in preparation.
+///
+/// The same categories are saved as in [`crate::save_mmcif_raw`]: the entry, unit cell, symmetry,
+/// scale, origx, MtriX, and the atoms. The indices in matrix items are part of the item name in
+/// PDBML (`_atom_sites.Cartn_transf_matrix[1][2]` is saved as `Cartn_transf_matrix12`).
+#[allow(clippy::unwrap_used)]
+pub fn save_pdbml_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>) {
+    let name = escape(pdb.identifier.as_deref().unwrap_or("?"));
+    let mut categories = Vec::new();
+
+    categories.push(category(
+        "entry",
+        vec![(vec![("id", name.clone())], vec![])],
+    ));
+
+    if let Some(unit_cell) = &pdb.unit_cell {
+        let mut items = vec![
+            ("length_a", print_float(unit_cell.a())),
+            ("length_b", print_float(unit_cell.b())),
+            ("length_c", print_float(unit_cell.c())),
+            ("angle_alpha", print_float(unit_cell.alpha())),
+            ("angle_beta", print_float(unit_cell.beta())),
+            ("angle_gamma", print_float(unit_cell.gamma())),
+        ];
+        if let Some(symmetry) = &pdb.symmetry {
+            items.push(("Z_PDB", symmetry.z().to_string()));
+        }
+        categories.push(category(
+            "cell",
+            vec![(vec![("entry_id", name.clone())], items)],
+        ));
+    }
+
+    if let Some(symmetry) = &pdb.symmetry {
+        categories.push(category(
+            "symmetry",
+            vec![(
+                vec![("entry_id", name.clone())],
+                vec![
+                    ("Int_Tables_number", symmetry.index().to_string()),
+                    (
+                        "pdbx_full_space_group_name_H-M",
+                        symmetry.herman_mauguin_symbol().to_string(),
+                    ),
+                    (
+                        "space_group_name_H-M",
+                        symmetry.herman_mauguin_symbol().to_string(),
+                    ),
+                ],
+            )],
+        ));
+    }
+
+    if let Some(scale) = &pdb.scale {
+        categories.push((
+            "atom_sites",
+            vec![(
+                vec![("entry_id", name.clone())],
+                matrix_items("Cartn_transf_matrix", "Cartn_transf_vector", scale),
+            )],
+        ));
+    }
+
+    if let Some(origx) = &pdb.origx {
+        categories.push((
+            "database_PDB_matrix",
+            vec![(
+                vec![("entry_id", name.clone())],
+                matrix_items("origx", "origx_vector", origx),
+            )],
+        ));
+    }
+
+    let mtrix: Vec<_> = pdb
+        .mtrix()
+        .map(|mtrix| {
+            let mut items = vec![(
+                "code".to_string(),
+                if mtrix.contained { "given" } else { "generate" }.to_string(),
+            )];
+            items.extend(matrix_items("matrix", "vector", &mtrix.transformation));
+            (vec![("id", mtrix.serial_number.to_string())], items)
+        })
+        .collect();
+    if !mtrix.is_empty() {
+        categories.push(("struct_ncs_oper", mtrix));
+    }
+
+    let mut atoms = Vec::new();
+    for model in pdb.models() {
+        for (chain_index, chain) in model.chains().enumerate() {
+            for (residue_index, residue) in chain.residues().enumerate() {
+                for conformer in residue.conformers() {
+                    for atom in conformer.atoms() {
+                        let mut items = vec![
+                            ("B_iso_or_equiv", print_float(atom.b_factor())),
+                            ("Cartn_x", print_float(atom.x())),
+                            ("Cartn_y", print_float(atom.y())),
+                            ("Cartn_z", print_float(atom.z())),
+                            ("auth_asym_id", escape(chain.id())),
+                            ("auth_seq_id", residue.serial_number().to_string()),
+                            (
+                                "group_PDB",
+                                if atom.hetero() { "HETATM" } else { "ATOM" }.to_string(),
+                            ),
+                        ];
+                        if let Some(alt_loc) = conformer.alternative_location() {
+                            items.push(("label_alt_id", escape(alt_loc)));
+                        }
+                        items.extend([
+                            ("label_asym_id", number_to_base26(chain_index + 1)),
+                            ("label_atom_id", escape(atom.name())),
+                            ("label_comp_id", escape(conformer.name())),
+                            ("label_entity_id", (chain_index + 1).to_string()),
+                            ("label_seq_id", (residue_index + 1).to_string()),
+                            ("occupancy", print_float(atom.occupancy())),
+                            ("pdbx_PDB_model_num", model.serial_number().to_string()),
+                        ]);
+                        if let Some(insertion_code) = residue.insertion_code() {
+                            items.push(("pdbx_PDB_ins_code", escape(insertion_code)));
+                        }
+                        items.extend([
+                            ("pdbx_formal_charge", atom.charge().to_string()),
+                            (
+                                "type_symbol",
+                                atom.element().map_or("", Element::symbol).to_string(),
+                            ),
+                        ]);
+                        if let Some(matrix) = atom.anisotropic_temperature_factors() {
+                            for (r, row) in matrix.iter().enumerate() {
+                                for (c, value) in row.iter().enumerate() {
+                                    items.push((ANISOU[r * 3 + c], print_float(*value)));
+                                }
+                            }
+                        }
+                        atoms.push((vec![("id", atom.serial_number().to_string())], items));
+                    }
+                }
+            }
+        }
+    }
+    if !atoms.is_empty() {
+        categories.push(category("atom_site", atoms));
+    }
+
+    writeln!(sink, "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>").unwrap();
+    writeln!(
+        sink,
+        "<PDBx:datablock datablockName=\"{name}\"
+   xmlns:PDBx=\"http://pdbml.pdb.org/schema/pdbx-v50.xsd\"
+   xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"
+   xsi:schemaLocation=\"http://pdbml.pdb.org/schema/pdbx-v50.xsd pdbx-v50.xsd\">"
+    )
+    .unwrap();
+    for (category, rows) in categories {
+        writeln!(sink, "   <PDBx:{category}Category>").unwrap();
+        for (keys, items) in rows {
+            write!(sink, "      <PDBx:{category}").unwrap();
+            for (key, value) in keys {
+                write!(sink, " {key}=\"{value}\"").unwrap();
+            }
+            writeln!(sink, ">").unwrap();
+            for (item, value) in items {
+                if value.is_empty() {
+                    writeln!(sink, "         <PDBx:{item} xsi:nil=\"true\" />").unwrap();
+                } else {
+                    writeln!(sink, "         <PDBx:{item}>{value}</PDBx:{item}>").unwrap();
+                }
+            }
+            writeln!(sink, "      </PDBx:{category}>").unwrap();
+        }
+        writeln!(sink, "   </PDBx:{category}Category>").unwrap();
+    }
+    writeln!(sink, "</PDBx:datablock>").unwrap();
+
+    sink.flush().unwrap();
+}
+
+/// The names of the anisotropic temperature factor items
+const ANISOU: [&str; 9] = [
+    "aniso_U11",
+    "aniso_U12",
+    "aniso_U13",
+    "aniso_U21",
+    "aniso_U22",
+    "aniso_U23",
+    "aniso_U31",
+    "aniso_U32",
+    "aniso_U33",
+];
+
+/// The key items of a row, these are saved as attributes
+type Keys = Vec<(&'static str, String)>;
+
+/// A row of a category, the key items and the other items
+type Row = (Keys, Vec<(String, String)>);
+
+/// Create a category with the given rows
+fn category(
+    name: &'static str,
+    rows: Vec<(Keys, Vec<(&str, String)>)>,
+) -> (&'static str, Vec<Row>) {
+    (
+        name,
+        rows.into_iter()
+            .map(|(keys, items)| {
+                (
+                    keys,
+                    items
+                        .into_iter()
+                        .map(|(item, value)| (item.to_string(), value))
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Get the items for a transformation matrix, the matrix items are followed by the vector items
+fn matrix_items(
+    matrix_name: &str,
+    vector_name: &str,
+    transformation: &TransformationMatrix,
+) -> Vec<(String, String)> {
+    let matrix = transformation.matrix();
+    let mut items = Vec::new();
+    for (r, row) in matrix.iter().enumerate() {
+        for (c, value) in row.iter().take(3).enumerate() {
+            items.push((
+                format!("{matrix_name}{}{}", r + 1, c + 1),
+                print_float(*value),
+            ));
+        }
+    }
+    for (r, row) in matrix.iter().enumerate() {
+        items.push((format!("{vector_name}{}", r + 1), print_float(row[3])));
+    }
+    items
+}
+
+/// Escape the characters that have a special meaning in XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{Format, ReadOptions};
+    use std::io::BufReader;
+
+    #[test]
+    fn round_trip() {
+        let (pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.cif")
+            .unwrap();
+        let mut buffer = Vec::new();
+        save_pdbml_raw(&pdb, BufWriter::new(&mut buffer));
+        let (read, _) = ReadOptions::new()
+            .set_format(Format::Pdbml)
+            .set_level(StrictnessLevel::Loose)
+            .read_raw(BufReader::new(buffer.as_slice()))
+            .unwrap();
+        assert_eq!(read.identifier, pdb.identifier);
+        assert_eq!(read.unit_cell, pdb.unit_cell);
+        assert_eq!(read.symmetry, pdb.symmetry);
+        assert_eq!(read.scale, pdb.scale);
+        assert_eq!(read.origx, pdb.origx);
+        assert_eq!(read.chain_count(), pdb.chain_count());
+        assert_eq!(read.residue_count(), pdb.residue_count());
+        assert_eq!(read.atom_count(), pdb.atom_count());
+        for (a, b) in pdb.atoms_with_hierarchy().zip(read.atoms_with_hierarchy()) {
+            assert_eq!(a.atom(), b.atom());
+            assert_eq!(a.chain().id(), b.chain().id());
+            assert_eq!(a.residue().id(), b.residue().id());
+            assert_eq!(a.conformer().id(), b.conformer().id());
+        }
+    }
+
+    #[test]
+    fn escaped() {
+        let mut pdb = PDB::new();
+        pdb.identifier = Some("A&B".to_string());
+        let mut model = Model::new(1);
+        model.add_atom(
+            Atom::new(true, 1, "C'", 1.0, 2.0, 3.0, 1.0, 0.0, "C", 0).unwrap(),
+            "A",
+            (1, None),
+            ("LIG", None),
+        );
+        pdb.add_model(model);
+        let mut buffer = Vec::new();
+        save_pdbml_raw(&pdb, BufWriter::new(&mut buffer));
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("datablockName=\"A&amp;B\""));
+        assert!(text.contains("<PDBx:label_atom_id>C&apos;</PDBx:label_atom_id>"));
+        let (read, _) = ReadOptions::new()
+            .set_format(Format::Pdbml)
+            .set_level(StrictnessLevel::Loose)
+            .read_raw(BufReader::new(text.as_bytes()))
+            .unwrap();
+        assert_eq!(read.identifier.as_deref(), Some("A&B"));
+        assert_eq!(read.atom(0).unwrap().name(), "C'");
+    }
+}
@@ -0,0 +1,221 @@
+use crate::StrictnessLevel;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use crate::{validate, Context, ErrorLevel, PDBError};
+use crate::{Conformer, PDB};
+
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+
+/// Save the given PDB struct to the given file in the PQR format, validating it beforehand.
+/// The partial charge and radius of each atom are taken from [`crate::Atom::partial_charge`] and
+/// [`crate::Atom::radius`], see [`save_pqr_raw`] for the values used if these are not set.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_pqr_raw` function.
+pub fn save_pqr(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+) -> Result<(), Vec<PDBError>> {
+    save_pqr_(pdb, filename, level, BufWriter::new)
+}
+
+/// Save the given PDB struct to the given file in the PQR format, validating it beforehand,
+/// and use gzip compression.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_pqr_raw` function.
+#[cfg(feature = "compression")]
+pub fn save_pqr_gz(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    compression_level: Option<Compression>,
+) -> Result<(), Vec<PDBError>> {
+    save_pqr_(pdb, filename, level, |file| {
+        BufWriter::new(GzEncoder::new(file, compression_level.unwrap_or_default()))
+    })
+}
+
+/// Generic function to save the given PDB struct to the given file, validating it beforehand.
+fn save_pqr_<T, W>(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    writer: W,
+) -> Result<(), Vec<PDBError>>
+where
+    T: Write,
+    W: FnOnce(File) -> BufWriter<T>,
+{
+    let filename = filename.as_ref();
+    let mut errors = validate(pdb);
+    if errors.iter().any(|e| e.fails(level)) {
+        return Err(errors);
+    }
+
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(_e) => {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+                Context::show(filename)
+            ));
+            return Err(errors);
+        }
+    };
+
+    save_pqr_raw(pdb, writer(file));
+    Ok(())
+}
+
+/// Save the given PDB struct to the given BufWriter in the PQR format.
+/// It does not validate or renumber the PDB, so if that is needed, that needs to be done in preparation.
+///
+/// The fields are separated by whitespace, so chain identifiers or coordinates that do not fit the
+/// PDB columns do not run into each other. Atoms without a partial charge are saved with a
+/// charge of zero, atoms without a radius are saved with the van der Waals radius of their
+/// element (or zero if that is not known). Alternative locations are not saved, only the atoms
+/// without an alternative location and the atoms of the first alternative location of every
+/// residue are saved.
+#[allow(clippy::unwrap_used)]
+pub fn save_pqr_raw<T: Write>(pdb: &PDB, mut sink: BufWriter<T>) {
+    let multiple_models = pdb.model_count() > 1;
+    for model in pdb.models() {
+        if multiple_models {
+            writeln!(sink, "MODEL {:>8}", model.serial_number()).unwrap();
+        }
+        for chain in model.chains().filter(|c| c.atoms().next().is_some()) {
+            for residue in chain.residues() {
+                let first = residue
+                    .conformers()
+                    .find_map(Conformer::alternative_location);
+                for conformer in residue.conformers().filter(|c| {
+                    c.alternative_location().is_none() || c.alternative_location() == first
+                }) {
+                    for atom in conformer.atoms() {
+                        let name = if atom.name().len() < 4 {
+                            format!(" {}", atom.name())
+                        } else {
+                            atom.name().to_string()
+                        };
+                        let radius = atom.radius().unwrap_or_else(|| {
+                            atom.element()
+                                .and_then(|e| e.atomic_radius().van_der_waals)
+                                .unwrap_or(0.0)
+                        });
+                        writeln!(
+                            sink,
+                            "{:<6} {:>5} {:<4} {:>3} {} {:>4}{:<1}   {:>8.3} {:>8.3} {:>8.3} {:>7.4} {:>6.4}",
+                            if atom.hetero() { "HETATM" } else { "ATOM" },
+                            atom.serial_number(),
+                            name,
+                            conformer.name(),
+                            chain.id(),
+                            residue.serial_number(),
+                            residue.insertion_code().unwrap_or(""),
+                            atom.x(),
+                            atom.y(),
+                            atom.z(),
+                            atom.partial_charge().unwrap_or(0.0),
+                            radius,
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+            writeln!(sink, "TER").unwrap();
+        }
+        if multiple_models {
+            writeln!(sink, "ENDMDL").unwrap();
+        }
+    }
+    writeln!(sink, "END").unwrap();
+    sink.flush().unwrap();
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::structs::*;
+    use crate::ReadOptions;
+    use std::io::BufReader;
+
+    #[test]
+    fn round_trip() {
+        let mut model = Model::new(1);
+        let mut atom = Atom::new(false, 1, "N", -10.234, 4.123, 2.345, 1.0, 0.0, "N", 0).unwrap();
+        atom.set_partial_charge(-0.3).unwrap();
+        atom.set_radius(1.824).unwrap();
+        model.add_atom(atom, "A", (1, None), ("MET", None));
+        let mut atom = Atom::new(true, 2, "ZN", 1.0, 2.0, 3.0, 1.0, 0.0, "Zn", 0).unwrap();
+        atom.set_partial_charge(2.0).unwrap();
+        model.add_atom(atom, "B", (2, Some("A")), ("ZN", None));
+        let mut pdb = PDB::new();
+        pdb.add_model(model);
+
+        let mut buffer = Vec::new();
+        save_pqr_raw(&pdb, BufWriter::new(&mut buffer));
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.starts_with(
+            "ATOM       1  N   MET A    1     -10.234    4.123    2.345 -0.3000 1.8240\n"
+        ));
+
+        let (read, errors) = ReadOptions::new()
+            .set_format(crate::Format::Pqr)
+            .read_raw(BufReader::new(text.as_bytes()))
+            .unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(read.atom_count(), 2);
+        for (a, b) in pdb.atoms().zip(read.atoms()) {
+            assert_eq!(a.name(), b.name());
+            assert_eq!(a.element(), b.element());
+            assert_eq!(a.pos(), b.pos());
+            assert_eq!(a.partial_charge(), b.partial_charge());
+        }
+        assert_eq!(read.atom(0).unwrap().radius(), Some(1.824));
+        // The radius of zinc is not set, so the van der Waals radius is used
+        assert_eq!(
+            read.atom(1).unwrap().radius(),
+            Element::Zn.atomic_radius().van_der_waals
+        );
+        assert_eq!(read.residues().nth(1).unwrap().id(), (2, Some("A")));
+    }
+
+    #[test]
+    fn alternative_locations() {
+        let mut model = Model::new(1);
+        let atoms = [
+            ("N", None, 0.0),
+            ("CB", Some("A"), 1.0),
+            ("CB", Some("B"), 2.0),
+        ];
+        for (serial, (name, alternative_location, x)) in atoms.into_iter().enumerate() {
+            let atom = Atom::new(false, serial + 1, name, x, 0.0, 0.0, 1.0, 0.0, "C", 0).unwrap();
+            model.add_atom(atom, "A", (1, None), ("SER", alternative_location));
+        }
+        let mut pdb = PDB::new();
+        pdb.add_model(model);
+        assert_eq!(pdb.atom_count(), 3);
+
+        let mut buffer = Vec::new();
+        save_pqr_raw(&pdb, BufWriter::new(&mut buffer));
+        let text = String::from_utf8(buffer).unwrap();
+        let (read, _) = ReadOptions::new()
+            .set_format(crate::Format::Pqr)
+            .read_raw(BufReader::new(text.as_bytes()))
+            .unwrap();
+        assert_eq!(read.atom_count(), 2);
+        assert_eq!(read.atom(1).unwrap().name(), "CB");
+        assert_eq!(read.atom(1).unwrap().x(), 1.0);
+    }
+}
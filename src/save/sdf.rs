@@ -0,0 +1,319 @@
+use crate::structs::*;
+use crate::StrictnessLevel;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use crate::{validate, Context, ErrorLevel, PDBError};
+
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+
+/// Save the hetero residues of the given PDB struct to the given file in the SDF format,
+/// validating it beforehand. See [`save_sdf_raw`] for details.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_sdf_raw` function.
+pub fn save_sdf(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+) -> Result<(), Vec<PDBError>> {
+    save_sdf_(pdb, filename, level, BufWriter::new)
+}
+
+/// Save the hetero residues of the given PDB struct to the given file in the SDF format,
+/// validating it beforehand, and use gzip compression.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_sdf_raw` function.
+#[cfg(feature = "compression")]
+pub fn save_sdf_gz(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    compression_level: Option<Compression>,
+) -> Result<(), Vec<PDBError>> {
+    save_sdf_(pdb, filename, level, |file| {
+        BufWriter::new(GzEncoder::new(file, compression_level.unwrap_or_default()))
+    })
+}
+
+/// Generic function to save the given PDB struct to the given file, validating it beforehand.
+fn save_sdf_<T, W>(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    writer: W,
+) -> Result<(), Vec<PDBError>>
+where
+    T: Write,
+    W: FnOnce(File) -> BufWriter<T>,
+{
+    let filename = filename.as_ref();
+    let mut errors = validate(pdb);
+    if errors.iter().any(|e| e.fails(level)) {
+        return Err(errors);
+    }
+
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(_e) => {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+                Context::show(filename)
+            ));
+            return Err(errors);
+        }
+    };
+
+    save_sdf_raw(pdb, writer(file));
+    Ok(())
+}
+
+/// Save the hetero residues of the given PDB struct to the given BufWriter in the SDF format.
+/// It does not validate or renumber the PDB, so if that is needed, that needs to be done in preparation.
+///
+/// Every residue in the first model of which all atoms are hetero atoms, except for waters
+/// (`HOH`), is saved as a separate record. See [`save_sdf_residues_raw`] for details.
+pub fn save_sdf_raw<T: Write>(pdb: &PDB, sink: BufWriter<T>) {
+    let residues = pdb.models().take(1).flat_map(|model| {
+        model.residues().filter(|residue| {
+            residue.name() != Some("HOH")
+                && residue.atom_count() > 0
+                && residue.atoms().all(Atom::hetero)
+        })
+    });
+    save_sdf_residues_raw(pdb, residues, sink);
+}
+
+/// Save the given residues of the PDB struct to the given BufWriter in the SDF format.
+///
+/// Every residue is saved as a separate record, named after the residue, with the atoms of its
+/// first conformer and the bonds between these atoms as found in [`PDB::bonds`]. The bond order
+/// is saved for covalent, double, triple, and aromatic bonds, other bonds without a bond order
+/// (like hydrogen bonds) are not saved. The formal charges of the atoms are saved in `M  CHG`
+/// lines. Records with more than 999 atoms or bonds are saved as V3000, others as V2000.
+#[allow(clippy::unwrap_used)]
+pub fn save_sdf_residues_raw<'a, T: Write>(
+    pdb: &PDB,
+    residues: impl IntoIterator<Item = &'a Residue>,
+    mut sink: BufWriter<T>,
+) {
+    let bonds: Vec<_> = pdb.bonds().collect();
+    for residue in residues {
+        let atoms: Vec<&Atom> = residue.atoms().collect();
+        let index = |atom: &Atom| atoms.iter().position(|a| a.counter() == atom.counter());
+        let residue_bonds: Vec<(usize, usize, usize)> = bonds
+            .iter()
+            .filter_map(|(a, b, bond)| {
+                let kind = match bond.order()? {
+                    order if (order - 1.5).abs() < f64::EPSILON => 4,
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    order => order as usize,
+                };
+                Some((index(a)? + 1, index(b)? + 1, kind))
+            })
+            .collect();
+        let charges: Vec<(usize, isize)> = atoms
+            .iter()
+            .enumerate()
+            .filter(|(_, atom)| atom.charge() != 0)
+            .map(|(i, atom)| (i + 1, atom.charge()))
+            .collect();
+        let symbol = |atom: &Atom| atom.element().map_or("*", Element::symbol);
+
+        writeln!(sink, "{}", residue.name().unwrap_or("")).unwrap();
+        writeln!(sink, "  pdbtbx            3D").unwrap();
+        writeln!(sink).unwrap();
+        if atoms.len() <= 999 && residue_bonds.len() <= 999 {
+            writeln!(
+                sink,
+                "{:>3}{:>3}  0  0  0  0  0  0  0  0999 V2000",
+                atoms.len(),
+                residue_bonds.len()
+            )
+            .unwrap();
+            for atom in &atoms {
+                let charge = match atom.charge() {
+                    charge @ -3..=3 if charge != 0 => 4 - charge,
+                    _ => 0,
+                };
+                writeln!(
+                    sink,
+                    "{:>10.4}{:>10.4}{:>10.4} {:<3} 0{:>3}  0  0  0  0  0  0  0  0  0  0",
+                    atom.x(),
+                    atom.y(),
+                    atom.z(),
+                    symbol(atom),
+                    charge
+                )
+                .unwrap();
+            }
+            for (a, b, kind) in &residue_bonds {
+                writeln!(sink, "{a:>3}{b:>3}{kind:>3}  0").unwrap();
+            }
+            for chunk in charges.chunks(8) {
+                write!(sink, "M  CHG{:>3}", chunk.len()).unwrap();
+                for (index, charge) in chunk {
+                    write!(sink, " {index:>3} {charge:>3}").unwrap();
+                }
+                writeln!(sink).unwrap();
+            }
+        } else {
+            writeln!(sink, "  0  0  0     0  0            999 V3000").unwrap();
+            writeln!(sink, "M  V30 BEGIN CTAB").unwrap();
+            writeln!(
+                sink,
+                "M  V30 COUNTS {} {} 0 0 0",
+                atoms.len(),
+                residue_bonds.len()
+            )
+            .unwrap();
+            writeln!(sink, "M  V30 BEGIN ATOM").unwrap();
+            for (index, atom) in atoms.iter().enumerate() {
+                write!(
+                    sink,
+                    "M  V30 {} {} {:.4} {:.4} {:.4} 0",
+                    index + 1,
+                    symbol(atom),
+                    atom.x(),
+                    atom.y(),
+                    atom.z()
+                )
+                .unwrap();
+                if atom.charge() != 0 {
+                    write!(sink, " CHG={}", atom.charge()).unwrap();
+                }
+                writeln!(sink).unwrap();
+            }
+            writeln!(sink, "M  V30 END ATOM").unwrap();
+            writeln!(sink, "M  V30 BEGIN BOND").unwrap();
+            for (index, (a, b, kind)) in residue_bonds.iter().enumerate() {
+                writeln!(sink, "M  V30 {} {kind} {a} {b}", index + 1).unwrap();
+            }
+            writeln!(sink, "M  V30 END BOND").unwrap();
+            writeln!(sink, "M  V30 END CTAB").unwrap();
+        }
+        writeln!(sink, "M  END").unwrap();
+        writeln!(sink, "$$$$").unwrap();
+    }
+    sink.flush().unwrap();
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::ReadOptions;
+    use std::io::BufReader;
+
+    fn ligand(text: &str) -> Ligand {
+        let (mut ligands, errors) = ReadOptions::new()
+            .read_sdf_raw(BufReader::new(text.as_bytes()))
+            .unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        ligands.remove(0)
+    }
+
+    fn save(pdb: &PDB) -> String {
+        let mut buffer = Vec::new();
+        save_sdf_raw(pdb, BufWriter::new(&mut buffer));
+        String::from_utf8(buffer).unwrap()
+    }
+
+    const FORMATE: &str = "formate
+  pdbtbx            3D
+
+  3  2  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.2500    0.0000    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+   -0.6250    1.0825    0.0000 O   0  5  0  0  0  0  0  0  0  0  0  0
+  1  2  2  0
+  1  3  1  0
+M  CHG  1   3  -1
+M  END
+$$$$
+";
+
+    #[test]
+    fn round_trip() {
+        let mut pdb = PDB::new();
+        let mut model = Model::new(1);
+        model.add_atom(
+            Atom::new(false, 1, "CA", 5.0, 5.0, 5.0, 1.0, 0.0, "C", 0).unwrap(),
+            "A",
+            (1, None),
+            ("GLY", None),
+        );
+        pdb.add_model(model);
+        let mut formate = ligand(FORMATE);
+        assert!(formate
+            .residue_mut()
+            .conformer_mut(0)
+            .unwrap()
+            .set_name("FMT"));
+        pdb.add_ligand(formate, "A");
+
+        let chain = pdb.chains().next().unwrap();
+        assert_eq!(chain.residue_count(), 2);
+        let residue = chain.residue(1).unwrap();
+        assert_eq!(residue.serial_number(), 2);
+        assert_eq!(residue.name(), Some("FMT"));
+        let serials: Vec<usize> = residue.atoms().map(Atom::serial_number).collect();
+        assert_eq!(serials, [2, 3, 4]);
+        assert_eq!(pdb.bonds().count(), 2);
+
+        let text = save(&pdb);
+        assert!(text.starts_with("FMT\n"));
+        assert!(text.contains("M  CHG  1   3  -1\n"));
+        let read = ligand(&text);
+        assert_eq!(read.residue().atom_count(), 3);
+        for (a, b) in residue.atoms().zip(read.residue().atoms()) {
+            assert_eq!(a.element(), b.element());
+            assert_eq!(a.charge(), b.charge());
+            assert!(a.distance(b) < 1e-4);
+        }
+        let bonds: Vec<_> = read
+            .bonds()
+            .map(|(a, b, bond)| (a.name(), b.name(), bond))
+            .collect();
+        assert_eq!(
+            bonds,
+            [("C1", "O1", Bond::Double), ("C1", "O2", Bond::Covalent)]
+        );
+    }
+
+    #[test]
+    fn v3000() {
+        let mut conformer = Conformer::new("CL", None, None).unwrap();
+        for serial_number in 1..=1000 {
+            #[allow(clippy::cast_precision_loss)]
+            let x = serial_number as f64;
+            conformer.add_atom(
+                Atom::new(true, serial_number, "CL", x, 0.0, 0.0, 1.0, 0.0, "CL", -1).unwrap(),
+            );
+        }
+        let mut pdb = PDB::new();
+        pdb.add_ligand(
+            Ligand::new(
+                "chloride",
+                Residue::new(1, None, Some(conformer)).unwrap(),
+                &[],
+                Vec::new(),
+            ),
+            "B",
+        );
+        let text = save(&pdb);
+        assert!(text.contains("V3000"));
+        let read = ligand(&text);
+        assert_eq!(read.residue().atom_count(), 1000);
+        assert!(read.residue().atoms().all(|a| a.charge() == -1));
+        assert_eq!(read.residue().atom(999).unwrap().x(), 1000.0);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::structs::*;
+use crate::StrictnessLevel;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+
+use crate::{validate, Context, ErrorLevel, PDBError};
+
+#[cfg(feature = "compression")]
+use flate2::{write::GzEncoder, Compression};
+
+/// Save the given PDB struct to the given file in the extended XYZ format, validating it beforehand.
+/// Every model is saved as a separate frame, see [`save_xyz_raw`] for details.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_xyz_raw` function.
+pub fn save_xyz(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+) -> Result<(), Vec<PDBError>> {
+    save_xyz_(pdb, Search::Known(true), filename, level, BufWriter::new)
+}
+
+/// Save the atoms matching the given search to the given file in the extended XYZ format,
+/// validating the PDB beforehand. Every model is saved as a separate frame.
+///
+/// ```no_run
+/// use pdbtbx::*;
+/// let (pdb, _errors) = ReadOptions::new().read("example-pdbs/1ubq.pdb").unwrap();
+/// // Save the residues 40 to 45 for a QM calculation
+/// let selection = Term::ResidueSerialNumberRange(40, 45) & !Term::Element(Element::H);
+/// save_xyz_selection(&pdb, selection, "dump/qm_region.xyz", StrictnessLevel::Loose).unwrap();
+/// ```
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_xyz_selection_raw` function.
+pub fn save_xyz_selection(
+    pdb: &PDB,
+    search: impl Into<Search>,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+) -> Result<(), Vec<PDBError>> {
+    save_xyz_(pdb, search.into(), filename, level, BufWriter::new)
+}
+
+/// Save the given PDB struct to the given file in the extended XYZ format, validating it
+/// beforehand, and use gzip compression.
+///
+/// # Errors
+/// It fails if the validation fails with the given `level`.
+/// If validation gives rise to problems, use the `save_xyz_raw` function.
+#[cfg(feature = "compression")]
+pub fn save_xyz_gz(
+    pdb: &PDB,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    compression_level: Option<Compression>,
+) -> Result<(), Vec<PDBError>> {
+    save_xyz_(pdb, Search::Known(true), filename, level, |file| {
+        BufWriter::new(GzEncoder::new(file, compression_level.unwrap_or_default()))
+    })
+}
+
+/// Generic function to save the given PDB struct to the given file, validating it beforehand.
+fn save_xyz_<T, W>(
+    pdb: &PDB,
+    search: Search,
+    filename: impl AsRef<str>,
+    level: StrictnessLevel,
+    writer: W,
+) -> Result<(), Vec<PDBError>>
+where
+    T: Write,
+    W: FnOnce(File) -> BufWriter<T>,
+{
+    let filename = filename.as_ref();
+    let mut errors = validate(pdb);
+    if errors.iter().any(|e| e.fails(level)) {
+        return Err(errors);
+    }
+
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(_e) => {
+            errors.push(PDBError::new(
+                ErrorLevel::BreakingError,
+                "Could not open file",
+                "Could not open the file for writing, make sure you have permission for this file and no other program is currently using it.",
+                Context::show(filename)
+            ));
+            return Err(errors);
+        }
+    };
+
+    save_xyz_selection_raw(pdb, search, writer(file));
+    Ok(())
+}
+
+/// Save the given PDB struct to the given BufWriter in the extended XYZ format.
+/// It does not validate or renumber the PDB, so if that is needed, that needs to be done in preparation.
+///
+/// Every model is saved as a frame with the element symbol and position of every atom. The
+/// comment line contains the `Lattice` (if the PDB has a unit cell) and the `Properties` of the
+/// atom lines. The partial charges and radii are saved as `charge` and `radius` columns if all
+/// atoms in the frame have them. Atoms without an element are saved with the symbol `X`.
+/// Extended XYZ is a superset of XYZ, so these files can be read as plain XYZ files as well.
+pub fn save_xyz_raw<T: Write>(pdb: &PDB, sink: BufWriter<T>) {
+    save_xyz_selection_raw(pdb, Search::Known(true), sink);
+}
+
+/// Save the atoms matching the given search to the given BufWriter in the extended XYZ format.
+/// See [`save_xyz_raw`] for details.
+#[allow(clippy::unwrap_used)]
+pub fn save_xyz_selection_raw<T: Write>(
+    pdb: &PDB,
+    search: impl Into<Search>,
+    mut sink: BufWriter<T>,
+) {
+    let search = search.into();
+    let lattice = pdb.unit_cell.as_ref().map(|cell| {
+        let values: Vec<String> = cell
+            .vectors()
+            .iter()
+            .flatten()
+            .map(|v| format!("{v:.8}"))
+            .collect();
+        format!("Lattice=\"{}\" ", values.join(" "))
+    });
+
+    for model in pdb.models() {
+        let selection: Vec<_> = model.find(search.add_model_info(model)).collect();
+        let atoms: Vec<&Atom> = selection.iter().map(ContainsAtomConformer::atom).collect();
+        let charges = !atoms.is_empty() && atoms.iter().all(|a| a.partial_charge().is_some());
+        let radii = !atoms.is_empty() && atoms.iter().all(|a| a.radius().is_some());
+
+        writeln!(sink, "{}", atoms.len()).unwrap();
+        write!(
+            sink,
+            "{}Properties=species:S:1:pos:R:3",
+            lattice.as_deref().unwrap_or("")
+        )
+        .unwrap();
+        if charges {
+            write!(sink, ":charge:R:1").unwrap();
+        }
+        if radii {
+            write!(sink, ":radius:R:1").unwrap();
+        }
+        if lattice.is_some() {
+            write!(sink, " pbc=\"T T T\"").unwrap();
+        }
+        writeln!(sink, " model={}", model.serial_number()).unwrap();
+
+        for atom in atoms {
+            write!(
+                sink,
+                "{:<2} {:15.8} {:15.8} {:15.8}",
+                atom.element().map_or("X", Element::symbol),
+                atom.x(),
+                atom.y(),
+                atom.z()
+            )
+            .unwrap();
+            if let (true, Some(charge)) = (charges, atom.partial_charge()) {
+                write!(sink, " {charge:12.8}").unwrap();
+            }
+            if let (true, Some(radius)) = (radii, atom.radius()) {
+                write!(sink, " {radius:12.8}").unwrap();
+            }
+            writeln!(sink).unwrap();
+        }
+    }
+    sink.flush().unwrap();
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{Format, ReadOptions};
+    use std::io::BufReader;
+
+    fn pdb() -> PDB {
+        let mut model = Model::new(1);
+        for (serial_number, (name, element, residue)) in
+            [("N", "N", 1), ("CA", "C", 1), ("N", "N", 2), ("CA", "C", 2)]
+                .iter()
+                .enumerate()
+        {
+            #[allow(clippy::cast_precision_loss)]
+            let x = serial_number as f64;
+            let mut atom = Atom::new(
+                false,
+                serial_number + 1,
+                *name,
+                x,
+                1.5,
+                -2.25,
+                1.0,
+                0.0,
+                *element,
+                0,
+            )
+            .unwrap();
+            atom.set_partial_charge(0.5 - x).unwrap();
+            model.add_atom(atom, "A", (*residue, None), ("GLY", None));
+        }
+        let mut pdb = PDB::new();
+        pdb.add_model(model);
+        pdb.unit_cell = Some(UnitCell::new(30.0, 30.0, 40.0, 90.0, 90.0, 120.0));
+        pdb
+    }
+
+    fn read(text: &str) -> PDB {
+        let (pdb, errors) = ReadOptions::new()
+            .set_format(Format::Xyz)
+            .read_raw(BufReader::new(text.as_bytes()))
+            .unwrap();
+        assert!(errors.is_empty(), "{errors:?}");
+        pdb
+    }
+
+    #[test]
+    fn round_trip() {
+        let pdb = pdb();
+        let mut buffer = Vec::new();
+        save_xyz_raw(&pdb, BufWriter::new(&mut buffer));
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.starts_with("4\nLattice=\""));
+        assert!(text.contains("Properties=species:S:1:pos:R:3:charge:R:1 pbc=\"T T T\""));
+
+        let read = read(&text);
+        assert_eq!(read.atom_count(), 4);
+        for (a, b) in pdb.atoms().zip(read.atoms()) {
+            assert_eq!(a.element(), b.element());
+            assert!(a.distance(b) < 1e-8);
+            assert_eq!(a.partial_charge(), b.partial_charge());
+        }
+        let cell = read.unit_cell.unwrap();
+        assert!((cell.gamma() - 120.0).abs() < 1e-6);
+        assert!((cell.c() - 40.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn selection() {
+        let pdb = pdb();
+        let mut buffer = Vec::new();
+        save_xyz_selection_raw(
+            &pdb,
+            Term::ResidueSerialNumber(2) & Term::Element(Element::C),
+            BufWriter::new(&mut buffer),
+        );
+        let read = read(&String::from_utf8(buffer).unwrap());
+        assert_eq!(read.atom_count(), 1);
+        assert_eq!(read.atom(0).unwrap().x(), 3.0);
+    }
+}
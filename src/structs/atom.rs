@@ -37,6 +37,16 @@ pub struct Atom {
     charge: isize,
     /// The anisotropic temperature factors, if applicable
     atf: Option<[[f64; 3]; 3]>,
+    /// The partial charge of the Atom (e), if applicable
+    partial_charge: Option<f64>,
+    /// The radius of the Atom (Å), if applicable
+    radius: Option<f64>,
+    /// The velocity of the Atom (Å/ps), if applicable
+    velocity: Option<(f64, f64, f64)>,
+    /// The force field atom type of the Atom (e.g. the SYBYL type `C.ar`), if applicable
+    atom_type: Option<String>,
+    /// The segment identifier of the Atom, as used by molecular dynamics packages, if applicable
+    segment_id: Option<String>,
 }
 
 impl Atom {
@@ -93,6 +103,11 @@ impl Atom {
                 element,
                 charge,
                 atf: None,
+                partial_charge: None,
+                radius: None,
+                velocity: None,
+                atom_type: None,
+                segment_id: None,
             })
         } else {
             None
@@ -350,6 +365,128 @@ impl Atom {
         self.atf = Some(factors);
     }
 
+    /// Get the partial charge of this atom in elementary charges, if available.
+    /// This is read from and saved to PQR files.
+    pub const fn partial_charge(&self) -> Option<f64> {
+        self.partial_charge
+    }
+
+    /// Set the partial charge of this atom in elementary charges.
+    /// # Errors
+    /// It fails if the value is not finite (`f64.is_finite()`).
+    pub fn set_partial_charge(&mut self, new_partial_charge: f64) -> Result<(), String> {
+        if new_partial_charge.is_finite() {
+            self.partial_charge = Some(new_partial_charge);
+            Ok(())
+        } else {
+            Err(format!(
+                "The value of the new partial_charge is not finite for atom {} value {}",
+                self.serial_number, new_partial_charge
+            ))
+        }
+    }
+
+    /// Get the radius of this atom in Å, if available.
+    /// This is read from and saved to PQR files.
+    pub const fn radius(&self) -> Option<f64> {
+        self.radius
+    }
+
+    /// Set the radius of this atom in Å.
+    /// # Errors
+    /// It fails if the value is not finite (`f64.is_finite()`) or if it is negative.
+    pub fn set_radius(&mut self, new_radius: f64) -> Result<(), String> {
+        if !new_radius.is_finite() {
+            Err(format!(
+                "The value of the new radius is not finite for atom {} value {}",
+                self.serial_number, new_radius
+            ))
+        } else if new_radius < 0.0 {
+            Err(format!(
+                "The value of the new radius is negative for atom {} value {}",
+                self.serial_number, new_radius
+            ))
+        } else {
+            self.radius = Some(new_radius);
+            Ok(())
+        }
+    }
+
+    /// Get the velocity of this atom as a tuple of `f64` in Å/ps, in the following order: (x, y, z), if available.
+    /// This is read from and saved to GRO files.
+    pub const fn velocity(&self) -> Option<(f64, f64, f64)> {
+        self.velocity
+    }
+
+    /// Set the velocity of this atom in Å/ps.
+    /// # Errors
+    /// It fails if one or more of the numbers are not finite (`f64.is_finite()`).
+    pub fn set_velocity(&mut self, new_velocity: (f64, f64, f64)) -> Result<(), String> {
+        if new_velocity.0.is_finite() && new_velocity.1.is_finite() && new_velocity.2.is_finite() {
+            self.velocity = Some(new_velocity);
+            Ok(())
+        } else {
+            Err(format!(
+                "One (or more) of values of the new velocity is not finite for atom {} values {:?}",
+                self.serial_number, new_velocity
+            ))
+        }
+    }
+
+    /// Get the force field atom type of this atom, if available.
+    /// This is read from and saved to MOL2 files, where it is the SYBYL atom type (e.g. `C.ar` or `N.am`).
+    pub fn atom_type(&self) -> Option<&str> {
+        self.atom_type.as_deref()
+    }
+
+    /// Set the force field atom type of this atom. The atom type is case sensitive.
+    /// # Errors
+    /// It fails if the new atom type contains invalid characters.
+    pub fn set_atom_type(&mut self, new_atom_type: impl Into<String>) -> Result<(), String> {
+        let new_atom_type = new_atom_type.into();
+        if valid_identifier(&new_atom_type) {
+            self.atom_type = Some(new_atom_type.trim().to_string());
+            Ok(())
+        } else {
+            Err(format!(
+                "New atom type has invalid characters for atom {} atom type {}",
+                self.serial_number, new_atom_type
+            ))
+        }
+    }
+
+    /// Get the segment identifier of this atom, if available.
+    /// This is read from and saved to PDB files (columns 73-76), where it is used by molecular
+    /// dynamics packages like CHARMM and NAMD to group atoms. See
+    /// [`crate::ReadOptions::set_chains_from_segments`] to use the segments as chains.
+    pub fn segment_id(&self) -> Option<&str> {
+        self.segment_id.as_deref()
+    }
+
+    /// Set the segment identifier of this atom. The identifier is trimmed, an empty identifier
+    /// removes the segment identifier. In PDB files the identifier can at most contain 4
+    /// characters, longer identifiers are reported by [`crate::validate_pdb`] when saving.
+    /// # Errors
+    /// It fails if the new segment identifier contains invalid characters.
+    pub fn set_segment_id(&mut self, new_segment_id: impl Into<String>) -> Result<(), String> {
+        let new_segment_id = new_segment_id.into();
+        if valid_identifier(&new_segment_id) {
+            let trimmed = new_segment_id.trim();
+            self.segment_id = (!trimmed.is_empty()).then(|| trimmed.to_string());
+            Ok(())
+        } else {
+            Err(format!(
+                "New segment identifier has invalid characters for atom {} segment identifier {}",
+                self.serial_number, new_segment_id
+            ))
+        }
+    }
+
+    /// Remove the segment identifier of this atom.
+    pub fn remove_segment_id(&mut self) {
+        self.segment_id = None;
+    }
+
     /// Determine whether this atom is likely to be a part of the backbone of a protein.
     /// This is based on this Atom only, for a more precise definition use [`hierarchy::ContainsAtomConformer::is_backbone`].
     pub fn is_backbone(&self) -> bool {
@@ -612,6 +749,11 @@ impl Clone for Atom {
         )
         .expect("Invalid Atom properties in a clone");
         atom.atf = self.atf;
+        atom.partial_charge = self.partial_charge;
+        atom.radius = self.radius;
+        atom.velocity = self.velocity;
+        atom.atom_type = self.atom_type.clone();
+        atom.segment_id = self.segment_id.clone();
         atom
     }
 }
@@ -623,6 +765,11 @@ impl PartialEq for Atom {
             && self.element() == other.element()
             && self.charge() == other.charge()
             && self.atf == other.atf
+            && self.partial_charge == other.partial_charge
+            && self.radius == other.radius
+            && self.velocity == other.velocity
+            && self.atom_type == other.atom_type
+            && self.segment_id == other.segment_id
             && self.pos() == other.pos()
             && self.occupancy == other.occupancy
             && self.b_factor == other.b_factor
@@ -686,6 +833,40 @@ mod tests {
         a.set_name("").unwrap();
     }
 
+    #[test]
+    fn partial_charge_and_radius() {
+        let mut a = Atom::new(false, 0, "N", 0.0, 0.0, 0.0, 1.0, 0.0, "N", 0).unwrap();
+        assert_eq!(a.partial_charge(), None);
+        assert_eq!(a.radius(), None);
+        a.set_partial_charge(-0.3).unwrap();
+        a.set_radius(1.824).unwrap();
+        assert!(a.set_partial_charge(f64::NAN).is_err());
+        assert!(a.set_radius(-1.0).is_err());
+        assert!(a.set_radius(f64::INFINITY).is_err());
+        let b = a.clone();
+        assert_eq!(b.partial_charge(), Some(-0.3));
+        assert_eq!(b.radius(), Some(1.824));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn velocity() {
+        let mut a = Atom::new(false, 0, "OW", 0.0, 0.0, 0.0, 1.0, 0.0, "O", 0).unwrap();
+        assert_eq!(a.velocity(), None);
+        a.set_velocity((1.0, -2.0, 0.5)).unwrap();
+        assert!(a.set_velocity((f64::NAN, 0.0, 0.0)).is_err());
+        assert_eq!(a.clone().velocity(), Some((1.0, -2.0, 0.5)));
+    }
+
+    #[test]
+    fn atom_type() {
+        let mut a = Atom::new(false, 0, "CA", 0.0, 0.0, 0.0, 1.0, 0.0, "C", 0).unwrap();
+        assert_eq!(a.atom_type(), None);
+        a.set_atom_type("C.ar").unwrap();
+        assert!(a.set_atom_type("C\u{1F9EA}").is_err());
+        assert_eq!(a.clone().atom_type(), Some("C.ar"));
+    }
+
     #[test]
     fn distance() {
         let a = Atom::new(false, 0, "", 1.0, 0.0, 0.0, 0.0, 0.0, "C", 0).unwrap();
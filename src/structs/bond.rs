@@ -1,12 +1,19 @@
 /// Bond types between two atoms
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
 pub enum Bond {
-    /// A covalent bond
+    /// A covalent bond, a single bond if the bond order is known
     Covalent,
+    /// A covalent double bond
+    Double,
+    /// A covalent triple bond
+    Triple,
+    /// An aromatic bond
+    Aromatic,
     /// A disulfide bond S-S
     Disulfide,
-    /// A hydrogen bond H-H
+    /// A hydrogen bond, from the donor to the acceptor
     Hydrogen,
     /// ?
     MetalCoordination,
@@ -23,3 +30,25 @@ pub enum Bond {
     /// ?
     CovalentModificationNucleotidePhosphate,
 }
+
+impl Bond {
+    /// Get the bond order of this bond, 1.5 for aromatic bonds. Returns `None` for bonds that are
+    /// not covalent, like hydrogen bonds.
+    pub const fn order(&self) -> Option<f64> {
+        match self {
+            Self::Covalent
+            | Self::Disulfide
+            | Self::CovalentModificationResidue
+            | Self::CovalentModificationNucleotideBase
+            | Self::CovalentModificationNucleotideSugar
+            | Self::CovalentModificationNucleotidePhosphate => Some(1.0),
+            Self::Double => Some(2.0),
+            Self::Triple => Some(3.0),
+            Self::Aromatic => Some(1.5),
+            Self::Hydrogen
+            | Self::MetalCoordination
+            | Self::MisMatchedBasePairs
+            | Self::SaltBridge => None,
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use crate::structs::*;
+use std::collections::HashSet;
+
+/// The score of two identical residue names in the sequence alignment
+const IDENTICAL: f64 = 2.0;
+/// The score of two different residue names in the sequence alignment
+const MISMATCH: f64 = -1.0;
+/// The score of opening a gap (including the first position) in the sequence alignment
+const GAP_OPEN: f64 = -4.0;
+/// The score of extending a gap in the sequence alignment
+const GAP_EXTEND: f64 = -0.5;
+/// The minimal fraction of identical residues in the aligned positions to pair two chains
+const MIN_IDENTITY: f64 = 0.3;
+
+/// The correspondence between the atoms of two structures, for example an apo and holo
+/// structure, to compare them or to superpose them with [`Superposition`]. The chains are
+/// matched by a global alignment of their residue names, so differences in numbering and missing
+/// residues are handled. Within matched residues the atoms are paired by name (using the first
+/// atom with a given name if there are multiple conformers). Waters are ignored.
+#[derive(Debug, Clone)]
+pub struct Correspondence<'a> {
+    /// The pairs of atoms, first from the first structure then from the second structure
+    pairs: Vec<(AtomConformerResidueChain<'a>, AtomConformerResidueChain<'a>)>,
+    /// The pairs of aligned residues, first from the first structure then from the second structure
+    residue_pairs: Vec<(&'a Residue, &'a Residue)>,
+    /// The statistics for every pair of matched chains
+    chains: Vec<ChainCorrespondence>,
+    /// The total number of (non water) residues in the first and second structure
+    residues: (usize, usize),
+    /// The total number of atoms in (non water) residues in the first and second structure
+    atoms: (usize, usize),
+}
+
+/// The statistics of a pair of matched chains in a [`Correspondence`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainCorrespondence {
+    /// The id of the chain in the first structure
+    first: String,
+    /// The id of the chain in the second structure
+    second: String,
+    /// The number of aligned residues
+    aligned: usize,
+    /// The number of aligned residues with identical names
+    identical: usize,
+    /// The number of atom pairs
+    atoms: usize,
+}
+
+impl ChainCorrespondence {
+    /// Get the id of the chain in the first structure
+    #[must_use]
+    pub fn first(&self) -> &str {
+        &self.first
+    }
+
+    /// Get the id of the chain in the second structure
+    #[must_use]
+    pub fn second(&self) -> &str {
+        &self.second
+    }
+
+    /// Get the number of aligned residues
+    #[must_use]
+    pub const fn aligned_residues(&self) -> usize {
+        self.aligned
+    }
+
+    /// Get the number of aligned residues with identical names
+    #[must_use]
+    pub const fn identical_residues(&self) -> usize {
+        self.identical
+    }
+
+    /// Get the fraction of the aligned residues with identical names
+    #[must_use]
+    pub fn sequence_identity(&self) -> f64 {
+        fraction(self.identical, self.aligned)
+    }
+
+    /// Get the number of atom pairs in these chains
+    #[must_use]
+    pub const fn atom_pairs(&self) -> usize {
+        self.atoms
+    }
+}
+
+impl<'a> Correspondence<'a> {
+    /// Find the correspondence between two models. Every chain of the first model is aligned
+    /// to every chain of the second model, and the chains are paired greedily by the number of
+    /// identical residues in the alignment (preferring chains with the same id on ties). Only
+    /// chains with at least 30% identical residues in the aligned positions are paired.
+    #[must_use]
+    pub fn new(first: &'a Model, second: &'a Model) -> Self {
+        let mut candidates = Vec::new();
+        for (a, first_chain) in first.chains().enumerate() {
+            for (b, second_chain) in second.chains().enumerate() {
+                let alignment = align_chains(first_chain, second_chain);
+                let identical = identical_residues(&alignment);
+                if identical > 0 && fraction(identical, alignment.len()) >= MIN_IDENTITY {
+                    candidates.push((
+                        identical,
+                        first_chain.id() == second_chain.id(),
+                        a,
+                        b,
+                        alignment,
+                    ));
+                }
+            }
+        }
+        candidates.sort_by(|x, y| y.0.cmp(&x.0).then(y.1.cmp(&x.1)));
+
+        let mut correspondence = Self::empty(first.chains(), second.chains());
+        let (mut used_first, mut used_second) = (HashSet::new(), HashSet::new());
+        for (_, _, a, b, alignment) in candidates {
+            if !used_first.contains(&a) && !used_second.contains(&b) {
+                used_first.insert(a);
+                used_second.insert(b);
+                correspondence.add(first.chains().nth(a), second.chains().nth(b), &alignment);
+            }
+        }
+        correspondence
+    }
+
+    /// Find the correspondence between two chains, which are always paired
+    #[must_use]
+    pub fn from_chains(first: &'a Chain, second: &'a Chain) -> Self {
+        let mut correspondence = Self::empty(std::iter::once(first), std::iter::once(second));
+        correspondence.add(Some(first), Some(second), &align_chains(first, second));
+        correspondence
+    }
+
+    /// Create a correspondence without pairs, counting the residues and atoms of the chains
+    fn empty<'b>(
+        first: impl Iterator<Item = &'b Chain>,
+        second: impl Iterator<Item = &'b Chain>,
+    ) -> Self {
+        let count = |chains: Vec<&Chain>| {
+            let residues = chains.iter().flat_map(|c| polymer(c));
+            residues.fold((0, 0), |(r, a), residue| (r + 1, a + residue.atom_count()))
+        };
+        let (first, second) = (count(first.collect()), count(second.collect()));
+        Correspondence {
+            pairs: Vec::new(),
+            residue_pairs: Vec::new(),
+            chains: Vec::new(),
+            residues: (first.0, second.0),
+            atoms: (first.1, second.1),
+        }
+    }
+
+    /// Pair the atoms of the aligned residues of the given chains
+    fn add(
+        &mut self,
+        first: Option<&'a Chain>,
+        second: Option<&'a Chain>,
+        alignment: &[(&'a Residue, &'a Residue)],
+    ) {
+        let (first, second) = match (first, second) {
+            (Some(first), Some(second)) => (first, second),
+            _ => return,
+        };
+        let start = self.pairs.len();
+        self.residue_pairs.extend(alignment);
+        for (a, b) in alignment {
+            let mut names = HashSet::new();
+            for atom in a.atoms_with_hierarchy() {
+                let name = atom.atom().name().to_string();
+                if names.contains(&name) {
+                    continue;
+                }
+                let other = b.atoms_with_hierarchy().find(|h| h.atom().name() == name);
+                names.insert(name);
+                if let Some(other) = other {
+                    self.pairs
+                        .push((atom.extend(a).extend(first), other.extend(b).extend(second)));
+                }
+            }
+        }
+        self.chains.push(ChainCorrespondence {
+            first: first.id().to_string(),
+            second: second.id().to_string(),
+            aligned: alignment.len(),
+            identical: identical_residues(alignment),
+            atoms: self.pairs.len() - start,
+        });
+    }
+
+    /// Get the pairs of atoms with their hierarchy, first from the first structure then from
+    /// the second structure
+    #[must_use]
+    pub fn pairs(&self) -> &[(AtomConformerResidueChain<'a>, AtomConformerResidueChain<'a>)] {
+        &self.pairs
+    }
+
+    /// Get the pairs of aligned residues, first from the first structure then from the second
+    /// structure. These can be used to score the similarity of the structures with
+    /// [`TmScore`], [`GdtScore`], and [`Lddt`].
+    #[must_use]
+    pub fn residue_pairs(&self) -> &[(&'a Residue, &'a Residue)] {
+        &self.residue_pairs
+    }
+
+    /// Get the pairs of atoms, first from the first structure then from the second structure.
+    /// These can be used directly to superpose the first structure onto the second with
+    /// [`Superposition::new`].
+    pub fn atom_pairs(&self) -> impl DoubleEndedIterator<Item = (&Atom, &Atom)> + '_ {
+        self.pairs.iter().map(|(a, b)| (a.atom(), b.atom()))
+    }
+
+    /// Get the statistics for every pair of matched chains
+    #[must_use]
+    pub fn chains(&self) -> &[ChainCorrespondence] {
+        &self.chains
+    }
+
+    /// Get the fraction of the (non water) residues of the first and second structure that
+    /// are aligned to a residue in the other structure
+    #[must_use]
+    pub fn residue_coverage(&self) -> (f64, f64) {
+        let aligned = self.residue_pairs.len();
+        (
+            fraction(aligned, self.residues.0),
+            fraction(aligned, self.residues.1),
+        )
+    }
+
+    /// Get the fraction of the atoms in (non water) residues of the first and second structure
+    /// that are paired with an atom in the other structure
+    #[must_use]
+    pub fn atom_coverage(&self) -> (f64, f64) {
+        (
+            fraction(self.pairs.len(), self.atoms.0),
+            fraction(self.pairs.len(), self.atoms.1),
+        )
+    }
+}
+
+/// Get the non water residues of a chain
+fn polymer(chain: &Chain) -> impl Iterator<Item = &Residue> + '_ {
+    chain.residues().filter(|r| r.name() != Some("HOH"))
+}
+
+/// Align the residues of two chains by their names
+fn align_chains<'a>(first: &'a Chain, second: &'a Chain) -> Vec<(&'a Residue, &'a Residue)> {
+    let first: Vec<_> = polymer(first).collect();
+    let second: Vec<_> = polymer(second).collect();
+    align(
+        first.len(),
+        second.len(),
+        |a, b| {
+            if first[a].name() == second[b].name() {
+                IDENTICAL
+            } else {
+                MISMATCH
+            }
+        },
+        GAP_OPEN,
+        GAP_EXTEND,
+    )
+    .into_iter()
+    .map(|(a, b)| (first[a], second[b]))
+    .collect()
+}
+
+/// Count the aligned residues with identical names
+fn identical_residues(alignment: &[(&Residue, &Residue)]) -> usize {
+    alignment
+        .iter()
+        .filter(|(a, b)| a.name() == b.name())
+        .count()
+}
+
+/// Get the fraction of part in total, zero if the total is zero
+#[allow(clippy::cast_precision_loss)]
+fn fraction(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+/// Globally align two sequences with affine gap penalties (Gotoh), without penalties for gaps
+/// at the start and end. Returns the aligned index pairs in order.
+/// ## Arguments
+/// * `first` - the length of the first sequence
+/// * `second` - the length of the second sequence
+/// * `score` - the score of aligning the given indices of the first and second sequence
+/// * `gap_open` - the score of opening a gap, including the first position of the gap
+/// * `gap_extend` - the score of every further position of a gap
+pub(crate) fn align(
+    first: usize,
+    second: usize,
+    score: impl Fn(usize, usize) -> f64,
+    gap_open: f64,
+    gap_extend: f64,
+) -> Vec<(usize, usize)> {
+    // The three states: aligned, gap in the second sequence, gap in the first sequence
+    const ALIGNED: usize = 0;
+    const GAP_SECOND: usize = 1;
+    const GAP_FIRST: usize = 2;
+    let width = second + 1;
+    let mut scores = vec![[f64::NEG_INFINITY; 3]; (first + 1) * width];
+    let mut trace = vec![[ALIGNED; 3]; (first + 1) * width];
+    scores[0][ALIGNED] = 0.0;
+    for i in 1..=first {
+        scores[i * width][GAP_SECOND] = 0.0;
+    }
+    for cell in &mut scores[1..=second] {
+        cell[GAP_FIRST] = 0.0;
+    }
+    let best = |options: [f64; 3]| {
+        (1..3).fold(ALIGNED, |b, s| if options[s] > options[b] { s } else { b })
+    };
+    for i in 1..=first {
+        for j in 1..=second {
+            let here = i * width + j;
+            let diagonal = scores[here - width - 1];
+            let state = best(diagonal);
+            scores[here][ALIGNED] = diagonal[state] + score(i - 1, j - 1);
+            trace[here][ALIGNED] = state;
+
+            let up = scores[here - width];
+            let options = [up[0] + gap_open, up[1] + gap_extend, up[2] + gap_open];
+            let state = best(options);
+            scores[here][GAP_SECOND] = options[state];
+            trace[here][GAP_SECOND] = state;
+
+            let left = scores[here - 1];
+            let options = [left[0] + gap_open, left[1] + gap_open, left[2] + gap_extend];
+            let state = best(options);
+            scores[here][GAP_FIRST] = options[state];
+            trace[here][GAP_FIRST] = state;
+        }
+    }
+
+    // Gaps at the end are free, so start the trace back at the best cell of the last row or
+    // column
+    let ends = (0..=second)
+        .map(|j| (first, j))
+        .chain((0..first).map(|i| (i, second)));
+    let mut position = (first, second, ALIGNED);
+    let mut max = f64::NEG_INFINITY;
+    for (i, j) in ends {
+        let cell = scores[i * width + j];
+        let state = best(cell);
+        if cell[state] > max {
+            max = cell[state];
+            position = (i, j, state);
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j, mut state) = position;
+    while i > 0 && j > 0 {
+        let previous = trace[i * width + j][state];
+        match state {
+            ALIGNED => {
+                pairs.push((i - 1, j - 1));
+                i -= 1;
+                j -= 1;
+            }
+            GAP_SECOND => i -= 1,
+            _ => j -= 1,
+        }
+        state = previous;
+    }
+    pairs.reverse();
+    pairs
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn alignment() {
+        let first = b"ACDEFGHIKLMNPQ";
+        let second = b"CDEFKLMNPQRS";
+        let pairs = align(
+            first.len(),
+            second.len(),
+            |a, b| if first[a] == second[b] { 2.0 } else { -1.0 },
+            -4.0,
+            -0.5,
+        );
+        assert_eq!(pairs.len(), 10);
+        assert!(pairs.iter().all(|(a, b)| first[*a] == second[*b]));
+        assert!(align(0, 5, |_, _| 1.0, -1.0, -1.0).is_empty());
+    }
+
+    #[test]
+    fn apo_holo() {
+        let (pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let mut other = pdb.clone();
+        {
+            let chain = other.chains_mut().next().unwrap();
+            chain.remove_residues_by(|r| (20..25).contains(&r.serial_number()));
+            for residue in chain.residues_mut() {
+                residue.set_serial_number(residue.serial_number() + 100);
+            }
+            chain.set_id("B");
+            let residue = chain.residues_mut().nth(20).unwrap();
+            residue.conformers_mut().next().unwrap().set_name("ALA");
+        }
+        let (first, second) = (pdb.model(0).unwrap(), other.model(0).unwrap());
+        let correspondence = Correspondence::new(first, second);
+        assert_eq!(correspondence.chains().len(), 1);
+        let chain = &correspondence.chains()[0];
+        assert_eq!((chain.first(), chain.second()), ("A", "B"));
+        assert_eq!(chain.aligned_residues(), 71);
+        assert_eq!(chain.identical_residues(), 70);
+        assert_eq!(correspondence.residue_pairs().len(), 71);
+        for (a, b) in correspondence.pairs() {
+            assert_eq!(a.atom().name(), b.atom().name());
+            if a.residue().name() == b.residue().name() {
+                assert_eq!(
+                    a.residue().serial_number() + 100,
+                    b.residue().serial_number()
+                );
+                assert_eq!(a.atom().pos(), b.atom().pos());
+            }
+        }
+        let (first_coverage, second_coverage) = correspondence.residue_coverage();
+        assert!((first_coverage - 71.0 / 76.0).abs() < 1e-9);
+        assert!((second_coverage - 1.0).abs() < 1e-9);
+        let (first_coverage, second_coverage) = correspondence.atom_coverage();
+        assert!(first_coverage < 1.0);
+        assert!((second_coverage - 1.0).abs() < 1e-9);
+
+        let superposition = Superposition::new(correspondence.atom_pairs(), false).unwrap();
+        assert!(superposition.rmsd() < 1e-6);
+        assert_eq!(superposition.pairs(), correspondence.pairs().len());
+
+        let chains = Correspondence::from_chains(first.chain(0).unwrap(), second.chain(0).unwrap());
+        assert_eq!(chains.pairs().len(), correspondence.pairs().len());
+    }
+}
@@ -0,0 +1,933 @@
+use crate::structs::*;
+use doc_cfg::doc_cfg;
+
+/// The radii (in Å) of the shells sampled for a Q-score, 0.0 to 2.0 in steps of 0.1
+const Q_SCORE_RADII: usize = 21;
+/// The spacing between the Q-score shells in Å
+const Q_SCORE_STEP: f64 = 0.1;
+/// The width of the reference Gaussian for a Q-score in Å
+const Q_SCORE_SIGMA: f64 = 0.6;
+/// The number of directions sampled on every Q-score shell
+const Q_SCORE_DIRECTIONS: usize = 24;
+
+/// A density map on a regular grid in a unit cell, as read from a CCP4/MRC file. The grid divides
+/// the unit cell into `sampling` intervals along the three axes, and the map covers the block of
+/// `size` grid points starting at grid index `start`. The grid point with index `(i, j, k)` lies
+/// at `origin + i / sampling.0 * a + j / sampling.1 * b + k / sampling.2 * c` with `a`, `b`, and
+/// `c` the vectors of the unit cell (see [`UnitCell::vectors`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityMap {
+    /// The number of grid points along the x, y, and z axes
+    size: [usize; 3],
+    /// The index of the first grid point along the x, y, and z axes
+    start: [isize; 3],
+    /// The number of intervals the unit cell is divided in along the x, y, and z axes
+    sampling: [usize; 3],
+    /// The unit cell
+    unit_cell: UnitCell,
+    /// The position of grid index (0, 0, 0) in Å
+    origin: [f64; 3],
+    /// The values, with the x index changing fastest and the z index slowest
+    data: Vec<f32>,
+    /// The matrix to convert a position relative to the origin to fractional coordinates
+    fractional: [[f64; 3]; 3],
+}
+
+impl DensityMap {
+    /// Create a new density map. The data should contain `size.0 * size.1 * size.2` values with
+    /// the x index changing fastest and the z index slowest.
+    ///
+    /// It returns `None` if the length of the data does not match the size, if the sampling is
+    /// zero along any axis, or if the unit cell has no volume.
+    #[must_use]
+    pub fn new(
+        size: [usize; 3],
+        start: [isize; 3],
+        sampling: [usize; 3],
+        unit_cell: UnitCell,
+        origin: [f64; 3],
+        data: Vec<f32>,
+    ) -> Option<Self> {
+        let count = size
+            .iter()
+            .try_fold(1_usize, |acc, value| acc.checked_mul(*value))?;
+        if count != data.len() || sampling.contains(&0) {
+            return None;
+        }
+        let fractional = unit_cell.fractionalization_matrix()?;
+        Some(DensityMap {
+            size,
+            start,
+            sampling,
+            unit_cell,
+            origin,
+            data,
+            fractional,
+        })
+    }
+
+    /// Calculate the electron density of the atoms of the first model of the given PDB on a grid
+    /// dividing the given unit cell in `sampling` intervals along the three axes. The map covers
+    /// the whole unit cell, so the density of atoms outside of the cell is wrapped into the cell.
+    /// See [`DensityMap::calculate_in_box`] for details on the calculation.
+    ///
+    /// It returns `None` if the sampling is zero along any axis or if the unit cell has no volume.
+    #[must_use]
+    pub fn calculate(pdb: &PDB, unit_cell: UnitCell, sampling: [usize; 3]) -> Option<Self> {
+        let count = sampling
+            .iter()
+            .try_fold(1_usize, |acc, value| acc.checked_mul(*value))?;
+        let mut map = Self::new(
+            sampling,
+            [0; 3],
+            sampling,
+            unit_cell,
+            [0.0; 3],
+            vec![0.0; count],
+        )?;
+        map.add_atoms(pdb);
+        Some(map)
+    }
+
+    /// Calculate the electron density of the atoms of the first model of the given PDB on an
+    /// orthogonal grid with the given spacing (in Å) covering the bounding box of the atoms,
+    /// extended by the given margin (in Å) on all sides.
+    ///
+    /// Every atom contributes the Fourier transform of the scattering factor of its element (see
+    /// [`Element::scattering_factor`]), blurred by its anisotropic temperature factors or, if
+    /// these are not given, its B-factor, and weighted by its occupancy. B-factors below 1 Å² are
+    /// raised to 1 Å² to keep the density finite. Atoms without an element are skipped.
+    ///
+    /// It returns `None` if the first model has no atoms or if the spacing is not positive.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn calculate_in_box(pdb: &PDB, spacing: f64, margin: f64) -> Option<Self> {
+        if spacing <= 0.0 || !spacing.is_finite() || !margin.is_finite() {
+            return None;
+        }
+        let mut min = [f64::MAX; 3];
+        let mut max = [f64::MIN; 3];
+        for atom in pdb.model(0)?.atoms() {
+            let position = atom.pos();
+            for (axis, value) in [position.0, position.1, position.2].iter().enumerate() {
+                min[axis] = min[axis].min(*value);
+                max[axis] = max[axis].max(*value);
+            }
+        }
+        if min[0] > max[0] {
+            return None;
+        }
+        let sampling = [0, 1, 2].map(|axis| {
+            ((2.0f64.mul_add(margin, max[axis] - min[axis]) / spacing).ceil() as usize).max(1)
+        });
+        #[allow(clippy::cast_precision_loss)]
+        let unit_cell = UnitCell::new(
+            sampling[0] as f64 * spacing,
+            sampling[1] as f64 * spacing,
+            sampling[2] as f64 * spacing,
+            90.0,
+            90.0,
+            90.0,
+        );
+        // One more grid point than intervals so the box is closed and does not wrap around
+        let size = [
+            sampling[0].checked_add(1)?,
+            sampling[1].checked_add(1)?,
+            sampling[2].checked_add(1)?,
+        ];
+        let count = size
+            .iter()
+            .try_fold(1_usize, |acc, value| acc.checked_mul(*value))?;
+        let mut map = Self::new(
+            size,
+            [0; 3],
+            sampling,
+            unit_cell,
+            min.map(|value| value - margin),
+            vec![0.0; count],
+        )?;
+        map.add_atoms(pdb);
+        Some(map)
+    }
+
+    /// Add the density of the atoms of the first model of the given PDB to this map
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn add_atoms(&mut self, pdb: &PDB) {
+        let vectors = self.unit_cell.vectors();
+        let normalisation = (2.0 * std::f64::consts::PI).powf(1.5);
+        let atoms = pdb.model(0).into_iter().flat_map(Model::atoms);
+        for atom in atoms {
+            let element = match atom.element() {
+                Some(element) => element,
+                None => continue,
+            };
+            let factor = element.scattering_factor();
+            let displacement = displacement(atom);
+            // Every Gaussian (and the constant) becomes a Gaussian in real space with covariance
+            // U + b / (8 pi^2), stored as (weight, inverse covariance)
+            let mut widest: f64 = 0.0;
+            let terms: Vec<(f64, [[f64; 3]; 3])> = factor
+                .a
+                .iter()
+                .zip(factor.b)
+                .chain(std::iter::once((&factor.c, 0.0)))
+                .filter_map(|(a, b)| {
+                    let mut covariance = displacement;
+                    for (axis, row) in covariance.iter_mut().enumerate() {
+                        row[axis] += b / (8.0 * std::f64::consts::PI.powi(2));
+                    }
+                    let (inverse, determinant) = invert_matrix(covariance)?;
+                    widest = widest.max(covariance[0][0] + covariance[1][1] + covariance[2][2]);
+                    Some((
+                        a * atom.occupancy() / (normalisation * determinant.sqrt()),
+                        inverse,
+                    ))
+                })
+                .collect();
+            let cutoff = 3.0 * widest.sqrt();
+            let center = self.grid_index(atom.pos());
+            let mut low = [0; 3];
+            let mut high = [0; 3];
+            for axis in 0..3 {
+                let norm = self.fractional[axis]
+                    .iter()
+                    .map(|v| v * v)
+                    .sum::<f64>()
+                    .sqrt();
+                let extent = cutoff * norm * self.sampling[axis] as f64;
+                low[axis] = (center[axis] - extent).floor() as isize;
+                high[axis] = (center[axis] + extent).ceil() as isize;
+            }
+            let position = atom.pos();
+            for k in low[2]..=high[2] {
+                for j in low[1]..=high[1] {
+                    for i in low[0]..=high[0] {
+                        let offset = match self.offset([i, j, k]) {
+                            Some(offset) => offset,
+                            None => continue,
+                        };
+                        let mut delta = [
+                            self.origin[0] - position.0,
+                            self.origin[1] - position.1,
+                            self.origin[2] - position.2,
+                        ];
+                        for (axis, (index, vector)) in [i, j, k].iter().zip(vectors).enumerate() {
+                            let fraction = *index as f64 / self.sampling[axis] as f64;
+                            for (value, component) in delta.iter_mut().zip(vector) {
+                                *value += fraction * component;
+                            }
+                        }
+                        let value: f64 = terms
+                            .iter()
+                            .map(|(weight, inverse)| {
+                                let mut exponent = 0.0;
+                                for (row, a) in inverse.iter().zip(delta) {
+                                    for (m, b) in row.iter().zip(delta) {
+                                        exponent += a * m * b;
+                                    }
+                                }
+                                weight * (-0.5 * exponent).exp()
+                            })
+                            .sum();
+                        self.data[offset] += value as f32;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get the number of grid points along the x, y, and z axes
+    #[must_use]
+    pub const fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    /// Get the index of the first grid point along the x, y, and z axes
+    #[must_use]
+    pub const fn start(&self) -> [isize; 3] {
+        self.start
+    }
+
+    /// Get the number of intervals the unit cell is divided in along the x, y, and z axes
+    #[must_use]
+    pub const fn sampling(&self) -> [usize; 3] {
+        self.sampling
+    }
+
+    /// Get the unit cell
+    #[must_use]
+    pub const fn unit_cell(&self) -> &UnitCell {
+        &self.unit_cell
+    }
+
+    /// Get the position of grid index (0, 0, 0) in Å
+    #[must_use]
+    pub const fn origin(&self) -> [f64; 3] {
+        self.origin
+    }
+
+    /// Set the position of grid index (0, 0, 0) in Å
+    pub fn set_origin(&mut self, origin: [f64; 3]) {
+        self.origin = origin;
+    }
+
+    /// Get the values, with the x index changing fastest and the z index slowest
+    #[must_use]
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+
+    /// Get the size of a voxel along the a, b, and c axes in Å
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn voxel_size(&self) -> [f64; 3] {
+        [
+            self.unit_cell.a() / self.sampling[0] as f64,
+            self.unit_cell.b() / self.sampling[1] as f64,
+            self.unit_cell.c() / self.sampling[2] as f64,
+        ]
+    }
+
+    /// Get the mean of all values in the map, or 0.0 if the map is empty
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self) -> f64 {
+        if self.data.is_empty() {
+            return 0.0;
+        }
+        self.data.iter().map(|v| f64::from(*v)).sum::<f64>() / self.data.len() as f64
+    }
+
+    /// Get the root mean square deviation of the values from the mean, or 0.0 if the map is empty
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn rms(&self) -> f64 {
+        if self.data.is_empty() {
+            return 0.0;
+        }
+        let mean = self.mean();
+        (self
+            .data
+            .iter()
+            .map(|v| (f64::from(*v) - mean).powi(2))
+            .sum::<f64>()
+            / self.data.len() as f64)
+            .sqrt()
+    }
+
+    /// Get the lowest value in the map, or 0.0 if the map is empty
+    #[must_use]
+    pub fn min(&self) -> f64 {
+        self.data
+            .iter()
+            .copied()
+            .reduce(f32::min)
+            .map_or(0.0, f64::from)
+    }
+
+    /// Get the highest value in the map, or 0.0 if the map is empty
+    #[must_use]
+    pub fn max(&self) -> f64 {
+        self.data
+            .iter()
+            .copied()
+            .reduce(f32::max)
+            .map_or(0.0, f64::from)
+    }
+
+    /// Get the value at the given grid index. Along an axis where the map covers the whole unit
+    /// cell the index wraps around, along the other axes it returns `None` outside of the map.
+    #[must_use]
+    pub fn value(&self, index: [isize; 3]) -> Option<f32> {
+        self.offset(index).map(|offset| self.data[offset])
+    }
+
+    /// Get the position in the data of the given grid index, see [`DensityMap::value`]
+    fn offset(&self, index: [isize; 3]) -> Option<usize> {
+        let mut offset = 0;
+        let mut stride = 1;
+        for (axis, index) in index.iter().enumerate() {
+            let size = isize::try_from(self.size[axis]).ok()?;
+            let mut local = index - self.start[axis];
+            if self.size[axis] == self.sampling[axis] {
+                local = local.rem_euclid(size);
+            }
+            if !(0..size).contains(&local) {
+                return None;
+            }
+            offset += usize::try_from(local).ok()? * stride;
+            stride *= self.size[axis];
+        }
+        Some(offset)
+    }
+
+    /// Get the position in Å of the given (possibly fractional) grid index
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn grid_position(&self, index: [f64; 3]) -> (f64, f64, f64) {
+        let vectors = self.unit_cell.vectors();
+        let mut position = self.origin;
+        for (axis, vector) in vectors.iter().enumerate() {
+            let fraction = index[axis] / self.sampling[axis] as f64;
+            for (value, component) in position.iter_mut().zip(vector) {
+                *value += fraction * component;
+            }
+        }
+        (position[0], position[1], position[2])
+    }
+
+    /// Get the (fractional) grid index of the given position in Å
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn grid_index(&self, position: (f64, f64, f64)) -> [f64; 3] {
+        let relative = [
+            position.0 - self.origin[0],
+            position.1 - self.origin[1],
+            position.2 - self.origin[2],
+        ];
+        let mut index = [0.0; 3];
+        for (axis, row) in self.fractional.iter().enumerate() {
+            let fraction: f64 = row.iter().zip(relative).map(|(m, v)| m * v).sum();
+            index[axis] = fraction * self.sampling[axis] as f64;
+        }
+        index
+    }
+
+    /// Get the value at the given position in Å, interpolated trilinearly from the eight
+    /// surrounding grid points. It returns `None` if any of these points lies outside of the map.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn interpolate(&self, position: (f64, f64, f64)) -> Option<f64> {
+        let index = self.grid_index(position);
+        if index.iter().any(|value| !value.is_finite()) {
+            return None;
+        }
+        let floor = index.map(f64::floor);
+        let base = floor.map(|value| value as isize);
+        let t = [
+            index[0] - floor[0],
+            index[1] - floor[1],
+            index[2] - floor[2],
+        ];
+        let mut result = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut weight = 1.0;
+            let mut point = base;
+            for axis in 0..3 {
+                if offset[axis] == 1 {
+                    weight *= t[axis];
+                    point[axis] += 1;
+                } else {
+                    weight *= 1.0 - t[axis];
+                }
+            }
+            result += weight * f64::from(self.value(point)?);
+        }
+        Some(result)
+    }
+
+    /// Get the value of the map at the position of the given atom, see [`DensityMap::interpolate`]
+    #[must_use]
+    pub fn atom_value(&self, atom: &Atom) -> Option<f64> {
+        self.interpolate(atom.pos())
+    }
+
+    /// Get the map value of every atom in the given PDB, `None` for atoms outside of the map
+    pub fn atom_values<'a>(
+        &self,
+        pdb: &'a PDB,
+    ) -> Vec<(hierarchy::AtomConformerResidueChainModel<'a>, Option<f64>)> {
+        pdb.atoms_with_hierarchy()
+            .map(|hierarchy| {
+                let value = self.atom_value(hierarchy.atom());
+                (hierarchy, value)
+            })
+            .collect()
+    }
+
+    /// Summarise the map values of the atoms of every residue in the given PDB. The summary is
+    /// `None` if none of the atoms of the residue lie in the map.
+    pub fn residue_values<'a>(&self, pdb: &'a PDB) -> Vec<ResidueMapValues<'a>> {
+        residues(pdb)
+            .map(|(model, chain, residue)| {
+                let values = residue.atoms().filter_map(|atom| self.atom_value(atom));
+                ResidueMapValues {
+                    model,
+                    chain,
+                    residue,
+                    summary: MapSummary::from_values(values),
+                }
+            })
+            .collect()
+    }
+
+    /// Get a Q-score-like measure of the resolvability of the given atom (Pintilie et al., Nature
+    /// Methods 2020). The map is sampled on shells around the atom from 0.0 to 2.0 Å, skipping
+    /// points that are closer to any other atom in the given tree, and the sampled values are
+    /// correlated (Pearson, about the mean) with a Gaussian with a width of 0.6 Å. A score of 1.0
+    /// means the density around the atom has exactly this Gaussian shape.
+    ///
+    /// It returns `None` if the atom lies outside of the map or if too few points could be
+    /// sampled to calculate a correlation.
+    #[doc_cfg(feature = "rstar")]
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn q_score(&self, atom: &Atom, environment: &rstar::RTree<&Atom>) -> Option<f64> {
+        let center = atom.pos();
+        let mut sampled = vec![(self.interpolate(center)?, 1.0)];
+        for shell in 1..Q_SCORE_RADII {
+            let radius = shell as f64 * Q_SCORE_STEP;
+            let reference = (-radius * radius / (2.0 * Q_SCORE_SIGMA * Q_SCORE_SIGMA)).exp();
+            for direction in sphere_directions() {
+                let point = (
+                    radius.mul_add(direction[0], center.0),
+                    radius.mul_add(direction[1], center.1),
+                    radius.mul_add(direction[2], center.2),
+                );
+                let closest = environment
+                    .nearest_neighbor(&point)
+                    .map_or(f64::MAX, |other| {
+                        rstar::PointDistance::distance_2(other, &point)
+                    });
+                if closest < radius.mul_add(radius, -1e-6) {
+                    continue;
+                }
+                if let Some(value) = self.interpolate(point) {
+                    sampled.push((value, reference));
+                }
+            }
+        }
+        correlation(&sampled)
+    }
+
+    /// Get the Q-score of every atom in the given PDB, see [`DensityMap::q_score`]. The
+    /// environment of an atom consists of all atoms in the same model.
+    #[doc_cfg(feature = "rstar")]
+    pub fn atom_q_scores<'a>(
+        &self,
+        pdb: &'a PDB,
+    ) -> Vec<(hierarchy::AtomConformerResidueChainModel<'a>, Option<f64>)> {
+        let mut output = Vec::with_capacity(pdb.total_atom_count());
+        for model in pdb.models() {
+            let environment = rstar::RTree::bulk_load(model.atoms().collect());
+            output.extend(model.atoms_with_hierarchy().map(|hierarchy| {
+                let score = self.q_score(hierarchy.atom(), &environment);
+                (hierarchy.extend(model), score)
+            }));
+        }
+        output
+    }
+
+    /// Summarise the Q-scores of the atoms of every residue in the given PDB, see
+    /// [`DensityMap::q_score`]. The summary is `None` if no atom of the residue has a Q-score.
+    #[doc_cfg(feature = "rstar")]
+    pub fn residue_q_scores<'a>(&self, pdb: &'a PDB) -> Vec<ResidueMapValues<'a>> {
+        let mut output = Vec::with_capacity(pdb.total_residue_count());
+        for model in pdb.models() {
+            let environment = rstar::RTree::bulk_load(model.atoms().collect());
+            output.extend(model.chains().flat_map(|chain| {
+                let environment = &environment;
+                chain.residues().map(move |residue| {
+                    let scores = residue
+                        .atoms()
+                        .filter_map(|atom| self.q_score(atom, environment));
+                    ResidueMapValues {
+                        model,
+                        chain,
+                        residue,
+                        summary: MapSummary::from_values(scores),
+                    }
+                })
+            }));
+        }
+        output
+    }
+}
+
+/// The mean, minimum, and maximum of a set of values sampled from a [`DensityMap`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapSummary {
+    /// The number of values
+    count: usize,
+    /// The mean value
+    mean: f64,
+    /// The lowest value
+    min: f64,
+    /// The highest value
+    max: f64,
+}
+
+impl MapSummary {
+    /// Summarise the given values, returns `None` if there are no values
+    #[allow(clippy::cast_precision_loss)]
+    fn from_values(values: impl Iterator<Item = f64>) -> Option<Self> {
+        let mut summary: Option<Self> = None;
+        let mut sum = 0.0;
+        for value in values {
+            sum += value;
+            summary = Some(summary.map_or(
+                MapSummary {
+                    count: 1,
+                    mean: value,
+                    min: value,
+                    max: value,
+                },
+                |s| MapSummary {
+                    count: s.count + 1,
+                    mean: 0.0,
+                    min: s.min.min(value),
+                    max: s.max.max(value),
+                },
+            ));
+        }
+        summary.map(|s| MapSummary {
+            mean: sum / s.count as f64,
+            ..s
+        })
+    }
+
+    /// Get the number of values
+    #[must_use]
+    pub const fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Get the mean value
+    #[must_use]
+    pub const fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Get the lowest value
+    #[must_use]
+    pub const fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// Get the highest value
+    #[must_use]
+    pub const fn max(&self) -> f64 {
+        self.max
+    }
+}
+
+/// A summary of the map values (or Q-scores) of the atoms of a single residue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResidueMapValues<'a> {
+    /// The model containing the residue
+    model: &'a Model,
+    /// The chain containing the residue
+    chain: &'a Chain,
+    /// The residue
+    residue: &'a Residue,
+    /// The summary of the values of the atoms
+    summary: Option<MapSummary>,
+}
+
+impl<'a> ResidueMapValues<'a> {
+    /// Get the model containing the residue
+    #[must_use]
+    pub const fn model(&self) -> &'a Model {
+        self.model
+    }
+
+    /// Get the chain containing the residue
+    #[must_use]
+    pub const fn chain(&self) -> &'a Chain {
+        self.chain
+    }
+
+    /// Get the residue
+    #[must_use]
+    pub const fn residue(&self) -> &'a Residue {
+        self.residue
+    }
+
+    /// Get the summary of the values of the atoms, `None` if no atom has a value
+    #[must_use]
+    pub const fn summary(&self) -> Option<MapSummary> {
+        self.summary
+    }
+}
+
+/// Iterate over all residues in the PDB with their model and chain
+fn residues(pdb: &PDB) -> impl Iterator<Item = (&Model, &Chain, &Residue)> + '_ {
+    pdb.models().flat_map(|model| {
+        model
+            .chains()
+            .flat_map(move |chain| chain.residues().map(move |residue| (model, chain, residue)))
+    })
+}
+
+/// Get the anisotropic displacement matrix U (in Å²) of the given atom. If the atom has no
+/// (valid) anisotropic temperature factors the isotropic matrix of the B-factor is used, with
+/// the B-factor raised to at least 1 Å².
+fn displacement(atom: &Atom) -> [[f64; 3]; 3] {
+    if let Some(factors) = atom.anisotropic_temperature_factors() {
+        if invert_matrix(factors).map_or(false, |(_, determinant)| determinant > 0.0) {
+            return factors;
+        }
+    }
+    let u = atom.b_factor().max(1.0) / (8.0 * std::f64::consts::PI.powi(2));
+    [[u, 0.0, 0.0], [0.0, u, 0.0], [0.0, 0.0, u]]
+}
+
+/// Get evenly spread directions on the unit sphere (a Fibonacci lattice)
+#[cfg(feature = "rstar")]
+#[allow(clippy::cast_precision_loss)]
+fn sphere_directions() -> impl Iterator<Item = [f64; 3]> {
+    let golden_angle = std::f64::consts::PI * (3.0 - 5.0_f64.sqrt());
+    (0..Q_SCORE_DIRECTIONS).map(move |i| {
+        let z = 1.0 - (2.0 * i as f64 + 1.0) / Q_SCORE_DIRECTIONS as f64;
+        let radius = z.mul_add(-z, 1.0).sqrt();
+        let angle = golden_angle * i as f64;
+        [radius * angle.cos(), radius * angle.sin(), z]
+    })
+}
+
+/// Get the Pearson correlation of the given pairs, `None` if either has no variance (beyond
+/// rounding errors relative to the magnitude of the values)
+#[cfg(feature = "rstar")]
+#[allow(clippy::cast_precision_loss)]
+fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 2 {
+        return None;
+    }
+    let count = pairs.len() as f64;
+    let mean_a = pairs.iter().map(|p| p.0).sum::<f64>() / count;
+    let mean_b = pairs.iter().map(|p| p.1).sum::<f64>() / count;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    let (mut square_a, mut square_b) = (0.0, 0.0);
+    for (a, b) in pairs {
+        covariance += (a - mean_a) * (b - mean_b);
+        variance_a += (a - mean_a).powi(2);
+        variance_b += (b - mean_b).powi(2);
+        square_a += a * a;
+        square_b += b * b;
+    }
+    let tolerance = 1e-12;
+    (variance_a > tolerance * square_a && variance_b > tolerance * square_b)
+        .then(|| covariance / (variance_a * variance_b).sqrt())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// A map of a 10 Å cubic cell sampled every 0.5 Å with the given function of the position
+    fn map(function: impl Fn(f64, f64, f64) -> f64) -> DensityMap {
+        let mut data = Vec::new();
+        for z in 0..20 {
+            for y in 0..20 {
+                for x in 0..20 {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let value =
+                        function(f64::from(x) * 0.5, f64::from(y) * 0.5, f64::from(z) * 0.5) as f32;
+                    data.push(value);
+                }
+            }
+        }
+        DensityMap::new(
+            [20, 20, 20],
+            [0, 0, 0],
+            [20, 20, 20],
+            UnitCell::new(10.0, 10.0, 10.0, 90.0, 90.0, 90.0),
+            [0.0; 3],
+            data,
+        )
+        .unwrap()
+    }
+
+    fn pdb(positions: &[(f64, f64, f64)]) -> PDB {
+        let mut model = Model::new(1);
+        for (i, (x, y, z)) in positions.iter().enumerate() {
+            let atom = Atom::new(false, i + 1, "C", *x, *y, *z, 1.0, 20.0, "C", 0).unwrap();
+            model.add_atom(atom, "A", (i as isize + 1, None), ("GLY", None));
+        }
+        let mut pdb = PDB::new();
+        pdb.add_model(model);
+        pdb
+    }
+
+    #[test]
+    fn new() {
+        let cell = UnitCell::new(10.0, 10.0, 10.0, 90.0, 90.0, 90.0);
+        assert!(DensityMap::new(
+            [2, 2, 2],
+            [0; 3],
+            [2; 3],
+            cell.clone(),
+            [0.0; 3],
+            vec![0.0; 7]
+        )
+        .is_none());
+        assert!(
+            DensityMap::new([2, 2, 2], [0; 3], [2, 0, 2], cell, [0.0; 3], vec![0.0; 8]).is_none()
+        );
+        let flat = UnitCell::new(10.0, 0.0, 10.0, 90.0, 90.0, 90.0);
+        assert!(DensityMap::new([2, 2, 2], [0; 3], [2; 3], flat, [0.0; 3], vec![0.0; 8]).is_none());
+    }
+
+    #[test]
+    fn statistics() {
+        let map = map(|x, _, _| if x < 5.0 { 1.0 } else { -1.0 });
+        assert!(map.mean().abs() < 1e-12);
+        assert!((map.rms() - 1.0).abs() < 1e-12);
+        assert_eq!(map.min(), -1.0);
+        assert_eq!(map.max(), 1.0);
+        assert_eq!(map.voxel_size(), [0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn interpolation() {
+        let map = map(|x, y, z| x + 2.0 * y - z);
+        let value = map.interpolate((1.3, 2.7, 4.1)).unwrap();
+        assert!((value - (1.3 + 5.4 - 4.1)).abs() < 1e-5);
+        assert_eq!(map.value([1, 0, 0]), Some(0.5));
+        // The map covers the whole cell so indices wrap around
+        assert_eq!(map.value([21, 0, 0]), Some(0.5));
+        assert_eq!(map.value([-19, 0, 0]), Some(0.5));
+        let position = map.grid_position([3.0, 4.0, 5.0]);
+        assert!((position.0 - 1.5).abs() < 1e-9);
+        assert!((position.1 - 2.0).abs() < 1e-9);
+        assert!((position.2 - 2.5).abs() < 1e-9);
+        let index = map.grid_index(position);
+        for (value, expected) in index.iter().zip([3.0, 4.0, 5.0]) {
+            assert!((value - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn partial_map() {
+        let cell = UnitCell::new(20.0, 20.0, 20.0, 90.0, 90.0, 90.0);
+        let map = DensityMap::new(
+            [2, 2, 2],
+            [4, 4, 4],
+            [20; 3],
+            cell,
+            [1.0, 0.0, 0.0],
+            vec![1.0; 8],
+        )
+        .unwrap();
+        assert_eq!(map.value([4, 5, 5]), Some(1.0));
+        assert_eq!(map.value([3, 4, 4]), None);
+        assert_eq!(map.value([24, 4, 4]), None);
+        assert!((map.interpolate((5.5, 4.5, 4.5)).unwrap() - 1.0).abs() < 1e-12);
+        assert_eq!(map.interpolate((4.5, 4.5, 4.5)), None);
+    }
+
+    #[test]
+    fn oblique_cell() {
+        let cell = UnitCell::new(10.0, 12.0, 14.0, 80.0, 95.0, 110.0);
+        let map = DensityMap::new(
+            [2, 2, 2],
+            [0; 3],
+            [10, 12, 14],
+            cell,
+            [0.0; 3],
+            vec![0.0; 8],
+        )
+        .unwrap();
+        let index = map.grid_index(map.grid_position([1.5, -2.0, 7.25]));
+        for (value, expected) in index.iter().zip([1.5, -2.0, 7.25]) {
+            assert!((value - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn residue_values() {
+        let map = map(|x, _, _| x);
+        let pdb = pdb(&[(1.0, 1.0, 1.0), (2.0, 1.0, 1.0)]);
+        let atoms = map.atom_values(&pdb);
+        assert_eq!(atoms.len(), 2);
+        assert!((atoms[1].1.unwrap() - 2.0).abs() < 1e-6);
+        let residues = map.residue_values(&pdb);
+        assert_eq!(residues.len(), 2);
+        assert_eq!(residues[0].residue().serial_number(), 1);
+        let summary = residues[1].summary().unwrap();
+        assert_eq!(summary.count(), 1);
+        assert!((summary.mean() - 2.0).abs() < 1e-6);
+    }
+
+    /// Integrate the map, in electrons
+    fn integral(map: &DensityMap) -> f64 {
+        let [a, b, c] = map.voxel_size();
+        map.data().iter().map(|v| f64::from(*v)).sum::<f64>() * a * b * c
+    }
+
+    #[test]
+    fn calculate() {
+        let mut pdb = pdb(&[(5.0, 5.0, 5.0)]);
+        let cell = UnitCell::new(10.0, 10.0, 10.0, 90.0, 90.0, 90.0);
+        let map = DensityMap::calculate(&pdb, cell.clone(), [40, 40, 40]).unwrap();
+        assert!((integral(&map) - 6.0).abs() < 0.01);
+        let peak = map.interpolate((5.0, 5.0, 5.0)).unwrap();
+        assert!((peak - map.max()).abs() < 1e-3);
+        assert!(map.interpolate((6.0, 5.0, 5.0)).unwrap() < peak / 2.0);
+
+        // Atoms outside of the cell are wrapped into the cell
+        pdb.atoms_mut()
+            .next()
+            .unwrap()
+            .set_pos((15.0, -5.0, 25.0))
+            .unwrap();
+        let wrapped = DensityMap::calculate(&pdb, cell, [40, 40, 40]).unwrap();
+        assert!(
+            (wrapped.value([20, 20, 20]).unwrap() - map.value([20, 20, 20]).unwrap()).abs() < 1e-4
+        );
+
+        // Anisotropic temperature factors elongate the density
+        pdb.atoms_mut()
+            .next()
+            .unwrap()
+            .set_anisotropic_temperature_factors([
+                [0.1, 0.0, 0.0],
+                [0.0, 0.1, 0.0],
+                [0.0, 0.0, 0.5],
+            ]);
+        let anisotropic = DensityMap::calculate_in_box(&pdb, 0.25, 4.0).unwrap();
+        let center = (15.0, -5.0, 25.0);
+        let along_x = anisotropic.interpolate((15.8, -5.0, 25.0)).unwrap();
+        let along_z = anisotropic.interpolate((15.0, -5.0, 25.8)).unwrap();
+        assert!(along_z > 2.0 * along_x);
+        assert!(anisotropic.interpolate(center).unwrap() > along_z);
+    }
+
+    #[test]
+    fn calculate_in_box() {
+        let pdb = pdb(&[(1.0, 2.0, 3.0), (2.0, 4.5, 3.5)]);
+        let map = DensityMap::calculate_in_box(&pdb, 0.4, 5.0).unwrap();
+        assert_eq!(map.sampling(), [28, 32, 27]);
+        assert_eq!(map.size(), [29, 33, 28]);
+        assert_eq!(map.origin(), [-4.0, -3.0, -2.0]);
+        assert!((integral(&map) - 12.0).abs() < 0.05);
+        assert_eq!(map.value([29, 0, 0]), None);
+        assert!(DensityMap::calculate_in_box(&PDB::new(), 0.4, 5.0).is_none());
+        assert!(DensityMap::calculate_in_box(&pdb, 0.0, 5.0).is_none());
+        assert!(DensityMap::calculate_in_box(&pdb, 1e-300, 5.0).is_none());
+    }
+
+    #[cfg(feature = "rstar")]
+    #[test]
+    fn q_scores() {
+        let gaussian = |center: (f64, f64, f64)| {
+            move |x: f64, y: f64, z: f64| {
+                let distance =
+                    (x - center.0).powi(2) + (y - center.1).powi(2) + (z - center.2).powi(2);
+                (-distance / (2.0 * 0.6 * 0.6)).exp()
+            }
+        };
+        let sharp = map(gaussian((5.0, 5.0, 5.0)));
+        let pdb = pdb(&[(5.0, 5.0, 5.0), (8.0, 5.0, 5.0)]);
+        let scores = sharp.atom_q_scores(&pdb);
+        assert!(scores[0].1.unwrap() > 0.95);
+        assert!(scores[1].1.unwrap() < 0.5);
+        let residues = sharp.residue_q_scores(&pdb);
+        assert!(residues[0].summary().unwrap().mean() > 0.95);
+
+        let flat = map(|_, _, _| 1.0);
+        assert_eq!(flat.atom_q_scores(&pdb)[0].1, None);
+    }
+}
@@ -0,0 +1,640 @@
+use crate::structs::*;
+
+/// The maximal length in Å of a peptide bond, longer bonds are chain breaks
+const MAX_PEPTIDE_BOND: f64 = 2.5;
+/// The maximal distance in Å between the CA atoms of residues to consider hydrogen bonds
+const MAX_CA_DISTANCE: f64 = 9.0;
+/// The minimal distance in Å between atoms for the hydrogen bond energy
+const MIN_DISTANCE: f64 = 0.5;
+/// The minimal hydrogen bond energy in kcal/mol
+const MIN_ENERGY: f64 = -9.9;
+/// The maximal energy in kcal/mol of a hydrogen bond
+const MAX_ENERGY: f64 = -0.5;
+/// The electrostatic constant (q1 * q2 * f) of the DSSP hydrogen bond energy in kcal/mol Å
+const COUPLING: f64 = -27.888;
+/// The minimal angle in degrees between CA atoms to call a bend
+const MIN_BEND_ANGLE: f64 = 70.0;
+/// The phi and psi angles in degrees of a polyproline II helix
+const POLYPROLINE: (f64, f64) = (-75.0, 145.0);
+/// The allowed deviation in degrees from the phi and psi angles of a polyproline II helix
+const POLYPROLINE_EPSILON: f64 = 29.0;
+/// The minimal length of a polyproline II helix
+const POLYPROLINE_LENGTH: usize = 3;
+
+/// The secondary structure of a residue as assigned by DSSP, see Kabsch, W., & Sander, C.
+/// (1983). Dictionary of protein secondary structure: pattern recognition of hydrogen-bonded
+/// and geometrical features. Biopolymers, 22(12), 2577–2637.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecondaryStructure {
+    /// An α helix (`H`)
+    AlphaHelix,
+    /// An isolated β bridge (`B`)
+    BetaBridge,
+    /// An extended strand, part of a β ladder (`E`)
+    Strand,
+    /// A 3<sub>10</sub> helix (`G`)
+    ThreeTenHelix,
+    /// A π helix (`I`)
+    PiHelix,
+    /// A polyproline II helix (`P`)
+    PolyprolineHelix,
+    /// A hydrogen bonded turn (`T`)
+    Turn,
+    /// A bend (`S`)
+    Bend,
+    /// No secondary structure, loop or irregular (` `)
+    Coil,
+}
+
+impl SecondaryStructure {
+    /// Get the one letter code as used by DSSP, which is a space for coil
+    pub const fn code(&self) -> char {
+        match self {
+            Self::AlphaHelix => 'H',
+            Self::BetaBridge => 'B',
+            Self::Strand => 'E',
+            Self::ThreeTenHelix => 'G',
+            Self::PiHelix => 'I',
+            Self::PolyprolineHelix => 'P',
+            Self::Turn => 'T',
+            Self::Bend => 'S',
+            Self::Coil => ' ',
+        }
+    }
+
+    /// Get the secondary structure for the given one letter code, a space, `-`, `C`, and `L`
+    /// are read as coil
+    pub const fn from_code(code: char) -> Option<Self> {
+        match code {
+            'H' => Some(Self::AlphaHelix),
+            'B' => Some(Self::BetaBridge),
+            'E' => Some(Self::Strand),
+            'G' => Some(Self::ThreeTenHelix),
+            'I' => Some(Self::PiHelix),
+            'P' => Some(Self::PolyprolineHelix),
+            'T' => Some(Self::Turn),
+            'S' => Some(Self::Bend),
+            ' ' | '-' | 'C' | 'L' => Some(Self::Coil),
+            _ => None,
+        }
+    }
+
+    /// Returns if this is a helix (α, 3<sub>10</sub>, π or polyproline)
+    pub const fn is_helix(&self) -> bool {
+        matches!(
+            self,
+            Self::AlphaHelix | Self::ThreeTenHelix | Self::PiHelix | Self::PolyprolineHelix
+        )
+    }
+
+    /// Returns if this is part of a β sheet (strand or isolated bridge)
+    pub const fn is_sheet(&self) -> bool {
+        matches!(self, Self::Strand | Self::BetaBridge)
+    }
+}
+
+impl std::fmt::Display for SecondaryStructure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// A position as an array for easier vector calculations
+type Vector = [f64; 3];
+
+/// The backbone of a residue used in DSSP
+struct Backbone {
+    /// The index of the residue in the model
+    index: usize,
+    /// The index of the continuous segment (without chain breaks) this residue is part of
+    segment: usize,
+    /// If this is a proline, which can not donate a hydrogen bond
+    proline: bool,
+    /// The N atom
+    n: Vector,
+    /// The CA atom
+    ca: Vector,
+    /// The C atom
+    c: Vector,
+    /// The O atom
+    o: Vector,
+    /// The (calculated) H atom on the N atom
+    h: Vector,
+}
+
+/// The position of a residue in a helix of a certain stride, or in a turn
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HelixFlag {
+    /// Not in a turn
+    None,
+    /// The first residue of a turn
+    Start,
+    /// A residue within a turn
+    Middle,
+    /// The last residue of a turn
+    End,
+    /// The last residue of a turn and the first of the next turn
+    StartAndEnd,
+}
+
+/// The kind of a β bridge
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BridgeKind {
+    /// The strands run in the same direction
+    Parallel,
+    /// The strands run in opposite directions
+    Antiparallel,
+}
+
+/// A β ladder, a set of consecutive bridges of the same kind
+struct Ladder {
+    /// The kind of the bridges
+    kind: BridgeKind,
+    /// The residues on the first strand, in increasing order
+    i: Vec<usize>,
+    /// The residues on the second strand, in increasing order
+    j: Vec<usize>,
+}
+
+/// Assign the secondary structure of all residues in the model with DSSP, in the order of
+/// [`Model::residues`]. Only residues with N, CA, C, and O atoms are taken into account, the
+/// other residues are assigned coil. This follows the implementation of DSSP version 4, so π
+/// helices take priority over α helices and polyproline II helices are assigned to the residues
+/// that are not part of any other structure, including bends.
+pub(crate) fn assign(model: &Model) -> Vec<SecondaryStructure> {
+    let backbone = backbones(model);
+    let n = backbone.len();
+    let mut result = vec![SecondaryStructure::Coil; model.residue_count()];
+    if n == 0 {
+        return result;
+    }
+    let acceptors = hydrogen_bonds(&backbone);
+    // NH of the donor is bonded to the C=O of the acceptor
+    let bond = |donor: usize, acceptor: usize| {
+        acceptors[donor]
+            .iter()
+            .any(|(partner, energy)| *partner == Some(acceptor) && *energy < MAX_ENERGY)
+    };
+    let unbroken = |a: usize, b: usize| backbone[a].segment == backbone[b].segment;
+    let mut structure = vec![SecondaryStructure::Coil; n];
+
+    // β bridges, ladders, and sheets
+    let mut ladders: Vec<Ladder> = Vec::new();
+    for i in 1..n.saturating_sub(4) {
+        for j in i + 3..n - 1 {
+            if !unbroken(i - 1, i + 1) || !unbroken(j - 1, j + 1) {
+                continue;
+            }
+            let kind = if (bond(i + 1, j) && bond(j, i - 1)) || (bond(j + 1, i) && bond(i, j - 1)) {
+                BridgeKind::Parallel
+            } else if (bond(i + 1, j - 1) && bond(j + 1, i - 1)) || (bond(j, i) && bond(i, j)) {
+                BridgeKind::Antiparallel
+            } else {
+                continue;
+            };
+            let existing = ladders.iter_mut().find(|l| {
+                l.kind == kind
+                    && l.i.last() == Some(&(i - 1))
+                    && match kind {
+                        BridgeKind::Parallel => l.j.last() == Some(&(j - 1)),
+                        BridgeKind::Antiparallel => l.j.first() == Some(&(j + 1)),
+                    }
+            });
+            if let Some(ladder) = existing {
+                ladder.i.push(i);
+                match kind {
+                    BridgeKind::Parallel => ladder.j.push(j),
+                    BridgeKind::Antiparallel => ladder.j.insert(0, j),
+                }
+            } else {
+                ladders.push(Ladder {
+                    kind,
+                    i: vec![i],
+                    j: vec![j],
+                });
+            }
+        }
+    }
+    ladders.sort_by_key(|l| l.i[0]);
+    // Join ladders separated by a β bulge
+    let mut a = 0;
+    while a < ladders.len() {
+        let mut b = a + 1;
+        while b < ladders.len() {
+            let (first, second) = (&ladders[a], &ladders[b]);
+            let (ibi, iei, jbi, jei) = (first.i[0], last(&first.i), first.j[0], last(&first.j));
+            let (ibj, iej, jbj, jej) = (second.i[0], last(&second.i), second.j[0], last(&second.j));
+            if first.kind != second.kind
+                || !unbroken(ibi.min(ibj), iei.max(iej))
+                || !unbroken(jbi.min(jbj), jei.max(jej))
+                || ibj.wrapping_sub(iei) >= 6
+                || (iei >= ibj && ibi <= iej)
+            {
+                b += 1;
+                continue;
+            }
+            let bulge = match first.kind {
+                BridgeKind::Parallel => {
+                    (jbj.wrapping_sub(jei) < 6 && ibj.wrapping_sub(iei) < 3)
+                        || jbj.wrapping_sub(jei) < 3
+                }
+                BridgeKind::Antiparallel => {
+                    (jbi.wrapping_sub(jej) < 6 && ibj.wrapping_sub(iei) < 3)
+                        || jbi.wrapping_sub(jej) < 3
+                }
+            };
+            if bulge {
+                let second = ladders.remove(b);
+                let first = &mut ladders[a];
+                first.i.extend(second.i);
+                match first.kind {
+                    BridgeKind::Parallel => first.j.extend(second.j),
+                    BridgeKind::Antiparallel => {
+                        first.j.splice(0..0, second.j);
+                    }
+                }
+            } else {
+                b += 1;
+            }
+        }
+        a += 1;
+    }
+    for ladder in &ladders {
+        let kind = if ladder.i.len() > 1 {
+            SecondaryStructure::Strand
+        } else {
+            SecondaryStructure::BetaBridge
+        };
+        for strand in [&ladder.i, &ladder.j] {
+            let (start, end) = (strand.iter().min(), strand.iter().max());
+            if let (Some(start), Some(end)) = (start, end) {
+                for residue in &mut structure[*start..=*end] {
+                    if *residue != SecondaryStructure::Strand {
+                        *residue = kind;
+                    }
+                }
+            }
+        }
+    }
+
+    // Turns and helices, for the strides 3, 4, and 5
+    let mut flags = vec![[HelixFlag::None; 3]; n];
+    for stride in 3..=5 {
+        let s = stride - 3;
+        for i in 0..n.saturating_sub(stride) {
+            if unbroken(i, i + stride) && bond(i + stride, i) {
+                flags[i + stride][s] = HelixFlag::End;
+                for flag in &mut flags[i + 1..i + stride] {
+                    if flag[s] == HelixFlag::None {
+                        flag[s] = HelixFlag::Middle;
+                    }
+                }
+                flags[i][s] = if flags[i][s] == HelixFlag::End {
+                    HelixFlag::StartAndEnd
+                } else {
+                    HelixFlag::Start
+                };
+            }
+        }
+    }
+    let start = |i: usize, stride: usize| {
+        matches!(
+            flags[i][stride - 3],
+            HelixFlag::Start | HelixFlag::StartAndEnd
+        )
+    };
+    for i in 1..n.saturating_sub(4) {
+        if start(i, 4) && start(i - 1, 4) {
+            for residue in &mut structure[i..i + 4] {
+                *residue = SecondaryStructure::AlphaHelix;
+            }
+        }
+    }
+    for i in 1..n.saturating_sub(3) {
+        if start(i, 3)
+            && start(i - 1, 3)
+            && structure[i..i + 3].iter().all(|s| {
+                matches!(
+                    s,
+                    SecondaryStructure::Coil | SecondaryStructure::ThreeTenHelix
+                )
+            })
+        {
+            for residue in &mut structure[i..i + 3] {
+                *residue = SecondaryStructure::ThreeTenHelix;
+            }
+        }
+    }
+    for i in 1..n.saturating_sub(5) {
+        if start(i, 5)
+            && start(i - 1, 5)
+            && structure[i..i + 5].iter().all(|s| {
+                matches!(
+                    s,
+                    SecondaryStructure::Coil
+                        | SecondaryStructure::PiHelix
+                        | SecondaryStructure::AlphaHelix
+                )
+            })
+        {
+            for residue in &mut structure[i..i + 5] {
+                *residue = SecondaryStructure::PiHelix;
+            }
+        }
+    }
+    for (i, residue) in structure
+        .iter_mut()
+        .enumerate()
+        .take(n.saturating_sub(1))
+        .skip(1)
+    {
+        if *residue == SecondaryStructure::Coil
+            && (3..=5).any(|stride| (1..stride).any(|k| i >= k && start(i - k, stride)))
+        {
+            *residue = SecondaryStructure::Turn;
+        }
+    }
+
+    // Bends
+    for i in 2..n.saturating_sub(2) {
+        if structure[i] == SecondaryStructure::Coil && unbroken(i - 2, i + 2) {
+            let before = sub(backbone[i].ca, backbone[i - 2].ca);
+            let after = sub(backbone[i + 2].ca, backbone[i].ca);
+            let cosine = dot(before, after) / (norm(before) * norm(after));
+            if cosine.clamp(-1.0, 1.0).acos().to_degrees() > MIN_BEND_ANGLE {
+                structure[i] = SecondaryStructure::Bend;
+            }
+        }
+    }
+
+    // Polyproline II helices
+    let in_range = |angle: Option<f64>, centre: f64| {
+        angle.map_or(false, |a| (a - centre).abs() <= POLYPROLINE_EPSILON)
+    };
+    let polyproline: Vec<bool> = (0..n)
+        .map(|i| {
+            let phi = (i > 0 && unbroken(i - 1, i)).then(|| {
+                dihedral(
+                    backbone[i - 1].c,
+                    backbone[i].n,
+                    backbone[i].ca,
+                    backbone[i].c,
+                )
+            });
+            let psi = (i + 1 < n && unbroken(i, i + 1)).then(|| {
+                dihedral(
+                    backbone[i].n,
+                    backbone[i].ca,
+                    backbone[i].c,
+                    backbone[i + 1].n,
+                )
+            });
+            in_range(phi, POLYPROLINE.0) && in_range(psi, POLYPROLINE.1)
+        })
+        .collect();
+    for i in 0..n.saturating_sub(POLYPROLINE_LENGTH - 1) {
+        let end = i + POLYPROLINE_LENGTH - 1;
+        if unbroken(i, end) && polyproline[i..=end].iter().all(|p| *p) {
+            for residue in &mut structure[i..=end] {
+                if *residue == SecondaryStructure::Coil {
+                    *residue = SecondaryStructure::PolyprolineHelix;
+                }
+            }
+        }
+    }
+
+    for (residue, assigned) in backbone.iter().zip(structure) {
+        result[residue.index] = assigned;
+    }
+    result
+}
+
+/// Get the backbone of all residues with N, CA, C, and O atoms, with the chain breaks
+fn backbones(model: &Model) -> Vec<Backbone> {
+    let mut backbone: Vec<Backbone> = Vec::new();
+    let mut index = 0;
+    let mut segment = 0;
+    for chain in model.chains() {
+        let chain_start = index;
+        for residue in chain.residues() {
+            let find = |name: &str| {
+                residue
+                    .atoms()
+                    .find(|a| a.name() == name)
+                    .map(|a| [a.x(), a.y(), a.z()])
+            };
+            if let (Some(n), Some(ca), Some(c), Some(o)) =
+                (find("N"), find("CA"), find("C"), find("O"))
+            {
+                let previous = backbone.last().filter(|p| {
+                    p.index >= chain_start
+                        && p.index + 1 == index
+                        && norm(sub(n, p.c)) <= MAX_PEPTIDE_BOND
+                });
+                let h = if let Some(previous) = previous {
+                    let co = sub(previous.c, previous.o);
+                    let length = norm(co);
+                    [
+                        n[0] + co[0] / length,
+                        n[1] + co[1] / length,
+                        n[2] + co[2] / length,
+                    ]
+                } else {
+                    segment += 1;
+                    n
+                };
+                backbone.push(Backbone {
+                    index,
+                    segment,
+                    proline: residue.name() == Some("PRO"),
+                    n,
+                    ca,
+                    c,
+                    o,
+                    h,
+                });
+            }
+            index += 1;
+        }
+    }
+    backbone
+}
+
+/// The two best (lowest energy) hydrogen bonds as donor for every residue, with the acceptor
+type Acceptors = Vec<[(Option<usize>, f64); 2]>;
+
+/// Calculate the hydrogen bond energies between all residues and keep for every residue the
+/// two best bonds with its N-H group as donor
+fn hydrogen_bonds(backbone: &[Backbone]) -> Acceptors {
+    let mut acceptors = vec![[(None, 0.0); 2]; backbone.len()];
+    let mut add = |donor: usize, acceptor: usize| {
+        let energy = energy(&backbone[donor], &backbone[acceptor]);
+        let slots = &mut acceptors[donor];
+        if energy < slots[0].1 {
+            slots[1] = slots[0];
+            slots[0] = (Some(acceptor), energy);
+        } else if energy < slots[1].1 {
+            slots[1] = (Some(acceptor), energy);
+        }
+    };
+    for i in 0..backbone.len() {
+        for j in i + 1..backbone.len() {
+            let ca = sub(backbone[i].ca, backbone[j].ca);
+            if dot(ca, ca) >= MAX_CA_DISTANCE * MAX_CA_DISTANCE {
+                continue;
+            }
+            add(i, j);
+            if j != i + 1 {
+                add(j, i);
+            }
+        }
+    }
+    acceptors
+}
+
+/// The electrostatic hydrogen bond energy in kcal/mol between the N-H of the donor and the
+/// C=O of the acceptor
+fn energy(donor: &Backbone, acceptor: &Backbone) -> f64 {
+    if donor.proline {
+        return 0.0;
+    }
+    let distance = |a: Vector, b: Vector| norm(sub(a, b));
+    let ho = distance(donor.h, acceptor.o);
+    let hc = distance(donor.h, acceptor.c);
+    let nc = distance(donor.n, acceptor.c);
+    let no = distance(donor.n, acceptor.o);
+    if ho < MIN_DISTANCE || hc < MIN_DISTANCE || nc < MIN_DISTANCE || no < MIN_DISTANCE {
+        return MIN_ENERGY;
+    }
+    let energy = COUPLING * (1.0 / ho - 1.0 / hc + 1.0 / nc - 1.0 / no);
+    ((energy * 1000.0).round() / 1000.0).max(MIN_ENERGY)
+}
+
+/// The last index of a non empty ladder strand
+fn last(strand: &[usize]) -> usize {
+    strand[strand.len() - 1]
+}
+
+/// The difference of two vectors
+fn sub(a: Vector, b: Vector) -> Vector {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+/// The dot product of two vectors
+fn dot(a: Vector, b: Vector) -> f64 {
+    a[0].mul_add(b[0], a[1].mul_add(b[1], a[2] * b[2]))
+}
+
+/// The cross product of two vectors
+fn cross(a: Vector, b: Vector) -> Vector {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// The length of a vector
+fn norm(a: Vector) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// The signed dihedral angle in degrees defined by four positions
+fn dihedral(a: Vector, b: Vector, c: Vector, d: Vector) -> f64 {
+    let (ab, bc, cd) = (sub(b, a), sub(c, b), sub(d, c));
+    let (n1, n2) = (cross(ab, bc), cross(bc, cd));
+    let m = cross(n1, bc);
+    let x = dot(n1, n2);
+    let y = dot(m, n2) / norm(bc);
+    (-y).atan2(x).to_degrees()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn ubiquitin() {
+        let (pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let model = pdb.model(0).unwrap();
+        let structure = model.dssp();
+        assert_eq!(structure.len(), model.residue_count());
+        let code: String = structure.iter().take(76).map(|s| s.code()).collect();
+        assert_eq!(
+            code,
+            " EEEEEETTS EEEEE  TTSBHHHHHHHHHHHH   GGGEEEEETTEEPPTTSBTGGGT  TT EEEEEE  S  "
+        );
+        let range = |from: usize, to: usize| &code[from - 1..to];
+        assert_eq!(range(23, 34), "HHHHHHHHHHHH", "{code}");
+        assert!(range(2, 7).chars().all(|c| c == 'E'), "{code}");
+        assert!(range(12, 16).chars().all(|c| c == 'E'), "{code}");
+        assert!(range(41, 45).chars().all(|c| c == 'E'), "{code}");
+        assert!(range(66, 71).chars().all(|c| c == 'E'), "{code}");
+        assert_eq!(range(38, 40), "GGG", "{code}");
+        assert_eq!(range(57, 59), "GGG", "{code}");
+        assert!(structure[76..]
+            .iter()
+            .all(|s| *s == SecondaryStructure::Coil));
+        assert_eq!(
+            SecondaryStructure::from_code('H'),
+            Some(SecondaryStructure::AlphaHelix)
+        );
+        assert_eq!(SecondaryStructure::Bend.to_string(), "S");
+    }
+
+    #[test]
+    fn helix_records() {
+        // The HELIX records in the PDB file are based on DSSP but include the caps
+        let path = "example-pdbs/1kmk.pdb";
+        let (pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read(path)
+            .unwrap();
+        let model = pdb.model(0).unwrap();
+        let structure = model.dssp();
+        let text = std::fs::read_to_string(path).unwrap();
+        let mut count = 0;
+        // Only the α helices (class 1)
+        for line in text
+            .lines()
+            .filter(|l| l.starts_with("HELIX") && l[38..40].trim() == "1")
+        {
+            let number = |range: std::ops::Range<usize>| line[range].trim().parse().unwrap();
+            let (start, end): (isize, isize) = (number(21..25), number(33..37));
+            let residues = model
+                .chains()
+                .flat_map(|c| c.residues().map(move |r| (c.id(), r.serial_number())));
+            for ((chain, number), assigned) in residues.zip(&structure) {
+                if chain == line[19..20].trim() && number > start && number < end {
+                    assert!(assigned.is_helix(), "{line}");
+                    count += 1;
+                }
+            }
+        }
+        assert_eq!(count, 156);
+    }
+
+    #[test]
+    fn dihedral_sign() {
+        let angle = dihedral(
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 1.0, 1.0],
+        );
+        assert!((angle + 90.0).abs() < 1e-9);
+        let mirrored = dihedral(
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 1.0, -1.0],
+        );
+        assert!((angle + mirrored).abs() < 1e-9);
+    }
+}
@@ -406,6 +406,129 @@ impl Element {
     pub const fn electro_negativity(&self) -> Option<f64> {
         ELEMENT_ELECTRON_NEGATIVITY[self.atomic_number() - 1]
     }
+
+    /// Get the X-ray scattering factor for the neutral atom of this element, as the four Gaussian
+    /// approximation of Cromer and Mann. Coefficients are tabulated for the elements common in
+    /// macromolecular structures, for other elements the coefficients of carbon scaled by the
+    /// atomic number are used as a rough approximation.
+    /// Source: International Tables for Crystallography (2006). Vol. C, Table 6.1.1.4. <https://doi.org/10.1107/97809553602060000600>
+    pub fn scattering_factor(&self) -> ScatteringFactor {
+        #[allow(clippy::cast_precision_loss)]
+        let scale = self.atomic_number() as f64 / 6.0;
+        match self {
+            Element::H => ScatteringFactor::new(
+                [0.489_918, 0.262_003, 0.196_767, 0.049_879],
+                [20.6593, 7.74039, 49.5519, 2.20159],
+                0.001_305,
+            ),
+            Element::He => ScatteringFactor::new(
+                [0.8734, 0.6309, 0.3112, 0.178],
+                [9.1037, 3.3568, 22.9276, 0.9821],
+                0.0064,
+            ),
+            Element::C => CARBON_SCATTERING_FACTOR,
+            Element::N => ScatteringFactor::new(
+                [12.2126, 3.1322, 2.0125, 1.1663],
+                [0.0057, 9.8933, 28.9975, 0.5826],
+                -11.529,
+            ),
+            Element::O => ScatteringFactor::new(
+                [3.0485, 2.2868, 1.5463, 0.867],
+                [13.2771, 5.7011, 0.3239, 32.9089],
+                0.2508,
+            ),
+            Element::F => ScatteringFactor::new(
+                [3.5392, 2.6412, 1.517, 1.0243],
+                [10.2825, 4.2944, 0.2615, 26.1476],
+                0.2776,
+            ),
+            Element::Na => ScatteringFactor::new(
+                [4.7626, 3.1736, 1.2674, 1.1128],
+                [3.285, 8.8422, 0.3136, 129.424],
+                0.676,
+            ),
+            Element::Mg => ScatteringFactor::new(
+                [5.4204, 2.1735, 1.2269, 2.3073],
+                [2.8275, 79.2611, 0.3808, 7.1937],
+                0.8584,
+            ),
+            Element::P => ScatteringFactor::new(
+                [6.4345, 4.1791, 1.78, 1.4908],
+                [1.9067, 27.157, 0.526, 68.1645],
+                1.1149,
+            ),
+            Element::S => ScatteringFactor::new(
+                [6.9053, 5.2034, 1.4379, 1.5863],
+                [1.4679, 22.2151, 0.2536, 56.172],
+                0.8669,
+            ),
+            Element::Cl => ScatteringFactor::new(
+                [11.4604, 7.1964, 6.2556, 1.6455],
+                [0.0104, 1.1662, 18.5194, 47.7784],
+                -9.5574,
+            ),
+            Element::K => ScatteringFactor::new(
+                [8.2186, 7.4398, 1.0519, 0.8659],
+                [12.7949, 0.7748, 213.187, 41.6841],
+                1.4228,
+            ),
+            Element::Ca => ScatteringFactor::new(
+                [8.6266, 7.3873, 1.5899, 1.0211],
+                [10.4421, 0.6599, 85.7484, 178.437],
+                1.3751,
+            ),
+            Element::Mn => ScatteringFactor::new(
+                [11.2819, 7.3573, 3.0193, 2.2441],
+                [5.3409, 0.3432, 17.8674, 83.7543],
+                1.0896,
+            ),
+            Element::Fe => ScatteringFactor::new(
+                [11.7695, 7.3573, 3.5222, 2.3045],
+                [4.7611, 0.3072, 15.3535, 76.8805],
+                1.0369,
+            ),
+            Element::Co => ScatteringFactor::new(
+                [12.2841, 7.3409, 4.0034, 2.3488],
+                [4.2791, 0.2784, 13.5359, 71.1692],
+                1.0118,
+            ),
+            Element::Ni => ScatteringFactor::new(
+                [12.8376, 7.292, 4.4438, 2.38],
+                [3.8785, 0.2565, 12.1763, 66.3421],
+                1.0341,
+            ),
+            Element::Cu => ScatteringFactor::new(
+                [13.338, 7.1676, 5.6158, 1.6735],
+                [3.5828, 0.247, 11.3966, 64.8126],
+                1.191,
+            ),
+            Element::Zn => ScatteringFactor::new(
+                [14.0743, 7.0318, 5.1652, 2.41],
+                [3.2655, 0.2333, 10.3163, 58.7097],
+                1.3041,
+            ),
+            Element::Se => ScatteringFactor::new(
+                [17.0006, 5.8196, 3.9731, 4.3543],
+                [2.4098, 0.2726, 15.2372, 43.8163],
+                2.8409,
+            ),
+            Element::Br => ScatteringFactor::new(
+                [17.1789, 5.2358, 5.6377, 3.9851],
+                [2.1723, 16.5796, 0.2609, 41.4328],
+                2.9557,
+            ),
+            Element::I => ScatteringFactor::new(
+                [20.1472, 18.9949, 7.5138, 2.2735],
+                [4.347, 0.3814, 27.766, 66.8776],
+                4.0712,
+            ),
+            _ => ScatteringFactor::new(
+                CARBON_SCATTERING_FACTOR.a.map(|a| a * scale),
+                CARBON_SCATTERING_FACTOR.b,
+                CARBON_SCATTERING_FACTOR.c * scale,
+            ),
+        }
+    }
 }
 
 #[allow(clippy::use_debug)]
@@ -452,6 +575,19 @@ mod tests {
         let element: Element = "Cl".try_into().unwrap();
         assert_eq!(Element::Cl.atomic_number(), element.atomic_number());
     }
+
+    #[test]
+    fn scattering_factor() {
+        // At zero angle the scattering factor equals the number of electrons
+        for number in 1..=118 {
+            let element = Element::new(number).unwrap();
+            let zero = element.scattering_factor().evaluate(0.0);
+            assert!((zero - number as f64).abs() < 0.02, "{element}: {zero}");
+        }
+        let carbon = Element::C.scattering_factor();
+        assert!(carbon.evaluate(0.25) < 2.0);
+        assert!(carbon.evaluate(0.25) > 1.5);
+    }
 }
 
 /// The symbols of the elements of the periodic table
@@ -627,6 +763,43 @@ impl AtomicRadius {
     }
 }
 
+/// The coefficients of the X-ray scattering factor of an atom, approximated as the sum of four
+/// Gaussians and a constant: `f(s) = sum(a[i] * exp(-b[i] * s^2)) + c` with `s = sin(θ)/λ` in Å⁻¹.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScatteringFactor {
+    /// The heights of the Gaussians, in electrons
+    pub a: [f64; 4],
+    /// The widths of the Gaussians, in Å²
+    pub b: [f64; 4],
+    /// The constant term, in electrons
+    pub c: f64,
+}
+
+impl ScatteringFactor {
+    /// Create a scattering factor from its coefficients
+    pub const fn new(a: [f64; 4], b: [f64; 4], c: f64) -> Self {
+        Self { a, b, c }
+    }
+
+    /// Get the scattering factor in electrons at the given `(sin(θ)/λ)²` in Å⁻², this is
+    /// `1 / (4 d²)` for a reflection with resolution `d`
+    pub fn evaluate(&self, stol_squared: f64) -> f64 {
+        self.a
+            .iter()
+            .zip(self.b)
+            .map(|(a, b)| a * (-b * stol_squared).exp())
+            .sum::<f64>()
+            + self.c
+    }
+}
+
+/// The scattering factor of carbon, also used to approximate elements without tabulated values
+const CARBON_SCATTERING_FACTOR: ScatteringFactor = ScatteringFactor::new(
+    [2.31, 1.02, 1.5886, 0.865],
+    [20.8439, 10.2075, 0.5687, 51.6512],
+    0.2156,
+);
+
 /// All atomic radii for all atoms
 const ELEMENT_ATOMIC_RADII: [AtomicRadius; 118] = [
     AtomicRadius::new(Some(1.54), Some(1.2), 0.32, None, None),
@@ -45,6 +45,28 @@ pub fn number_to_base26(mut num: usize) -> String {
     output.iter().rev().collect::<String>()
 }
 
+/// Invert a 3x3 matrix, returns the inverse and the determinant, or `None` if it is singular
+pub(crate) fn invert_matrix(m: [[f64; 3]; 3]) -> Option<([[f64; 3]; 3], f64)> {
+    let cofactor = |row: usize, column: usize| {
+        let (r1, r2) = ((row + 1) % 3, (row + 2) % 3);
+        let (c1, c2) = ((column + 1) % 3, (column + 2) % 3);
+        m[r1][c1].mul_add(m[r2][c2], -m[r1][c2] * m[r2][c1])
+    };
+    let determinant = (0..3)
+        .map(|column| m[0][column] * cofactor(0, column))
+        .sum::<f64>();
+    if determinant == 0.0 || !determinant.is_finite() {
+        return None;
+    }
+    let mut inverse = [[0.0; 3]; 3];
+    for (row, values) in inverse.iter_mut().enumerate() {
+        for (column, value) in values.iter_mut().enumerate() {
+            *value = cofactor(column, row) / determinant;
+        }
+    }
+    Some((inverse, determinant))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,546 @@
+use crate::structs::*;
+
+/// The maximal distance in Å between a hydrogen and the atom it is bonded to
+const HYDROGEN_BOND_LENGTH: f64 = 1.3;
+/// The maximal distance in Å between two covalently bonded heavy atoms
+const COVALENT_BOND_LENGTH: f64 = 1.9;
+
+/// The atoms of amino acids and nucleotides that can donate a hydrogen, next to the backbone N
+/// of amino acids (except proline)
+const DONORS: &[(&str, &str)] = &[
+    ("ARG", "NE"),
+    ("ARG", "NH1"),
+    ("ARG", "NH2"),
+    ("ASN", "ND2"),
+    ("CYS", "SG"),
+    ("GLN", "NE2"),
+    ("HIS", "ND1"),
+    ("HIS", "NE2"),
+    ("LYS", "NZ"),
+    ("SER", "OG"),
+    ("THR", "OG1"),
+    ("TRP", "NE1"),
+    ("TYR", "OH"),
+    ("HOH", "O"),
+    ("A", "N6"),
+    ("A", "O2'"),
+    ("C", "N4"),
+    ("C", "O2'"),
+    ("G", "N1"),
+    ("G", "N2"),
+    ("G", "O2'"),
+    ("U", "N3"),
+    ("U", "O2'"),
+    ("DA", "N6"),
+    ("DC", "N4"),
+    ("DG", "N1"),
+    ("DG", "N2"),
+    ("DT", "N3"),
+];
+
+/// The atoms of amino acids and nucleotides that can accept a hydrogen, next to the backbone O
+/// and OXT of amino acids
+const ACCEPTORS: &[(&str, &str)] = &[
+    ("ASN", "OD1"),
+    ("ASP", "OD1"),
+    ("ASP", "OD2"),
+    ("GLN", "OE1"),
+    ("GLU", "OE1"),
+    ("GLU", "OE2"),
+    ("HIS", "ND1"),
+    ("HIS", "NE2"),
+    ("MET", "SD"),
+    ("SER", "OG"),
+    ("THR", "OG1"),
+    ("TYR", "OH"),
+    ("HOH", "O"),
+    ("A", "N1"),
+    ("A", "N3"),
+    ("A", "N7"),
+    ("C", "O2"),
+    ("C", "N3"),
+    ("G", "O6"),
+    ("G", "N3"),
+    ("G", "N7"),
+    ("U", "O2"),
+    ("U", "O4"),
+    ("DA", "N1"),
+    ("DA", "N3"),
+    ("DA", "N7"),
+    ("DC", "O2"),
+    ("DC", "N3"),
+    ("DG", "O6"),
+    ("DG", "N3"),
+    ("DG", "N7"),
+    ("DT", "O2"),
+    ("DT", "O4"),
+];
+
+/// The atoms of the nucleotide backbone that can accept a hydrogen
+const NUCLEOTIDE_ACCEPTORS: &[&str] = &["OP1", "OP2", "O1P", "O2P", "O2'", "O3'", "O4'", "O5'"];
+
+/// The nucleotides with backbone acceptors
+const NUCLEOTIDES: &[&str] = &["A", "C", "G", "U", "DA", "DC", "DG", "DT"];
+
+/// The criteria used to detect hydrogen bonds, see [`PDB::hydrogen_bonds`]. If the residue of
+/// the donor has explicit hydrogens the distance between the hydrogen bonded to the donor and the
+/// acceptor and the angle at the hydrogen are used, so donors without a hydrogen (like the
+/// unprotonated nitrogen of histidine) do not donate. Otherwise the distance between the donor
+/// and the acceptor is used, with the angles between the donor, the acceptor, and the heavy atoms
+/// covalently bonded to either of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HydrogenBondCriteria {
+    /// The maximal distance in Å between the donor and the acceptor
+    max_distance: f64,
+    /// The maximal distance in Å between the hydrogen and the acceptor
+    max_hydrogen_distance: f64,
+    /// The minimal angle in degrees of donor - hydrogen - acceptor
+    min_angle: f64,
+    /// The minimal angle in degrees at the donor and acceptor with their bonded heavy atoms
+    min_antecedent_angle: f64,
+}
+
+impl Default for HydrogenBondCriteria {
+    fn default() -> Self {
+        Self {
+            max_distance: 3.5,
+            max_hydrogen_distance: 2.5,
+            min_angle: 120.0,
+            min_antecedent_angle: 90.0,
+        }
+    }
+}
+
+impl HydrogenBondCriteria {
+    /// Constructs new criteria with the default values: at most 3.5 Å between the donor and the
+    /// acceptor, at most 2.5 Å between the hydrogen and the acceptor, at least 120° for the angle
+    /// at the hydrogen, and at least 90° for the angles with the bonded heavy atoms.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximal distance in Å between the donor and the acceptor.
+    pub fn set_max_distance(&mut self, distance: f64) -> &mut Self {
+        self.max_distance = distance;
+        self
+    }
+
+    /// Sets the maximal distance in Å between the hydrogen and the acceptor, used when the
+    /// residue of the donor has explicit hydrogens.
+    pub fn set_max_hydrogen_distance(&mut self, distance: f64) -> &mut Self {
+        self.max_hydrogen_distance = distance;
+        self
+    }
+
+    /// Sets the minimal angle in degrees of donor - hydrogen - acceptor, used when the residue of
+    /// the donor has explicit hydrogens.
+    pub fn set_min_angle(&mut self, angle: f64) -> &mut Self {
+        self.min_angle = angle;
+        self
+    }
+
+    /// Sets the minimal angle in degrees of any heavy atom bonded to the donor - donor -
+    /// acceptor and donor - acceptor - any heavy atom bonded to the acceptor, used when the
+    /// residue of the donor has no explicit hydrogens.
+    pub fn set_min_antecedent_angle(&mut self, angle: f64) -> &mut Self {
+        self.min_antecedent_angle = angle;
+        self
+    }
+
+    /// Get the maximal distance in Å between the donor and the acceptor
+    #[must_use]
+    pub const fn max_distance(&self) -> f64 {
+        self.max_distance
+    }
+
+    /// Get the maximal distance in Å between the hydrogen and the acceptor
+    #[must_use]
+    pub const fn max_hydrogen_distance(&self) -> f64 {
+        self.max_hydrogen_distance
+    }
+
+    /// Get the minimal angle in degrees of donor - hydrogen - acceptor
+    #[must_use]
+    pub const fn min_angle(&self) -> f64 {
+        self.min_angle
+    }
+
+    /// Get the minimal angle in degrees at the donor and acceptor with their bonded heavy atoms
+    #[must_use]
+    pub const fn min_antecedent_angle(&self) -> f64 {
+        self.min_antecedent_angle
+    }
+}
+
+/// A hydrogen bond between a donor and an acceptor atom, see [`PDB::hydrogen_bonds`]
+#[derive(Debug, Clone)]
+pub struct HydrogenBond<'a> {
+    /// The donor atom
+    donor: AtomConformerResidueChainModel<'a>,
+    /// The explicit hydrogen, if present
+    hydrogen: Option<&'a Atom>,
+    /// The acceptor atom
+    acceptor: AtomConformerResidueChainModel<'a>,
+}
+
+impl<'a> HydrogenBond<'a> {
+    /// Get the donor atom with its hierarchy
+    #[must_use]
+    pub const fn donor(&self) -> &AtomConformerResidueChainModel<'a> {
+        &self.donor
+    }
+
+    /// Get the hydrogen, `None` if the donor has no explicit hydrogens
+    #[must_use]
+    pub const fn hydrogen(&self) -> Option<&'a Atom> {
+        self.hydrogen
+    }
+
+    /// Get the acceptor atom with its hierarchy
+    #[must_use]
+    pub const fn acceptor(&self) -> &AtomConformerResidueChainModel<'a> {
+        &self.acceptor
+    }
+
+    /// Get the distance in Å between the donor and the acceptor
+    #[must_use]
+    pub fn distance(&self) -> f64 {
+        self.donor.atom().distance(self.acceptor.atom())
+    }
+
+    /// Get the angle in degrees of donor - hydrogen - acceptor, `None` if the donor has no
+    /// explicit hydrogens
+    #[must_use]
+    pub fn angle(&self) -> Option<f64> {
+        self.hydrogen
+            .map(|hydrogen| self.donor.atom().angle(hydrogen, self.acceptor.atom()))
+    }
+}
+
+/// A donor or acceptor atom with the atoms bonded to it
+struct Polar<'a> {
+    /// The atom
+    hierarchy: AtomConformerResidueChain<'a>,
+    /// The index of the residue in the list of all residues
+    residue: usize,
+    /// If this atom can donate a hydrogen
+    donor: bool,
+    /// If this atom can accept a hydrogen
+    acceptor: bool,
+    /// The heavy atoms bonded to this atom
+    antecedents: Vec<&'a Atom>,
+    /// The hydrogens bonded to this atom
+    hydrogens: Vec<&'a Atom>,
+    /// If the conformer of this atom has explicit hydrogens
+    explicit: bool,
+}
+
+/// A hydrogen bond found by [`detect`], as donor, hydrogen if present, and acceptor
+pub(crate) type Found<'a> = (
+    AtomConformerResidueChain<'a>,
+    Option<&'a Atom>,
+    AtomConformerResidueChain<'a>,
+);
+
+/// Detect the hydrogen bonds between the atoms in the given chains meeting the criteria, for
+/// which `keep` returns true given the donor and the acceptor
+pub(crate) fn detect<'a>(
+    chains: impl IntoIterator<Item = &'a Chain>,
+    criteria: &HydrogenBondCriteria,
+    keep: impl Fn(&AtomConformerResidueChain<'a>, &AtomConformerResidueChain<'a>) -> bool,
+) -> Vec<Found<'a>> {
+    let mut polar = Vec::new();
+    let mut residue_index = 0;
+    for chain in chains {
+        for residue in chain.residues() {
+            residue_index += 1;
+            for conformer in residue.conformers() {
+                let explicit = conformer.atoms().any(|a| a.element() == Some(&Element::H));
+                for atom in conformer.atoms() {
+                    let (donor, acceptor) = classify(conformer, atom);
+                    if !donor && !acceptor {
+                        continue;
+                    }
+                    let (mut antecedents, mut hydrogens) = (Vec::new(), Vec::new());
+                    for other in conformer.atoms() {
+                        if std::ptr::eq(atom, other) {
+                            continue;
+                        }
+                        let distance = atom.distance(other);
+                        if other.element() == Some(&Element::H) {
+                            if distance <= HYDROGEN_BOND_LENGTH {
+                                hydrogens.push(other);
+                            }
+                        } else if distance <= COVALENT_BOND_LENGTH {
+                            antecedents.push(other);
+                        }
+                    }
+                    polar.push(Polar {
+                        hierarchy: AtomConformerResidueChain::new(atom, conformer, residue, chain),
+                        residue: residue_index,
+                        donor,
+                        acceptor,
+                        antecedents,
+                        hydrogens,
+                        explicit,
+                    });
+                }
+            }
+        }
+    }
+
+    let donors: Vec<_> = polar.iter().filter(|p| p.donor).collect();
+    let acceptors: Vec<_> = polar.iter().filter(|p| p.acceptor).collect();
+    let positions =
+        |list: &[&Polar<'_>]| -> Vec<_> { list.iter().map(|p| p.hierarchy.atom().pos()).collect() };
+    let close = candidates(
+        &positions(&donors),
+        &positions(&acceptors),
+        criteria.max_distance,
+    );
+    let mut found = Vec::new();
+    for (donor, close) in donors.into_iter().zip(close) {
+        for acceptor in close.into_iter().map(|index| acceptors[index]) {
+            if donor.residue == acceptor.residue
+                || !compatible(&donor.hierarchy, &acceptor.hierarchy)
+                || (donor.residue.abs_diff(acceptor.residue) == 1
+                    && donor.hierarchy.atom().is_backbone()
+                    && acceptor.hierarchy.atom().is_backbone()
+                    && std::ptr::eq(donor.hierarchy.chain(), acceptor.hierarchy.chain()))
+            {
+                continue;
+            }
+            let (d, a) = (donor.hierarchy.atom(), acceptor.hierarchy.atom());
+            if !keep(&donor.hierarchy, &acceptor.hierarchy) {
+                continue;
+            }
+            if !donor.explicit {
+                let min = criteria.min_antecedent_angle;
+                if donor.antecedents.iter().all(|x| x.angle(d, a) >= min)
+                    && acceptor.antecedents.iter().all(|x| d.angle(a, x) >= min)
+                {
+                    found.push((donor.hierarchy.clone(), None, acceptor.hierarchy.clone()));
+                }
+            } else if let Some(hydrogen) = donor
+                .hydrogens
+                .iter()
+                .filter(|h| {
+                    h.distance(a) <= criteria.max_hydrogen_distance
+                        && d.angle(h, a) >= criteria.min_angle
+                })
+                .min_by(|x, y| x.distance(a).total_cmp(&y.distance(a)))
+            {
+                found.push((
+                    donor.hierarchy.clone(),
+                    Some(*hydrogen),
+                    acceptor.hierarchy.clone(),
+                ));
+            }
+        }
+    }
+    found
+}
+
+/// Get the indices of the acceptors within the given distance of every donor, sorted, using an
+/// R*-tree
+#[cfg(feature = "rstar")]
+fn candidates(
+    donors: &[(f64, f64, f64)],
+    acceptors: &[(f64, f64, f64)],
+    distance: f64,
+) -> Vec<Vec<usize>> {
+    use rstar::primitives::GeomWithData;
+    let tree = rstar::RTree::bulk_load(
+        acceptors
+            .iter()
+            .enumerate()
+            .map(|(index, p)| GeomWithData::new([p.0, p.1, p.2], index))
+            .collect(),
+    );
+    donors
+        .iter()
+        .map(|p| {
+            let mut close: Vec<_> = tree
+                .locate_within_distance([p.0, p.1, p.2], distance * distance)
+                .map(|acceptor| acceptor.data)
+                .collect();
+            close.sort_unstable();
+            close
+        })
+        .collect()
+}
+
+/// Get the indices of the acceptors within the given distance of every donor, sorted, by
+/// checking all pairs
+#[cfg(not(feature = "rstar"))]
+fn candidates(
+    donors: &[(f64, f64, f64)],
+    acceptors: &[(f64, f64, f64)],
+    distance: f64,
+) -> Vec<Vec<usize>> {
+    donors
+        .iter()
+        .map(|d| {
+            (0..acceptors.len())
+                .filter(|index| {
+                    let a = acceptors[*index];
+                    (d.0 - a.0).powi(2) + (d.1 - a.1).powi(2) + (d.2 - a.2).powi(2)
+                        <= distance * distance
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Determine if the given atom can donate and if it can accept a hydrogen
+fn classify(conformer: &Conformer, atom: &Atom) -> (bool, bool) {
+    let (residue, name) = (conformer.name(), atom.name());
+    let listed = |list: &[(&str, &str)]| list.iter().any(|(r, a)| *r == residue && *a == name);
+    let amino_acid = conformer.is_amino_acid();
+    let donor = (amino_acid && name == "N" && residue != "PRO") || listed(DONORS);
+    let acceptor = (amino_acid && matches!(name, "O" | "OXT"))
+        || listed(ACCEPTORS)
+        || (NUCLEOTIDES.contains(&residue) && NUCLEOTIDE_ACCEPTORS.contains(&name));
+    (donor, acceptor)
+}
+
+/// Determine if the two atoms can be present at the same time, so they are not in different
+/// alternative locations
+fn compatible(a: &AtomConformerResidueChain<'_>, b: &AtomConformerResidueChain<'_>) -> bool {
+    match (
+        a.conformer().alternative_location(),
+        b.conformer().alternative_location(),
+    ) {
+        (Some(x), Some(y)) => x == y,
+        _ => true,
+    }
+}
+
+/// Detect the hydrogen bonds in all models of the given PDB
+pub(crate) fn hydrogen_bonds<'a>(
+    pdb: &'a PDB,
+    criteria: &HydrogenBondCriteria,
+) -> Vec<HydrogenBond<'a>> {
+    pdb.models()
+        .flat_map(|model| {
+            detect(model.chains(), criteria, |_, _| true)
+                .into_iter()
+                .map(move |(donor, hydrogen, acceptor)| HydrogenBond {
+                    donor: donor.extend(model),
+                    hydrogen,
+                    acceptor: acceptor.extend(model),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn ubiquitin() {
+        let (mut pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let criteria = HydrogenBondCriteria::new();
+        // This file has explicit hydrogens
+        let explicit = pdb.hydrogen_bonds(&criteria);
+        assert!(explicit.len() > 50);
+        for bond in explicit
+            .iter()
+            .filter(|b| b.donor().conformer().name() != "HOH")
+        {
+            let hydrogen = bond.hydrogen().unwrap();
+            assert!(hydrogen.distance(bond.acceptor().atom()) <= 2.5);
+            assert!(bond.angle().unwrap() >= 120.0);
+        }
+        pdb.remove_atoms_by(|a| a.element() == Some(&Element::H));
+        let bonds = pdb.hydrogen_bonds(&criteria);
+        assert!(bonds.len() > 50);
+        for bond in &bonds {
+            assert!(bond.distance() <= 3.5);
+            assert!(bond.hydrogen().is_none());
+            assert!(bond.angle().is_none());
+        }
+        // The backbone hydrogen bonds of the helix 23-34 from residue i + 4 to i
+        let helix = bonds
+            .iter()
+            .filter(|b| {
+                b.donor().atom().name() == "N"
+                    && b.acceptor().atom().name() == "O"
+                    && b.donor().residue().serial_number()
+                        == b.acceptor().residue().serial_number() + 4
+                    && (23..=30).contains(&b.acceptor().residue().serial_number())
+            })
+            .count();
+        assert!(helix >= 6);
+        // Stricter criteria give fewer bonds
+        let strict = pdb.hydrogen_bonds(HydrogenBondCriteria::new().set_max_distance(3.0));
+        assert!(strict.len() < bonds.len());
+
+        let count = bonds.len();
+        assert_eq!(pdb.add_hydrogen_bonds(&criteria), count);
+        assert_eq!(
+            pdb.bonds().filter(|(_, _, b)| *b == Bond::Hydrogen).count(),
+            count
+        );
+        // Adding them again does not give duplicates
+        assert_eq!(pdb.add_hydrogen_bonds(&criteria), 0);
+    }
+
+    #[test]
+    fn explicit_hydrogens() {
+        let mut model = Model::new(0);
+        let atoms = [
+            ("A", 1, "SER", "OG", "O", (0.0, 0.0, 0.0)),
+            ("A", 1, "SER", "CB", "C", (-1.4, -0.3, 0.0)),
+            ("A", 1, "SER", "HG", "H", (0.96, 0.0, 0.0)),
+            ("A", 5, "ASP", "OD1", "O", (2.8, 0.0, 0.0)),
+            ("A", 5, "ASP", "CG", "C", (4.0, 0.5, 0.0)),
+            ("A", 9, "THR", "OG1", "O", (0.0, 3.0, 0.0)),
+        ];
+        for (serial, (chain, residue, name, atom, element, pos)) in atoms.iter().enumerate() {
+            model.add_atom(
+                Atom::new(
+                    false,
+                    serial + 1,
+                    *atom,
+                    pos.0,
+                    pos.1,
+                    pos.2,
+                    1.0,
+                    0.0,
+                    *element,
+                    0,
+                )
+                .unwrap(),
+                *chain,
+                (*residue, None),
+                (*name, None),
+            );
+        }
+        let mut pdb = PDB::new();
+        pdb.add_model(model);
+        let bonds = pdb.hydrogen_bonds(&HydrogenBondCriteria::new());
+        // The serine hydroxyl only donates along its hydrogen to the aspartate, the threonine
+        // without hydrogens donates to the serine
+        let pairs: Vec<_> = bonds
+            .iter()
+            .map(|b| (b.donor().atom().name(), b.acceptor().atom().name()))
+            .collect();
+        assert!(pairs.contains(&("OG", "OD1")));
+        assert!(!pairs.contains(&("OG", "OG1")));
+        assert!(pairs.contains(&("OG1", "OG")));
+        let bond = bonds
+            .iter()
+            .find(|b| b.donor().atom().name() == "OG")
+            .unwrap();
+        assert_eq!(bond.hydrogen().unwrap().name(), "HG");
+        assert!((bond.angle().unwrap() - 180.0).abs() < 1e-6);
+    }
+}
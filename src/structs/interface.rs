@@ -0,0 +1,428 @@
+use super::sasa::{overlapping, surface_areas_of};
+use crate::structs::*;
+
+/// The probe radius in Å used to calculate the buried surface area
+const PROBE_RADIUS: f64 = 1.4;
+/// The maximal distance in Å between two atoms in contact
+const CONTACT_DISTANCE: f64 = 4.0;
+/// The maximal distance in Å between the charged atoms of a salt bridge
+const SALT_BRIDGE_DISTANCE: f64 = 4.0;
+
+/// The positively charged side chain atoms of amino acids
+const POSITIVE: &[(&str, &str)] = &[
+    ("ARG", "NE"),
+    ("ARG", "NH1"),
+    ("ARG", "NH2"),
+    ("HIS", "ND1"),
+    ("HIS", "NE2"),
+    ("LYS", "NZ"),
+];
+/// The negatively charged side chain atoms of amino acids
+const NEGATIVE: &[(&str, &str)] = &[
+    ("ASP", "OD1"),
+    ("ASP", "OD2"),
+    ("GLU", "OE1"),
+    ("GLU", "OE2"),
+];
+
+/// The interface between two chains, with the residues and surface area buried upon complex
+/// formation, the atoms in contact, and the hydrogen bonds and salt bridges across the
+/// interface, in the style of PISA (Krissinel, E., & Henrick, K. (2007). Inference of
+/// macromolecular assemblies from crystalline state. Journal of Molecular Biology, 372(3),
+/// 774–797). The surface areas are calculated with [`Sasa`] with a probe radius of 1.4 Å. Atoms
+/// are in contact if they are within 4.0 Å. Hydrogen bonds are detected with the default
+/// [`HydrogenBondCriteria`] (see [`PDB::hydrogen_bonds`]), salt bridges are made between the
+/// charged atoms of Arg, Lys, or His and Asp or Glu within 4.0 Å. Waters are ignored, and the
+/// surface areas, contacts, and salt bridges use the heavy atoms of the first conformer of every
+/// residue.
+#[derive(Debug, Clone)]
+pub struct Interface<'a> {
+    /// The first chain
+    first: &'a Chain,
+    /// The second chain
+    second: &'a Chain,
+    /// The residues of the first chain with their buried area in Å²
+    first_residues: Vec<(&'a Residue, f64)>,
+    /// The residues of the second chain with their buried area in Å²
+    second_residues: Vec<(&'a Residue, f64)>,
+    /// The number of atom contacts by type
+    contacts: ContactCounts,
+    /// The hydrogen bonds, donor first
+    hydrogen_bonds: Vec<(AtomConformerResidueChain<'a>, AtomConformerResidueChain<'a>)>,
+    /// The salt bridges, positive atom first
+    salt_bridges: Vec<(AtomConformerResidueChain<'a>, AtomConformerResidueChain<'a>)>,
+}
+
+/// The number of atom contacts across an interface, split by the type of the atoms. Nitrogen
+/// and oxygen atoms are polar, all other atoms are apolar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContactCounts {
+    /// The number of contacts between two apolar atoms
+    apolar: usize,
+    /// The number of contacts between two polar atoms
+    polar: usize,
+    /// The number of contacts between a polar and an apolar atom
+    mixed: usize,
+}
+
+impl ContactCounts {
+    /// Get the number of contacts between two apolar atoms
+    #[must_use]
+    pub const fn apolar(&self) -> usize {
+        self.apolar
+    }
+
+    /// Get the number of contacts between two polar (N or O) atoms
+    #[must_use]
+    pub const fn polar(&self) -> usize {
+        self.polar
+    }
+
+    /// Get the number of contacts between a polar and an apolar atom
+    #[must_use]
+    pub const fn mixed(&self) -> usize {
+        self.mixed
+    }
+
+    /// Get the total number of contacts
+    #[must_use]
+    pub const fn total(&self) -> usize {
+        self.apolar + self.polar + self.mixed
+    }
+}
+
+impl<'a> Interface<'a> {
+    /// Analyse the interface between the two given chains.
+    ///
+    /// ## Fails
+    /// Returns `None` if the chains are not in contact, so no surface area is buried and no
+    /// atoms are in contact.
+    #[must_use]
+    pub fn new(first: &'a Chain, second: &'a Chain) -> Option<Self> {
+        Self::from_isolated(
+            &Sasa::from_chains([first], PROBE_RADIUS),
+            &Sasa::from_chains([second], PROBE_RADIUS),
+        )
+    }
+
+    /// Analyse the interface between two chains given the accessible surface areas of both
+    /// chains in isolation. Only the atoms whose spheres overlap with the spheres of the other
+    /// chain can lose accessible area, so only those are evaluated again in the complex.
+    fn from_isolated(first: &Sasa<'a>, second: &Sasa<'a>) -> Option<Self> {
+        let (first_chain, second_chain) = (first.chains()[0].0, second.chains()[0].0);
+        let cross = overlapping(first.spheres(), second.spheres());
+        let mut partners = vec![false; second.spheres().len()];
+        for other in cross.iter().flatten() {
+            partners[*other] = true;
+        }
+        let offset = first.spheres().len();
+        let spheres: Vec<_> = first
+            .spheres()
+            .iter()
+            .chain(second.spheres())
+            .copied()
+            .collect();
+        let selection: Vec<_> = (0..offset)
+            .filter(|index| !cross[*index].is_empty())
+            .chain(
+                (0..partners.len())
+                    .filter(|index| partners[*index])
+                    .map(|index| index + offset),
+            )
+            .collect();
+        let mut first_buried = vec![0.0; first.residues().len()];
+        let mut second_buried = vec![0.0; second.residues().len()];
+        for (index, together) in selection.iter().zip(surface_areas_of(&spheres, &selection)) {
+            if let Some(other) = index.checked_sub(offset) {
+                second_buried[second.residue_index(other)] += second.atoms()[other].1 - together;
+            } else {
+                first_buried[first.residue_index(*index)] += first.atoms()[*index].1 - together;
+            }
+        }
+        let buried = |isolated: &Sasa<'a>, areas: Vec<f64>| -> Vec<_> {
+            isolated
+                .residues()
+                .iter()
+                .zip(areas)
+                .filter(|(_, area)| *area > 0.0)
+                .map(|((residue, _), area)| (*residue, area))
+                .collect()
+        };
+        let first_residues = buried(first, first_buried);
+        let second_residues = buried(second, second_buried);
+
+        let hydrogen_bonds = super::hydrogen_bond::detect(
+            [first_chain, second_chain],
+            &HydrogenBondCriteria::default(),
+            |donor, acceptor| {
+                !std::ptr::eq(donor.chain(), acceptor.chain())
+                    && donor.conformer().name() != "HOH"
+                    && acceptor.conformer().name() != "HOH"
+            },
+        )
+        .into_iter()
+        .map(|(donor, _, acceptor)| (donor, acceptor))
+        .collect();
+        // All atoms within the contact distance have overlapping spheres, as every sphere has a
+        // radius of at least the probe radius plus the smallest van der Waals radius (1.43 Å)
+        let mut contacts = ContactCounts::default();
+        let mut salt_bridges = Vec::new();
+        for (index, others) in cross.iter().enumerate() {
+            let a = &first.atoms()[index].0;
+            for other in others {
+                let b = &second.atoms()[*other].0;
+                let distance = a.atom().distance(b.atom());
+                if distance > CONTACT_DISTANCE {
+                    continue;
+                }
+                match (is_polar(a.atom()), is_polar(b.atom())) {
+                    (true, true) => contacts.polar += 1,
+                    (false, false) => contacts.apolar += 1,
+                    _ => contacts.mixed += 1,
+                }
+                if distance <= SALT_BRIDGE_DISTANCE {
+                    if is_in(POSITIVE, a) && is_in(NEGATIVE, b) {
+                        salt_bridges.push((a.clone(), b.clone()));
+                    } else if is_in(POSITIVE, b) && is_in(NEGATIVE, a) {
+                        salt_bridges.push((b.clone(), a.clone()));
+                    }
+                }
+            }
+        }
+        if contacts.total() == 0 && first_residues.is_empty() && second_residues.is_empty() {
+            return None;
+        }
+        Some(Interface {
+            first: first_chain,
+            second: second_chain,
+            first_residues,
+            second_residues,
+            contacts,
+            hydrogen_bonds,
+            salt_bridges,
+        })
+    }
+
+    /// Get the first chain
+    #[must_use]
+    pub const fn first(&self) -> &'a Chain {
+        self.first
+    }
+
+    /// Get the second chain
+    #[must_use]
+    pub const fn second(&self) -> &'a Chain {
+        self.second
+    }
+
+    /// Get the interface residues of the first chain, the residues losing accessible surface
+    /// area upon complex formation, with the buried area in Å²
+    #[must_use]
+    pub fn first_residues(&self) -> &[(&'a Residue, f64)] {
+        &self.first_residues
+    }
+
+    /// Get the interface residues of the second chain, the residues losing accessible surface
+    /// area upon complex formation, with the buried area in Å²
+    #[must_use]
+    pub fn second_residues(&self) -> &[(&'a Residue, f64)] {
+        &self.second_residues
+    }
+
+    /// Get the total accessible surface area in Å² buried upon complex formation, summed over
+    /// both chains
+    #[must_use]
+    pub fn buried_area(&self) -> f64 {
+        self.first_residues
+            .iter()
+            .chain(&self.second_residues)
+            .map(|(_, area)| area)
+            .sum()
+    }
+
+    /// Get the interface area in Å², which is half of the buried area as defined by PISA
+    #[must_use]
+    pub fn interface_area(&self) -> f64 {
+        self.buried_area() / 2.0
+    }
+
+    /// Get the number of atom contacts across the interface by type
+    #[must_use]
+    pub const fn contacts(&self) -> ContactCounts {
+        self.contacts
+    }
+
+    /// Get the hydrogen bonds across the interface, as pairs of the donor and acceptor atoms
+    #[must_use]
+    pub fn hydrogen_bonds(
+        &self,
+    ) -> &[(AtomConformerResidueChain<'a>, AtomConformerResidueChain<'a>)] {
+        &self.hydrogen_bonds
+    }
+
+    /// Get the salt bridges across the interface, as pairs of the positive and negative atoms
+    #[must_use]
+    pub fn salt_bridges(
+        &self,
+    ) -> &[(AtomConformerResidueChain<'a>, AtomConformerResidueChain<'a>)] {
+        &self.salt_bridges
+    }
+}
+
+/// Analyse the interfaces between all pairs of chains in the given model that are in contact
+pub(crate) fn interfaces(model: &Model) -> Vec<Interface<'_>> {
+    let isolated: Vec<_> = model
+        .chains()
+        .map(|chain| Sasa::from_chains([chain], PROBE_RADIUS))
+        .collect();
+    let boxes: Vec<_> = isolated.iter().map(bounding_box).collect();
+    let mut output = Vec::new();
+    for (i, first) in isolated.iter().enumerate() {
+        for (j, second) in isolated.iter().enumerate().skip(i + 1) {
+            if let (Some(a), Some(b)) = (boxes[i], boxes[j]) {
+                if a.0.iter().zip(b.1).any(|(min, max)| *min > max)
+                    || b.0.iter().zip(a.1).any(|(min, max)| *min > max)
+                {
+                    continue;
+                }
+                output.extend(Interface::from_isolated(first, second));
+            }
+        }
+    }
+    output
+}
+
+/// Get the bounding box of the atoms in the given surface, with every atom expanded by its
+/// radius including the probe radius (or half the contact distance if that is larger), so the
+/// boxes of two sets of atoms that could interact overlap
+fn bounding_box(sasa: &Sasa<'_>) -> Option<([f64; 3], [f64; 3])> {
+    sasa.atoms().iter().fold(None, |found, (hierarchy, _)| {
+        let atom = hierarchy.atom();
+        let radius = atom
+            .element()
+            .and_then(|e| e.atomic_radius().van_der_waals)
+            .unwrap_or_default();
+        let margin = (radius + PROBE_RADIUS).max(CONTACT_DISTANCE / 2.0);
+        let (x, y, z) = atom.pos();
+        let (mut min, mut max) = found.unwrap_or(([f64::MAX; 3], [f64::MIN; 3]));
+        for (index, value) in [x, y, z].into_iter().enumerate() {
+            min[index] = min[index].min(value - margin);
+            max[index] = max[index].max(value + margin);
+        }
+        Some((min, max))
+    })
+}
+
+/// Determine if the given atom is polar (nitrogen or oxygen)
+fn is_polar(atom: &Atom) -> bool {
+    matches!(atom.element(), Some(Element::N | Element::O))
+}
+
+/// Determine if the given atom is in the list of residue and atom names
+fn is_in(list: &[(&str, &str)], hierarchy: &AtomConformerResidueChain<'_>) -> bool {
+    let (residue, atom) = (hierarchy.conformer().name(), hierarchy.atom().name());
+    list.iter().any(|(r, a)| *r == residue && *a == atom)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn dimer() {
+        let (pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let mut model = pdb.model(0).unwrap().clone();
+        let mut copy = model.chain(0).unwrap().clone();
+        assert!(copy.set_id("B"));
+        let far = copy.clone();
+        // Stack a copy of the chain directly on top to create an interface
+        for atom in copy.atoms_mut() {
+            atom.set_z(atom.z() + 25.0).unwrap();
+        }
+        model.add_chain(copy);
+        let mut far = far;
+        assert!(far.set_id("C"));
+        for atom in far.atoms_mut() {
+            atom.set_x(atom.x() + 200.0).unwrap();
+        }
+        model.add_chain(far);
+
+        let interfaces = model.interfaces();
+        assert_eq!(interfaces.len(), 1);
+        let interface = &interfaces[0];
+        assert_eq!(interface.first().id(), "A");
+        assert_eq!(interface.second().id(), "B");
+        assert!(interface.contacts().total() > 0);
+        assert!(!interface.first_residues().is_empty());
+        assert!(!interface.second_residues().is_empty());
+        assert!((interface.buried_area() - 2.0 * interface.interface_area()).abs() < 1e-9);
+        // Only evaluating the atoms near the other chain gives the same buried area as
+        // evaluating the whole complex
+        let (a, b) = (model.chain(0).unwrap(), model.chain(1).unwrap());
+        let full = Sasa::from_chains([a], PROBE_RADIUS).total()
+            + Sasa::from_chains([b], PROBE_RADIUS).total()
+            - Sasa::from_chains([a, b], PROBE_RADIUS).total();
+        assert!((interface.buried_area() - full).abs() < 1e-6);
+        for (donor, acceptor) in interface.hydrogen_bonds() {
+            assert!(donor.atom().distance(acceptor.atom()) <= 3.5);
+            assert_ne!(donor.chain().id(), acceptor.chain().id());
+        }
+
+        let direct = Interface::new(model.chain(0).unwrap(), model.chain(1).unwrap()).unwrap();
+        assert_eq!(direct.contacts(), interface.contacts());
+        assert!((direct.buried_area() - interface.buried_area()).abs() < 1e-9);
+        assert!(Interface::new(model.chain(0).unwrap(), model.chain(2).unwrap()).is_none());
+    }
+
+    #[test]
+    fn salt_bridge() {
+        let mut model = Model::new(0);
+        let atoms = [
+            ("A", "LYS", "NZ", "N", 0.0),
+            ("A", "LYS", "CE", "C", -1.5),
+            ("B", "ASP", "OD1", "O", 3.0),
+            ("B", "ASP", "CG", "C", 3.8),
+        ];
+        for (serial, (chain, residue, name, element, x)) in atoms.iter().enumerate() {
+            model.add_atom(
+                Atom::new(
+                    false,
+                    serial + 1,
+                    *name,
+                    *x,
+                    0.0,
+                    0.0,
+                    1.0,
+                    0.0,
+                    *element,
+                    0,
+                )
+                .unwrap(),
+                *chain,
+                (1, None),
+                (*residue, None),
+            );
+        }
+        // A water in the second chain hydrogen bonded to the lysine is not part of the interface
+        model.add_atom(
+            Atom::new(true, 5, "O", 0.5, 2.8, 0.0, 1.0, 0.0, "O", 0).unwrap(),
+            "B",
+            (2, None),
+            ("HOH", None),
+        );
+        let interface = Interface::new(model.chain(0).unwrap(), model.chain(1).unwrap()).unwrap();
+        assert_eq!(interface.salt_bridges().len(), 1);
+        assert_eq!(interface.salt_bridges()[0].0.atom().name(), "NZ");
+        assert_eq!(interface.hydrogen_bonds().len(), 1);
+        assert_eq!(interface.contacts().polar(), 1);
+        assert_eq!(interface.contacts().mixed(), 1);
+        assert_eq!(interface.contacts().apolar(), 0);
+        assert_eq!(interface.first_residues().len(), 1);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::structs::*;
+
+/// A small molecule as read from an MDL Molfile or SDF file, see [`crate::ReadOptions::read_sdf`].
+/// It consists of a single hetero [`Residue`] containing all atoms, the bonds between these atoms
+/// (including the bond order), and the data items of the SDF record. It can be placed in a
+/// structure with [`PDB::add_ligand`].
+#[derive(Debug)]
+pub struct Ligand {
+    /// The name of the molecule, as given on the first line of the molfile
+    name: String,
+    /// The residue containing the atoms
+    residue: Residue,
+    /// The bonds between the atoms, stored with the atom counters
+    bonds: Vec<(usize, usize, Bond)>,
+    /// The data items (`> <name>`) of the SDF record
+    properties: Vec<(String, String)>,
+}
+
+impl Ligand {
+    /// Create a new ligand, the bonds are given as the indices of the atoms in the residue
+    pub(crate) fn new(
+        name: impl Into<String>,
+        residue: Residue,
+        bonds: &[(usize, usize, Bond)],
+        properties: Vec<(String, String)>,
+    ) -> Self {
+        let counters: Vec<usize> = residue.atoms().map(Atom::counter).collect();
+        Ligand {
+            name: name.into(),
+            bonds: bonds
+                .iter()
+                .filter_map(|(a, b, bond)| Some((*counters.get(*a)?, *counters.get(*b)?, *bond)))
+                .collect(),
+            residue,
+            properties,
+        }
+    }
+
+    /// Get the name of the molecule, as given on the first line of the molfile.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the residue containing all atoms of this molecule.
+    pub const fn residue(&self) -> &Residue {
+        &self.residue
+    }
+
+    /// Get the residue containing all atoms of this molecule mutably, this can for example be
+    /// used to change the residue name or serial number before adding it to a structure.
+    /// Bonds to atoms that are removed from the residue are ignored.
+    pub fn residue_mut(&mut self) -> &mut Residue {
+        &mut self.residue
+    }
+
+    /// Get the bonds between the atoms of this molecule.
+    pub fn bonds(&self) -> impl DoubleEndedIterator<Item = (&Atom, &Atom, Bond)> + '_ {
+        self.bonds.iter().filter_map(move |(a, b, bond)| {
+            Some((
+                self.residue.atoms().find(|atom| atom.counter() == *a)?,
+                self.residue.atoms().find(|atom| atom.counter() == *b)?,
+                *bond,
+            ))
+        })
+    }
+
+    /// Get the data items of the SDF record, as (name, value) pairs.
+    pub fn properties(&self) -> &[(String, String)] {
+        &self.properties
+    }
+
+    /// Get the value of the data item with the given name, if present.
+    pub fn property(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(key, _)| key == name.as_ref())
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Split this ligand into its residue and its bonds (as atom counters).
+    pub(crate) fn into_parts(self) -> (Residue, Vec<(usize, usize, Bond)>) {
+        let counters: Vec<usize> = self.residue.atoms().map(Atom::counter).collect();
+        let bonds = self
+            .bonds
+            .into_iter()
+            .filter(|(a, b, _)| counters.contains(a) && counters.contains(b))
+            .collect();
+        (self.residue, bonds)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ligand() {
+        let mut conformer = Conformer::new("LIG", None, None).unwrap();
+        conformer.add_atom(Atom::new(true, 1, "C1", 0.0, 0.0, 0.0, 1.0, 0.0, "C", 0).unwrap());
+        conformer.add_atom(Atom::new(true, 2, "O1", 1.2, 0.0, 0.0, 1.0, 0.0, "O", 0).unwrap());
+        let residue = Residue::new(1, None, Some(conformer)).unwrap();
+        let mut ligand = Ligand::new(
+            "formaldehyde",
+            residue,
+            &[(0, 1, Bond::Double), (0, 5, Bond::Covalent)],
+            vec![("ID".to_string(), "42".to_string())],
+        );
+        assert_eq!(ligand.name(), "formaldehyde");
+        assert_eq!(ligand.property("ID"), Some("42"));
+        assert_eq!(ligand.bonds().count(), 1);
+        let (a, b, bond) = ligand.bonds().next().unwrap();
+        assert_eq!((a.name(), b.name(), bond), ("C1", "O1", Bond::Double));
+
+        ligand.residue_mut().remove_atoms_by(|a| a.name() == "O1");
+        assert_eq!(ligand.bonds().count(), 0);
+        assert!(ligand.into_parts().1.is_empty());
+    }
+}
@@ -3,15 +3,28 @@ mod atom;
 mod bond;
 mod chain;
 mod conformer;
+mod correspondence;
 mod database_reference;
+mod density_map;
+mod dssp;
 mod elements;
 mod helper;
 mod hierarchy;
+mod hydrogen_bond;
+mod interface;
+mod ligand;
 mod model;
 mod mtrix;
+mod pae;
 mod pdb;
+mod pose_energies;
 mod residue;
+mod sasa;
 mod search;
+mod similarity;
+mod structural_alignment;
+mod structure_factor;
+mod superposition;
 mod symmetry;
 mod unit_cell;
 
@@ -19,14 +32,27 @@ pub use atom::Atom;
 pub use bond::Bond;
 pub use chain::Chain;
 pub use conformer::Conformer;
+pub use correspondence::{ChainCorrespondence, Correspondence};
 pub use database_reference::*;
-pub use elements::{AtomicRadius, Element};
+pub use density_map::{DensityMap, MapSummary, ResidueMapValues};
+pub use dssp::SecondaryStructure;
+pub use elements::{AtomicRadius, Element, ScatteringFactor};
 pub use helper::*;
 pub use hierarchy::*;
+pub use hydrogen_bond::{HydrogenBond, HydrogenBondCriteria};
+pub use interface::{ContactCounts, Interface};
+pub use ligand::Ligand;
 pub use model::Model;
 pub use mtrix::MtriX;
+pub use pae::PredictedAlignedError;
 pub use pdb::PDB;
+pub use pose_energies::PoseEnergies;
 pub use residue::Residue;
+pub use sasa::Sasa;
 pub use search::*;
+pub use similarity::{GdtScore, Lddt, TmScore};
+pub use structural_alignment::StructuralAlignment;
+pub use structure_factor::StructureFactor;
+pub use superposition::Superposition;
 pub use symmetry::Symmetry;
 pub use unit_cell::UnitCell;
@@ -324,6 +324,28 @@ impl<'a> Model {
         self.chains().flat_map(Chain::residues)
     }
 
+    /// Assign the secondary structure of all Residues in this Model with DSSP, based on the
+    /// backbone hydrogen bonds and geometry. Returns the secondary structure for every Residue
+    /// in the order of [`Self::residues`], Residues without N, CA, C, and O atoms are coil.
+    /// See [`SecondaryStructure`].
+    pub fn dssp(&self) -> Vec<SecondaryStructure> {
+        super::dssp::assign(self)
+    }
+
+    /// Calculate the solvent accessible surface area of this Model with the Shrake-Rupley
+    /// algorithm, using the given probe radius in Å (1.4 Å is the usual value for water).
+    /// See [`Sasa`].
+    pub fn sasa(&self, probe_radius: f64) -> Sasa<'_> {
+        Sasa::new(self, probe_radius)
+    }
+
+    /// Analyse the interfaces between all pairs of Chains in this Model that are in contact,
+    /// giving the interface residues, buried surface area, contacts, hydrogen bonds, and salt
+    /// bridges. See [`Interface`].
+    pub fn interfaces(&self) -> Vec<Interface<'_>> {
+        super::interface::interfaces(self)
+    }
+
     /// Get a parallel iterator of references to Residues making up this Model.
     #[doc_cfg(feature = "rayon")]
     pub fn par_residues(&self) -> impl ParallelIterator<Item = &Residue> + '_ {
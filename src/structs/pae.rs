@@ -0,0 +1,137 @@
+/// The predicted aligned error (PAE) of a predicted structure, as given by AlphaFold. The value
+/// at (i, j) is the expected position error in Å of residue j when the predicted and true
+/// structures are aligned on residue i. The residues are numbered by their index in the
+/// structure, so index 0 is the first Residue of the first Model. See
+/// [`crate::ReadOptions::read_pae`] to read it from a JSON file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictedAlignedError {
+    /// The number of residues
+    size: usize,
+    /// The values, with the aligned residue as rows
+    values: Vec<f64>,
+    /// The maximal possible value, if given
+    max: Option<f64>,
+}
+
+impl PredictedAlignedError {
+    /// Create a new predicted aligned error matrix from the rows of values, with the aligned
+    /// residue as rows. Returns `None` if the matrix is not square.
+    #[must_use]
+    pub fn new(rows: Vec<Vec<f64>>, max: Option<f64>) -> Option<Self> {
+        let size = rows.len();
+        rows.iter()
+            .all(|row| row.len() == size)
+            .then(|| PredictedAlignedError {
+                size,
+                values: rows.into_iter().flatten().collect(),
+                max,
+            })
+    }
+
+    /// Get the number of residues
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get the maximal possible value, if given in the file
+    #[must_use]
+    pub const fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    /// Get the expected position error of the residue with index `residue` when aligned on the
+    /// residue with index `aligned`, returns `None` if either is out of range.
+    #[must_use]
+    pub fn get(&self, aligned: usize, residue: usize) -> Option<f64> {
+        (aligned < self.size && residue < self.size)
+            .then(|| self.values[aligned * self.size + residue])
+    }
+
+    /// Get the mean error between two groups of residues (given as indices), in both directions.
+    /// This can be used to judge the confidence in the relative placement of two domains or
+    /// chains. Returns `None` if either group is empty or contains an index out of range.
+    #[must_use]
+    pub fn mean_between(&self, first: &[usize], second: &[usize]) -> Option<f64> {
+        if first.is_empty() || second.is_empty() {
+            return None;
+        }
+        let mut sum = 0.0;
+        for a in first {
+            for b in second {
+                sum += self.get(*a, *b)? + self.get(*b, *a)?;
+            }
+        }
+        #[allow(clippy::cast_precision_loss)]
+        Some(sum / (2 * first.len() * second.len()) as f64)
+    }
+
+    /// Split the residues in domains that are confidently placed relative to each other. Two
+    /// residues are linked if the error is below `max_error` in both directions, the domains are
+    /// the groups of (indirectly) linked residues with at least `min_size` residues. The domains
+    /// are sorted on their first residue and contain the residue indices in order.
+    #[must_use]
+    pub fn domains(&self, max_error: f64, min_size: usize) -> Vec<Vec<usize>> {
+        // Union find with path halving
+        let mut parent: Vec<usize> = (0..self.size).collect();
+        let find = |parent: &mut Vec<usize>, mut index: usize| {
+            while parent[index] != index {
+                parent[index] = parent[parent[index]];
+                index = parent[index];
+            }
+            index
+        };
+        for a in 0..self.size {
+            for b in (a + 1)..self.size {
+                if self.values[a * self.size + b] < max_error
+                    && self.values[b * self.size + a] < max_error
+                {
+                    let root_a = find(&mut parent, a);
+                    let root_b = find(&mut parent, b);
+                    parent[root_a.max(root_b)] = root_a.min(root_b);
+                }
+            }
+        }
+        let mut domains: Vec<Vec<usize>> = Vec::new();
+        let mut domain_of_root: Vec<Option<usize>> = vec![None; self.size];
+        for index in 0..self.size {
+            let root = find(&mut parent, index);
+            if let Some(domain) = domain_of_root[root] {
+                domains[domain].push(index);
+            } else {
+                domain_of_root[root] = Some(domains.len());
+                domains.push(vec![index]);
+            }
+        }
+        domains.retain(|domain| domain.len() >= min_size);
+        domains
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domains() {
+        let size = 6;
+        let rows = (0..size)
+            .map(|a| {
+                (0..size)
+                    .map(|b| if (a < 4) == (b < 4) { 1.0 } else { 20.0 })
+                    .collect()
+            })
+            .collect();
+        let pae = PredictedAlignedError::new(rows, Some(31.75)).unwrap();
+        assert_eq!(pae.size(), 6);
+        assert_eq!(pae.get(0, 5), Some(20.0));
+        assert_eq!(pae.get(0, 6), None);
+        assert_eq!(pae.domains(5.0, 1), vec![vec![0, 1, 2, 3], vec![4, 5]]);
+        assert_eq!(pae.domains(5.0, 3), vec![vec![0, 1, 2, 3]]);
+        assert_eq!(pae.domains(25.0, 1), vec![vec![0, 1, 2, 3, 4, 5]]);
+        assert_eq!(pae.mean_between(&[0, 1], &[4]), Some(20.0));
+        assert_eq!(pae.mean_between(&[0, 1], &[]), None);
+        assert!(PredictedAlignedError::new(vec![vec![1.0, 2.0]], None).is_none());
+    }
+}
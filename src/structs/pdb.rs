@@ -53,6 +53,10 @@ pub struct PDB {
     models: Vec<Model>,
     /// Bonds in this PDB.
     bonds: Vec<(usize, usize, Bond)>,
+    /// The global confidence metrics of a predicted structure, containing the name and value.
+    confidence_metrics: Vec<(String, f64)>,
+    /// The pose energies table of a Rosetta model, the energies per residue are stored on the Residues.
+    pose_energies: Option<PoseEnergies>,
 }
 
 /// # Creators
@@ -70,6 +74,8 @@ impl PDB {
             symmetry: None,
             models: Vec::new(),
             bonds: Vec::new(),
+            confidence_metrics: Vec::new(),
+            pose_energies: None,
         }
     }
 }
@@ -195,6 +201,79 @@ impl PDB {
     }
 }
 
+/// # Confidence
+/// Functionality for working with the confidence of predicted structures, like those from
+/// AlphaFold. The per residue confidence is stored on the Residues, see [`Residue::confidence`].
+impl PDB {
+    /// Get an iterator of references to the global confidence metrics, containing the name and
+    /// value. These are read from the `_ma_qa_metric_global` category of ModelCIF files.
+    pub fn confidence_metrics(&self) -> impl DoubleEndedIterator<Item = &(String, f64)> + '_ {
+        self.confidence_metrics.iter()
+    }
+
+    /// Get the value of the global confidence metric with the given name, if present.
+    pub fn confidence_metric(&self, name: impl AsRef<str>) -> Option<f64> {
+        let name = name.as_ref();
+        self.confidence_metrics
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Add a global confidence metric.
+    pub fn add_confidence_metric(&mut self, name: impl Into<String>, value: f64) {
+        self.confidence_metrics.push((name.into(), value));
+    }
+
+    /// Set the confidence of all Residues to the mean B factor of their Atoms. Predicted
+    /// structures in the PDB format, like those from AlphaFold, store the pLDDT in the B factor
+    /// column. Residues without Atoms are left unchanged.
+    pub fn set_confidence_from_b_factors(&mut self) {
+        for residue in self.residues_mut() {
+            let (sum, count) = residue.atoms().fold((0.0, 0_usize), |(sum, count), atom| {
+                (sum + atom.b_factor(), count + 1)
+            });
+            if count > 0 {
+                #[allow(clippy::cast_precision_loss)]
+                residue.set_confidence(sum / count as f64);
+            }
+        }
+    }
+
+    /// Remove all Residues with a confidence below the given threshold, for example 70 to only
+    /// keep the confidently predicted parts of an AlphaFold model. Residues without a confidence
+    /// are kept.
+    pub fn remove_low_confidence_residues(&mut self, threshold: f64) {
+        self.remove_residues_by(|residue| {
+            residue
+                .confidence()
+                .map_or(false, |confidence| confidence < threshold)
+        });
+    }
+}
+
+/// # Energies
+/// Functionality for working with the pose energies table of Rosetta models.
+impl PDB {
+    /// Get the pose energies table of a Rosetta model, containing the score terms with their
+    /// weights and the totals for the whole pose. The energies per residue are stored on the
+    /// Residues of the first Model, see [`Residue::energies`].
+    pub const fn pose_energies(&self) -> Option<&PoseEnergies> {
+        self.pose_energies.as_ref()
+    }
+
+    /// Set the pose energies table, it is saved in PDB files together with the energies of the
+    /// Residues of the first Model.
+    pub fn set_pose_energies(&mut self, energies: PoseEnergies) {
+        self.pose_energies = Some(energies);
+    }
+
+    /// Remove the pose energies table, the energies of the Residues are left unchanged.
+    pub fn remove_pose_energies(&mut self) {
+        self.pose_energies = None;
+    }
+}
+
 impl<'a> PDB {
     /// Adds a Model to this PDB.
     pub fn add_model(&mut self, new_model: Model) {
@@ -949,6 +1028,103 @@ impl<'a> PDB {
         rstar::RTree::bulk_load(self.atoms_with_hierarchy().collect())
     }
 
+    /// Calculate the structure factors (Fcalc) of the atoms of the first model for the given
+    /// Miller indices, using the unit cell and the space group of this PDB (P 1 if no space group
+    /// is given). Every atom contributes the scattering factor of its element (see
+    /// [`Element::scattering_factor`]) weighted by its occupancy and damped by its anisotropic
+    /// temperature factors or, if these are not given, its B-factor. Atoms without an element are
+    /// skipped. Bulk solvent and anomalous scattering are not taken into account.
+    ///
+    /// ```no_run
+    /// use pdbtbx::*;
+    /// let (pdb, _errors) = ReadOptions::new().read("example-pdbs/1ubq.pdb").unwrap();
+    /// let factors = pdb.structure_factors(&[[1, 0, 0], [0, 2, 1]]).unwrap();
+    /// println!("{:.1} {:.1}", factors[0].amplitude(), factors[0].phase());
+    /// ```
+    ///
+    /// It returns `None` if the PDB has no unit cell or if the unit cell has no volume.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn structure_factors(&self, indices: &[[i32; 3]]) -> Option<Vec<StructureFactor>> {
+        let fractional = self.unit_cell.as_ref()?.fractionalization_matrix()?;
+        let operations = self.symmetry.as_ref().map_or_else(
+            || vec![TransformationMatrix::identity()],
+            Symmetry::transformations,
+        );
+        // The reciprocal lattice vector in Cartesian coordinates (Å⁻¹) of the given indices
+        let reciprocal = |index: [f64; 3]| {
+            [0, 1, 2].map(|column| {
+                (0..3)
+                    .map(|row| index[row] * fractional[row][column])
+                    .sum::<f64>()
+            })
+        };
+        let tau = 2.0 * std::f64::consts::PI;
+        let atoms: Vec<_> = self
+            .model(0)
+            .into_iter()
+            .flat_map(Model::atoms)
+            .filter_map(|atom| {
+                let element = atom.element()?;
+                let (x, y, z) = atom.pos();
+                let position =
+                    fractional.map(|row| row[2].mul_add(z, row[0].mul_add(x, row[1] * y)));
+                Some((
+                    position,
+                    element.scattering_factor(),
+                    atom.occupancy(),
+                    atom.anisotropic_temperature_factors(),
+                    atom.b_factor(),
+                ))
+            })
+            .collect();
+
+        Some(
+            indices
+                .iter()
+                .map(|index| {
+                    let h = index.map(f64::from);
+                    let length_squared: f64 = reciprocal(h).iter().map(|v| v * v).sum();
+                    let stol_squared = length_squared / 4.0;
+                    let (mut real, mut imaginary) = (0.0, 0.0);
+                    for operation in &operations {
+                        // h . (R x + t) = (R^T h) . x + h . t
+                        let matrix = operation.matrix();
+                        let rotated = [0, 1, 2].map(|column| {
+                            (0..3).map(|row| h[row] * matrix[row][column]).sum::<f64>()
+                        });
+                        let shift: f64 = (0..3).map(|row| h[row] * matrix[row][3]).sum();
+                        let rotated_reciprocal = reciprocal(rotated);
+                        for (position, factor, occupancy, anisotropic, b_factor) in &atoms {
+                            let damping = anisotropic.map_or_else(
+                                || (-b_factor * stol_squared).exp(),
+                                |u| {
+                                    let mut exponent = 0.0;
+                                    for (row, a) in u.iter().zip(rotated_reciprocal) {
+                                        for (value, b) in row.iter().zip(rotated_reciprocal) {
+                                            exponent += a * value * b;
+                                        }
+                                    }
+                                    (-2.0 * std::f64::consts::PI.powi(2) * exponent).exp()
+                                },
+                            );
+                            let amplitude = factor.evaluate(stol_squared) * occupancy * damping;
+                            let phase = tau
+                                * (rotated
+                                    .iter()
+                                    .zip(position)
+                                    .map(|(a, b)| a * b)
+                                    .sum::<f64>()
+                                    + shift);
+                            real += amplitude * phase.cos();
+                            imaginary += amplitude * phase.sin();
+                        }
+                    }
+                    StructureFactor::new(*index, 1.0 / length_squared.sqrt(), real, imaginary)
+                })
+                .collect(),
+        )
+    }
+
     /// Finds the square bounding box around the PDB. The first tuple
     /// is the bottom left point, lowest value for all dimensions
     /// for all points. The second tuple is the top right point, the
@@ -1014,12 +1190,86 @@ impl<'a> PDB {
         Some(())
     }
 
+    /// Detect the hydrogen bonds in all Models of this PDB with the given criteria, for amino
+    /// acids, nucleotides, and waters. Explicit hydrogens are used if the donor has any,
+    /// otherwise the geometry of the donor and acceptor atoms is used, see
+    /// [`HydrogenBondCriteria`]. Bonds are only detected within a Model.
+    pub fn hydrogen_bonds(&self, criteria: &HydrogenBondCriteria) -> Vec<HydrogenBond<'_>> {
+        super::hydrogen_bond::hydrogen_bonds(self, criteria)
+    }
+
+    /// Detect the hydrogen bonds with the given criteria (see [`PDB::hydrogen_bonds`]) and add
+    /// them to the list of bonds in this PDB as [`Bond::Hydrogen`] from the donor to the
+    /// acceptor. Hydrogen bonds that are already present are not added again. Returns the number
+    /// of added bonds.
+    pub fn add_hydrogen_bonds(&mut self, criteria: &HydrogenBondCriteria) -> usize {
+        let found: Vec<_> = self
+            .hydrogen_bonds(criteria)
+            .iter()
+            .map(|bond| {
+                (
+                    bond.donor().atom().counter(),
+                    bond.acceptor().atom().counter(),
+                )
+            })
+            .collect();
+        let mut added = 0;
+        for (donor, acceptor) in found {
+            if !self.bonds.contains(&(donor, acceptor, Bond::Hydrogen)) {
+                self.bonds.push((donor, acceptor, Bond::Hydrogen));
+                added += 1;
+            }
+        }
+        added
+    }
+
     /// Add a bond of the given type to the list of bonds in this PDB.
     /// The raw counters of the atoms are given.
     pub(crate) fn add_bond_counters(&mut self, atom1: usize, atom2: usize, bond: Bond) {
         self.bonds.push((atom1, atom2, bond));
     }
 
+    /// Add the given ligand to the Chain with the given id in the first Model, together with its
+    /// bonds. If there is no Model or no Chain with this id it is created. The residue of the
+    /// ligand is given the serial number following the last Residue in the Chain, and the atoms
+    /// are numbered following the highest atom serial number in the Model.
+    ///
+    /// ## Panics
+    /// It panics if the Chain ID contains any invalid characters.
+    pub fn add_ligand(&mut self, ligand: Ligand, chain_id: impl AsRef<str>) {
+        let (mut residue, bonds) = ligand.into_parts();
+        if self.models.is_empty() {
+            self.models.push(Model::new(1));
+        }
+        let model = &mut self.models[0];
+        let mut serial_number = model.atoms().map(Atom::serial_number).max().unwrap_or(0);
+        for atom in residue.atoms_mut() {
+            serial_number += 1;
+            atom.set_serial_number(serial_number);
+        }
+
+        let chain_id = chain_id.as_ref().trim();
+        let index = model.chains().position(|c| c.id() == chain_id);
+        let chain = if let Some(index) = index {
+            model.chain_mut(index).expect("Chain index out of bounds")
+        } else {
+            model.add_chain(Chain::new(chain_id).expect("Invalid characters in chain creation"));
+            model
+                .chains_mut()
+                .next_back()
+                .expect("Chain was just added")
+        };
+        residue.set_serial_number(
+            chain
+                .residues()
+                .map(Residue::serial_number)
+                .max()
+                .map_or(1, |n| n + 1),
+        );
+        chain.add_residue(residue);
+        self.bonds.extend(bonds);
+    }
+
     /// Returns a HashMap with the chains in contact within a given distance.
     ///
     /// # Arguments
@@ -1118,7 +1368,10 @@ mod tests {
         for entry in std::fs::read_dir(pdb_dir).unwrap() {
             let entry = entry.unwrap();
             let path = entry.path();
-            if path.extension().unwrap() != "pdb" {
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "pdb")
+            {
                 continue;
             }
             let (pdb, _) = ReadOptions::default()
@@ -0,0 +1,74 @@
+/// The pose energies table of a Rosetta model, containing the score terms with their weights and
+/// the totals for the whole pose. The energies per residue are stored on the Residues, see
+/// [`crate::Residue::energies`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoseEnergies {
+    /// The name of the table, normally the name of the decoy
+    name: Option<String>,
+    /// The score terms with their weight, the weight is `None` if not applicable (like for `total`)
+    terms: Vec<(String, Option<f64>)>,
+    /// The total of every score term for the whole pose
+    totals: Vec<f64>,
+}
+
+impl PoseEnergies {
+    /// Create a new pose energies table.
+    ///
+    /// ## Arguments
+    /// * `name` - the name of the table, normally the name of the decoy
+    /// * `terms` - the score terms with their weight, `None` if not applicable
+    /// * `totals` - the total of every score term for the whole pose, in the same order as the terms
+    ///
+    /// ## Fails
+    /// Returns `None` if the number of terms and totals differ.
+    #[must_use]
+    pub fn new(
+        name: Option<String>,
+        terms: Vec<(String, Option<f64>)>,
+        totals: Vec<f64>,
+    ) -> Option<Self> {
+        (terms.len() == totals.len()).then_some(PoseEnergies {
+            name,
+            terms,
+            totals,
+        })
+    }
+
+    /// Get the name of the table, normally the name of the decoy
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Get an iterator over the score term names, in the order of the table
+    pub fn terms(&self) -> impl DoubleEndedIterator<Item = &str> + '_ {
+        self.terms.iter().map(|(term, _)| term.as_str())
+    }
+
+    /// Get the weight of the given score term, if present and applicable
+    #[must_use]
+    pub fn weight(&self, term: &str) -> Option<f64> {
+        self.terms
+            .iter()
+            .find(|(t, _)| t == term)
+            .and_then(|(_, weight)| *weight)
+    }
+
+    /// Get the total of the given score term for the whole pose, if present
+    #[must_use]
+    pub fn total(&self, term: &str) -> Option<f64> {
+        self.terms
+            .iter()
+            .position(|(t, _)| t == term)
+            .map(|index| self.totals[index])
+    }
+
+    /// Get an iterator over the score terms with their total for the whole pose
+    pub fn totals(&self) -> impl DoubleEndedIterator<Item = (&str, f64)> + '_ {
+        self.terms
+            .iter()
+            .zip(&self.totals)
+            .map(|((term, _), total)| (term.as_str(), *total))
+    }
+}
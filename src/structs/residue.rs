@@ -8,7 +8,7 @@ use std::cmp::Ordering;
 use std::fmt;
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 /// A Residue containing multiple Conformers
 pub struct Residue {
     /// The serial number of this Residue, can be negative as that is used sometimes. See <https://proteopedia.org/wiki/index.php/Unusual_sequence_numbering>.
@@ -17,6 +17,10 @@ pub struct Residue {
     insertion_code: Option<String>,
     /// The list of conformers making up this Residue
     conformers: Vec<Conformer>,
+    /// The confidence of a predicted structure for this Residue, like the pLDDT (0-100) of AlphaFold
+    confidence: Option<f64>,
+    /// The (Rosetta) energy of this Residue per score term, containing the name and value
+    energies: Vec<(String, f64)>,
 }
 
 impl<'a> Residue {
@@ -39,6 +43,8 @@ impl<'a> Residue {
             serial_number: number,
             insertion_code: None,
             conformers: Vec::new(),
+            confidence: None,
+            energies: Vec::new(),
         };
         if let Some(ic) = insertion_code {
             if !res.set_insertion_code(ic) {
@@ -83,6 +89,64 @@ impl<'a> Residue {
         self.insertion_code = None;
     }
 
+    /// Get the confidence of a predicted structure for this Residue, like the pLDDT (0-100) of
+    /// AlphaFold. This is read from the local QA metrics of ModelCIF files, see
+    /// [`PDB::set_confidence_from_b_factors`] for PDB files.
+    #[must_use]
+    pub const fn confidence(&self) -> Option<f64> {
+        self.confidence
+    }
+
+    /// Set the confidence of a predicted structure for this Residue.
+    /// Fails and returns false if the `new_confidence` is not finite
+    pub fn set_confidence(&mut self, new_confidence: f64) -> bool {
+        new_confidence
+            .is_finite()
+            .then(|| self.confidence = Some(new_confidence))
+            .is_some()
+    }
+
+    /// Set the confidence of the Residue to None.
+    pub fn remove_confidence(&mut self) {
+        self.confidence = None;
+    }
+
+    /// Get an iterator of references to the energies of this Residue per score term, containing
+    /// the name and value. These are read from the pose energies table of Rosetta models, see
+    /// [`PDB::pose_energies`] for the terms and totals of the whole pose.
+    pub fn energies(&self) -> impl DoubleEndedIterator<Item = &(String, f64)> + '_ {
+        self.energies.iter()
+    }
+
+    /// Get the energy of this Residue for the given score term, if present.
+    #[must_use]
+    pub fn energy(&self, term: &str) -> Option<f64> {
+        self.energies
+            .iter()
+            .find(|(t, _)| t == term)
+            .map(|(_, value)| *value)
+    }
+
+    /// Set the energy of this Residue for the given score term, replacing the previous value.
+    /// Fails and returns false if the `value` is not finite
+    pub fn set_energy(&mut self, term: impl Into<String>, value: f64) -> bool {
+        if !value.is_finite() {
+            return false;
+        }
+        let term = term.into();
+        if let Some(energy) = self.energies.iter_mut().find(|(t, _)| *t == term) {
+            energy.1 = value;
+        } else {
+            self.energies.push((term, value));
+        }
+        true
+    }
+
+    /// Remove all energies of this Residue.
+    pub fn remove_energies(&mut self) {
+        self.energies.clear();
+    }
+
     /// Returns the uniquely identifying construct for this Residue,
     /// consisting of the serial number and the insertion code.
     #[must_use]
@@ -512,6 +576,20 @@ impl fmt::Display for Residue {
     }
 }
 
+impl PartialEq for Residue {
+    fn eq(&self, other: &Self) -> bool {
+        self.serial_number == other.serial_number
+            && self.insertion_code == other.insertion_code
+            && self.conformers == other.conformers
+            && self.confidence == other.confidence
+            && self.energies == other.energies
+    }
+}
+
+/// As the confidence and energies can only be set to finite values Residue satisfies the
+/// properties needed for Eq while having f64 values.
+impl Eq for Residue {}
+
 impl PartialOrd for Residue {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.id().cmp(&other.id()))
@@ -0,0 +1,311 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::reference_tables;
+use crate::structs::*;
+
+/// The number of points on the sphere around every atom used to sample the surface
+const POINTS: usize = 960;
+
+/// The position and the radius (including the probe radius) of an atom
+pub(crate) type Sphere = ((f64, f64, f64), f64);
+
+/// The solvent accessible surface area (SASA) of the atoms in a set of chains, calculated with
+/// the Shrake-Rupley algorithm (Shrake, A., & Rupley, J. A. (1973). Environment and exposure to
+/// solvent of protein atoms. Lysozyme and insulin. Journal of Molecular Biology, 79(2),
+/// 351–371). Every atom is represented by a sphere with the van der Waals radius of its element
+/// (see [`AtomicRadius`]) increased by the probe radius, and the fraction of the points on this
+/// sphere not buried in any other sphere gives the accessible area. Only the first conformer of
+/// every residue is used, and hydrogens, waters, and atoms without a known van der Waals radius
+/// are ignored. With the `rstar` feature the neighbouring atoms are found with an R*-tree, with
+/// the `rayon` feature the atoms are handled in parallel.
+#[derive(Debug, Clone)]
+pub struct Sasa<'a> {
+    /// The accessible area in Å² of every atom
+    atoms: Vec<(AtomConformerResidueChain<'a>, f64)>,
+    /// The accessible area in Å² of every residue
+    residues: Vec<(&'a Residue, f64)>,
+    /// The accessible area in Å² of every chain
+    chains: Vec<(&'a Chain, f64)>,
+    /// The sphere of every atom
+    spheres: Vec<Sphere>,
+    /// The index of the residue of every atom
+    residue_indices: Vec<usize>,
+    /// The probe radius in Å
+    probe_radius: f64,
+}
+
+impl<'a> Sasa<'a> {
+    /// Calculate the solvent accessible surface area of all chains in the given model, with the
+    /// given probe radius in Å (1.4 Å is the usual value for water).
+    #[must_use]
+    pub fn new(model: &'a Model, probe_radius: f64) -> Self {
+        Self::from_chains(model.chains(), probe_radius)
+    }
+
+    /// Calculate the solvent accessible surface area of the given chains, with the given probe
+    /// radius in Å, as if no other atoms were present. This can be used to find the area of a
+    /// chain in isolation.
+    #[must_use]
+    pub fn from_chains(chains: impl IntoIterator<Item = &'a Chain>, probe_radius: f64) -> Self {
+        let mut atoms = Vec::new();
+        let mut spheres = Vec::new();
+        let mut residue_indices = Vec::new();
+        let mut residue_ranges = Vec::new();
+        let mut chain_ranges = Vec::new();
+        for chain in chains {
+            let chain_start = residue_ranges.len();
+            for residue in chain.residues() {
+                if residue.name() == Some("HOH") {
+                    continue;
+                }
+                let start = atoms.len();
+                if let Some(conformer) = residue.conformers().next() {
+                    for atom in conformer.atoms() {
+                        let radius = match atom.element() {
+                            Some(Element::H) | None => continue,
+                            Some(element) => element.atomic_radius().van_der_waals,
+                        };
+                        if let Some(radius) = radius {
+                            atoms.push(AtomConformerResidueChain::new(
+                                atom, conformer, residue, chain,
+                            ));
+                            spheres.push((atom.pos(), radius + probe_radius));
+                            residue_indices.push(residue_ranges.len());
+                        }
+                    }
+                }
+                residue_ranges.push((residue, start..atoms.len()));
+            }
+            chain_ranges.push((chain, chain_start..residue_ranges.len()));
+        }
+
+        let areas = surface_areas(&spheres);
+        let residues: Vec<_> = residue_ranges
+            .into_iter()
+            .map(|(residue, range)| (residue, areas[range].iter().sum()))
+            .collect();
+        let chains = chain_ranges
+            .into_iter()
+            .map(|(chain, range)| (chain, residues[range].iter().map(|(_, a)| a).sum()))
+            .collect();
+        Sasa {
+            atoms: atoms.into_iter().zip(areas).collect(),
+            residues,
+            chains,
+            spheres,
+            residue_indices,
+            probe_radius,
+        }
+    }
+
+    /// Get the probe radius in Å
+    #[must_use]
+    pub const fn probe_radius(&self) -> f64 {
+        self.probe_radius
+    }
+
+    /// Get the total accessible area in Å²
+    #[must_use]
+    pub fn total(&self) -> f64 {
+        self.chains.iter().map(|(_, area)| area).sum()
+    }
+
+    /// Get the accessible area in Å² of every atom that was taken into account
+    #[must_use]
+    pub fn atoms(&self) -> &[(AtomConformerResidueChain<'a>, f64)] {
+        &self.atoms
+    }
+
+    /// Get the accessible area in Å² of every residue (excluding waters)
+    #[must_use]
+    pub fn residues(&self) -> &[(&'a Residue, f64)] {
+        &self.residues
+    }
+
+    /// Get the relative accessibility of every residue (excluding waters), which is the
+    /// accessible area divided by the maximal accessible area for its amino acid (Tien et al.
+    /// 2013). This is `None` for residues that are not one of the 20 common amino acids.
+    pub fn relative_residues(&self) -> impl Iterator<Item = (&'a Residue, Option<f64>)> + '_ {
+        self.residues.iter().map(|(residue, area)| {
+            let maximum = residue.name().and_then(reference_tables::max_accessibility);
+            (*residue, maximum.map(|maximum| area / maximum))
+        })
+    }
+
+    /// Get the accessible area in Å² of every chain
+    #[must_use]
+    pub fn chains(&self) -> &[(&'a Chain, f64)] {
+        &self.chains
+    }
+
+    /// Get the sphere of every atom, in the same order as [`Self::atoms`]
+    pub(crate) fn spheres(&self) -> &[Sphere] {
+        &self.spheres
+    }
+
+    /// Get the index into [`Self::residues`] of the residue of the atom with the given index
+    pub(crate) fn residue_index(&self, atom: usize) -> usize {
+        self.residue_indices[atom]
+    }
+}
+
+/// Get the accessible area in Å² of every given sphere (with a radius including the probe
+/// radius) with the Shrake-Rupley algorithm
+pub(crate) fn surface_areas(spheres: &[Sphere]) -> Vec<f64> {
+    let all: Vec<usize> = (0..spheres.len()).collect();
+    surface_areas_of(spheres, &all)
+}
+
+/// Get the accessible area in Å² of the selected spheres (given by index), taking all given
+/// spheres into account as neighbours
+pub(crate) fn surface_areas_of(spheres: &[Sphere], selection: &[usize]) -> Vec<f64> {
+    let directions = sphere_points();
+    let selected: Vec<Sphere> = selection.iter().map(|index| spheres[*index]).collect();
+    let neighbours: Vec<Vec<usize>> = overlapping(&selected, spheres)
+        .into_iter()
+        .zip(selection)
+        .map(|(close, index)| close.into_iter().filter(|other| other != index).collect())
+        .collect();
+    let area = |(index, close): (&usize, &Vec<usize>)| -> f64 {
+        let (center, radius) = spheres[*index];
+        let buried = |point: (f64, f64, f64), other: usize| {
+            let (position, other_radius) = spheres[other];
+            (point.0 - position.0).powi(2)
+                + (point.1 - position.1).powi(2)
+                + (point.2 - position.2).powi(2)
+                < other_radius * other_radius
+        };
+        // The last neighbour burying a point is likely to also bury the next point
+        let mut last = 0;
+        let accessible = directions
+            .iter()
+            .filter(|direction| {
+                let point = (
+                    radius.mul_add(direction[0], center.0),
+                    radius.mul_add(direction[1], center.1),
+                    radius.mul_add(direction[2], center.2),
+                );
+                if close.get(last).map_or(false, |other| buried(point, *other)) {
+                    return false;
+                }
+                if let Some(found) = close.iter().position(|other| buried(point, *other)) {
+                    last = found;
+                    false
+                } else {
+                    true
+                }
+            })
+            .count();
+        #[allow(clippy::cast_precision_loss)]
+        let fraction = accessible as f64 / POINTS as f64;
+        4.0 * std::f64::consts::PI * radius * radius * fraction
+    };
+    #[cfg(feature = "rayon")]
+    let areas = selection.par_iter().zip(&neighbours).map(area).collect();
+    #[cfg(not(feature = "rayon"))]
+    let areas = selection.iter().zip(&neighbours).map(area).collect();
+    areas
+}
+
+/// Get the indices of the target spheres overlapping with every query sphere, using an R*-tree
+#[cfg(feature = "rstar")]
+pub(crate) fn overlapping(query: &[Sphere], targets: &[Sphere]) -> Vec<Vec<usize>> {
+    use rstar::primitives::GeomWithData;
+    let tree = rstar::RTree::bulk_load(
+        targets
+            .iter()
+            .enumerate()
+            .map(|(index, (p, _))| GeomWithData::new([p.0, p.1, p.2], index))
+            .collect(),
+    );
+    let largest = targets.iter().map(|(_, r)| *r).fold(0.0, f64::max);
+    query
+        .iter()
+        .map(|sphere| {
+            let (p, radius) = sphere;
+            tree.locate_within_distance([p.0, p.1, p.2], (radius + largest).powi(2))
+                .filter(|other| overlap(sphere, &targets[other.data]))
+                .map(|other| other.data)
+                .collect()
+        })
+        .collect()
+}
+
+/// Get the indices of the target spheres overlapping with every query sphere, by checking all
+/// pairs
+#[cfg(not(feature = "rstar"))]
+pub(crate) fn overlapping(query: &[Sphere], targets: &[Sphere]) -> Vec<Vec<usize>> {
+    query
+        .iter()
+        .map(|sphere| {
+            (0..targets.len())
+                .filter(|other| overlap(sphere, &targets[*other]))
+                .collect()
+        })
+        .collect()
+}
+
+/// Determine if the two given spheres overlap
+fn overlap((pa, ra): &Sphere, (pb, rb): &Sphere) -> bool {
+    (pa.0 - pb.0).powi(2) + (pa.1 - pb.1).powi(2) + (pa.2 - pb.2).powi(2) < (ra + rb).powi(2)
+}
+
+/// Get evenly spread points on the unit sphere (a Fibonacci lattice)
+#[allow(clippy::cast_precision_loss)]
+fn sphere_points() -> Vec<[f64; 3]> {
+    let golden_angle = std::f64::consts::PI * (3.0 - 5.0_f64.sqrt());
+    (0..POINTS)
+        .map(|i| {
+            let z = 1.0 - (2.0 * i as f64 + 1.0) / POINTS as f64;
+            let radius = z.mul_add(-z, 1.0).sqrt();
+            let angle = golden_angle * i as f64;
+            [radius * angle.cos(), radius * angle.sin(), z]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn spheres() {
+        let areas = surface_areas(&[((0.0, 0.0, 0.0), 2.0)]);
+        assert!((areas[0] - 16.0 * PI).abs() < 1e-6);
+        // Two spheres half overlapping lose a cap of height 1.0 each
+        let areas = surface_areas(&[((0.0, 0.0, 0.0), 2.0), ((2.0, 0.0, 0.0), 2.0)]);
+        for area in areas {
+            assert!((area - 12.0 * PI).abs() < 0.2);
+        }
+    }
+
+    #[test]
+    fn ubiquitin() {
+        let (pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let model = pdb.model(0).unwrap();
+        let sasa = Sasa::new(model, 1.4);
+        assert_eq!(sasa.probe_radius(), 1.4);
+        assert_eq!(sasa.atoms().len(), 602);
+        assert_eq!(sasa.residues().len(), 76);
+        assert_eq!(sasa.chains().len(), 1);
+        let atoms: f64 = sasa.atoms().iter().map(|(_, a)| a).sum();
+        assert!((atoms - sasa.total()).abs() < 1e-6);
+        assert!(sasa.total() > 4000.0 && sasa.total() < 6500.0);
+        // The C-terminal glycines are exposed, the core residues are not
+        let relative: Vec<_> = sasa.relative_residues().map(|(_, r)| r.unwrap()).collect();
+        assert!(relative[75] > 0.8);
+        assert!(relative.iter().filter(|r| **r < 0.05).count() > 5);
+
+        let larger = Sasa::new(model, 2.0);
+        assert!(larger.total() > sasa.total());
+        let none = Sasa::from_chains(std::iter::empty(), 1.4);
+        assert_eq!(none.total(), 0.0);
+    }
+}
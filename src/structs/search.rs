@@ -331,6 +331,12 @@ impl ops::Not for Term {
     }
 }
 
+impl From<Term> for Search {
+    fn from(term: Term) -> Self {
+        Search::Single(term)
+    }
+}
+
 impl ops::BitAnd<Search> for Term {
     type Output = Search;
     fn bitand(self, rhs: Search) -> Self::Output {
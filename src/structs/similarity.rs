@@ -0,0 +1,472 @@
+use crate::structs::*;
+use crate::TransformationMatrix;
+use std::collections::HashSet;
+
+/// The cutoffs in Å used for GDT-TS
+const GDT_TS_CUTOFFS: [f64; 4] = [1.0, 2.0, 4.0, 8.0];
+/// The cutoffs in Å used for GDT-HA
+const GDT_HA_CUTOFFS: [f64; 4] = [0.5, 1.0, 2.0, 4.0];
+/// The inclusion radius in Å for atom pairs in the reference structure for lDDT
+const LDDT_INCLUSION_RADIUS: f64 = 15.0;
+/// The thresholds in Å for preserved distances for lDDT
+const LDDT_THRESHOLDS: [f64; 4] = [0.5, 1.0, 2.0, 4.0];
+/// The maximal number of iterations when refining a superposition
+const MAX_ITERATIONS: usize = 20;
+
+/// The TM-score of a model compared to a reference structure, see Zhang, Y., & Skolnick, J.
+/// (2004). Scoring function for automated assessment of protein structure template quality.
+/// Proteins, 57(4), 702–710. The optimal superposition is searched by superposing fragments of
+/// decreasing length and iteratively refining these superpositions on the residues close to
+/// the reference. The residues are represented by their CA atom (or C3' atom for nucleotides).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TmScore {
+    /// The TM-score
+    score: f64,
+    /// The contribution of every residue pair, before normalisation by the length
+    per_residue: Vec<f64>,
+    /// The distance scale d0 in Å
+    d0: f64,
+    /// The superposition of the model onto the reference giving the score
+    transformation: TransformationMatrix,
+}
+
+impl TmScore {
+    /// Calculate the TM-score for the given residue pairs (model, reference), as can be found
+    /// with [`Correspondence::residue_pairs`].
+    ///
+    /// ## Arguments
+    /// * `pairs` - the pairs of (model, reference) residues
+    /// * `length` - the length used to normalise the score, generally the number of residues in
+    ///   the reference structure
+    ///
+    /// ## Fails
+    /// Returns `None` if the length is zero or if there are no pairs with a CA (or C3') atom in
+    /// both residues.
+    #[must_use]
+    pub fn new(pairs: &[(&Residue, &Residue)], length: usize) -> Option<Self> {
+        if length == 0 {
+            return None;
+        }
+        let (model, reference, indices) = representatives(pairs);
+        #[allow(clippy::cast_precision_loss)]
+        let length = length as f64;
+        let d0 = tm_d0(length);
+        let contribution = |d: f64| 1.0 / (1.0 + (d / d0).powi(2));
+        let (transformation, distances) = search(
+            &model,
+            &reference,
+            d0.clamp(4.5, 8.0),
+            |distances: &[f64]| distances.iter().map(|d| contribution(*d)).sum(),
+        )?;
+        let mut per_residue = vec![0.0; pairs.len()];
+        for (index, distance) in indices.into_iter().zip(distances) {
+            per_residue[index] = contribution(distance);
+        }
+        Some(TmScore {
+            score: per_residue.iter().sum::<f64>() / length,
+            per_residue,
+            d0,
+            transformation,
+        })
+    }
+
+    /// Get the TM-score, between 0 and 1 where 1 is a perfect match
+    #[must_use]
+    pub const fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Get the contribution of every residue pair to the score (between 0 and 1), in the order
+    /// of the pairs, this is zero for residues without a CA (or C3') atom
+    #[must_use]
+    pub fn per_residue(&self) -> &[f64] {
+        &self.per_residue
+    }
+
+    /// Get the distance scale d0 in Å, which depends on the length
+    #[must_use]
+    pub const fn d0(&self) -> f64 {
+        self.d0
+    }
+
+    /// Get the transformation superposing the model onto the reference giving this score
+    #[must_use]
+    pub const fn transformation(&self) -> &TransformationMatrix {
+        &self.transformation
+    }
+}
+
+/// The global distance test (GDT) scores of a model compared to a reference structure. This
+/// is the fraction of the residues that can be superposed within a cutoff distance of the
+/// reference, averaged over four cutoffs. GDT-TS uses 1, 2, 4 and 8 Å, GDT-HA uses 0.5, 1, 2
+/// and 4 Å. The superposition is optimised separately for every cutoff. The residues are
+/// represented by their CA atom (or C3' atom for nucleotides).
+#[derive(Debug, Clone, PartialEq)]
+pub struct GdtScore {
+    /// The GDT-TS score
+    ts: f64,
+    /// The GDT-HA score
+    ha: f64,
+    /// The GDT-TS score for every residue pair
+    per_residue_ts: Vec<f64>,
+    /// The GDT-HA score for every residue pair
+    per_residue_ha: Vec<f64>,
+}
+
+impl GdtScore {
+    /// Calculate the GDT scores for the given residue pairs (model, reference), as can be found
+    /// with [`Correspondence::residue_pairs`].
+    ///
+    /// ## Arguments
+    /// * `pairs` - the pairs of (model, reference) residues
+    /// * `length` - the length used to normalise the scores, generally the number of residues in
+    ///   the reference structure
+    ///
+    /// ## Fails
+    /// Returns `None` if the length is zero or if there are no pairs with a CA (or C3') atom in
+    /// both residues.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(pairs: &[(&Residue, &Residue)], length: usize) -> Option<Self> {
+        if length == 0 {
+            return None;
+        }
+        let (model, reference, indices) = representatives(pairs);
+        let mut cutoffs: Vec<f64> = GDT_TS_CUTOFFS.into_iter().chain(GDT_HA_CUTOFFS).collect();
+        cutoffs.sort_by(f64::total_cmp);
+        cutoffs.dedup();
+        // The fraction of residues within every cutoff, and which residues are within it
+        let mut results = Vec::new();
+        for cutoff in cutoffs {
+            let (_, distances) = search(&model, &reference, cutoff, |distances: &[f64]| {
+                distances.iter().filter(|d| **d <= cutoff).count() as f64
+            })?;
+            let mut within = vec![false; pairs.len()];
+            for (index, distance) in indices.iter().zip(distances) {
+                within[*index] = distance <= cutoff;
+            }
+            let fraction = within.iter().filter(|w| **w).count() as f64 / length as f64;
+            results.push((cutoff, fraction, within));
+        }
+        let score = |selected: [f64; 4]| {
+            let selected: Vec<_> = results
+                .iter()
+                .filter(|(cutoff, _, _)| selected.contains(cutoff))
+                .collect();
+            let global = selected.iter().map(|(_, f, _)| f).sum::<f64>() / 4.0;
+            let per_residue = (0..pairs.len())
+                .map(|index| selected.iter().filter(|(_, _, w)| w[index]).count() as f64 / 4.0)
+                .collect();
+            (global, per_residue)
+        };
+        let (ts, per_residue_ts) = score(GDT_TS_CUTOFFS);
+        let (ha, per_residue_ha) = score(GDT_HA_CUTOFFS);
+        Some(GdtScore {
+            ts,
+            ha,
+            per_residue_ts,
+            per_residue_ha,
+        })
+    }
+
+    /// Get the GDT-TS score, between 0 and 1 where 1 is a perfect match
+    #[must_use]
+    pub const fn ts(&self) -> f64 {
+        self.ts
+    }
+
+    /// Get the GDT-HA score, between 0 and 1 where 1 is a perfect match
+    #[must_use]
+    pub const fn ha(&self) -> f64 {
+        self.ha
+    }
+
+    /// Get the GDT-TS score for every residue pair (the fraction of the cutoffs for which the
+    /// residue is within the cutoff), in the order of the pairs
+    #[must_use]
+    pub fn per_residue_ts(&self) -> &[f64] {
+        &self.per_residue_ts
+    }
+
+    /// Get the GDT-HA score for every residue pair (the fraction of the cutoffs for which the
+    /// residue is within the cutoff), in the order of the pairs
+    #[must_use]
+    pub fn per_residue_ha(&self) -> &[f64] {
+        &self.per_residue_ha
+    }
+}
+
+/// The local distance difference test (lDDT) score of a model compared to a reference
+/// structure, see Mariani, V., Biasini, M., Barbato, A., & Schwede, T. (2013). lDDT: a local
+/// superposition-free score for comparing protein structures and models using distance
+/// difference tests. Bioinformatics, 29(21), 2722–2728. All distances between heavy atoms of
+/// different residues within 15 Å in the reference are checked in the model, a distance is
+/// preserved if it differs less than 0.5, 1, 2 and 4 Å, and the score is the average
+/// fraction of preserved distances over these four thresholds. Atoms are matched by name,
+/// atoms missing in the model count as not preserved. Only the residues in the given pairs are
+/// taken into account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lddt {
+    /// The global lDDT score
+    score: f64,
+    /// The lDDT score for every residue pair
+    per_residue: Vec<Option<f64>>,
+}
+
+impl Lddt {
+    /// Calculate the lDDT score for the given residue pairs (model, reference), as can be found
+    /// with [`Correspondence::residue_pairs`].
+    ///
+    /// ## Fails
+    /// Returns `None` if there are no distances to check in the reference.
+    #[must_use]
+    pub fn new(pairs: &[(&Residue, &Residue)]) -> Option<Self> {
+        // The residue index, reference position, and model position (if present) of every atom
+        let mut atoms = Vec::new();
+        for (index, (model, reference)) in pairs.iter().enumerate() {
+            let mut names = HashSet::new();
+            for atom in reference.atoms() {
+                if atom.element() == Some(&Element::H) || !names.insert(atom.name()) {
+                    continue;
+                }
+                let position = model
+                    .atoms()
+                    .find(|a| a.name() == atom.name())
+                    .map(Atom::pos);
+                atoms.push((index, atom.pos(), position));
+            }
+        }
+
+        let mut total = vec![0_usize; pairs.len()];
+        let mut preserved = vec![0_usize; pairs.len()];
+        for (i, (first_index, first_reference, first_model)) in atoms.iter().enumerate() {
+            for (second_index, second_reference, second_model) in &atoms[i + 1..] {
+                if first_index == second_index {
+                    continue;
+                }
+                let reference = distance(*first_reference, *second_reference);
+                if reference >= LDDT_INCLUSION_RADIUS {
+                    continue;
+                }
+                let count = if let (Some(a), Some(b)) = (first_model, second_model) {
+                    let difference = (distance(*a, *b) - reference).abs();
+                    LDDT_THRESHOLDS.iter().filter(|t| difference < **t).count()
+                } else {
+                    0
+                };
+                for index in [*first_index, *second_index] {
+                    total[index] += LDDT_THRESHOLDS.len();
+                    preserved[index] += count;
+                }
+            }
+        }
+        let all: usize = total.iter().sum();
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = |part: usize, all: usize| (all > 0).then(|| part as f64 / all as f64);
+        Some(Lddt {
+            score: ratio(preserved.iter().sum(), all)?,
+            per_residue: preserved
+                .into_iter()
+                .zip(total)
+                .map(|(part, all)| ratio(part, all))
+                .collect(),
+        })
+    }
+
+    /// Get the global lDDT score, between 0 and 1 where 1 is a perfect match
+    #[must_use]
+    pub const fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Get the lDDT score for every residue pair, in the order of the pairs, this is `None`
+    /// if there are no distances to check for this residue
+    #[must_use]
+    pub fn per_residue(&self) -> &[Option<f64>] {
+        &self.per_residue
+    }
+}
+
+/// The distance between two positions
+pub(crate) fn distance(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+/// Get the CA (or C3') atom positions of the model and reference residues, for the pairs where
+/// both residues have this atom, with the index of the pair
+type Representatives = (Vec<(f64, f64, f64)>, Vec<(f64, f64, f64)>, Vec<usize>);
+
+/// Get the CA (or C3') positions of the model and reference residues, see [`Representatives`]
+fn representatives(pairs: &[(&Residue, &Residue)]) -> Representatives {
+    let mut result = (Vec::new(), Vec::new(), Vec::new());
+    for (index, (model, reference)) in pairs.iter().enumerate() {
+        if let (Some(m), Some(r)) = (trace_position(model), trace_position(reference)) {
+            result.0.push(m);
+            result.1.push(r);
+            result.2.push(index);
+        }
+    }
+    result
+}
+
+/// Get the TM-score distance scale d0 in Å for the given length
+pub(crate) fn tm_d0(length: f64) -> f64 {
+    if length > 21.0 {
+        1.24f64.mul_add((length - 15.0).cbrt(), -1.8).max(0.5)
+    } else {
+        0.5
+    }
+}
+
+/// Get the position of the CA atom (or C3' atom for nucleotides) of a residue
+pub(crate) fn trace_position(residue: &Residue) -> Option<(f64, f64, f64)> {
+    ["CA", "C3'"]
+        .iter()
+        .find_map(|name| residue.atoms().find(|a| a.name() == *name))
+        .map(Atom::pos)
+}
+
+/// A score found while searching a superposition, with the transformation and the distances
+/// between all model and reference positions after this transformation
+pub(crate) type Found = (f64, TransformationMatrix, Vec<f64>);
+
+/// Search the superposition of the model onto the reference maximising the given score (which
+/// is given the distances between all model and reference positions after superposition).
+/// Every fragment of decreasing lengths is used as an initial superposition, which is then
+/// refined by superposing on the positions within the cutoff distance. Returns the best
+/// transformation with the distances after this transformation, or `None` if there are no
+/// positions.
+pub(crate) fn search(
+    model: &[(f64, f64, f64)],
+    reference: &[(f64, f64, f64)],
+    cutoff: f64,
+    score: impl Fn(&[f64]) -> f64,
+) -> Option<(TransformationMatrix, Vec<f64>)> {
+    let size = model.len();
+    let mut best = None;
+    let mut length = size;
+    loop {
+        for start in (0..=size - length).step_by((length / 2).max(1)) {
+            let selection = (start..start + length).collect();
+            refine(model, reference, selection, cutoff, &score, &mut best);
+        }
+        if length <= 4 {
+            break;
+        }
+        length = (length / 2).max(4);
+    }
+    best.map(|(_, transformation, distances)| (transformation, distances))
+}
+
+/// Refine a superposition of the model onto the reference, starting with the superposition on
+/// the selected positions and then iteratively superposing on the positions within the cutoff
+/// distance. Every superposition with a higher score than the best found so far is stored as
+/// the best.
+pub(crate) fn refine(
+    model: &[(f64, f64, f64)],
+    reference: &[(f64, f64, f64)],
+    mut selection: Vec<usize>,
+    cutoff: f64,
+    score: &impl Fn(&[f64]) -> f64,
+    best: &mut Option<Found>,
+) {
+    for _ in 0..MAX_ITERATIONS {
+        let superposition = if let Some(s) = Superposition::from_positions(
+            &selection.iter().map(|i| model[*i]).collect::<Vec<_>>(),
+            &selection.iter().map(|i| reference[*i]).collect::<Vec<_>>(),
+            None,
+        ) {
+            s
+        } else {
+            break;
+        };
+        let current: Vec<f64> = model
+            .iter()
+            .zip(reference)
+            .map(|(m, r)| distance(superposition.transformation().apply(*m), *r))
+            .collect();
+        let value = score(&current);
+        let new_selection = select(&current, cutoff);
+        if best.as_ref().map_or(true, |(b, _, _)| value > *b) {
+            *best = Some((value, superposition.transformation().clone(), current));
+        }
+        if new_selection == selection {
+            break;
+        }
+        selection = new_selection;
+    }
+}
+
+/// Select the positions within the cutoff distance, the cutoff is increased in steps of 0.5 Å
+/// until at least three positions (or all if there are less) are selected
+fn select(distances: &[f64], mut cutoff: f64) -> Vec<usize> {
+    loop {
+        let selection: Vec<usize> = (0..distances.len())
+            .filter(|i| distances[*i] < cutoff)
+            .collect();
+        if selection.len() >= 3.min(distances.len()) {
+            return selection;
+        }
+        cutoff += 0.5;
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn scores() {
+        let (reference, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let mut model = reference.clone();
+        model.apply_transformation(&TransformationMatrix::rotation_y(40.0));
+        let residues = |pdb: &PDB| -> Vec<Residue> {
+            pdb.residues()
+                .filter(|r| r.name() != Some("HOH"))
+                .cloned()
+                .collect()
+        };
+        let (reference_residues, model_residues) = (residues(&reference), residues(&model));
+        let pairs: Vec<_> = model_residues.iter().zip(&reference_residues).collect();
+
+        let tm = TmScore::new(&pairs, 76).unwrap();
+        assert!((tm.score() - 1.0).abs() < 1e-6);
+        assert_eq!(tm.per_residue().len(), 76);
+        let gdt = GdtScore::new(&pairs, 76).unwrap();
+        assert!((gdt.ts() - 1.0).abs() < 1e-9);
+        assert!((gdt.ha() - 1.0).abs() < 1e-9);
+        let lddt = Lddt::new(&pairs).unwrap();
+        assert!((lddt.score() - 1.0).abs() < 1e-9);
+
+        // Move the last ten residues
+        let mut moved = model_residues.clone();
+        for residue in moved.iter_mut().skip(66) {
+            for atom in residue.atoms_mut() {
+                let (x, y, z) = atom.pos();
+                atom.set_pos((x + 3.0, y, z)).unwrap();
+            }
+        }
+        let pairs: Vec<_> = moved.iter().zip(&reference_residues).collect();
+        let tm = TmScore::new(&pairs, 76).unwrap();
+        assert!(tm.score() < 1.0 && tm.score() > 0.8);
+        assert!(tm.per_residue()[0] > tm.per_residue()[70]);
+        let gdt = GdtScore::new(&pairs, 76).unwrap();
+        assert!(gdt.ha() < 1.0 && gdt.ha() >= 66.0 / 76.0);
+        assert!(gdt.ts() > gdt.ha());
+        assert!((gdt.per_residue_ts()[0] - 1.0).abs() < 1e-9);
+        let lddt = Lddt::new(&pairs).unwrap();
+        assert!(lddt.score() < 1.0 && lddt.score() > 0.8);
+        assert!(lddt.per_residue()[0].unwrap() > lddt.per_residue()[70].unwrap());
+
+        // The normalisation by the reference length
+        let half = TmScore::new(&pairs[..38], 76).unwrap();
+        assert!(half.score() < 0.51);
+        assert!(TmScore::new(&[], 76).is_none());
+        assert!(Lddt::new(&[]).is_none());
+    }
+}
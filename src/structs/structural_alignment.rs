@@ -0,0 +1,316 @@
+use super::correspondence::align;
+use super::similarity::{distance, refine, tm_d0, trace_position, Found};
+use crate::structs::*;
+use crate::TransformationMatrix;
+
+/// The maximal number of rounds of dynamic programming when refining an alignment
+const MAX_ROUNDS: usize = 30;
+/// The gap opening score for the dynamic programming on the superposed structures
+const GAP_OPEN: f64 = -0.6;
+/// The maximal distance in Å for aligned residues in the final alignment
+const MAX_DISTANCE: f64 = 5.0;
+
+/// An alignment (as pairs of indices) with its TM-score and the superposition giving this score
+type Candidate = (f64, Vec<(usize, usize)>, TransformationMatrix);
+
+/// A sequence independent structural alignment of two chains, in the style of TM-align (Zhang,
+/// Y., & Skolnick, J. (2005). TM-align: a protein structure alignment algorithm based on the
+/// TM-score. Nucleic Acids Research, 33(7), 2302–2309). The chains are represented by their CA
+/// atoms (or C3' atoms for nucleotides). Initial alignments are made by gapless threading and by
+/// aligning the secondary structure (derived from the CA distances), these are refined with
+/// dynamic programming on the distances after superposition until the TM-score (normalised by
+/// the second chain) does not improve.
+#[derive(Debug, Clone)]
+pub struct StructuralAlignment<'a> {
+    /// The aligned residues, first from the first chain then from the second chain
+    residue_pairs: Vec<(&'a Residue, &'a Residue)>,
+    /// The TM-score normalised by the length of the first chain
+    tm_score_first: f64,
+    /// The TM-score normalised by the length of the second chain
+    tm_score_second: f64,
+    /// The RMSD of the aligned residues after superposition
+    rmsd: f64,
+    /// The transformation superposing the first chain onto the second chain
+    transformation: TransformationMatrix,
+}
+
+impl<'a> StructuralAlignment<'a> {
+    /// Structurally align the first chain to the second chain.
+    ///
+    /// ## Fails
+    /// Returns `None` if one of the chains has no residues with a CA (or C3') atom.
+    #[must_use]
+    pub fn new(first: &'a Chain, second: &'a Chain) -> Option<Self> {
+        let trace = |chain: &'a Chain| -> (Vec<&'a Residue>, Vec<(f64, f64, f64)>) {
+            chain
+                .residues()
+                .filter_map(|r| trace_position(r).map(|p| (r, p)))
+                .unzip()
+        };
+        let (first_residues, x) = trace(first);
+        let (second_residues, y) = trace(second);
+        let (n, m) = (x.len(), y.len());
+        if n == 0 || m == 0 {
+            return None;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let length = m as f64;
+        let d0 = tm_d0(length);
+        let cutoff = d0.clamp(4.5, 8.0);
+        let contribution = |d: f64| 1.0 / (1.0 + (d / d0).powi(2));
+        let tm_score =
+            |distances: &[f64]| distances.iter().map(|d| contribution(*d)).sum::<f64>() / length;
+        // A quick estimate of the TM-score of an alignment, starting from the superposition on
+        // all aligned residues
+        let evaluate = |alignment: &[(usize, usize)]| -> Option<Found> {
+            let model: Vec<_> = alignment.iter().map(|(i, _)| x[*i]).collect();
+            let reference: Vec<_> = alignment.iter().map(|(_, j)| y[*j]).collect();
+            let mut best = None;
+            let selection = (0..alignment.len()).collect();
+            refine(&model, &reference, selection, cutoff, &tm_score, &mut best);
+            best
+        };
+
+        let mut initial = Vec::new();
+        // Gapless threading, with at least half of the shortest chain aligned
+        let mut threading: Option<(f64, Vec<(usize, usize)>)> = None;
+        let minimal = (n.min(m) / 2).max(1);
+        for offset in 0..n + m - 1 {
+            // The index in the first chain aligned to the start of the second chain is
+            // `offset - (m - 1)`
+            let alignment: Vec<(usize, usize)> = (0..n)
+                .filter_map(|i| {
+                    (i + m - 1)
+                        .checked_sub(offset)
+                        .filter(|j| *j < m)
+                        .map(|j| (i, j))
+                })
+                .collect();
+            if alignment.len() < minimal {
+                continue;
+            }
+            if let Some((score, _, _)) = evaluate(&alignment) {
+                if threading.as_ref().map_or(true, |(best, _)| score > *best) {
+                    threading = Some((score, alignment));
+                }
+            }
+        }
+        initial.extend(threading.map(|(_, alignment)| alignment));
+        // Alignment of the secondary structure
+        let (first_structure, second_structure) =
+            (secondary_structure(&x), secondary_structure(&y));
+        initial.push(align(
+            n,
+            m,
+            |i, j| f64::from(u8::from(first_structure[i] == second_structure[j])),
+            -1.0,
+            -1.0,
+        ));
+
+        // Refine the initial alignments
+        let mut best: Option<Candidate> = None;
+        for mut alignment in initial {
+            for _ in 0..MAX_ROUNDS {
+                let (score, transformation, _) = if let Some(found) = evaluate(&alignment) {
+                    found
+                } else {
+                    break;
+                };
+                let moved: Vec<_> = x.iter().map(|p| transformation.apply(*p)).collect();
+                if best.as_ref().map_or(true, |(b, _, _)| score > *b) {
+                    best = Some((score, alignment.clone(), transformation));
+                }
+                let next = align(
+                    n,
+                    m,
+                    |i, j| contribution(distance(moved[i], y[j])),
+                    GAP_OPEN,
+                    0.0,
+                );
+                if next == alignment {
+                    break;
+                }
+                alignment = next;
+            }
+        }
+        let (_, alignment, transformation) = best?;
+
+        // Only keep the residues close together in the final alignment
+        let close: Vec<_> = alignment
+            .iter()
+            .copied()
+            .filter(|(i, j)| distance(transformation.apply(x[*i]), y[*j]) < MAX_DISTANCE)
+            .collect();
+        let alignment = if close.is_empty() { alignment } else { close };
+        let residue_pairs: Vec<_> = alignment
+            .iter()
+            .map(|(i, j)| (first_residues[*i], second_residues[*j]))
+            .collect();
+        let tm_score_first = TmScore::new(&residue_pairs, n)?;
+        let tm_score_second = TmScore::new(&residue_pairs, m)?;
+        let rmsd = Superposition::from_positions(
+            &alignment.iter().map(|(i, _)| x[*i]).collect::<Vec<_>>(),
+            &alignment.iter().map(|(_, j)| y[*j]).collect::<Vec<_>>(),
+            None,
+        )?
+        .rmsd();
+        Some(StructuralAlignment {
+            residue_pairs,
+            tm_score_first: tm_score_first.score(),
+            tm_score_second: tm_score_second.score(),
+            rmsd,
+            transformation: tm_score_second.transformation().clone(),
+        })
+    }
+
+    /// Get the aligned residues, first from the first chain then from the second chain
+    #[must_use]
+    pub fn residue_pairs(&self) -> &[(&'a Residue, &'a Residue)] {
+        &self.residue_pairs
+    }
+
+    /// Get the number of aligned residues
+    #[must_use]
+    pub fn aligned_length(&self) -> usize {
+        self.residue_pairs.len()
+    }
+
+    /// Get the TM-score normalised by the length of the first chain
+    #[must_use]
+    pub const fn tm_score_first(&self) -> f64 {
+        self.tm_score_first
+    }
+
+    /// Get the TM-score normalised by the length of the second chain
+    #[must_use]
+    pub const fn tm_score_second(&self) -> f64 {
+        self.tm_score_second
+    }
+
+    /// Get the RMSD in Å of the aligned residues after superposition
+    #[must_use]
+    pub const fn rmsd(&self) -> f64 {
+        self.rmsd
+    }
+
+    /// Get the transformation superposing the first chain onto the second chain
+    #[must_use]
+    pub const fn transformation(&self) -> &TransformationMatrix {
+        &self.transformation
+    }
+}
+
+/// Assign the secondary structure of a CA trace based on the distances to the neighbouring
+/// residues as in TM-align, giving `H` for helices, `E` for strands, `T` for turns, and `C`
+/// for coil
+fn secondary_structure(trace: &[(f64, f64, f64)]) -> Vec<u8> {
+    let near = |distances: [f64; 6], expected: [f64; 6], delta: f64| {
+        distances
+            .iter()
+            .zip(expected)
+            .all(|(d, e)| (d - e).abs() < delta)
+    };
+    (0..trace.len())
+        .map(|i| {
+            if i < 2 || i + 2 >= trace.len() {
+                return b'C';
+            }
+            let d = |a: usize, b: usize| distance(trace[a], trace[b]);
+            let distances = [
+                d(i - 2, i),
+                d(i - 2, i + 1),
+                d(i - 2, i + 2),
+                d(i - 1, i + 1),
+                d(i - 1, i + 2),
+                d(i, i + 2),
+            ];
+            if near(distances, [5.45, 5.18, 6.37, 5.45, 5.18, 5.45], 2.1) {
+                b'H'
+            } else if near(distances, [6.1, 10.4, 13.0, 6.1, 10.4, 6.1], 1.42) {
+                b'E'
+            } else if distances[2] < 8.0 {
+                b'T'
+            } else {
+                b'C'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn ubiquitin() {
+        let (pdb, _) = ReadOptions::new()
+            .set_level(StrictnessLevel::Loose)
+            .read("example-pdbs/1ubq.pdb")
+            .unwrap();
+        let mut other = pdb.clone();
+        other.apply_transformation(
+            &TransformationMatrix::rotation_z(70.0)
+                .combine(&TransformationMatrix::translation(10.0, 2.0, -5.0)),
+        );
+        {
+            let chain = other.chains_mut().next().unwrap();
+            chain.remove_residues_by(|r| {
+                r.serial_number() <= 5 || (30..35).contains(&r.serial_number())
+            });
+            for residue in chain.residues_mut() {
+                residue.set_serial_number(residue.serial_number() + 1000);
+            }
+        }
+        let (first, second) = (pdb.chain(0).unwrap(), other.chain(0).unwrap());
+        let alignment = StructuralAlignment::new(first, second).unwrap();
+        assert_eq!(alignment.aligned_length(), 66);
+        assert!(alignment
+            .residue_pairs()
+            .iter()
+            .all(|(a, b)| a.serial_number() + 1000 == b.serial_number()));
+        assert!(alignment.rmsd() < 1e-6);
+        assert!((alignment.tm_score_second() - 1.0).abs() < 1e-6);
+        assert!(alignment.tm_score_first() < 1.0);
+        assert!(alignment.tm_score_first() > 0.85);
+        let moved = alignment.transformation().apply(
+            pdb.residue(10)
+                .unwrap()
+                .atoms()
+                .find(|a| a.name() == "CA")
+                .unwrap()
+                .pos(),
+        );
+        let target = other
+            .residues()
+            .find(|r| r.serial_number() == 1011)
+            .unwrap()
+            .atoms()
+            .find(|a| a.name() == "CA")
+            .unwrap()
+            .pos();
+        assert!(distance(moved, target) < 1e-6);
+
+        // The alignment only depends on the structure, not on the residue names
+        let mut unknown = other.clone();
+        for conformer in unknown.conformers_mut() {
+            assert!(conformer.set_name("UNK"));
+        }
+        let blind = StructuralAlignment::new(first, unknown.chain(0).unwrap()).unwrap();
+        let pairs = |alignment: &StructuralAlignment<'_>| -> Vec<_> {
+            alignment
+                .residue_pairs()
+                .iter()
+                .map(|(a, b)| (a.serial_number(), b.serial_number()))
+                .collect()
+        };
+        assert_eq!(pairs(&blind), pairs(&alignment));
+        assert!((blind.tm_score_first() - alignment.tm_score_first()).abs() < 1e-6);
+        assert!((blind.tm_score_second() - alignment.tm_score_second()).abs() < 1e-6);
+
+        let empty = Chain::new("X").unwrap();
+        assert!(StructuralAlignment::new(first, &empty).is_none());
+    }
+}
@@ -0,0 +1,118 @@
+/// A structure factor calculated from a model for a single reflection, see
+/// [`crate::PDB::structure_factors`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StructureFactor {
+    /// The Miller indices h, k, and l
+    index: [i32; 3],
+    /// The resolution in Å
+    resolution: f64,
+    /// The real part in electrons
+    real: f64,
+    /// The imaginary part in electrons
+    imaginary: f64,
+}
+
+impl StructureFactor {
+    /// Create a new structure factor
+    pub(crate) const fn new(index: [i32; 3], resolution: f64, real: f64, imaginary: f64) -> Self {
+        StructureFactor {
+            index,
+            resolution,
+            real,
+            imaginary,
+        }
+    }
+
+    /// Get the Miller indices h, k, and l
+    #[must_use]
+    pub const fn index(&self) -> [i32; 3] {
+        self.index
+    }
+
+    /// Get the resolution (the spacing d of the lattice planes) in Å, infinite for (0, 0, 0)
+    #[must_use]
+    pub const fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    /// Get the real part in electrons
+    #[must_use]
+    pub const fn real(&self) -> f64 {
+        self.real
+    }
+
+    /// Get the imaginary part in electrons
+    #[must_use]
+    pub const fn imaginary(&self) -> f64 {
+        self.imaginary
+    }
+
+    /// Get the amplitude (Fcalc) in electrons
+    #[must_use]
+    pub fn amplitude(&self) -> f64 {
+        self.real.hypot(self.imaginary)
+    }
+
+    /// Get the phase in degrees, from -180 to 180
+    #[must_use]
+    pub fn phase(&self) -> f64 {
+        self.imaginary.atan2(self.real).to_degrees()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::*;
+
+    fn pdb(atoms: &[(f64, f64, f64, &str)], symmetry: Option<&str>) -> PDB {
+        let mut model = Model::new(1);
+        for (i, (x, y, z, element)) in atoms.iter().enumerate() {
+            let atom =
+                Atom::new(false, i + 1, *element, *x, *y, *z, 1.0, 0.0, *element, 0).unwrap();
+            model.add_atom(atom, "A", (1, None), ("LIG", None));
+        }
+        let mut pdb = PDB::new();
+        pdb.add_model(model);
+        pdb.unit_cell = Some(UnitCell::new(10.0, 10.0, 10.0, 90.0, 90.0, 90.0));
+        pdb.symmetry = symmetry.map(|symbol| Symmetry::new(symbol).unwrap());
+        pdb
+    }
+
+    #[test]
+    fn single_atom() {
+        let pdb = pdb(&[(2.5, 0.0, 0.0, "C")], None);
+        let factors = pdb
+            .structure_factors(&[[0, 0, 0], [1, 0, 0], [2, 0, 0]])
+            .unwrap();
+        assert!((factors[0].amplitude() - 6.0).abs() < 1e-2);
+        assert!(factors[0].resolution().is_infinite());
+        let expected = Element::C.scattering_factor().evaluate(1.0 / 400.0);
+        assert!((factors[1].amplitude() - expected).abs() < 1e-9);
+        assert!((factors[1].phase() - 90.0).abs() < 1e-9);
+        assert!((factors[1].resolution() - 10.0).abs() < 1e-9);
+        assert!((factors[2].phase().abs() - 180.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn symmetry() {
+        let pdb = pdb(&[(1.0, 2.0, 3.0, "O")], Some("P -1"));
+        let factors = pdb.structure_factors(&[[1, 2, 3], [2, -1, 0]]).unwrap();
+        for factor in factors {
+            let [h, k, l] = factor.index().map(f64::from);
+            let f = Element::O
+                .scattering_factor()
+                .evaluate((h * h + k * k + l * l) / 400.0);
+            let phase = 2.0 * std::f64::consts::PI * (0.1 * h + 0.2 * k + 0.3 * l);
+            assert!(factor.imaginary().abs() < 1e-9);
+            assert!((factor.real() - 2.0 * f * phase.cos()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn no_unit_cell() {
+        let mut pdb = pdb(&[(1.0, 2.0, 3.0, "O")], None);
+        pdb.unit_cell = None;
+        assert!(pdb.structure_factors(&[[1, 0, 0]]).is_none());
+    }
+}
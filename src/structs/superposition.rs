@@ -0,0 +1,331 @@
+use crate::structs::*;
+use crate::TransformationMatrix;
+
+/// The optimal superposition of one set of positions (the mobile set) onto another set of
+/// positions (the fixed set), with the root mean square deviation (RMSD) after superposition.
+/// This is calculated with the Kabsch algorithm, using the quaternion formulation by Horn
+/// (Horn, B. K. P. Closed-form solution of absolute orientation using unit quaternions. J. Opt.
+/// Soc. Am. A 4, 629 (1987)) which always results in a proper rotation.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Superposition {
+    /// The transformation moving the mobile set onto the fixed set
+    transformation: TransformationMatrix,
+    /// The RMSD after superposition
+    rmsd: f64,
+    /// The number of pairs used
+    pairs: usize,
+}
+
+impl Superposition {
+    /// Superpose the first atom of every pair onto the second atom of the pair. The pairs can
+    /// be made by hand, by combining two [`Search`] results, or by zipping the
+    /// `atoms_with_hierarchy` of two structures after matching them on chain, residue and atom
+    /// name.
+    ///
+    /// ## Arguments
+    /// * `pairs` - the pairs of (mobile, fixed) atoms
+    /// * `mass_weighted` - weight every pair with the atomic weight of the mobile atom, see
+    ///   [`Element::weight`]
+    ///
+    /// ## Fails
+    /// Returns `None` if there are no pairs, or if `mass_weighted` is set and an atom has no
+    /// element or no known atomic weight.
+    pub fn new<'a>(
+        pairs: impl IntoIterator<Item = (&'a Atom, &'a Atom)>,
+        mass_weighted: bool,
+    ) -> Option<Self> {
+        let mut mobile = Vec::new();
+        let mut fixed = Vec::new();
+        let mut weights = Vec::new();
+        for (a, b) in pairs {
+            mobile.push(a.pos());
+            fixed.push(b.pos());
+            if mass_weighted {
+                weights.push(a.element().and_then(Element::weight)?);
+            }
+        }
+        Self::from_positions(&mobile, &fixed, mass_weighted.then_some(&weights))
+    }
+
+    /// Superpose the mobile positions onto the fixed positions, with an optional weight for
+    /// every pair of positions.
+    ///
+    /// ## Fails
+    /// Returns `None` if there are no positions, if the number of mobile positions, fixed
+    /// positions, and weights differ, or if a weight is negative or not finite, or if all
+    /// weights are zero.
+    #[must_use]
+    pub fn from_positions(
+        mobile: &[(f64, f64, f64)],
+        fixed: &[(f64, f64, f64)],
+        weights: Option<&[f64]>,
+    ) -> Option<Self> {
+        if mobile.is_empty()
+            || mobile.len() != fixed.len()
+            || weights.map_or(false, |w| {
+                w.len() != mobile.len() || w.iter().any(|w| !w.is_finite() || *w < 0.0)
+            })
+        {
+            return None;
+        }
+        let weight = |index: usize| weights.map_or(1.0, |w| w[index]);
+        let total: f64 = (0..mobile.len()).map(weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let centroid = |positions: &[(f64, f64, f64)]| {
+            let mut sum = [0.0; 3];
+            for (index, position) in positions.iter().enumerate() {
+                let w = weight(index);
+                sum[0] += w * position.0;
+                sum[1] += w * position.1;
+                sum[2] += w * position.2;
+            }
+            [sum[0] / total, sum[1] / total, sum[2] / total]
+        };
+        let (mobile_centre, fixed_centre) = (centroid(mobile), centroid(fixed));
+
+        // The weighted covariance between the centred mobile and fixed positions
+        let mut s = [[0.0; 3]; 3];
+        for (index, (m, f)) in mobile.iter().zip(fixed).enumerate() {
+            let w = weight(index);
+            let m = [
+                m.0 - mobile_centre[0],
+                m.1 - mobile_centre[1],
+                m.2 - mobile_centre[2],
+            ];
+            let f = [
+                f.0 - fixed_centre[0],
+                f.1 - fixed_centre[1],
+                f.2 - fixed_centre[2],
+            ];
+            for (row, m) in s.iter_mut().zip(m) {
+                for (cell, f) in row.iter_mut().zip(f) {
+                    *cell += w * m * f;
+                }
+            }
+        }
+
+        // The quaternion with the largest eigenvalue of this matrix gives the optimal rotation
+        let n = [
+            [
+                s[0][0] + s[1][1] + s[2][2],
+                s[1][2] - s[2][1],
+                s[2][0] - s[0][2],
+                s[0][1] - s[1][0],
+            ],
+            [
+                s[1][2] - s[2][1],
+                s[0][0] - s[1][1] - s[2][2],
+                s[0][1] + s[1][0],
+                s[2][0] + s[0][2],
+            ],
+            [
+                s[2][0] - s[0][2],
+                s[0][1] + s[1][0],
+                -s[0][0] + s[1][1] - s[2][2],
+                s[1][2] + s[2][1],
+            ],
+            [
+                s[0][1] - s[1][0],
+                s[2][0] + s[0][2],
+                s[1][2] + s[2][1],
+                -s[0][0] - s[1][1] + s[2][2],
+            ],
+        ];
+        let (values, vectors) = symmetric_eigen(n);
+        let largest = (1..4).fold(0, |best, i| if values[i] > values[best] { i } else { best });
+        let q = [
+            vectors[0][largest],
+            vectors[1][largest],
+            vectors[2][largest],
+            vectors[3][largest],
+        ];
+        let length = q.iter().map(|v| v * v).sum::<f64>().sqrt();
+        let [a, b, c, d] = q.map(|v| v / length);
+        let rotation = [
+            [
+                a * a + b * b - c * c - d * d,
+                2.0 * (b * c - a * d),
+                2.0 * (b * d + a * c),
+            ],
+            [
+                2.0 * (b * c + a * d),
+                a * a - b * b + c * c - d * d,
+                2.0 * (c * d - a * b),
+            ],
+            [
+                2.0 * (b * d - a * c),
+                2.0 * (c * d + a * b),
+                a * a - b * b - c * c + d * d,
+            ],
+        ];
+        let mut matrix = [[0.0; 4]; 3];
+        for ((row, rotation), centre) in matrix.iter_mut().zip(rotation).zip(fixed_centre) {
+            row[..3].copy_from_slice(&rotation);
+            row[3] = centre
+                - rotation[0] * mobile_centre[0]
+                - rotation[1] * mobile_centre[1]
+                - rotation[2] * mobile_centre[2];
+        }
+        let transformation = TransformationMatrix::from_matrix(matrix);
+
+        let deviation: f64 = mobile
+            .iter()
+            .zip(fixed)
+            .enumerate()
+            .map(|(index, (m, f))| {
+                let m = transformation.apply(*m);
+                weight(index) * ((m.0 - f.0).powi(2) + (m.1 - f.1).powi(2) + (m.2 - f.2).powi(2))
+            })
+            .sum();
+        Some(Superposition {
+            transformation,
+            rmsd: (deviation / total).sqrt(),
+            pairs: mobile.len(),
+        })
+    }
+
+    /// Get the transformation that moves the mobile set onto the fixed set
+    #[must_use]
+    pub const fn transformation(&self) -> &TransformationMatrix {
+        &self.transformation
+    }
+
+    /// Get the (weighted) root mean square deviation in Å between the pairs after superposition
+    #[must_use]
+    pub const fn rmsd(&self) -> f64 {
+        self.rmsd
+    }
+
+    /// Get the number of pairs used for the superposition
+    #[must_use]
+    pub const fn pairs(&self) -> usize {
+        self.pairs
+    }
+
+    /// Apply the transformation to all atoms of the given PDB, to move it onto the fixed set
+    pub fn apply(&self, pdb: &mut PDB) {
+        pdb.apply_transformation(&self.transformation);
+    }
+}
+
+/// Calculate the eigenvalues and eigenvectors (as the columns of the returned matrix) of a
+/// symmetric 4x4 matrix using the cyclic Jacobi method
+fn symmetric_eigen(mut a: [[f64; 4]; 4]) -> ([f64; 4], [[f64; 4]; 4]) {
+    let mut v = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    for _ in 0..64 {
+        let off_diagonal: f64 = (0..4)
+            .flat_map(|p| (p + 1..4).map(move |q| (p, q)))
+            .map(|(p, q)| a[p][q] * a[p][q])
+            .sum();
+        if off_diagonal < 1e-30 {
+            break;
+        }
+        for p in 0..3 {
+            for q in p + 1..4 {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + theta.mul_add(theta, 1.0).sqrt());
+                let c = 1.0 / t.mul_add(t, 1.0).sqrt();
+                let s = t * c;
+                for row in &mut a {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+                let (rp, rq) = (a[p], a[q]);
+                for k in 0..4 {
+                    a[p][k] = c * rp[k] - s * rq[k];
+                    a[q][k] = s * rp[k] + c * rq[k];
+                }
+                for row in &mut v {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+            }
+        }
+    }
+    ([a[0][0], a[1][1], a[2][2], a[3][3]], v)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// Check that two positions are (nearly) the same
+    fn close(a: (f64, f64, f64), b: (f64, f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6 && (a.2 - b.2).abs() < 1e-6
+    }
+
+    #[test]
+    fn recover_transformation() {
+        let fixed = vec![
+            (0.0, 0.0, 0.0),
+            (1.5, 0.0, 0.0),
+            (2.0, 1.4, 0.0),
+            (3.5, 1.5, 0.7),
+            (4.1, 2.9, 1.2),
+        ];
+        let moved = TransformationMatrix::rotation_x(35.0)
+            .combine(&TransformationMatrix::rotation_z(-120.0))
+            .combine(&TransformationMatrix::translation(5.0, -3.0, 12.0));
+        let mobile: Vec<_> = fixed.iter().map(|p| moved.apply(*p)).collect();
+        let superposition = Superposition::from_positions(&mobile, &fixed, None).unwrap();
+        assert!(superposition.rmsd() < 1e-6);
+        assert_eq!(superposition.pairs(), 5);
+        for (m, f) in mobile.iter().zip(&fixed) {
+            assert!(close(superposition.transformation().apply(*m), *f));
+        }
+
+        // A mirror image can not be superposed perfectly
+        let mirror: Vec<_> = fixed.iter().map(|p| (-p.0, p.1, p.2)).collect();
+        let superposition = Superposition::from_positions(&mirror, &fixed, None).unwrap();
+        assert!(superposition.rmsd() > 0.1);
+        assert!(Superposition::from_positions(&[], &[], None).is_none());
+        assert!(Superposition::from_positions(&mirror, &fixed[1..], None).is_none());
+    }
+
+    #[test]
+    fn atoms() {
+        let atom = |name: &str, element: &str, x: f64| {
+            Atom::new(false, 1, name, x, 0.0, 0.0, 1.0, 0.0, element, 0).unwrap()
+        };
+        let fixed = [
+            atom("C", "C", 0.0),
+            atom("O", "O", 1.2),
+            atom("H", "H", 2.0),
+        ];
+        let mut mobile = fixed.clone();
+        mobile[2].set_pos((2.5, 0.0, 0.0)).unwrap();
+        let plain = Superposition::new(mobile.iter().zip(&fixed), false).unwrap();
+        let weighted = Superposition::new(mobile.iter().zip(&fixed), true).unwrap();
+        assert!(weighted.rmsd() < plain.rmsd());
+        assert!(weighted.rmsd() > 0.0);
+
+        let mut unknown = atom("X", "", 0.0);
+        unknown.set_element(Element::Og);
+        assert!(Superposition::new([(&unknown, &fixed[0])], true).is_none());
+        assert!(Superposition::new([(&unknown, &fixed[0])], false).is_some());
+
+        let mut pdb = PDB::new();
+        let mut model = Model::new(0);
+        model.add_atom(mobile[0].clone(), "A", (1, None), ("GLY", None));
+        pdb.add_model(model);
+        plain.apply(&mut pdb);
+        assert!(close(
+            pdb.atom(0).unwrap().pos(),
+            plain.transformation().apply(mobile[0].pos())
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use super::helper::invert_matrix;
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 /// A unit cell of a crystal, containing its dimensions and angles
@@ -148,6 +150,64 @@ impl UnitCell {
     pub const fn size(&self) -> (f64, f64, f64) {
         (self.a, self.b, self.c)
     }
+    /// Create a `UnitCell` from the three box vectors a, b, and c. The dimensions are the lengths
+    /// of the vectors and the angles are the angles between them.
+    #[must_use]
+    pub fn from_vectors(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> Self {
+        let length = |v: [f64; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+        let angle = |v: [f64; 3], w: [f64; 3]| {
+            let divisor = length(v) * length(w);
+            if divisor == 0.0 {
+                90.0
+            } else {
+                ((v[0] * w[0] + v[1] * w[1] + v[2] * w[2]) / divisor)
+                    .clamp(-1.0, 1.0)
+                    .acos()
+                    .to_degrees()
+            }
+        };
+        Self::new(
+            length(a),
+            length(b),
+            length(c),
+            angle(b, c),
+            angle(a, c),
+            angle(a, b),
+        )
+    }
+    /// Get the box vectors a, b, and c of this unit cell. The a vector is placed along the x-axis
+    /// and the b vector in the xy-plane, as is the convention in PDB and GROMACS files.
+    #[must_use]
+    pub fn vectors(&self) -> [[f64; 3]; 3] {
+        let (alpha, beta, gamma) = (
+            self.alpha.to_radians(),
+            self.beta.to_radians(),
+            self.gamma.to_radians(),
+        );
+        let cx = self.c * beta.cos();
+        let cy = self.c * (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+        [
+            [self.a, 0.0, 0.0],
+            [self.b * gamma.cos(), self.b * gamma.sin(), 0.0],
+            [
+                cx,
+                cy,
+                (self.c * self.c - cx * cx - cy * cy).max(0.0).sqrt(),
+            ],
+        ]
+    }
+    /// Get the matrix that converts a position in Å to fractional coordinates of this unit cell
+    /// (the inverse of the matrix with the box vectors as columns), given as rows. It returns
+    /// `None` if the unit cell has no volume.
+    #[must_use]
+    pub fn fractionalization_matrix(&self) -> Option<[[f64; 3]; 3]> {
+        let [a, b, c] = self.vectors();
+        // The columns of the matrix are the cell vectors
+        let matrix = [0, 1, 2].map(|row| [a[row], b[row], c[row]]);
+        invert_matrix(matrix)
+            .filter(|(_, determinant)| determinant.abs() >= f64::EPSILON)
+            .map(|(inverse, _)| inverse)
+    }
 }
 
 impl Default for UnitCell {
@@ -169,4 +229,38 @@ mod tests {
         assert_eq!(a, b);
         assert_ne!(a, c);
     }
+
+    #[test]
+    fn vectors() {
+        let cell = UnitCell::new(10.0, 12.0, 15.0, 80.0, 95.0, 110.0);
+        let [a, b, c] = cell.vectors();
+        assert_eq!(a, [10.0, 0.0, 0.0]);
+        assert_eq!(b[2], 0.0);
+        let back = UnitCell::from_vectors(a, b, c);
+        for (x, y) in [
+            (cell.a(), back.a()),
+            (cell.b(), back.b()),
+            (cell.c(), back.c()),
+            (cell.alpha(), back.alpha()),
+            (cell.beta(), back.beta()),
+            (cell.gamma(), back.gamma()),
+        ] {
+            assert!((x - y).abs() < 1e-9, "{x} != {y}");
+        }
+        let matrix = cell.fractionalization_matrix().unwrap();
+        for (vector, expected) in [
+            (a, [1.0, 0.0, 0.0]),
+            (b, [0.0, 1.0, 0.0]),
+            (c, [0.0, 0.0, 1.0]),
+        ] {
+            for (row, value) in matrix.iter().zip(expected) {
+                let fraction: f64 = row.iter().zip(vector).map(|(m, v)| m * v).sum();
+                assert!((fraction - value).abs() < 1e-9);
+            }
+        }
+        assert!(UnitCell::default().fractionalization_matrix().is_none());
+        let square = UnitCell::new(5.0, 5.0, 5.0, 90.0, 90.0, 90.0).vectors();
+        assert!((square[1][0]).abs() < 1e-12);
+        assert!((square[2][2] - 5.0).abs() < 1e-12);
+    }
 }
@@ -149,6 +149,17 @@ pub fn validate_pdb(pdb: &PDB) -> Vec<PDBError> {
                                 Context::None,
                             ));
                         }
+                        if atom.segment_id().map_or(false, |id| id.len() > 4) {
+                            errors.push(PDBError::new(
+                                ErrorLevel::LooseWarning,
+                                "Segment id too long",
+                                format!(
+                                    "Atom {} has a segment id which is too long, max 4 chars.",
+                                    atom.serial_number()
+                                ),
+                                Context::None,
+                            ));
+                        }
                         if atom.serial_number() > 99999 {
                             errors.push(PDBError::new(
                                 ErrorLevel::LooseWarning,
@@ -301,24 +312,30 @@ fn validate_models(pdb: &PDB) -> Vec<PDBError> {
 /// Conformers.
 pub fn reshuffle_conformers(pdb: &mut PDB) {
     for residue in pdb.residues_mut() {
-        let count = residue.conformer_count();
-        if count > 1 {
-            let mut blank = None;
-            for (index, conformer) in residue.conformers().enumerate() {
-                if conformer.alternative_location().is_none() {
-                    blank = Some(index);
-                }
+        reshuffle_residue_conformers(residue);
+    }
+}
+
+/// Join the conformer without an alternative location into all other conformers of the given
+/// residue, see [`reshuffle_conformers`].
+pub(crate) fn reshuffle_residue_conformers(residue: &mut Residue) {
+    let count = residue.conformer_count();
+    if count > 1 {
+        let mut blank = None;
+        for (index, conformer) in residue.conformers().enumerate() {
+            if conformer.alternative_location().is_none() {
+                blank = Some(index);
             }
-            #[allow(clippy::unwrap_used, clippy::cast_precision_loss)]
-            if let Some(index) = blank {
-                let mut shared = residue.conformer(index).unwrap().clone();
-                shared
-                    .atoms_mut()
-                    .for_each(|a| a.set_occupancy(a.occupancy() / (count as f64)).unwrap());
-                residue.remove_conformer(index);
-                for conformer in residue.conformers_mut() {
-                    conformer.join(shared.clone());
-                }
+        }
+        #[allow(clippy::unwrap_used, clippy::cast_precision_loss)]
+        if let Some(index) = blank {
+            let mut shared = residue.conformer(index).unwrap().clone();
+            shared
+                .atoms_mut()
+                .for_each(|a| a.set_occupancy(a.occupancy() / (count as f64)).unwrap());
+            residue.remove_conformer(index);
+            for conformer in residue.conformers_mut() {
+                conformer.join(shared.clone());
             }
         }
     }